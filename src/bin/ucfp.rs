@@ -34,21 +34,80 @@
 //!
 //! Other:
 //! - `UCFP_BIND` — listen address (default `0.0.0.0:8080`)
-//! - `UCFP_DATA_DIR` — directory for the redb file (default `./data`)
+//! - `UCFP_DATA_DIR` — directory for the database (default `./data`)
+//! - `UCFP_BACKEND` — storage engine: `redb` (default, `ucfp.redb` file)
+//!   or `fjall` (`ucfp.fjall/` keyspace; requires the `fjall` feature).
+//!   Pick fjall for sustained ingest past ~30 k writes/s (ARCHITECTURE §6).
 //! - `UCFP_BODY_LIMIT_MB` — request body cap (default 16 MiB)
+//! - `UCFP_EXPIRY_SWEEP_SECS` — how often the TTL sweeper reclaims
+//!   expired records (default 60; `0` disables the sweeper — expired
+//!   records stay hidden from reads either way)
+//! - `UCFP_CHANGES_KEEP_HOURS` — change-log retention for the
+//!   `/v1/changes` tail (default 168; `0` keeps everything), trimmed by
+//!   its own task whether or not the sweeper runs
+//! - `UCFP_VERSIONS_KEEP` / `UCFP_VERSIONS_KEEP_DAYS` — turn on record
+//!   versioning (redb only) and bound history by count (latest
+//!   included) and/or by days since a version was replaced
+//! - `UCFP_RETAIN_TEXT=1` — keep the text of BM25-indexed records so
+//!   `POST /v1/admin/tenants/{tenant_id}/reindex-bm25` can rebuild the
+//!   index and `?highlight=1` on `/v1/query` can cut snippets (redb only)
+//! - `UCFP_POSITIONS_TENANTS` — comma-separated tenant ids whose
+//!   BM25-indexed records also store token positions, for quoted-phrase
+//!   and `NEAR/n` queries (redb only)
+//! - `UCFP_BM25_ANALYZERS` — per-tenant BM25 analyzers as
+//!   `tenant:spec` pairs, e.g. `7:nfkc+cjk-jp,9:word+stop-en+stem-english`
+//!   (spec syntax in `ucfp::Analyzer`; redb only). Tenants whose index
+//!   was built with another analyzer are logged at startup until
+//!   reindexed
+//! - `UCFP_BM25_FIELDS` — per-tenant BM25F weights of text fields as
+//!   `tenant:name:boost[:b]` entries, e.g. `7:title:3:0.5,7:tags:2`
+//!   (`text` is the record body; unlisted fields get boost 1, and fields
+//!   without a `b` the tenant's; redb only)
+//! - `UCFP_BM25_PARAMS` — per-tenant BM25 scoring as `tenant:spec`
+//!   pairs, e.g. `7:bm25l+k1=1.5+b=0.3,9:tf-idf`: a model (`bm25`,
+//!   `bm25l`, `bm25-plus`, `tf-idf`) and `k1`, `b`, `delta` overrides of
+//!   the `bm25+k1=1.2+b=0.75` default (redb only)
+//! - `UCFP_PARQUET_DIR` — write the catalog and usage rollups (from
+//!   `UCFP_USAGE_LOG_PATH`) as Parquet there for DuckDB (redb only;
+//!   requires the `parquet` feature), incrementally every
+//!   `UCFP_PARQUET_EVERY_SECS` (default 3600)
 //!
 //! ## Auth shape
 //! `/healthz`, `/v1/info`, `/metrics` are public. Everything under
 //! `/v1/records*`, `/v1/query`, `/v1/ingest/*` requires
 //! `Authorization: Bearer <token>` and is wired through the auth +
 //! rate-limit + usage middleware in [`ucfp::server::router_with_state`].
+//!
+//! ## Subcommands
+//! - `ucfp restore <snapshot.redb> [--check]` — replace
+//!   `$UCFP_DATA_DIR/ucfp.redb` with a snapshot taken by
+//!   `POST /v1/admin/snapshot`. Validates `FORMAT_VERSION` and table
+//!   versions first; `--check` stops there. Run with the server stopped;
+//!   the old database is kept as `ucfp.redb.pre-restore`.
+//! - `ucfp export <tenant_id> [file]` — write one tenant as a portable
+//!   NDJSON archive (stdout without `file`). Same format as
+//!   `GET /v1/admin/tenants/{tenant_id}/export`.
+//! - `ucfp import <file> [--tenant <id>]` — load an archive, into
+//!   `--tenant` when given or the tenant it was exported from otherwise.
+//!   Both open the database directly, so run them with the server stopped.
+//! - `ucfp migrate [--dry-run]` — bring `$UCFP_DATA_DIR/ucfp.redb` to
+//!   this build's schema and print the steps (opening the server does
+//!   the same); `--dry-run` rolls the migrations back after running them.
+//! - `ucfp fsck [--tenant <id>] [--repair]` — check the invariants
+//!   between catalog, blobs and BM25 tables and print every violation;
+//!   `--repair` rebuilds the derivable ones. Exits non-zero while any
+//!   violation is left. Same report as `POST /v1/admin/fsck`.
+//! - `ucfp parquet <out_dir> [--full] [--db <file.redb>]` — one
+//!   `UCFP_PARQUET_DIR` pass, offline. Point `--db` at a snapshot to
+//!   export without stopping the server.
 
 #![cfg(all(feature = "server", feature = "embedded"))]
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-use std::sync::Arc;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use axum::{
@@ -63,11 +122,15 @@ use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use tower::limit::ConcurrencyLimitLayer;
 use tower_http::{limit::RequestBodyLimitLayer, timeout::TimeoutLayer, trace::TraceLayer};
 
-use ucfp::EmbeddedBackend;
+use ucfp::index::DELETE_BATCH;
 use ucfp::server::{
     ApiKeyLookup, InMemoryTokenBucket, LogUsageSink, NoopUsageSink, ServerState, StaticMapKey,
     StaticSingleKey, TenantRateLimiter, UsageSink, router_with_state,
 };
+use ucfp::{
+    Analyzer, Bm25Field, Bm25Model, Bm25Params, EmbeddedBackend, Error, IndexBackend, TenantStats,
+    VersionPolicy,
+};
 
 /// Per-request Prometheus metrics. Path label is the matched route
 /// template (bounded cardinality, never the raw URI). `/metrics` is
@@ -100,6 +163,277 @@ async fn http_metrics(matched: Option<MatchedPath>, req: Request, next: Next) ->
     resp
 }
 
+/// TTL sweeper. Every `every`, drains expired records in
+/// [`DELETE_BATCH`]-sized transactions until none are due. Exposes
+/// `ucfp_records_expired_total` and `ucfp_expiry_sweep_duration_seconds`;
+/// failed passes bump `ucfp_expiry_sweep_errors_total` and retry next tick.
+async fn sweep_expired_loop<I: IndexBackend>(backend: Arc<I>, every: Duration) {
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let start = Instant::now();
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        loop {
+            match backend.sweep_expired(now_ms, DELETE_BATCH).await {
+                Ok(n) => {
+                    metrics::counter!("ucfp_records_expired_total").increment(n);
+                    if n < DELETE_BATCH as u64 {
+                        break;
+                    }
+                }
+                Err(Error::Unsupported(msg)) => {
+                    tracing::warn!(%msg, "backend has no TTL sweep; expiry sweeper stopped");
+                    return;
+                }
+                Err(e) => {
+                    metrics::counter!("ucfp_expiry_sweep_errors_total").increment(1);
+                    tracing::warn!(error = %e, "expiry sweep failed");
+                    break;
+                }
+            }
+        }
+        metrics::histogram!("ucfp_expiry_sweep_duration_seconds")
+            .record(start.elapsed().as_secs_f64());
+    }
+}
+
+/// How often [`prune_versions_loop`] applies the version age bound.
+const VERSIONS_PRUNE_EVERY: Duration = Duration::from_secs(60);
+
+/// Version retention. Every [`VERSIONS_PRUNE_EVERY`], drops versions
+/// past the age bound of the backend's versioning policy in
+/// [`DELETE_BATCH`]-sized transactions until none are due, counted in
+/// `ucfp_versions_pruned_total`. Independent of the TTL sweeper.
+async fn prune_versions_loop<I: IndexBackend>(backend: Arc<I>) {
+    let mut tick = tokio::time::interval(VERSIONS_PRUNE_EVERY);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        loop {
+            match backend.prune_versions(now_ms, DELETE_BATCH).await {
+                Ok(n) => {
+                    metrics::counter!("ucfp_versions_pruned_total").increment(n);
+                    if n < DELETE_BATCH as u64 {
+                        break;
+                    }
+                }
+                Err(Error::Unsupported(_)) => return,
+                Err(e) => {
+                    tracing::warn!(error = %e, "version prune failed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// How often [`prune_changes_loop`] trims the change log.
+const CHANGES_PRUNE_EVERY: Duration = Duration::from_secs(60);
+
+/// Change-log retention. Every [`CHANGES_PRUNE_EVERY`], drops entries
+/// older than `keep` in [`DELETE_BATCH`]-sized transactions, counted in
+/// `ucfp_changes_pruned_total`. Independent of the TTL sweeper, so
+/// turning that off does not let the log grow without bound.
+async fn prune_changes_loop<I: IndexBackend>(backend: Arc<I>, keep: Duration) {
+    let mut tick = tokio::time::interval(CHANGES_PRUNE_EVERY);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let cutoff = now_ms.saturating_sub(keep.as_millis() as u64);
+        loop {
+            match backend.prune_changes(cutoff, DELETE_BATCH).await {
+                Ok(n) => {
+                    metrics::counter!("ucfp_changes_pruned_total").increment(n);
+                    if n < DELETE_BATCH as u64 {
+                        break;
+                    }
+                }
+                Err(Error::Unsupported(_)) => return,
+                Err(e) => {
+                    tracing::warn!(error = %e, "change log prune failed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Versioning policy from `UCFP_VERSIONS_KEEP` / `UCFP_VERSIONS_KEEP_DAYS`;
+/// `None` when neither is set.
+fn resolve_versioning() -> Result<Option<VersionPolicy>, Box<dyn std::error::Error>> {
+    let keep_versions = match std::env::var("UCFP_VERSIONS_KEEP") {
+        Ok(s) => Some(
+            s.parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("UCFP_VERSIONS_KEEP={s}: expected a positive integer"))?,
+        ),
+        Err(_) => None,
+    };
+    let keep_for_ms = match std::env::var("UCFP_VERSIONS_KEEP_DAYS") {
+        Ok(s) => {
+            let days: u64 = s
+                .parse()
+                .map_err(|_| format!("UCFP_VERSIONS_KEEP_DAYS={s}: expected whole days"))?;
+            Some(days.saturating_mul(86_400_000))
+        }
+        Err(_) => None,
+    };
+    if keep_versions.is_none() && keep_for_ms.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(VersionPolicy {
+        keep_versions,
+        keep_for_ms,
+    }))
+}
+
+/// Tenants listed in `UCFP_POSITIONS_TENANTS`; empty when unset.
+fn resolve_positions() -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let Ok(s) = std::env::var("UCFP_POSITIONS_TENANTS") else {
+        return Ok(Vec::new());
+    };
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.parse::<u32>()
+                .map_err(|_| format!("UCFP_POSITIONS_TENANTS={s}: expected tenant ids").into())
+        })
+        .collect()
+}
+
+/// Analyzers listed in `UCFP_BM25_ANALYZERS`; empty when unset.
+fn resolve_analyzers() -> Result<Vec<(u32, Analyzer)>, Box<dyn std::error::Error>> {
+    let Ok(s) = std::env::var("UCFP_BM25_ANALYZERS") else {
+        return Ok(Vec::new());
+    };
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|pair| {
+            let (tenant, spec) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("UCFP_BM25_ANALYZERS={s}: expected tenant:spec pairs"))?;
+            let tenant = tenant
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("UCFP_BM25_ANALYZERS={s}: `{tenant}` is not a tenant id"))?;
+            let analyzer = Analyzer::parse(spec)
+                .map_err(|e| format!("UCFP_BM25_ANALYZERS tenant {tenant}: {e}"))?;
+            Ok((tenant, analyzer))
+        })
+        .collect()
+}
+
+/// One `UCFP_BM25_FIELDS` entry: tenant, field name and its weights.
+type FieldWeights = (u32, String, Bm25Field);
+
+/// Per-tenant field weights listed in `UCFP_BM25_FIELDS`; empty when
+/// unset.
+fn resolve_bm25_fields() -> Result<Vec<FieldWeights>, Box<dyn std::error::Error>> {
+    let Ok(s) = std::env::var("UCFP_BM25_FIELDS") else {
+        return Ok(Vec::new());
+    };
+    let malformed = || format!("UCFP_BM25_FIELDS={s}: expected tenant:name:boost[:b] entries");
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|entry| {
+            let mut parts = entry.split(':').map(str::trim);
+            let tenant = parts.next().unwrap_or_default();
+            let tenant = tenant
+                .parse::<u32>()
+                .map_err(|_| format!("UCFP_BM25_FIELDS={s}: `{tenant}` is not a tenant id"))?;
+            let name = parts.next().ok_or_else(malformed)?;
+            let mut field = Bm25Field::default();
+            let boost = parts.next().ok_or_else(malformed)?;
+            field.boost = boost
+                .parse()
+                .ok()
+                .filter(|b: &f32| b.is_finite() && *b >= 0.0)
+                .ok_or_else(|| {
+                    format!(
+                        "UCFP_BM25_FIELDS tenant {tenant} field {name}: boost `{boost}` is not >= 0"
+                    )
+                })?;
+            if let Some(b) = parts.next() {
+                field.b = Some(
+                    b.parse()
+                        .ok()
+                        .filter(|b: &f32| (0.0..=1.0).contains(b))
+                        .ok_or_else(|| {
+                            format!(
+                                "UCFP_BM25_FIELDS tenant {tenant} field {name}: b `{b}` is not in [0, 1]"
+                            )
+                        })?,
+                );
+            }
+            if name.is_empty() || parts.next().is_some() {
+                return Err(malformed().into());
+            }
+            Ok((tenant, name.to_string(), field))
+        })
+        .collect()
+}
+
+/// Scoring listed in `UCFP_BM25_PARAMS`; empty when unset.
+fn resolve_bm25_params() -> Result<Vec<(u32, Bm25Params)>, Box<dyn std::error::Error>> {
+    let Ok(s) = std::env::var("UCFP_BM25_PARAMS") else {
+        return Ok(Vec::new());
+    };
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|pair| {
+            let (tenant, spec) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("UCFP_BM25_PARAMS={s}: expected tenant:spec pairs"))?;
+            let tenant = tenant
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("UCFP_BM25_PARAMS={s}: `{tenant}` is not a tenant id"))?;
+            let mut params = Bm25Params::default();
+            for step in spec.split('+').map(str::trim) {
+                let bad = || format!("UCFP_BM25_PARAMS tenant {tenant}: bad step `{step}`");
+                match step.split_once('=') {
+                    Some((key, value)) => {
+                        let value: f32 = value.trim().parse().map_err(|_| bad())?;
+                        match key.trim() {
+                            "k1" => params.k1 = value,
+                            "b" => params.b = value,
+                            "delta" => params.delta = Some(value),
+                            _ => return Err(bad().into()),
+                        }
+                    }
+                    None => {
+                        params.model = Bm25Model::ALL
+                            .into_iter()
+                            .find(|m| m.as_str() == step)
+                            .ok_or_else(bad)?;
+                    }
+                }
+            }
+            params
+                .check()
+                .map_err(|e| format!("UCFP_BM25_PARAMS tenant {tenant}: {e}"))?;
+            Ok((tenant, params))
+        })
+        .collect()
+}
+
 /// Resolve the configured [`ApiKeyLookup`] from env vars. Returns the
 /// trait-object Arc directly; the bin never names the concrete type
 /// after this point.
@@ -204,8 +538,238 @@ fn resolve_usage() -> Result<Arc<dyn UsageSink>, Box<dyn std::error::Error>> {
     Ok(Arc::new(NoopUsageSink))
 }
 
+/// `ucfp restore <snapshot> [--check]`. Prints the snapshot's
+/// [`ucfp::SnapshotInfo`] as JSON on success.
+fn restore(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (snapshot, check) = match args {
+        [snapshot] => (snapshot, false),
+        [snapshot, flag] if flag == "--check" => (snapshot, true),
+        _ => return Err("usage: ucfp restore <snapshot.redb> [--check]".into()),
+    };
+    if !matches!(
+        std::env::var("UCFP_BACKEND").as_deref(),
+        Err(_) | Ok("redb") | Ok("embedded")
+    ) {
+        return Err("ucfp restore only supports UCFP_BACKEND=redb".into());
+    }
+    let info = if check {
+        EmbeddedBackend::check_snapshot(snapshot)?
+    } else {
+        EmbeddedBackend::restore(snapshot, data_dir().join("ucfp.redb"))?
+    };
+    println!("{}", serde_json::to_string_pretty(&info)?);
+    Ok(())
+}
+
+/// `ucfp migrate [--dry-run]`. Prints the [`ucfp::MigrationReport`] as
+/// JSON.
+fn migrate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let dry_run = match args {
+        [] => false,
+        [flag] if flag == "--dry-run" => true,
+        _ => return Err("usage: ucfp migrate [--dry-run]".into()),
+    };
+    if !matches!(
+        std::env::var("UCFP_BACKEND").as_deref(),
+        Err(_) | Ok("redb") | Ok("embedded")
+    ) {
+        return Err("ucfp migrate only supports UCFP_BACKEND=redb".into());
+    }
+    let report = EmbeddedBackend::migrate(data_dir().join("ucfp.redb"), dry_run)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// `ucfp fsck [--tenant <id>] [--repair]`. Prints the
+/// [`ucfp::FsckReport`] as JSON; fails when violations remain.
+async fn fsck(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    const USAGE: &str = "usage: ucfp fsck [--tenant <id>] [--repair]";
+    let mut tenant = None;
+    let mut repair = false;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--repair" => repair = true,
+            "--tenant" => {
+                let id = it.next().ok_or(USAGE)?;
+                tenant = Some(
+                    id.parse::<u32>()
+                        .map_err(|e| format!("--tenant {id}: {e}"))?,
+                );
+            }
+            _ => return Err(USAGE.into()),
+        }
+    }
+    if !matches!(
+        std::env::var("UCFP_BACKEND").as_deref(),
+        Err(_) | Ok("redb") | Ok("embedded")
+    ) {
+        return Err("ucfp fsck only supports UCFP_BACKEND=redb".into());
+    }
+    let index = EmbeddedBackend::open(data_dir().join("ucfp.redb"))?;
+    let report = index.fsck(tenant, repair).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    match report.unrepaired() {
+        0 => Ok(()),
+        n => Err(format!("{n} violation(s) left").into()),
+    }
+}
+
+/// `ucfp export …` / `ucfp import …` against whichever backend
+/// `UCFP_BACKEND` names.
+async fn archive(cmd: &str, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = data_dir();
+    match std::env::var("UCFP_BACKEND").as_deref() {
+        Err(_) | Ok("redb") | Ok("embedded") => {
+            archive_with(&EmbeddedBackend::open(dir.join("ucfp.redb"))?, cmd, args).await
+        }
+        #[cfg(feature = "fjall")]
+        Ok("fjall") => {
+            archive_with(
+                &ucfp::FjallBackend::open(dir.join("ucfp.fjall"))?,
+                cmd,
+                args,
+            )
+            .await
+        }
+        Ok(other) => Err(format!("UCFP_BACKEND={other}: expected `redb` or `fjall`").into()),
+    }
+}
+
+async fn archive_with<I: IndexBackend>(
+    backend: &I,
+    cmd: &str,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use ucfp::index::archive;
+
+    match (cmd, args) {
+        ("export", [tenant, rest @ ..]) if rest.len() <= 1 => {
+            let tenant: u32 = tenant.parse()?;
+            let records = match rest.first() {
+                Some(file) => {
+                    let mut out = std::io::BufWriter::new(std::fs::File::create_new(file)?);
+                    archive::export_tenant(backend, tenant, &mut out).await?
+                }
+                None => {
+                    let mut out = std::io::stdout().lock();
+                    let n = archive::export_tenant(backend, tenant, &mut out).await?;
+                    out.flush()?;
+                    n
+                }
+            };
+            eprintln!("exported {records} records from tenant {tenant}");
+            Ok(())
+        }
+        ("import", [file]) | ("import", [file, _, _]) => {
+            let remap = match args {
+                [_, flag, id] if flag == "--tenant" => Some(id.parse::<u32>()?),
+                [_] => None,
+                _ => return Err("usage: ucfp import <file> [--tenant <id>]".into()),
+            };
+            let input = std::io::BufReader::new(std::fs::File::open(file)?);
+            let summary = archive::import(backend, input, remap).await?;
+            backend.flush().await?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+            Ok(())
+        }
+        ("export", _) => Err("usage: ucfp export <tenant_id> [file]".into()),
+        _ => Err("usage: ucfp import <file> [--tenant <id>]".into()),
+    }
+}
+
+/// `ucfp parquet <out_dir> [--full] [--db <file.redb>]`. Prints the
+/// [`ucfp::ParquetReport`] as JSON.
+#[cfg(feature = "parquet")]
+async fn parquet(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    const USAGE: &str = "usage: ucfp parquet <out_dir> [--full] [--db <file.redb>]";
+    let Some((out_dir, mut rest)) = args.split_first() else {
+        return Err(USAGE.into());
+    };
+    let (mut full, mut db) = (false, data_dir().join("ucfp.redb"));
+    while let Some((flag, tail)) = rest.split_first() {
+        rest = match (flag.as_str(), tail) {
+            ("--full", tail) => {
+                full = true;
+                tail
+            }
+            ("--db", [path, tail @ ..]) => {
+                db = path.into();
+                tail
+            }
+            _ => return Err(USAGE.into()),
+        };
+    }
+    let usage_log = std::env::var_os("UCFP_USAGE_LOG_PATH").map(std::path::PathBuf::from);
+    let report = EmbeddedBackend::open(db)?
+        .export_parquet(out_dir, usage_log.as_deref(), full)
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Periodic [`EmbeddedBackend::export_parquet`] into `out_dir`. Exposes
+/// `ucfp_parquet_export_duration_seconds` and
+/// `ucfp_parquet_export_rows_total`; failed passes bump
+/// `ucfp_parquet_export_errors_total` and retry next tick.
+#[cfg(feature = "parquet")]
+async fn parquet_export_loop(
+    backend: Arc<EmbeddedBackend>,
+    out_dir: std::path::PathBuf,
+    every: Duration,
+) {
+    let usage_log = std::env::var_os("UCFP_USAGE_LOG_PATH").map(std::path::PathBuf::from);
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let start = Instant::now();
+        match backend
+            .export_parquet(&out_dir, usage_log.as_deref(), false)
+            .await
+        {
+            Ok(report) => {
+                metrics::counter!("ucfp_parquet_export_rows_total")
+                    .increment(report.catalog_rows + report.deletes + report.usage_rows);
+                tracing::info!(?report, "parquet export");
+            }
+            Err(e) => {
+                metrics::counter!("ucfp_parquet_export_errors_total").increment(1);
+                tracing::warn!(error = %e, "parquet export failed");
+            }
+        }
+        metrics::histogram!("ucfp_parquet_export_duration_seconds")
+            .record(start.elapsed().as_secs_f64());
+    }
+}
+
+fn data_dir() -> std::path::PathBuf {
+    std::path::PathBuf::from(std::env::var("UCFP_DATA_DIR").unwrap_or_else(|_| "./data".into()))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("restore") {
+        return restore(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("migrate") {
+        return migrate(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("fsck") {
+        return fsck(&args[1..]).await;
+    }
+    if let Some(cmd @ ("export" | "import")) = args.first().map(String::as_str) {
+        return archive(cmd, &args[1..]).await;
+    }
+    if args.first().map(String::as_str) == Some("parquet") {
+        #[cfg(feature = "parquet")]
+        return parquet(&args[1..]).await;
+        #[cfg(not(feature = "parquet"))]
+        return Err("ucfp parquet requires the `parquet` feature".into());
+    }
+
     tracing_subscriber::fmt()
         .json()
         .with_env_filter(
@@ -223,16 +787,200 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let rate_limit = resolve_rate_limit()?;
     let usage = resolve_usage()?;
 
-    let data_dir = std::env::var("UCFP_DATA_DIR").unwrap_or_else(|_| "./data".into());
-    let db_path = std::path::PathBuf::from(&data_dir).join("ucfp.redb");
-    let backend = Arc::new(EmbeddedBackend::open(&db_path)?);
-    tracing::info!(path = %db_path.display(), "ucfp database open");
+    let data_dir = data_dir();
+    let versioning = resolve_versioning()?;
+    let retain_text = matches!(
+        std::env::var("UCFP_RETAIN_TEXT").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    let positional = resolve_positions()?;
+    let analyzers = resolve_analyzers()?;
+    let bm25_fields = resolve_bm25_fields()?;
+    let bm25_params = resolve_bm25_params()?;
+    let parquet_dir = std::env::var_os("UCFP_PARQUET_DIR").map(std::path::PathBuf::from);
+    #[cfg(not(feature = "parquet"))]
+    if parquet_dir.is_some() {
+        return Err("UCFP_PARQUET_DIR set but binary built without `parquet` feature".into());
+    }
+    match std::env::var("UCFP_BACKEND").as_deref() {
+        Err(_) | Ok("redb") | Ok("embedded") => {
+            let db_path = data_dir.join("ucfp.redb");
+            let mut backend = EmbeddedBackend::open(&db_path)?;
+            if let Some(policy) = versioning {
+                tracing::info!(?policy, "record versioning on");
+                backend = backend.with_versioning(policy);
+            }
+            if retain_text {
+                tracing::info!("text retention on");
+                backend = backend.with_retained_text();
+            }
+            if !positional.is_empty() {
+                tracing::info!(tenants = ?positional, "BM25 positions on");
+                backend = backend.with_positions(positional);
+            }
+            if !analyzers.is_empty() {
+                let specs: Vec<String> =
+                    analyzers.iter().map(|(t, a)| format!("{t}:{a}")).collect();
+                tracing::info!(analyzers = ?specs, "BM25 analyzers on");
+                backend = backend.with_analyzers(analyzers);
+            }
+            if !bm25_fields.is_empty() {
+                tracing::info!(fields = ?bm25_fields, "BM25F field weights on");
+                backend = backend.with_bm25_fields(bm25_fields);
+            }
+            if !bm25_params.is_empty() {
+                tracing::info!(params = ?bm25_params, "BM25 scoring parameters on");
+                backend = backend.with_bm25_params(bm25_params);
+            }
+            for m in backend.analyzer_mismatches()? {
+                tracing::warn!(
+                    tenant_id = m.tenant_id,
+                    indexed = %m.indexed,
+                    configured = %m.configured,
+                    "BM25 index built with another analyzer; queries will miss its terms \
+                     until POST /v1/admin/tenants/{{tenant_id}}/reindex-bm25"
+                );
+            }
+            let backend = Arc::new(backend);
+            tracing::info!(path = %db_path.display(), backend = "redb", "ucfp database open");
+            #[cfg(feature = "parquet")]
+            if let Some(dir) = parquet_dir {
+                let every: u64 = std::env::var("UCFP_PARQUET_EVERY_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600);
+                tracing::info!(dir = %dir.display(), every, "parquet export on");
+                tokio::spawn(parquet_export_loop(
+                    backend.clone(),
+                    dir,
+                    Duration::from_secs(every.max(1)),
+                ));
+            }
+            serve(backend, api_keys, rate_limit, usage, prom).await
+        }
+        #[cfg(feature = "fjall")]
+        Ok("fjall") => {
+            if versioning.is_some() {
+                return Err("UCFP_VERSIONS_KEEP* is only supported with UCFP_BACKEND=redb".into());
+            }
+            if parquet_dir.is_some() {
+                return Err("UCFP_PARQUET_DIR is only supported with UCFP_BACKEND=redb".into());
+            }
+            if retain_text {
+                return Err("UCFP_RETAIN_TEXT is only supported with UCFP_BACKEND=redb".into());
+            }
+            if !positional.is_empty() {
+                return Err(
+                    "UCFP_POSITIONS_TENANTS is only supported with UCFP_BACKEND=redb".into(),
+                );
+            }
+            if !analyzers.is_empty() {
+                return Err("UCFP_BM25_ANALYZERS is only supported with UCFP_BACKEND=redb".into());
+            }
+            if !bm25_fields.is_empty() {
+                return Err("UCFP_BM25_FIELDS is only supported with UCFP_BACKEND=redb".into());
+            }
+            if !bm25_params.is_empty() {
+                return Err("UCFP_BM25_PARAMS is only supported with UCFP_BACKEND=redb".into());
+            }
+            let db_path = data_dir.join("ucfp.fjall");
+            let backend = Arc::new(ucfp::FjallBackend::open(&db_path)?);
+            tracing::info!(path = %db_path.display(), backend = "fjall", "ucfp database open");
+            serve(backend, api_keys, rate_limit, usage, prom).await
+        }
+        #[cfg(not(feature = "fjall"))]
+        Ok("fjall") => Err("UCFP_BACKEND=fjall but binary built without `fjall` feature".into()),
+        Ok(other) => Err(format!("UCFP_BACKEND={other}: expected `redb` or `fjall`").into()),
+    }
+}
 
+/// Set the per-tenant gauges (`ucfp_tenant_records`, `ucfp_tenant_bytes`,
+/// `ucfp_tenant_vectors`, `ucfp_tenant_bm25_docs`,
+/// `ucfp_tenant_bm25_vocabulary`) from [`IndexBackend::tenant_stats`].
+/// Backends without counters leave them unset. A tenant in `reported`
+/// that has no stats any more (purged, or emptied) is zeroed.
+async fn record_tenant_gauges<I: IndexBackend>(backend: &I, reported: &Mutex<BTreeSet<u32>>) {
+    let stats = match backend.tenant_stats(None).await {
+        Ok(stats) => stats,
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => {
+            tracing::warn!(error = %e, "tenant stats for /metrics failed");
+            return;
+        }
+    };
+    let current: BTreeSet<u32> = stats.iter().map(|t| t.tenant_id).collect();
+    let gone: Vec<u32> = {
+        let mut reported = reported.lock().unwrap_or_else(|e| e.into_inner());
+        let gone = reported.difference(&current).copied().collect();
+        *reported = current;
+        gone
+    };
+    for t in &stats {
+        set_tenant_gauges(t);
+    }
+    for tenant_id in gone {
+        set_tenant_gauges(&TenantStats {
+            tenant_id,
+            ..TenantStats::default()
+        });
+    }
+}
+
+/// One tenant's gauges, labelled by its id.
+fn set_tenant_gauges(t: &TenantStats) {
+    let tenant = t.tenant_id.to_string();
+    metrics::gauge!("ucfp_tenant_records", "tenant_id" => tenant.clone()).set(t.records as f64);
+    for (kind, bytes) in [
+        ("fingerprint", t.fingerprint_bytes),
+        ("metadata", t.metadata_bytes),
+        ("vector", t.vector_bytes),
+    ] {
+        metrics::gauge!("ucfp_tenant_bytes", "tenant_id" => tenant.clone(), "kind" => kind)
+            .set(bytes as f64);
+    }
+    metrics::gauge!("ucfp_tenant_vectors", "tenant_id" => tenant.clone()).set(t.ann.vectors as f64);
+    metrics::gauge!("ucfp_tenant_bm25_docs", "tenant_id" => tenant.clone())
+        .set(t.bm25.doc_count as f64);
+    metrics::gauge!("ucfp_tenant_bm25_vocabulary", "tenant_id" => tenant)
+        .set(t.bm25.vocabulary as f64);
+}
+
+/// Build the router over `backend` and serve until ctrl-c. Generic so
+/// every storage engine shares one middleware stack.
+async fn serve<I: IndexBackend + 'static>(
+    backend: Arc<I>,
+    api_keys: Arc<dyn ApiKeyLookup>,
+    rate_limit: Arc<dyn TenantRateLimiter>,
+    usage: Arc<dyn UsageSink>,
+    prom: PrometheusHandle,
+) -> Result<(), Box<dyn std::error::Error>> {
     let body_limit_mb: usize = std::env::var("UCFP_BODY_LIMIT_MB")
         .ok()
         .and_then(|s| s.parse().ok())
         .unwrap_or(16);
 
+    let sweep_secs: u64 = std::env::var("UCFP_EXPIRY_SWEEP_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60);
+    if sweep_secs > 0 {
+        tokio::spawn(sweep_expired_loop(
+            backend.clone(),
+            Duration::from_secs(sweep_secs),
+        ));
+    }
+    tokio::spawn(prune_versions_loop(backend.clone()));
+    let changes_keep_hours: u64 = std::env::var("UCFP_CHANGES_KEEP_HOURS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(168);
+    if changes_keep_hours > 0 {
+        tokio::spawn(prune_changes_loop(
+            backend.clone(),
+            Duration::from_secs(changes_keep_hours * 3600),
+        ));
+    }
+
     let state = ServerState {
         index: backend.clone(),
         api_keys,
@@ -241,13 +989,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // /metrics: public, returns Prometheus text exposition format.
+    // Tenant gauges are refreshed from the backend's counters per scrape.
     let metrics_route = Router::new().route(
         "/metrics",
         get({
             let prom = prom.clone();
+            let backend = backend.clone();
+            let reported = Arc::new(Mutex::new(BTreeSet::new()));
             move || {
                 let prom = prom.clone();
-                async move { prom.render() }
+                let backend = backend.clone();
+                let reported = reported.clone();
+                async move {
+                    record_tenant_gauges(backend.as_ref(), &reported).await;
+                    prom.render()
+                }
             }
         }),
     );
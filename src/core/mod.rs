@@ -8,6 +8,9 @@
 //! Layout note: `tenant_id` lives on every shape. Per-tenant key prefixing
 //! is a day-one schema decision (see `docs/ARCHITECTURE.md` §8.1).
 
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
@@ -69,6 +72,17 @@ pub struct Record {
     /// itself. `None` for non-text modalities — the BM25 path is then a
     /// no-op for that record. See ARCHITECTURE §4.
     pub text: Option<String>,
+    /// Named text fields (`title`, `tags`, …) indexed for BM25 beside
+    /// `text`, each with its own boost and length normalization (BM25F).
+    /// Names are lowercase ASCII letters, digits and `_`, starting with a
+    /// letter; `text` is reserved for the field above. Empty for most
+    /// records.
+    pub text_fields: BTreeMap<String, String>,
+    /// Absolute expiry, unix milliseconds. Once past, the record drops
+    /// out of every read and query immediately; a background sweep
+    /// (`IndexBackend::sweep_expired`) reclaims its rows later. `None`
+    /// keeps the record until it is deleted.
+    pub expires_at: Option<u64>,
 }
 
 /// Metadata view of a stored fingerprint without materialising its bytes.
@@ -101,6 +115,499 @@ pub struct FingerprintMeta {
     pub model_id: Option<String>,
     /// Length of the application metadata blob in bytes.
     pub metadata_bytes: usize,
+    /// Absolute expiry in unix milliseconds, when the record has a TTL.
+    pub expires_at: Option<u64>,
+    /// Per-record write sequence: 1 for the first upsert, +1 for each
+    /// replacement. Backends that don't track it report 1.
+    pub version: u64,
+    /// When this version was written, unix milliseconds. `None` for rows
+    /// written before the backend tracked it.
+    pub written_at: Option<u64>,
+    /// `true` on the tombstone a delete leaves in version history; its
+    /// `written_at` is the delete time.
+    pub deleted: bool,
+}
+
+/// Precondition for one record of [`crate::IndexBackend::upsert_if`],
+/// checked against the stored row inside the write transaction. An
+/// expired record counts as absent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WriteCondition {
+    /// Write unconditionally — plain upsert.
+    #[default]
+    Always,
+    /// Write only if no record is stored under the key; otherwise skip.
+    IfAbsent,
+    /// Compare-and-set on [`FingerprintMeta::version`]: write only if the
+    /// stored version equals this. `0` expects the record to be absent.
+    IfVersion(u64),
+    /// Write only if a record is stored and its `config_hash` equals this.
+    IfConfigHash(u64),
+}
+
+impl WriteCondition {
+    /// Decide one write given the stored row's `(version, config_hash)`,
+    /// or `None` when nothing is stored.
+    pub fn evaluate(self, stored: Option<(u64, u64)>) -> WriteOutcome {
+        match (self, stored) {
+            (Self::Always, _) | (Self::IfAbsent, None) => WriteOutcome::Written,
+            (Self::IfAbsent, Some(_)) => WriteOutcome::Skipped,
+            (Self::IfVersion(0), None) => WriteOutcome::Written,
+            (Self::IfVersion(want), Some((version, _))) if want == version => WriteOutcome::Written,
+            (Self::IfConfigHash(want), Some((_, hash))) if want == hash => WriteOutcome::Written,
+            (Self::IfVersion(_) | Self::IfConfigHash(_), _) => WriteOutcome::Conflict,
+        }
+    }
+}
+
+/// What a conditional upsert did with one record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteOutcome {
+    /// The record was stored.
+    Written,
+    /// `IfAbsent` found a record already there; nothing changed.
+    Skipped,
+    /// A version or config-hash precondition failed; nothing changed.
+    Conflict,
+}
+
+/// Outcome of [`crate::IndexBackend::snapshot`] (and of validating or
+/// restoring a snapshot).
+#[derive(Clone, Debug, Serialize)]
+pub struct SnapshotInfo {
+    /// Where the snapshot file is.
+    pub path: std::path::PathBuf,
+    /// Size of the snapshot file.
+    pub bytes: u64,
+    /// [`crate::FORMAT_VERSION`] the snapshot was written with.
+    pub format_version: u32,
+    /// When the snapshot's read transaction began, unix milliseconds.
+    pub created_ms: u64,
+    /// Versioned table names the snapshot holds (`ucfp/catalog/v2`, …).
+    pub tables: Vec<String>,
+    /// Rows copied; 0 when only validated.
+    pub rows: u64,
+    /// Sidecar files (ANN dumps, mmap caches) carried along.
+    pub sidecars: usize,
+}
+
+/// Size and make-up of one tenant, from
+/// [`crate::IndexBackend::tenant_stats`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TenantStats {
+    /// Tenant described.
+    pub tenant_id: u32,
+    /// Stored records, expired-but-unswept ones included.
+    pub records: u64,
+    /// Records per modality (`audio`, `image`, `text`).
+    pub by_modality: BTreeMap<String, u64>,
+    /// Records per SDK algorithm tag.
+    pub by_algorithm: BTreeMap<String, u64>,
+    /// Records per embedding model id; records without one are left out.
+    pub by_model: BTreeMap<String, u64>,
+    /// Total fingerprint blob bytes.
+    pub fingerprint_bytes: u64,
+    /// Total application metadata bytes.
+    pub metadata_bytes: u64,
+    /// Total embedding bytes (4 per dimension).
+    pub vector_bytes: u64,
+    /// BM25 corpus of the tenant.
+    pub bm25: Bm25Stats,
+    /// Vector index of the tenant.
+    pub ann: AnnStatus,
+}
+
+/// BM25 corpus statistics in a [`TenantStats`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Bm25Stats {
+    /// Indexed documents.
+    pub doc_count: u64,
+    /// Mean document length in tokens.
+    pub avgdl: f32,
+    /// Distinct terms in the dictionary.
+    pub vocabulary: u64,
+}
+
+/// Vector index state in a [`TenantStats`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AnnStatus {
+    /// Search strategy: `brute_force` until a graph index is built.
+    pub kind: String,
+    /// Records with an embedding.
+    pub vectors: u64,
+}
+
+/// A dictionary term offered by [`crate::IndexBackend::suggest_terms`] or
+/// [`crate::IndexBackend::did_you_mean`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TermSuggestion {
+    /// The indexed term.
+    pub term: String,
+    /// Documents holding the term.
+    pub doc_freq: u64,
+    /// Edits between the typed token and `term`; 0 for a completion of a
+    /// prefix.
+    pub distance: u32,
+}
+
+/// Closest dictionary terms to one token of a query, from
+/// [`crate::IndexBackend::did_you_mean`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Correction {
+    /// The query token, as tokenized for the index.
+    pub token: String,
+    /// Best first. A token that is itself indexed comes first at
+    /// distance 0.
+    pub suggestions: Vec<TermSuggestion>,
+}
+
+/// Outcome of [`crate::IndexBackend::fsck`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FsckReport {
+    /// Tenants checked.
+    pub tenants: u64,
+    /// Catalog rows checked.
+    pub records: u64,
+    /// Every broken invariant found, in tenant order.
+    pub violations: Vec<FsckViolation>,
+    /// `true` when repair was requested.
+    pub repair: bool,
+}
+
+impl FsckReport {
+    /// Violations still present after the run.
+    pub fn unrepaired(&self) -> usize {
+        self.violations.iter().filter(|v| !v.repaired).count()
+    }
+}
+
+/// One broken invariant in an [`FsckReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FsckViolation {
+    /// Tenant the violation is in.
+    pub tenant_id: u32,
+    /// Invariant that failed, `<structure>.<field>` (`catalog.fingerprint_len`,
+    /// `bm25.corpus`, …).
+    pub check: String,
+    /// Record the violation is keyed by, when it is per record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<u64>,
+    /// BM25 term id the violation is keyed by, when it is per term.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub term_id: Option<u64>,
+    /// What was found versus expected.
+    pub detail: String,
+    /// `true` once repair fixed it.
+    pub repaired: bool,
+}
+
+/// Outcome of bringing an embedded database to the current schema
+/// (`EmbeddedBackend::migrate`).
+#[derive(Clone, Debug, Serialize)]
+pub struct MigrationReport {
+    /// Schema version the database was on.
+    pub from_version: u64,
+    /// Schema version of this build.
+    pub to_version: u64,
+    /// Migrations applied, in order; empty when already current.
+    pub steps: Vec<MigrationStep>,
+    /// `true` when nothing was committed.
+    pub dry_run: bool,
+}
+
+/// One applied migration in a [`MigrationReport`].
+#[derive(Clone, Debug, Serialize)]
+pub struct MigrationStep {
+    /// Schema version before the step.
+    pub from_version: u64,
+    /// Schema version after the step.
+    pub to_version: u64,
+    /// What the step rewrites (`ucfp/catalog/v1 -> ucfp/catalog/v2`).
+    pub name: String,
+    /// Rows rewritten.
+    pub rows: u64,
+}
+
+/// Outcome of one `EmbeddedBackend::export_parquet` run (feature
+/// `parquet`).
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ParquetReport {
+    /// Output directory.
+    pub out_dir: std::path::PathBuf,
+    /// `true` when the catalog was rewritten from scratch rather than
+    /// brought forward from the previous watermark.
+    pub full: bool,
+    /// Change-log sequence the exported catalog reflects; the next
+    /// incremental run starts after it.
+    pub watermark: u64,
+    /// Catalog rows written.
+    pub catalog_rows: u64,
+    /// Delete / expire / purge rows written.
+    pub deletes: u64,
+    /// Usage rollup rows written.
+    pub usage_rows: u64,
+    /// Usage log lines that did not parse and were skipped.
+    pub usage_skipped: u64,
+    /// Parquet files written.
+    pub files: usize,
+}
+
+/// What a [`ChangeEvent`] records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// A record was written (insert or replace).
+    Upsert,
+    /// A record was deleted, directly or by `delete_where`.
+    Delete,
+    /// A record was removed by the TTL sweeper.
+    Expire,
+    /// Every record of the tenant was dropped; no per-record events follow.
+    Purge,
+}
+
+/// One entry of the change log, see [`crate::IndexBackend::changes_since`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Global, dense sequence number: each change gets the previous + 1,
+    /// so a jump between consecutive events means retention dropped the
+    /// ones in between.
+    pub seq: u64,
+    /// Commit time of the write, unix milliseconds.
+    pub at_ms: u64,
+    /// What happened.
+    pub kind: ChangeKind,
+    /// Tenant the change belongs to.
+    pub tenant_id: u32,
+    /// Absent for [`ChangeKind::Purge`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<u64>,
+    /// [`FingerprintMeta::version`] written, for upserts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Which stored version of a record a read wants. See
+/// [`crate::IndexBackend::get_version`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VersionSpec {
+    /// The current version — what every other read returns.
+    #[default]
+    Latest,
+    /// An exact [`FingerprintMeta::version`].
+    Number(u64),
+    /// The version that was current at this instant (unix ms): the
+    /// newest one written at or before it.
+    AsOf(u64),
+}
+
+/// History retention for backends with versioning switched on. Both
+/// bounds apply when both are set; the latest version is always kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VersionPolicy {
+    /// Keep at most this many versions per record, latest included.
+    pub keep_versions: Option<usize>,
+    /// Drop superseded versions this long (milliseconds) after they
+    /// were replaced.
+    pub keep_for_ms: Option<u64>,
+}
+
+/// BM25F weighting of one text field: how much a match in it counts
+/// and how strongly its length is normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bm25Field {
+    /// Multiplier on the field's term frequencies, applied before
+    /// saturation, so a match in a boosted field counts like several
+    /// matches elsewhere.
+    pub boost: f32,
+    /// Length normalization in `[0, 1]`: `0` ignores the field's length,
+    /// `1` divides by its length relative to the average. `None` takes
+    /// the tenant's [`Bm25Params::b`].
+    pub b: Option<f32>,
+}
+
+impl Default for Bm25Field {
+    fn default() -> Self {
+        Self {
+            boost: 1.0,
+            b: None,
+        }
+    }
+}
+
+/// The function a BM25 query scores matches with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bm25Model {
+    /// Okapi BM25.
+    #[default]
+    Bm25,
+    /// BM25L (Lv & Zhai, 2011): shifts the normalized frequency by
+    /// `delta` before saturating, so long documents are not
+    /// over-penalized.
+    Bm25l,
+    /// BM25+ (Lv & Zhai, 2011): adds `delta` to the saturated frequency,
+    /// so any match outscores none however long the document.
+    Bm25Plus,
+    /// `ln(1 + tf) · ln(1 + N/n)`, without length normalization or
+    /// saturation; `k1`, `b` and `delta` are ignored.
+    TfIdf,
+}
+
+impl Bm25Model {
+    /// Every model, in manifest order.
+    pub const ALL: [Self; 4] = [Self::Bm25, Self::Bm25l, Self::Bm25Plus, Self::TfIdf];
+
+    /// Wire name: `bm25`, `bm25l`, `bm25-plus` or `tf-idf`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bm25 => "bm25",
+            Self::Bm25l => "bm25l",
+            Self::Bm25Plus => "bm25-plus",
+            Self::TfIdf => "tf-idf",
+        }
+    }
+
+    /// `delta` when none is given: 0.5 for BM25L, 1 for BM25+, unused
+    /// otherwise.
+    pub fn default_delta(self) -> f32 {
+        match self {
+            Self::Bm25l => 0.5,
+            Self::Bm25Plus => 1.0,
+            Self::Bm25 | Self::TfIdf => 0.0,
+        }
+    }
+}
+
+/// How a tenant's BM25 queries are scored. Applies at query time, so
+/// changing it needs no reindex.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bm25Params {
+    /// Scoring function.
+    pub model: Bm25Model,
+    /// Term-frequency saturation, `>= 0`: higher lets repeated matches
+    /// keep adding. Shared by every field, since BM25F saturates their
+    /// summed frequency once.
+    pub k1: f32,
+    /// Length normalization in `[0, 1]` for fields without their own
+    /// [`Bm25Field::b`].
+    pub b: f32,
+    /// BM25L / BM25+ shift, `>= 0`; `None` is
+    /// [`Bm25Model::default_delta`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<f32>,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self {
+            model: Bm25Model::Bm25,
+            k1: 1.2,
+            b: 0.75,
+            delta: None,
+        }
+    }
+}
+
+impl Bm25Params {
+    /// `delta`, or the model's default.
+    pub fn delta(&self) -> f32 {
+        self.delta.unwrap_or(self.model.default_delta())
+    }
+
+    /// [`crate::Error::Modality`] unless every parameter is in range.
+    pub fn check(&self) -> crate::Result<()> {
+        let ok = |v: f32| v.is_finite() && v >= 0.0;
+        if !ok(self.k1) {
+            return Err(crate::Error::Modality(format!(
+                "bm25 k1 {} is not >= 0",
+                self.k1
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.b) {
+            return Err(crate::Error::Modality(format!(
+                "bm25 b {} is not in [0, 1]",
+                self.b
+            )));
+        }
+        if !ok(self.delta()) {
+            return Err(crate::Error::Modality(format!(
+                "bm25 delta {} is not >= 0",
+                self.delta()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One stored version: its header plus the full record as it was.
+#[derive(Clone, Debug)]
+pub struct VersionedRecord {
+    /// Header, including [`FingerprintMeta::version`] / `written_at`.
+    pub meta: FingerprintMeta,
+    /// Blobs of that version.
+    pub record: Record,
+}
+
+/// Catalog predicate for [`crate::IndexBackend::scan`]. Every set field
+/// must match; the default filter matches everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordFilter {
+    /// Only records produced from this modality.
+    pub modality: Option<Modality>,
+    /// Only records with this exact SDK algorithm tag.
+    pub algorithm: Option<String>,
+}
+
+impl RecordFilter {
+    /// `true` if `meta` passes every set predicate.
+    pub fn matches(&self, meta: &FingerprintMeta) -> bool {
+        self.modality.is_none_or(|m| m == meta.modality)
+            && self
+                .algorithm
+                .as_deref()
+                .is_none_or(|a| a == meta.algorithm)
+    }
+}
+
+/// One page of a tenant scan, in ascending `record_id` order.
+#[derive(Clone, Debug, Default)]
+pub struct ScanPage {
+    /// Matching records in this page. May be shorter than the requested
+    /// limit — even empty — while `next_cursor` is still `Some`, when a
+    /// selective filter exhausted the per-call scan budget.
+    pub records: Vec<FingerprintMeta>,
+    /// Opaque resume token; `None` once the tenant is exhausted.
+    pub next_cursor: Option<String>,
+}
+
+/// Live counters for a long-running backend operation (bulk delete,
+/// purge, …). The backend bumps them after every committed batch; an
+/// observer holding the same `Arc<Progress>` reads them concurrently.
+#[derive(Debug, Default)]
+pub struct Progress {
+    records: AtomicU64,
+    batches: AtomicU64,
+}
+
+impl Progress {
+    /// Record one committed batch that touched `records` records.
+    pub fn add_batch(&self, records: u64) {
+        self.records.fetch_add(records, Ordering::Relaxed);
+        self.batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records processed so far.
+    pub fn records(&self) -> u64 {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// Transactions committed so far.
+    pub fn batches(&self) -> u64 {
+        self.batches.load(Ordering::Relaxed)
+    }
 }
 
 /// A single search result.
@@ -128,6 +635,9 @@ pub struct Hit {
     /// BM25 explainability — top-N matched terms with their contributions
     /// to this hit's score. Empty unless [`Query::explain`] is set.
     pub term_hits: Vec<TermHit>,
+    /// Fragments of the hit's retained text with its matched terms
+    /// marked. Empty unless [`Query::highlight`] is set.
+    pub snippets: Vec<Snippet>,
 }
 
 /// Which ranker produced a [`Hit`].
@@ -165,12 +675,18 @@ pub struct Query {
     /// Optional metadata pre-filter expression (RoaringBitmap-encoded
     /// in the embedded backend).
     pub filter: Option<Bytes>,
+    /// BM25 scoring for this query in place of the tenant's configured
+    /// [`Bm25Params`]; `None` keeps the tenant's.
+    pub bm25: Option<Bm25Params>,
     /// RRF fusion constant. Default 60 per ARCHITECTURE §4.
     pub rrf_k: u32,
     /// When `true`, retrievers populate per-hit explainability (matched
     /// BM25 terms with idf/tf/contribution). Off by default — surfaces
     /// only when the caller asks (`?explain=1` on `/v1/query`).
     pub explain: bool,
+    /// When set, BM25 hits carry [`Hit::snippets`] cut from their
+    /// retained text (`?highlight=1` on `/v1/query`).
+    pub highlight: Option<Highlight>,
 }
 
 impl Default for Query {
@@ -182,8 +698,10 @@ impl Default for Query {
             vector: None,
             terms: Vec::new(),
             filter: None,
+            bm25: None,
             rrf_k: 60,
             explain: false,
+            highlight: None,
         }
     }
 }
@@ -202,4 +720,45 @@ pub struct TermHit {
     pub tf: u32,
     /// BM25 contribution to the doc score from this term.
     pub contribution: f32,
+    /// The quoted phrase or `NEAR/n` clause the term matched as part of,
+    /// in which case `tf` counts the clause's matches. `None` for a term
+    /// matched on its own.
+    pub phrase: Option<String>,
+    /// The `term*` or `term~n` query term that expanded to `term`, in
+    /// which case `contribution` is already scaled down for the expansion.
+    /// `None` for a term the query named exactly.
+    pub expanded_from: Option<String>,
+    /// The text field the term matched in: `text`, or a name from
+    /// [`Record::text_fields`].
+    pub field: String,
+}
+
+/// How many [`Snippet`]s a hit gets and how long they are.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    /// Fragments per hit at most, best first.
+    pub fragments: usize,
+    /// Characters per fragment at most, not counting markup.
+    pub fragment_len: usize,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            fragments: 3,
+            fragment_len: 160,
+        }
+    }
+}
+
+/// A fragment of a hit's retained text around its matched terms.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    /// The text field the fragment was cut from: `text`, or a name from
+    /// [`Record::text_fields`].
+    pub field: String,
+    /// The fragment, HTML-escaped, with every token that matched a query
+    /// term wrapped in `<mark>`…`</mark>` and `…` where it cuts into
+    /// the text.
+    pub fragment: String,
 }
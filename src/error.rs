@@ -42,6 +42,11 @@ pub enum Error {
         record_id: u64,
     },
 
+    /// A non-record resource (admin job, …) does not exist. Maps to
+    /// HTTP 404 like [`Error::RecordNotFound`].
+    #[error("not found: {0}")]
+    NotFound(String),
+
     /// Operation is not supported by this build / backend / algorithm.
     /// Maps to HTTP 501 at the server boundary; used by the default
     /// `IndexBackend::get_record_metadata` impl and by handler dispatch
@@ -49,6 +54,18 @@ pub enum Error {
     #[error("operation not supported: {0}")]
     Unsupported(String),
 
+    /// A change-log read resumed after `since`, but retention already
+    /// dropped entries past it, so the reader would silently skip
+    /// changes. Maps to HTTP 410 at the server boundary; the reader has
+    /// to resynchronise and resume from `oldest - 1`.
+    #[error("change log pruned past seq {since}: oldest retained seq is {oldest}")]
+    ChangesPruned {
+        /// Sequence number the read resumed after.
+        since: u64,
+        /// Oldest sequence number still readable.
+        oldest: u64,
+    },
+
     /// Caller is authenticated but not allowed to access the requested
     /// tenant namespace. Maps to HTTP 403 at the server boundary.
     #[error("forbidden: key tenant {key_tenant} cannot access tenant {path_tenant}")]
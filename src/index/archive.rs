@@ -0,0 +1,500 @@
+//! Portable tenant archive: move a tenant between `ucfp` instances.
+//!
+//! The archive is NDJSON, one externally tagged object per line:
+//!
+//! ```text
+//! {"header":{"archive_version":2,"format_version":1,"crate_version":"…","tenant_id":7,"created_ms":…,"text_retained":true}}
+//! {"record":{"record_id":1,"modality":"Text","format_version":1,"algorithm":"minhash","config_hash":…,"fingerprint":"<base64>",…}}
+//! …
+//! {"end":{"records":1234}}
+//! ```
+//!
+//! Every record line carries its own algorithm / `format_version` /
+//! `config_hash`, so mixed-algorithm tenants survive the trip. The tenant
+//! id lives only in the header, which is what makes remapping on import
+//! free. The trailer lets an importer tell a complete archive from a
+//! truncated download.
+//!
+//! Fingerprint and metadata bytes are standard base64; version-1 archives
+//! wrote them as JSON number arrays, which the importer still reads.
+//! Record text only travels when the source retains it
+//! ([`IndexBackend::retains_text`]); the header says so, and importing an
+//! archive without text logs a warning, since BM25 stays empty for those
+//! records.
+//!
+//! Export is page-at-a-time over [`IndexBackend::scan`] and
+//! [`IndexBackend::get_records`] ([`export_page`]), so callers stream it
+//! without buffering the tenant. [`Importer`] is the matching push
+//! parser: feed it lines, upsert the batches it hands back.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Modality, Record, RecordFilter};
+use crate::error::{Error, Result};
+use crate::index::{IndexBackend, now_ms};
+
+/// Version of the archive framing itself (independent of
+/// [`crate::FORMAT_VERSION`], which versions the records inside).
+pub const ARCHIVE_VERSION: u32 = 2;
+
+/// Records per export page and per import upsert batch.
+pub const ARCHIVE_BATCH: usize = 500;
+
+/// First line of an archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveHeader {
+    /// [`ARCHIVE_VERSION`] of the writer.
+    pub archive_version: u32,
+    /// [`crate::FORMAT_VERSION`] of the writer.
+    pub format_version: u32,
+    /// Crate version of the writer, for diagnostics.
+    pub crate_version: String,
+    /// Tenant the records were exported from.
+    pub tenant_id: u32,
+    /// When the export started, unix milliseconds.
+    pub created_ms: u64,
+    /// Whether record lines carry their text. Absent from version-1
+    /// archives, which are read as `true`.
+    #[serde(default = "yes")]
+    pub text_retained: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl ArchiveHeader {
+    /// Header for exporting `tenant_id` with this build, from a source
+    /// that does (or does not) retain record text.
+    pub fn new(tenant_id: u32, text_retained: bool) -> Self {
+        Self {
+            archive_version: ARCHIVE_VERSION,
+            format_version: crate::FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            tenant_id,
+            created_ms: now_ms(),
+            text_retained,
+        }
+    }
+}
+
+/// One record line. Mirrors [`Record`] minus the tenant id.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ArchiveRecord {
+    record_id: u64,
+    modality: Modality,
+    format_version: u32,
+    algorithm: String,
+    config_hash: u64,
+    #[serde(with = "blob")]
+    fingerprint: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "blob")]
+    metadata: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    text_fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+}
+
+impl ArchiveRecord {
+    fn from_record(r: Record) -> Self {
+        Self {
+            record_id: r.record_id,
+            modality: r.modality,
+            format_version: r.format_version,
+            algorithm: r.algorithm,
+            config_hash: r.config_hash,
+            fingerprint: r.fingerprint.to_vec(),
+            embedding: r.embedding,
+            model_id: r.model_id,
+            metadata: r.metadata.to_vec(),
+            text: r.text,
+            text_fields: r.text_fields,
+            expires_at: r.expires_at,
+        }
+    }
+
+    fn into_record(self, tenant_id: u32) -> Record {
+        Record {
+            tenant_id,
+            record_id: self.record_id,
+            modality: self.modality,
+            format_version: self.format_version,
+            algorithm: self.algorithm,
+            config_hash: self.config_hash,
+            fingerprint: Bytes::from(self.fingerprint),
+            embedding: self.embedding,
+            model_id: self.model_id,
+            metadata: Bytes::from(self.metadata),
+            text: self.text,
+            text_fields: self.text_fields,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+/// Byte blobs as base64 strings; number arrays still read for version-1
+/// archives.
+mod blob {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Blob {
+            Base64(String),
+            Bytes(Vec<u8>),
+        }
+        match Blob::deserialize(d)? {
+            Blob::Base64(s) => STANDARD.decode(s).map_err(serde::de::Error::custom),
+            Blob::Bytes(b) => Ok(b),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Line {
+    Header(ArchiveHeader),
+    Record(ArchiveRecord),
+    End { records: u64 },
+}
+
+fn encode_line(line: &Line, out: &mut Vec<u8>) -> Result<()> {
+    serde_json::to_writer(&mut *out, line)
+        .map_err(|e| Error::Index(format!("archive encode: {e}")))?;
+    out.push(b'\n');
+    Ok(())
+}
+
+/// The header line, newline included.
+pub fn header_line(header: &ArchiveHeader) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encode_line(&Line::Header(header.clone()), &mut out)?;
+    Ok(out)
+}
+
+/// The trailer line for an archive of `records` records.
+pub fn end_line(records: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encode_line(&Line::End { records }, &mut out)?;
+    Ok(out)
+}
+
+/// One page of `tenant_id` as record lines, starting at `cursor` (`None`
+/// = from the beginning). Returns the lines, how many records they hold,
+/// and the cursor for the next page (`None` once exhausted).
+pub async fn export_page<I: IndexBackend + ?Sized>(
+    backend: &I,
+    tenant_id: u32,
+    cursor: Option<&str>,
+) -> Result<(Vec<u8>, u64, Option<String>)> {
+    let page = backend
+        .scan(tenant_id, cursor, ARCHIVE_BATCH, &RecordFilter::default())
+        .await?;
+    let ids: Vec<u64> = page.records.iter().map(|m| m.record_id).collect();
+    let mut out = Vec::new();
+    let mut n = 0;
+    if !ids.is_empty() {
+        for rec in backend.get_records(tenant_id, &ids).await? {
+            encode_line(&Line::Record(ArchiveRecord::from_record(rec)), &mut out)?;
+            n += 1;
+        }
+    }
+    Ok((out, n, page.next_cursor))
+}
+
+/// Write a whole tenant archive to `out`. Convenience over
+/// [`export_page`] for callers with a blocking writer (the CLI).
+pub async fn export_tenant<I, W>(backend: &I, tenant_id: u32, out: &mut W) -> Result<u64>
+where
+    I: IndexBackend + ?Sized,
+    W: std::io::Write,
+{
+    let header = ArchiveHeader::new(tenant_id, backend.retains_text());
+    out.write_all(&header_line(&header)?)?;
+    let mut cursor: Option<String> = None;
+    let mut total = 0;
+    loop {
+        let (lines, n, next) = export_page(backend, tenant_id, cursor.as_deref()).await?;
+        out.write_all(&lines)?;
+        total += n;
+        match next {
+            Some(c) => cursor = Some(c),
+            None => break,
+        }
+    }
+    out.write_all(&end_line(total)?)?;
+    out.flush()?;
+    Ok(total)
+}
+
+/// What an import did.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    /// Tenant named in the archive header.
+    pub source_tenant: u32,
+    /// Tenant the records were written to.
+    pub tenant_id: u32,
+    /// Records upserted.
+    pub records: u64,
+    /// Whether the archive carried record text (see
+    /// [`ArchiveHeader::text_retained`]).
+    pub text_retained: bool,
+}
+
+/// Push parser for an archive. Feed lines in order with
+/// [`Self::push_line`], upsert every batch it returns, then call
+/// [`Self::finish`] for the last batch and the summary.
+pub struct Importer {
+    remap_to: Option<u32>,
+    header: Option<ArchiveHeader>,
+    pending: Vec<Record>,
+    line: u64,
+    seen: u64,
+    end: Option<u64>,
+}
+
+impl Importer {
+    /// Records land in `remap_to`, or in the header's tenant when `None`.
+    pub fn new(remap_to: Option<u32>) -> Self {
+        Self {
+            remap_to,
+            header: None,
+            pending: Vec::new(),
+            line: 0,
+            seen: 0,
+            end: None,
+        }
+    }
+
+    /// Parse one line (trailing newline optional, blank lines ignored).
+    /// Returns a full batch once [`ARCHIVE_BATCH`] records are buffered.
+    pub fn push_line(&mut self, line: &[u8]) -> Result<Option<Vec<Record>>> {
+        self.line += 1;
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        if self.end.is_some() {
+            return Err(Error::Modality("archive: data after the end line".into()));
+        }
+        let parsed: Line = serde_json::from_slice(line)
+            .map_err(|e| Error::Modality(format!("archive line {}: {e}", self.line)))?;
+        match (parsed, &self.header) {
+            (Line::Header(h), None) => {
+                if h.archive_version > ARCHIVE_VERSION {
+                    return Err(Error::Incompatible(format!(
+                        "archive version {}, this build reads up to {ARCHIVE_VERSION}",
+                        h.archive_version
+                    )));
+                }
+                if h.format_version != crate::FORMAT_VERSION {
+                    return Err(Error::Incompatible(format!(
+                        "archive FORMAT_VERSION {}, this build reads {}",
+                        h.format_version,
+                        crate::FORMAT_VERSION
+                    )));
+                }
+                if !h.text_retained {
+                    tracing::warn!(
+                        source_tenant = h.tenant_id,
+                        "archive carries no record text (source did not retain it); \
+                         imported records will not be BM25-searchable"
+                    );
+                }
+                self.header = Some(h);
+                Ok(None)
+            }
+            (_, None) => Err(Error::Modality("archive: missing header line".into())),
+            (Line::Header(_), Some(_)) => {
+                Err(Error::Modality("archive: second header line".into()))
+            }
+            (Line::Record(r), Some(h)) => {
+                self.seen += 1;
+                self.pending
+                    .push(r.into_record(self.remap_to.unwrap_or(h.tenant_id)));
+                if self.pending.len() >= ARCHIVE_BATCH {
+                    return Ok(Some(std::mem::take(&mut self.pending)));
+                }
+                Ok(None)
+            }
+            (Line::End { records }, Some(_)) => {
+                if records != self.seen {
+                    return Err(Error::Modality(format!(
+                        "archive: end line counts {records} records, found {}",
+                        self.seen
+                    )));
+                }
+                self.end = Some(records);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Final batch plus summary. Errors on an archive without its end
+    /// line — most likely a truncated transfer; batches already upserted
+    /// stay, and re-importing the full archive is idempotent.
+    pub fn finish(self) -> Result<(Vec<Record>, ImportSummary)> {
+        let header = self
+            .header
+            .ok_or_else(|| Error::Modality("archive: empty".into()))?;
+        if self.end.is_none() {
+            return Err(Error::Modality(format!(
+                "archive: truncated after {} records (no end line)",
+                self.seen
+            )));
+        }
+        Ok((
+            self.pending,
+            ImportSummary {
+                source_tenant: header.tenant_id,
+                tenant_id: self.remap_to.unwrap_or(header.tenant_id),
+                records: self.seen,
+                text_retained: header.text_retained,
+            },
+        ))
+    }
+}
+
+/// Import a whole archive from a blocking reader (the CLI).
+pub async fn import<I, R>(backend: &I, input: R, remap_to: Option<u32>) -> Result<ImportSummary>
+where
+    I: IndexBackend + ?Sized,
+    R: std::io::BufRead,
+{
+    let mut importer = Importer::new(remap_to);
+    for line in input.split(b'\n') {
+        if let Some(batch) = importer.push_line(&line?)? {
+            backend.upsert(&batch).await?;
+        }
+    }
+    let (rest, summary) = importer.finish()?;
+    if !rest.is_empty() {
+        backend.upsert(&rest).await?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(archive: &[u8]) -> Vec<&[u8]> {
+        archive.split(|b| *b == b'\n').collect()
+    }
+
+    #[test]
+    fn importer_remaps_and_checks_framing() {
+        let rec = ArchiveRecord::from_record(Record {
+            tenant_id: 3,
+            record_id: 9,
+            modality: Modality::Text,
+            format_version: 1,
+            algorithm: "minhash".into(),
+            config_hash: 42,
+            fingerprint: Bytes::from_static(b"fp"),
+            embedding: Some(vec![0.5]),
+            model_id: None,
+            metadata: Bytes::new(),
+            text: Some("hello".into()),
+            text_fields: BTreeMap::new(),
+            expires_at: None,
+        });
+        let mut archive = header_line(&ArchiveHeader::new(3, true)).unwrap();
+        encode_line(&Line::Record(rec), &mut archive).unwrap();
+        archive.extend(end_line(1).unwrap());
+
+        let mut imp = Importer::new(Some(8));
+        for l in lines(&archive) {
+            assert!(imp.push_line(l).unwrap().is_none());
+        }
+        let (batch, summary) = imp.finish().unwrap();
+        assert_eq!(summary.source_tenant, 3);
+        assert_eq!(summary.tenant_id, 8);
+        assert_eq!(batch[0].tenant_id, 8);
+        assert_eq!(batch[0].config_hash, 42);
+        assert_eq!(batch[0].text.as_deref(), Some("hello"));
+
+        // Truncated: no end line.
+        let mut imp = Importer::new(None);
+        for l in lines(&archive).into_iter().take(2) {
+            imp.push_line(l).unwrap();
+        }
+        assert!(imp.finish().is_err());
+
+        // Records before a header.
+        let mut imp = Importer::new(None);
+        assert!(imp.push_line(lines(&archive)[1]).is_err());
+
+        // Newer record format.
+        let mut header = ArchiveHeader::new(3, true);
+        header.format_version += 1;
+        let mut imp = Importer::new(None);
+        assert!(matches!(
+            imp.push_line(&header_line(&header).unwrap()),
+            Err(Error::Incompatible(_))
+        ));
+    }
+
+    #[test]
+    fn blobs_are_base64_and_v1_arrays_still_read() {
+        let mut out = Vec::new();
+        let rec = ArchiveRecord::from_record(Record {
+            tenant_id: 3,
+            record_id: 1,
+            modality: Modality::Text,
+            format_version: 1,
+            algorithm: "minhash".into(),
+            config_hash: 0,
+            fingerprint: Bytes::from_static(b"\x00\xffhi"),
+            embedding: None,
+            model_id: None,
+            metadata: Bytes::new(),
+            text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: None,
+        });
+        encode_line(&Line::Record(rec), &mut out).unwrap();
+        assert!(
+            std::str::from_utf8(&out)
+                .unwrap()
+                .contains(r#""fingerprint":"AP9oaQ==""#)
+        );
+
+        // Version 1: number arrays and no `text_retained` in the header.
+        let v1 = br#"{"header":{"archive_version":1,"format_version":1,"crate_version":"0","tenant_id":3,"created_ms":0}}
+{"record":{"record_id":1,"modality":"Text","format_version":1,"algorithm":"minhash","config_hash":0,"fingerprint":[0,255,104,105],"metadata":[1]}}
+{"end":{"records":1}}"#;
+        let mut imp = Importer::new(None);
+        for l in lines(v1) {
+            imp.push_line(l).unwrap();
+        }
+        let (batch, summary) = imp.finish().unwrap();
+        assert!(summary.text_retained);
+        assert_eq!(&batch[0].fingerprint[..], b"\x00\xffhi");
+        assert_eq!(&batch[0].metadata[..], [1]);
+
+        // A source without text retention says so, and the summary echoes it.
+        let mut imp = Importer::new(None);
+        imp.push_line(&header_line(&ArchiveHeader::new(3, false)).unwrap())
+            .unwrap();
+        imp.push_line(&end_line(0).unwrap()).unwrap();
+        assert!(!imp.finish().unwrap().1.text_retained);
+    }
+}
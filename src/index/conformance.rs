@@ -0,0 +1,1161 @@
+//! Shared conformance battery for [`IndexBackend`] implementations.
+//!
+//! Every backend (embedded redb today; in-memory, Qdrant, … later) must
+//! agree on the observable semantics the matcher and HTTP layer rely on.
+//! This module encodes those semantics as a generic async test battery so
+//! a backend author can prove parity with one call from their own tests:
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn my_backend_conforms() {
+//!     ucfp::index::conformance::run_all(|| MyBackend::new()).await;
+//! }
+//! ```
+//!
+//! Each check panics with a `conformance[<check>]` prefix on the first
+//! violation, so a failing run names the contract that broke. Checks
+//! expect an *empty* backend; [`run_all`] calls the factory once per
+//! check. Individual checks are public so authors can run a subset while
+//! bringing a new backend up.
+//!
+//! Filter semantics are backend-specific (see [`IndexBackend::knn`]), so
+//! the battery cannot construct a filter every backend understands. What
+//! it does enforce is that a filter is never silently dropped: a backend
+//! must either honour it or refuse with [`Error::Unsupported`].
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+use crate::core::{
+    Bm25Model, Bm25Params, ChangeKind, Highlight, Hit, Modality, Progress, Record, RecordFilter,
+    Snippet, WriteCondition, WriteOutcome,
+};
+use crate::error::Error;
+use crate::index::IndexBackend;
+
+/// Run every check in the battery, each against a fresh backend from
+/// `make`. Panics on the first violation.
+pub async fn run_all<B, F>(mut make: F)
+where
+    B: IndexBackend,
+    F: FnMut() -> B,
+{
+    tenant_isolation(&make()).await;
+    idempotent_delete(&make()).await;
+    upsert_replaces(&make()).await;
+    stale_vector_removal(&make()).await;
+    bm25_delete_consistency(&make(), &make()).await;
+    bm25_term_expansion(&make()).await;
+    bm25_text_fields(&make()).await;
+    bm25_scoring_override(&make()).await;
+    bm25_highlight(&make()).await;
+    term_suggestions(&make()).await;
+    record_not_found(&make()).await;
+    filter_correctness(&make()).await;
+    get_records_round_trip(&make()).await;
+    scan_pagination(&make()).await;
+    delete_where_filter(&make()).await;
+    purge_tenant_clears_everything(&make()).await;
+    expired_records_are_hidden_then_swept(&make()).await;
+    version_counter_advances(&make()).await;
+    conditional_upsert(&make()).await;
+    change_log_follows_writes(&make()).await;
+    fsck_reports_clean_store(&make()).await;
+    tenant_stats_follow_writes(&make()).await;
+}
+
+// ── Fixtures ────────────────────────────────────────────────────────────
+
+fn record(
+    tenant_id: u32,
+    record_id: u64,
+    embedding: Option<Vec<f32>>,
+    text: Option<&str>,
+) -> Record {
+    Record {
+        tenant_id,
+        record_id,
+        modality: if text.is_some() {
+            Modality::Text
+        } else {
+            Modality::Image
+        },
+        format_version: 1,
+        algorithm: "conformance".into(),
+        config_hash: 0,
+        fingerprint: Bytes::from_static(b"fp"),
+        embedding,
+        model_id: None,
+        metadata: Bytes::new(),
+        text: text.map(str::to_string),
+        text_fields: BTreeMap::new(),
+        expires_at: None,
+    }
+}
+
+fn ids(hits: &[Hit]) -> Vec<u64> {
+    let mut out: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+    out.sort_unstable();
+    out
+}
+
+// ── Checks ──────────────────────────────────────────────────────────────
+
+/// The same `record_id` in two tenants is two independent records: reads,
+/// searches and deletes in one tenant never observe or touch the other.
+pub async fn tenant_isolation<B: IndexBackend>(backend: &B) {
+    let mut a = record(1, 7, Some(vec![1.0, 0.0]), Some("shared words here"));
+    a.algorithm = "tenant-one".into();
+    let mut b = record(2, 7, Some(vec![1.0, 0.0]), Some("shared words here"));
+    b.algorithm = "tenant-two".into();
+    backend.upsert(&[a, b]).await.expect("upsert");
+
+    let hits = backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert!(
+        hits.iter().all(|h| h.tenant_id == 1) && ids(&hits) == vec![7],
+        "conformance[tenant_isolation]: knn leaked across tenants: {hits:?}"
+    );
+    let hits = backend.bm25(1, &["shared"], 10, None).await.expect("bm25");
+    assert!(
+        hits.iter().all(|h| h.tenant_id == 1) && ids(&hits) == vec![7],
+        "conformance[tenant_isolation]: bm25 leaked across tenants: {hits:?}"
+    );
+    let meta = backend
+        .get_record_metadata(2, 7)
+        .await
+        .expect("get_record_metadata");
+    assert_eq!(
+        meta.algorithm, "tenant-two",
+        "conformance[tenant_isolation]: metadata read returned another tenant's row"
+    );
+
+    backend.delete(1, &[7]).await.expect("delete");
+    let hits = backend.knn(2, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert_eq!(
+        ids(&hits),
+        vec![7],
+        "conformance[tenant_isolation]: delete in tenant 1 removed tenant 2's record"
+    );
+    let hits = backend.bm25(2, &["shared"], 10, None).await.expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        vec![7],
+        "conformance[tenant_isolation]: delete in tenant 1 removed tenant 2's postings"
+    );
+}
+
+/// Deleting missing ids, an empty id list, or the same id twice succeeds.
+pub async fn idempotent_delete<B: IndexBackend>(backend: &B) {
+    backend
+        .delete(1, &[])
+        .await
+        .expect("conformance[idempotent_delete]: empty delete failed");
+    backend
+        .delete(1, &[404])
+        .await
+        .expect("conformance[idempotent_delete]: deleting a missing id failed");
+
+    backend
+        .upsert(&[record(1, 1, Some(vec![1.0, 0.0]), Some("alpha"))])
+        .await
+        .expect("upsert");
+    backend
+        .delete(1, &[1, 1, 404])
+        .await
+        .expect("conformance[idempotent_delete]: delete with duplicates failed");
+    backend
+        .delete(1, &[1])
+        .await
+        .expect("conformance[idempotent_delete]: second delete failed");
+
+    let hits = backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert!(
+        hits.is_empty(),
+        "conformance[idempotent_delete]: deleted record still searchable: {hits:?}"
+    );
+}
+
+/// Re-upserting `(tenant_id, record_id)` replaces every stored facet of
+/// the record — nothing from the prior version survives or duplicates.
+pub async fn upsert_replaces<B: IndexBackend>(backend: &B) {
+    let mut first = record(1, 5, Some(vec![1.0, 0.0]), Some("original words"));
+    first.algorithm = "v1".into();
+    backend.upsert(&[first]).await.expect("upsert");
+
+    let mut second = record(1, 5, Some(vec![0.0, 1.0]), Some("replacement text"));
+    second.algorithm = "v2".into();
+    second.fingerprint = Bytes::from_static(b"longer-fp");
+    second.metadata = Bytes::from_static(b"meta");
+    backend.upsert(&[second]).await.expect("upsert");
+
+    let meta = backend
+        .get_record_metadata(1, 5)
+        .await
+        .expect("get_record_metadata");
+    assert_eq!(
+        meta.algorithm, "v2",
+        "conformance[upsert_replaces]: stale algorithm"
+    );
+    assert_eq!(
+        meta.fingerprint_bytes, 9,
+        "conformance[upsert_replaces]: stale fingerprint length"
+    );
+    assert_eq!(
+        meta.metadata_bytes, 4,
+        "conformance[upsert_replaces]: stale metadata length"
+    );
+
+    let hits = backend.knn(1, &[0.0, 1.0], 10, None).await.expect("knn");
+    assert_eq!(
+        ids(&hits),
+        vec![5],
+        "conformance[upsert_replaces]: replaced record duplicated in knn"
+    );
+    assert!(
+        hits[0].score > 0.99,
+        "conformance[upsert_replaces]: knn scored the stale vector: {hits:?}"
+    );
+
+    let stale = backend
+        .bm25(1, &["original"], 10, None)
+        .await
+        .expect("bm25");
+    assert!(
+        stale.is_empty(),
+        "conformance[upsert_replaces]: stale text still matches: {stale:?}"
+    );
+    let fresh = backend
+        .bm25(1, &["replacement"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&fresh),
+        vec![5],
+        "conformance[upsert_replaces]: new text not indexed"
+    );
+}
+
+/// Re-upserting a record without an embedding drops its old vector.
+pub async fn stale_vector_removal<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[record(1, 3, Some(vec![1.0, 0.0]), None)])
+        .await
+        .expect("upsert");
+    backend
+        .upsert(&[record(1, 3, None, None)])
+        .await
+        .expect("upsert");
+
+    let hits = backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert!(
+        hits.is_empty(),
+        "conformance[stale_vector_removal]: stale vector still searchable: {hits:?}"
+    );
+    let meta = backend
+        .get_record_metadata(1, 3)
+        .await
+        .expect("get_record_metadata");
+    assert!(
+        !meta.has_embedding && meta.embedding_dim.is_none(),
+        "conformance[stale_vector_removal]: metadata still reports an embedding"
+    );
+}
+
+/// Deleting a document (or re-upserting it without text) removes every
+/// trace of it from BM25 — including corpus statistics. `dirty` sees
+/// writes followed by deletes, `fresh` sees only the survivors; their
+/// BM25 scores must match.
+pub async fn bm25_delete_consistency<B: IndexBackend>(dirty: &B, fresh: &B) {
+    dirty
+        .upsert(&[
+            record(1, 1, None, Some("rust async runtime")),
+            record(1, 2, None, Some("rust borrow checker rust")),
+            record(1, 3, None, Some("async await syntax")),
+            record(1, 4, None, Some("unrelated gardening notes")),
+        ])
+        .await
+        .expect("upsert");
+    dirty.delete(1, &[1]).await.expect("delete");
+    // Re-upsert without text: the record stays, its postings must go.
+    dirty
+        .upsert(&[record(1, 4, None, None)])
+        .await
+        .expect("upsert");
+
+    fresh
+        .upsert(&[
+            record(1, 2, None, Some("rust borrow checker rust")),
+            record(1, 3, None, Some("async await syntax")),
+        ])
+        .await
+        .expect("upsert");
+
+    for term in ["rust", "async", "gardening", "runtime"] {
+        let got = dirty.bm25(1, &[term], 10, None).await.expect("bm25");
+        let want = fresh.bm25(1, &[term], 10, None).await.expect("bm25");
+        assert_eq!(
+            ids(&got),
+            ids(&want),
+            "conformance[bm25_delete_consistency]: `{term}` matched a removed document"
+        );
+        for (g, w) in got.iter().zip(want.iter()) {
+            assert!(
+                (g.score - w.score).abs() < 1e-4,
+                "conformance[bm25_delete_consistency]: `{term}` scored {} vs {} — \
+                 corpus stats still count removed documents",
+                g.score,
+                w.score
+            );
+        }
+    }
+
+    dirty.delete(1, &[2, 3, 4]).await.expect("delete");
+    let hits = dirty
+        .bm25(1, &["rust", "async"], 10, None)
+        .await
+        .expect("bm25");
+    assert!(
+        hits.is_empty(),
+        "conformance[bm25_delete_consistency]: empty corpus still returns hits: {hits:?}"
+    );
+}
+
+/// `term*` and `term~n` match the dictionary terms they expand to,
+/// label them in explain output, and rank an exact match above them.
+/// Backends without expansion may refuse with [`Error::Unsupported`].
+pub async fn bm25_term_expansion<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, None, Some("contract signed")),
+            record(1, 2, None, Some("contracts signed")),
+            record(1, 3, None, Some("contractor hired")),
+            record(1, 4, None, Some("unrelated notes")),
+        ])
+        .await
+        .expect("upsert");
+
+    let hits = match backend.bm25_explain(1, &["contract*"], 10, None).await {
+        Err(Error::Unsupported(_)) => return,
+        other => other.expect("bm25"),
+    };
+    assert_eq!(
+        ids(&hits),
+        [1, 2, 3],
+        "conformance[bm25_term_expansion]: prefix matches"
+    );
+    assert_eq!(
+        hits[0].record_id, 1,
+        "conformance[bm25_term_expansion]: exact term must rank first"
+    );
+    for h in &hits {
+        assert!(
+            h.term_hits
+                .iter()
+                .all(|t| t.expanded_from.as_deref() == Some("contract*")),
+            "conformance[bm25_term_expansion]: unlabelled expansion {:?}",
+            h.term_hits
+        );
+    }
+
+    let hits = backend
+        .bm25(1, &["contrcat~2"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        [1],
+        "conformance[bm25_term_expansion]: fuzzy matches"
+    );
+}
+
+/// Named text fields are searchable on their own with `field:term`,
+/// take part in unqualified queries, and leave with their record.
+/// Backends without text fields may refuse the upsert with
+/// [`Error::Unsupported`].
+pub async fn bm25_text_fields<B: IndexBackend>(backend: &B) {
+    let mut titled = record(1, 1, None, Some("body words"));
+    titled
+        .text_fields
+        .insert("title".into(), "Lighthouse".into());
+    let mut other = record(1, 3, None, Some("more words"));
+    other.text_fields.insert("title".into(), "Harbour".into());
+    match backend
+        .upsert(&[titled, record(1, 2, None, Some("lighthouse keeper")), other])
+        .await
+    {
+        Err(Error::Unsupported(_)) => return,
+        other => other.expect("upsert"),
+    };
+
+    let hits = backend
+        .bm25(1, &["title:lighthouse"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        [1],
+        "conformance[bm25_text_fields]: qualified query"
+    );
+    let hits = backend
+        .bm25_explain(1, &["lighthouse"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        [1, 2],
+        "conformance[bm25_text_fields]: unqualified query"
+    );
+    let field_of = |rid: u64| {
+        let hit = hits.iter().find(|h| h.record_id == rid).unwrap();
+        hit.term_hits.first().map(|t| t.field.clone())
+    };
+    assert_eq!(field_of(1).as_deref(), Some("title"));
+    assert_eq!(field_of(2).as_deref(), Some("text"));
+
+    backend.delete(1, &[1]).await.expect("delete");
+    let hits = backend
+        .bm25(1, &["title:lighthouse"], 10, None)
+        .await
+        .expect("bm25");
+    assert!(
+        hits.is_empty(),
+        "conformance[bm25_text_fields]: deleted record still matches {hits:?}"
+    );
+    let hits = backend
+        .bm25(1, &["title:harbour"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(ids(&hits), [3], "conformance[bm25_text_fields]: survivor");
+}
+
+/// Scoring a query with the default [`Bm25Params`] ranks like
+/// [`IndexBackend::bm25`] on an unconfigured tenant, and every model
+/// finds the same documents. Backends without per-query parameters may
+/// refuse with [`Error::Unsupported`].
+pub async fn bm25_scoring_override<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, None, Some("tide tables")),
+            record(1, 2, None, Some("tide tide and more words about the sea")),
+            record(1, 3, None, Some("unrelated")),
+        ])
+        .await
+        .expect("upsert");
+
+    let default = Bm25Params::default();
+    let tuned = match backend
+        .bm25_with(1, &["tide"], 10, None, false, &default)
+        .await
+    {
+        Err(Error::Unsupported(_)) => return,
+        other => other.expect("bm25_with"),
+    };
+    let plain = backend.bm25(1, &["tide"], 10, None).await.expect("bm25");
+    assert_eq!(tuned.len(), plain.len());
+    for (a, b) in tuned.iter().zip(&plain) {
+        assert!(
+            a.record_id == b.record_id && (a.score - b.score).abs() < 1e-4,
+            "conformance[bm25_scoring_override]: default params changed the ranking \
+             {tuned:?} vs {plain:?}"
+        );
+    }
+    for model in Bm25Model::ALL {
+        let params = Bm25Params { model, ..default };
+        let hits = backend
+            .bm25_with(1, &["tide"], 10, None, false, &params)
+            .await
+            .expect("bm25_with");
+        assert_eq!(
+            ids(&hits),
+            [1, 2],
+            "conformance[bm25_scoring_override]: {model:?} matched other docs"
+        );
+    }
+}
+
+/// Explained BM25 hits get snippets of their retained text with the
+/// matched tokens marked and the rest escaped; unexplained hits, which
+/// carry no matched terms, get none. Backends without highlighting, or
+/// not retaining text, may refuse with [`Error::Unsupported`] or
+/// [`Error::Incompatible`].
+pub async fn bm25_highlight<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(
+                1,
+                1,
+                None,
+                Some("The harbour crane lifts <containers> at dawn"),
+            ),
+            record(1, 2, None, Some("a quiet harbour")),
+            record(1, 3, None, Some("unrelated")),
+        ])
+        .await
+        .expect("upsert");
+
+    let mut hits = backend
+        .bm25_explain(1, &["harbour", "crane"], 10, None)
+        .await
+        .expect("bm25_explain");
+    match backend.highlight(1, &mut hits, &Highlight::default()).await {
+        Err(Error::Unsupported(_) | Error::Incompatible(_)) => return,
+        other => other.expect("highlight"),
+    };
+    assert_eq!(ids(&hits), [1, 2]);
+    let one = hits.iter().find(|h| h.record_id == 1).unwrap();
+    assert_eq!(
+        one.snippets,
+        [Snippet {
+            field: "text".into(),
+            fragment:
+                "The <mark>harbour</mark> <mark>crane</mark> lifts &lt;containers&gt; at dawn"
+                    .into(),
+        }],
+        "conformance[bm25_highlight]: marked fragment"
+    );
+
+    let mut plain = backend.bm25(1, &["harbour"], 10, None).await.expect("bm25");
+    backend
+        .highlight(1, &mut plain, &Highlight::default())
+        .await
+        .expect("highlight");
+    assert!(
+        plain.iter().all(|h| h.snippets.is_empty()),
+        "conformance[bm25_highlight]: unexplained hits got snippets"
+    );
+}
+
+/// Suggestions rank completions by document frequency, correct typos
+/// by edit distance, and never offer a term whose documents are gone.
+/// Backends without suggestions may refuse with [`Error::Unsupported`].
+pub async fn term_suggestions<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, None, Some("brown fox")),
+            record(1, 2, None, Some("brown dog")),
+            record(1, 3, None, Some("brownie")),
+            record(1, 4, None, Some("browse")),
+            record(2, 1, None, Some("brownstone")),
+        ])
+        .await
+        .expect("upsert");
+    backend.delete(1, &[4]).await.expect("delete");
+
+    let got = match backend.suggest_terms(1, "brow", 10).await {
+        Err(Error::Unsupported(_)) => return,
+        other => other.expect("suggest_terms"),
+    };
+    let terms: Vec<(&str, u64)> = got.iter().map(|s| (s.term.as_str(), s.doc_freq)).collect();
+    assert_eq!(
+        terms,
+        [("brown", 2), ("brownie", 1)],
+        "conformance[term_suggestions]: completions"
+    );
+
+    let got = backend
+        .did_you_mean(1, "brwn", 10)
+        .await
+        .expect("did_you_mean");
+    assert_eq!(got.len(), 1);
+    assert_eq!(
+        got[0]
+            .suggestions
+            .first()
+            .map(|s| (s.term.as_str(), s.distance)),
+        Some(("brown", 1)),
+        "conformance[term_suggestions]: corrections {got:?}"
+    );
+}
+
+/// Missing, deleted, and other-tenant records surface as
+/// [`Error::RecordNotFound`] carrying the requested key.
+pub async fn record_not_found<B: IndexBackend>(backend: &B) {
+    match backend.get_record_metadata(1, 99).await {
+        Err(Error::RecordNotFound {
+            tenant_id: 1,
+            record_id: 99,
+        }) => {}
+        other => panic!("conformance[record_not_found]: missing record returned {other:?}"),
+    }
+
+    backend
+        .upsert(&[record(1, 10, None, None)])
+        .await
+        .expect("upsert");
+    match backend.get_record_metadata(2, 10).await {
+        Err(Error::RecordNotFound {
+            tenant_id: 2,
+            record_id: 10,
+        }) => {}
+        other => panic!("conformance[record_not_found]: other-tenant read returned {other:?}"),
+    }
+
+    backend.delete(1, &[10]).await.expect("delete");
+    match backend.get_record_metadata(1, 10).await {
+        Err(Error::RecordNotFound {
+            tenant_id: 1,
+            record_id: 10,
+        }) => {}
+        other => panic!("conformance[record_not_found]: deleted record returned {other:?}"),
+    }
+}
+
+/// A filtered query is either honoured — hits are a subset of the
+/// unfiltered tenant result — or refused with [`Error::Unsupported`].
+/// Returning hits outside the tenant, or failing with any other error
+/// class, is a violation.
+pub async fn filter_correctness<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, Some(vec![1.0, 0.0]), Some("filter target")),
+            record(1, 2, Some(vec![0.9, 0.1]), Some("filter target")),
+            record(2, 3, Some(vec![1.0, 0.0]), Some("filter target")),
+        ])
+        .await
+        .expect("upsert");
+    let filter = Bytes::from_static(b"\x00");
+
+    let all = ids(&backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn"));
+    match backend.knn(1, &[1.0, 0.0], 10, Some(&filter)).await {
+        Err(Error::Unsupported(_)) => {}
+        Ok(hits) => assert!(
+            hits.iter()
+                .all(|h| h.tenant_id == 1 && all.contains(&h.record_id)),
+            "conformance[filter_correctness]: filtered knn escaped the tenant: {hits:?}"
+        ),
+        Err(e) => panic!("conformance[filter_correctness]: filtered knn failed: {e}"),
+    }
+
+    let all = ids(&backend.bm25(1, &["filter"], 10, None).await.expect("bm25"));
+    match backend.bm25(1, &["filter"], 10, Some(&filter)).await {
+        Err(Error::Unsupported(_)) => {}
+        Ok(hits) => assert!(
+            hits.iter()
+                .all(|h| h.tenant_id == 1 && all.contains(&h.record_id)),
+            "conformance[filter_correctness]: filtered bm25 escaped the tenant: {hits:?}"
+        ),
+        Err(e) => panic!("conformance[filter_correctness]: filtered bm25 failed: {e}"),
+    }
+}
+
+/// `get_records` hands back exactly what was upserted, in request order,
+/// omitting missing and other-tenant IDs. Backends that don't implement
+/// it yet may refuse with [`Error::Unsupported`].
+pub async fn get_records_round_trip<B: IndexBackend>(backend: &B) {
+    let mut a = record(1, 1, Some(vec![0.25, -1.5, 3.0]), None);
+    a.fingerprint = Bytes::from_static(b"\x01\x02\x03\x04");
+    a.metadata = Bytes::from_static(b"{\"src\":\"a\"}");
+    a.model_id = Some("m".into());
+    let b = record(1, 2, None, None);
+    let other = record(2, 3, None, None);
+    backend
+        .upsert(&[a.clone(), b.clone(), other])
+        .await
+        .expect("upsert");
+
+    let got = match backend.get_records(1, &[2, 99, 1, 3]).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[get_records_round_trip]: get_records failed: {e}"),
+        Ok(got) => got,
+    };
+    let got_ids: Vec<u64> = got.iter().map(|r| r.record_id).collect();
+    assert_eq!(
+        got_ids,
+        vec![2, 1],
+        "conformance[get_records_round_trip]: wrong ids/order (missing and other-tenant ids must be omitted)"
+    );
+    let r = &got[1];
+    assert!(
+        r.tenant_id == a.tenant_id
+            && r.modality == a.modality
+            && r.algorithm == a.algorithm
+            && r.format_version == a.format_version
+            && r.config_hash == a.config_hash
+            && r.fingerprint == a.fingerprint
+            && r.embedding == a.embedding
+            && r.model_id == a.model_id
+            && r.metadata == a.metadata,
+        "conformance[get_records_round_trip]: record did not round-trip: {r:?}"
+    );
+    assert_eq!(
+        got[0].embedding, None,
+        "conformance[get_records_round_trip]: embedding-less record grew a vector"
+    );
+}
+
+/// Walking a tenant with `scan` visits every record exactly once in
+/// ascending id order, honours the filter, never crosses tenants, and a
+/// cursor stays valid across concurrent writes. Backends that don't
+/// implement scanning yet may refuse with [`Error::Unsupported`].
+pub async fn scan_pagination<B: IndexBackend>(backend: &B) {
+    let mut batch: Vec<Record> = (1..=5).map(|i| record(1, i * 10, None, None)).collect();
+    batch[1].modality = Modality::Audio;
+    batch.push(record(2, 25, None, None));
+    backend.upsert(&batch).await.expect("upsert");
+
+    let all = RecordFilter::default();
+    let first = match backend.scan(1, None, 2, &all).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[scan_pagination]: scan failed: {e}"),
+        Ok(page) => page,
+    };
+    let mut seen: Vec<u64> = first.records.iter().map(|m| m.record_id).collect();
+    assert!(
+        first.records.len() <= 2 && first.records.iter().all(|m| m.tenant_id == 1),
+        "conformance[scan_pagination]: bad first page: {first:?}"
+    );
+
+    // Writes landing mid-walk: one behind the cursor, one ahead of it.
+    backend
+        .upsert(&[record(1, 1, None, None), record(1, 45, None, None)])
+        .await
+        .expect("upsert");
+
+    let mut cursor = first.next_cursor;
+    let mut pages = 0;
+    while let Some(c) = cursor {
+        let page = backend
+            .scan(1, Some(&c), 2, &all)
+            .await
+            .expect("scan resume");
+        seen.extend(page.records.iter().map(|m| m.record_id));
+        cursor = page.next_cursor;
+        pages += 1;
+        assert!(
+            pages < 100,
+            "conformance[scan_pagination]: cursor never ended"
+        );
+    }
+    assert_eq!(
+        seen,
+        vec![10, 20, 30, 40, 45, 50],
+        "conformance[scan_pagination]: walk must be ordered, complete and repeat-free"
+    );
+
+    let only_audio = RecordFilter {
+        modality: Some(Modality::Audio),
+        ..RecordFilter::default()
+    };
+    let page = backend
+        .scan(1, None, 10, &only_audio)
+        .await
+        .expect("filtered scan");
+    let got: Vec<u64> = page.records.iter().map(|m| m.record_id).collect();
+    assert_eq!(
+        got,
+        vec![20],
+        "conformance[scan_pagination]: modality filter not honoured"
+    );
+}
+
+/// `delete_where` removes exactly the matching records of one tenant —
+/// from reads, k-NN and BM25 — and reports them through `progress`.
+/// May refuse with [`Error::Unsupported`] (e.g. no `scan` yet).
+pub async fn delete_where_filter<B: IndexBackend>(backend: &B) {
+    let mut batch = vec![
+        record(1, 1, Some(vec![1.0, 0.0]), Some("keep me")),
+        record(1, 2, Some(vec![1.0, 0.0]), Some("drop me")),
+        record(1, 3, Some(vec![1.0, 0.0]), Some("drop me too")),
+        record(2, 2, Some(vec![1.0, 0.0]), Some("drop me")),
+    ];
+    for r in &mut batch[1..] {
+        r.algorithm = "doomed".into();
+    }
+    backend.upsert(&batch).await.expect("upsert");
+
+    let filter = RecordFilter {
+        algorithm: Some("doomed".into()),
+        ..RecordFilter::default()
+    };
+    let progress = Progress::default();
+    let removed = match backend.delete_where(1, &filter, &progress).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[delete_where_filter]: delete_where failed: {e}"),
+        Ok(n) => n,
+    };
+    assert_eq!(
+        (removed, progress.records()),
+        (2, 2),
+        "conformance[delete_where_filter]: wrong removed / progress count"
+    );
+    let hits = backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert_eq!(
+        ids(&hits),
+        vec![1],
+        "conformance[delete_where_filter]: knn still sees deleted records"
+    );
+    let hits = backend.bm25(1, &["drop"], 10, None).await.expect("bm25");
+    assert!(
+        hits.is_empty(),
+        "conformance[delete_where_filter]: bm25 still sees deleted records: {hits:?}"
+    );
+    let hits = backend.bm25(2, &["drop"], 10, None).await.expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        vec![2],
+        "conformance[delete_where_filter]: delete_where crossed tenants"
+    );
+}
+
+/// After `purge_tenant` nothing of the tenant is observable, other
+/// tenants are untouched, and the tenant can be re-populated from
+/// scratch with correct BM25 statistics.
+pub async fn purge_tenant_clears_everything<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, Some(vec![1.0, 0.0]), Some("alpha beta")),
+            record(1, 2, Some(vec![0.0, 1.0]), Some("beta gamma")),
+            record(2, 1, Some(vec![1.0, 0.0]), Some("alpha beta")),
+        ])
+        .await
+        .expect("upsert");
+
+    let progress = Progress::default();
+    let removed = match backend.purge_tenant(1, &progress).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[purge_tenant]: purge failed: {e}"),
+        Ok(n) => n,
+    };
+    assert_eq!(
+        (removed, progress.records()),
+        (2, 2),
+        "conformance[purge_tenant]: wrong removed / progress count"
+    );
+    assert!(
+        matches!(
+            backend.get_record_metadata(1, 1).await,
+            Err(Error::RecordNotFound { .. })
+        ),
+        "conformance[purge_tenant]: record still readable"
+    );
+    assert!(
+        backend
+            .knn(1, &[1.0, 0.0], 10, None)
+            .await
+            .expect("knn")
+            .is_empty()
+            && backend
+                .bm25(1, &["beta"], 10, None)
+                .await
+                .expect("bm25")
+                .is_empty(),
+        "conformance[purge_tenant]: purged tenant still searchable"
+    );
+    assert_eq!(
+        ids(&backend.bm25(2, &["alpha"], 10, None).await.expect("bm25")),
+        vec![1],
+        "conformance[purge_tenant]: purge touched another tenant"
+    );
+
+    // Re-populated tenant scores like a brand-new one.
+    backend
+        .upsert(&[record(1, 9, None, Some("gamma"))])
+        .await
+        .expect("upsert");
+    let hits = backend.bm25(1, &["gamma"], 10, None).await.expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        vec![9],
+        "conformance[purge_tenant]: stale postings survived the purge: {hits:?}"
+    );
+}
+
+/// A record past its `expires_at` disappears from every read path at
+/// once; `sweep_expired` then reclaims exactly the expired rows, and a
+/// re-upsert without TTL revives a record whose old deadline passed.
+pub async fn expired_records_are_hidden_then_swept<B: IndexBackend>(backend: &B) {
+    let mut dead = record(1, 1, Some(vec![1.0, 0.0]), Some("fleeting sound"));
+    dead.expires_at = Some(1);
+    let mut alive = record(1, 2, Some(vec![1.0, 0.0]), Some("fleeting sound"));
+    alive.expires_at = Some(u64::MAX);
+    let mut revived = record(1, 3, Some(vec![1.0, 0.0]), Some("fleeting sound"));
+    revived.expires_at = Some(1);
+    backend
+        .upsert(&[dead, alive, revived.clone()])
+        .await
+        .expect("upsert");
+    revived.expires_at = None;
+    backend.upsert(&[revived]).await.expect("re-upsert");
+
+    let hits = backend.knn(1, &[1.0, 0.0], 10, None).await.expect("knn");
+    assert_eq!(
+        ids(&hits),
+        vec![2, 3],
+        "conformance[expiry]: knn served an expired record"
+    );
+    let hits = backend
+        .bm25(1, &["fleeting"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        vec![2, 3],
+        "conformance[expiry]: bm25 served an expired record"
+    );
+    // An expired record ranked into the top k gives its place up.
+    for k in [1, 2] {
+        let hits = backend.knn(1, &[1.0, 0.0], k, None).await.expect("knn");
+        assert_eq!(hits.len(), k, "conformance[expiry]: knn top-{k} not filled");
+        assert!(hits.iter().all(|h| h.record_id != 1));
+        let hits = backend.bm25(1, &["fleeting"], k, None).await.expect("bm25");
+        assert_eq!(
+            hits.len(),
+            k,
+            "conformance[expiry]: bm25 top-{k} not filled"
+        );
+        assert!(hits.iter().all(|h| h.record_id != 1));
+    }
+    match backend.get_record_metadata(1, 1).await {
+        Err(Error::RecordNotFound { .. }) | Err(Error::Unsupported(_)) => {}
+        other => panic!("conformance[expiry]: expired record still described: {other:?}"),
+    }
+
+    let swept = match backend.sweep_expired(u64::MAX - 1, 100).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[expiry]: sweep failed: {e}"),
+        Ok(n) => n,
+    };
+    assert_eq!(
+        swept, 1,
+        "conformance[expiry]: sweep count (only record 1 was due)"
+    );
+    assert_eq!(
+        backend
+            .sweep_expired(u64::MAX - 1, 100)
+            .await
+            .expect("sweep"),
+        0,
+        "conformance[expiry]: second sweep found more work"
+    );
+    let hits = backend
+        .bm25(1, &["fleeting"], 10, None)
+        .await
+        .expect("bm25");
+    assert_eq!(
+        ids(&hits),
+        vec![2, 3],
+        "conformance[expiry]: sweep touched live records"
+    );
+}
+
+/// Re-upserting a record bumps its version; history, where kept, lists
+/// versions oldest first and ends with the current one.
+pub async fn version_counter_advances<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[record(1, 1, None, Some("first draft"))])
+        .await
+        .expect("upsert");
+    backend
+        .upsert(&[record(1, 1, None, Some("second draft"))])
+        .await
+        .expect("re-upsert");
+    let versions = match backend.list_versions(1, 1).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[versions]: list failed: {e}"),
+        Ok(v) => v,
+    };
+    let latest = versions.last().expect("current version listed");
+    assert_eq!(latest.version, 2, "conformance[versions]: counter");
+    assert!(
+        versions.windows(2).all(|w| w[0].version < w[1].version),
+        "conformance[versions]: history out of order"
+    );
+    match backend.list_versions(2, 1).await {
+        Err(Error::RecordNotFound { .. }) => {}
+        other => panic!("conformance[versions]: other tenant saw history: {other:?}"),
+    }
+}
+
+/// `upsert_if` evaluates each condition against the stored row — earlier
+/// writes in the same batch included — and leaves losers untouched.
+pub async fn conditional_upsert<B: IndexBackend>(backend: &B) {
+    use WriteCondition::*;
+    use WriteOutcome::*;
+
+    let mut first = record(1, 1, None, None);
+    first.config_hash = 7;
+    let mut second = first.clone();
+    second.fingerprint = Bytes::from_static(b"second");
+    let outcomes = match backend
+        .upsert_if(
+            &[first.clone(), second.clone(), record(1, 2, None, None)],
+            &[IfAbsent, IfAbsent, IfVersion(3)],
+        )
+        .await
+    {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[conditional]: upsert_if failed: {e}"),
+        Ok(o) => o,
+    };
+    assert_eq!(
+        outcomes,
+        vec![Written, Skipped, Conflict],
+        "conformance[conditional]: first batch"
+    );
+    let stored = backend.get_records(1, &[1, 2]).await.expect("get_records");
+    assert_eq!(stored.len(), 1, "conformance[conditional]: conflict wrote");
+    assert_eq!(
+        stored[0].fingerprint.as_ref(),
+        b"fp",
+        "conformance[conditional]: skipped record overwrote"
+    );
+
+    let outcomes = backend
+        .upsert_if(
+            &[second.clone(), second.clone(), second],
+            &[IfConfigHash(8), IfConfigHash(7), IfVersion(0)],
+        )
+        .await
+        .expect("upsert_if");
+    assert_eq!(
+        outcomes,
+        vec![Conflict, Written, Conflict],
+        "conformance[conditional]: config-hash / absent checks"
+    );
+    let meta = backend.get_record_metadata(1, 1).await.expect("describe");
+    let outcomes = backend
+        .upsert_if(&[first], &[IfVersion(meta.version)])
+        .await
+        .expect("upsert_if");
+    assert_eq!(
+        outcomes,
+        vec![Written],
+        "conformance[conditional]: compare-and-set on the current version"
+    );
+}
+
+/// Every write lands in the change log with a dense sequence number;
+/// deleting a missing record logs nothing.
+pub async fn change_log_follows_writes<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[record(1, 1, None, None), record(2, 5, None, None)])
+        .await
+        .expect("upsert");
+    backend.delete(1, &[1, 99]).await.expect("delete");
+    let events = match backend.changes_since(0, 100).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[changes]: changes_since failed: {e}"),
+        Ok(ev) => ev,
+    };
+    let seen: Vec<(u64, ChangeKind, u32, Option<u64>)> = events
+        .iter()
+        .map(|e| (e.seq, e.kind, e.tenant_id, e.record_id))
+        .collect();
+    assert_eq!(
+        seen,
+        vec![
+            (1, ChangeKind::Upsert, 1, Some(1)),
+            (2, ChangeKind::Upsert, 2, Some(5)),
+            (3, ChangeKind::Delete, 1, Some(1)),
+        ],
+        "conformance[changes]: log contents"
+    );
+    let tail = backend.changes_since(2, 100).await.expect("changes_since");
+    assert_eq!(tail.len(), 1, "conformance[changes]: resume after seq");
+    assert_eq!(tail[0].seq, 3, "conformance[changes]: resume after seq");
+    assert!(
+        backend
+            .changes_since(3, 100)
+            .await
+            .expect("changes_since")
+            .is_empty(),
+        "conformance[changes]: caught-up read not empty"
+    );
+}
+
+/// A store written only through the trait passes `fsck` with nothing to
+/// report, across inserts, replacements, deletes and expiry. Backends
+/// without a checker may refuse with [`Error::Unsupported`].
+pub async fn fsck_reports_clean_store<B: IndexBackend>(backend: &B) {
+    let mut a = record(1, 1, Some(vec![1.0, 0.0]), Some("red fox jumps"));
+    a.metadata = Bytes::from_static(b"{\"k\":1}");
+    a.expires_at = Some(u64::MAX);
+    backend
+        .upsert(&[
+            a,
+            record(1, 2, None, Some("lazy red dog")),
+            record(1, 3, Some(vec![0.0, 1.0]), Some("")),
+            record(2, 1, Some(vec![1.0, 1.0]), Some("red fox")),
+        ])
+        .await
+        .expect("upsert");
+    backend
+        .upsert(&[
+            record(1, 1, None, Some("blue fox sleeps")),
+            record(1, 2, Some(vec![0.5, 0.5]), None),
+        ])
+        .await
+        .expect("re-upsert");
+    backend.delete(2, &[1]).await.expect("delete");
+
+    let report = match backend.fsck(None, false).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[fsck]: fsck failed: {e}"),
+        Ok(r) => r,
+    };
+    assert!(
+        report.violations.is_empty(),
+        "conformance[fsck]: clean store reported violations: {:?}",
+        report.violations
+    );
+    assert_eq!(report.records, 3, "conformance[fsck]: records checked");
+    let one = backend.fsck(Some(2), true).await.expect("fsck tenant");
+    assert!(
+        one.tenants == 1 && one.records == 0 && one.violations.is_empty(),
+        "conformance[fsck]: single-tenant run: {one:?}"
+    );
+}
+
+/// `tenant_stats` counts exactly what reads return: replacements count
+/// once, deletes and purges count out, and tenants stay separate.
+/// Backends without counters may refuse with [`Error::Unsupported`].
+pub async fn tenant_stats_follow_writes<B: IndexBackend>(backend: &B) {
+    backend
+        .upsert(&[
+            record(1, 1, Some(vec![1.0, 0.0]), None),
+            record(1, 2, None, Some("red fox")),
+            record(1, 3, None, Some("lazy dog")),
+            record(2, 1, Some(vec![1.0, 1.0]), None),
+        ])
+        .await
+        .expect("upsert");
+    backend
+        .upsert(&[record(1, 1, None, Some("blue fox"))])
+        .await
+        .expect("re-upsert");
+    backend.delete(1, &[3]).await.expect("delete");
+
+    let one = match backend.tenant_stats(Some(1)).await {
+        Err(Error::Unsupported(_)) => return,
+        Err(e) => panic!("conformance[tenant_stats]: stats failed: {e}"),
+        Ok(mut s) => s.remove(0),
+    };
+    assert_eq!(one.tenant_id, 1);
+    assert_eq!(one.records, 2, "conformance[tenant_stats]: records");
+    assert_eq!(
+        one.by_modality.get("text"),
+        Some(&2),
+        "conformance[tenant_stats]: by_modality {:?}",
+        one.by_modality
+    );
+    assert_eq!(one.fingerprint_bytes, 4, "conformance[tenant_stats]: bytes");
+    assert_eq!(one.ann.vectors, 0, "conformance[tenant_stats]: vectors");
+    assert_eq!(
+        one.bm25.doc_count, 2,
+        "conformance[tenant_stats]: bm25 docs"
+    );
+
+    backend
+        .purge_tenant(1, &Progress::default())
+        .await
+        .expect("purge");
+    let all = backend.tenant_stats(None).await.expect("stats");
+    let tenants: Vec<u32> = all.iter().map(|s| s.tenant_id).collect();
+    assert_eq!(
+        tenants,
+        [2],
+        "conformance[tenant_stats]: tenants after purge"
+    );
+    assert_eq!(all[0].ann.vectors, 1);
+}
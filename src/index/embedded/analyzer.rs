@@ -0,0 +1,459 @@
+//! Text analysis for BM25: how record and query text become terms.
+//!
+//! An [`Analyzer`] canonicalizes text, splits it into tokens, drops
+//! stopwords and stems what is left. A tenant's analyzer runs both when
+//! its records are indexed and when its queries are parsed, so a query
+//! term always meets the form it was indexed under.
+//!
+//! The default is the original tokenizer: lowercase, split on anything
+//! that is not alphanumeric, keep every token. That suits languages
+//! written with spaces but leaves a Japanese sentence as one token.
+//! Tenants given another analyzer with
+//! [`super::EmbeddedBackend::with_analyzers`] can use the `txtfp`
+//! canonicalizer and tokenizers the fingerprinting side runs.
+//!
+//! ## Spec
+//!
+//! An analyzer is written as `+`-separated steps, at most one of each
+//! kind, e.g. `nfkc+cjk-jp` or `nfkc+word+stop-en+stem-english`:
+//!
+//! | Step               | Kind          | Meaning                                                  | Feature             |
+//! | ------------------ | ------------- | -------------------------------------------------------- | ------------------- |
+//! | `lower`            | canonicalizer | lowercase (default)                                      |                     |
+//! | `nfkc`             | canonicalizer | `txtfp::Canonicalizer::default()`: NFKC, simple casefold, bidi / format controls dropped | `text` |
+//! | `confusable`       | canonicalizer | `nfkc` plus the UTS #39 confusable skeleton              | `text-security`     |
+//! | `alnum`            | tokenizer     | split on non-alphanumerics (default)                     |                     |
+//! | `word`             | tokenizer     | UAX #29 words                                            | `text`              |
+//! | `cjk-jp`, `cjk-ko` | tokenizer     | Lindera morphological segmentation                       | `text-cjk-japanese`, `text-cjk-korean` |
+//! | `stop-en`          | stopwords     | drop common English function words                       |                     |
+//! | `stem-<language>`  | stemmer       | Snowball stemmer, e.g. `stem-english`, `stem-german`     |                     |
+//!
+//! Tokens without a letter or digit (punctuation a segmenter emits) are
+//! dropped. A dropped stopword leaves no gap, so with `stop-en` the
+//! phrase `"act of god"` is the tokens `act god` on both sides.
+//!
+//! The [identity](Analyzer::identity) of the analyzer a tenant's index
+//! was built with is stored beside it; while it differs from the one the
+//! tenant is configured with, [`super::EmbeddedBackend::analyzer_mismatches`]
+//! lists the tenant for a reindex.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+use super::bm25::tokenize;
+use crate::error::{Error, Result};
+
+/// The original BM25 analyzer, used for tenants without one of their own.
+pub(crate) static DEFAULT: Analyzer = Analyzer {
+    canon: Canon::Lower,
+    split: Split::Alnum,
+    stop: None,
+    stem: None,
+};
+
+/// Words `stop-en` drops: the classic English stop set.
+const STOP_EN: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// Snowball algorithms by their `stem-` name.
+const STEMMERS: &[(&str, Algorithm)] = &[
+    ("arabic", Algorithm::Arabic),
+    ("danish", Algorithm::Danish),
+    ("dutch", Algorithm::Dutch),
+    ("english", Algorithm::English),
+    ("finnish", Algorithm::Finnish),
+    ("french", Algorithm::French),
+    ("german", Algorithm::German),
+    ("greek", Algorithm::Greek),
+    ("hungarian", Algorithm::Hungarian),
+    ("italian", Algorithm::Italian),
+    ("norwegian", Algorithm::Norwegian),
+    ("portuguese", Algorithm::Portuguese),
+    ("romanian", Algorithm::Romanian),
+    ("russian", Algorithm::Russian),
+    ("spanish", Algorithm::Spanish),
+    ("swedish", Algorithm::Swedish),
+    ("tamil", Algorithm::Tamil),
+    ("turkish", Algorithm::Turkish),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Canon {
+    Lower,
+    Nfkc,
+    Confusable,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Split {
+    Alnum,
+    Word,
+    CjkJp,
+    CjkKo,
+}
+
+#[derive(Clone, Debug)]
+struct Stopwords {
+    /// `en`, or a digest of a custom list.
+    name: String,
+    words: HashSet<String>,
+}
+
+/// How a tenant's BM25 text becomes terms; see the module docs.
+#[derive(Clone, Debug)]
+pub struct Analyzer {
+    canon: Canon,
+    split: Split,
+    stop: Option<Stopwords>,
+    stem: Option<(&'static str, Algorithm)>,
+}
+
+/// A tenant whose BM25 index was built with another analyzer than the
+/// one it is configured with; see
+/// [`super::EmbeddedBackend::analyzer_mismatches`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalyzerMismatch {
+    /// The tenant.
+    pub tenant_id: u32,
+    /// Identity of the analyzer the index was built with.
+    pub indexed: String,
+    /// Identity of the analyzer the tenant is configured with.
+    pub configured: String,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl Analyzer {
+    /// Parse a spec such as `nfkc+word+stop-en+stem-english`. An unknown
+    /// or repeated step is [`Error::Modality`]; one this build lacks the
+    /// feature for is [`Error::Unsupported`].
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (mut canon, mut split, mut stop, mut stem) = (None, None, None, None);
+        for step in spec.split('+').map(str::trim) {
+            let repeated = match step {
+                "lower" => once(&mut canon, Canon::Lower),
+                "nfkc" => once(&mut canon, Canon::Nfkc),
+                "confusable" => once(&mut canon, Canon::Confusable),
+                "alnum" => once(&mut split, Split::Alnum),
+                "word" => once(&mut split, Split::Word),
+                "cjk-jp" => once(&mut split, Split::CjkJp),
+                "cjk-ko" => once(&mut split, Split::CjkKo),
+                "stop-en" => once(&mut stop, ()),
+                _ => match step
+                    .strip_prefix("stem-")
+                    .and_then(|lang| STEMMERS.iter().find(|(name, _)| *name == lang))
+                {
+                    Some(&found) => once(&mut stem, found),
+                    None => {
+                        return Err(Error::Modality(format!(
+                            "analyzer `{spec}`: unknown step `{step}`"
+                        )));
+                    }
+                },
+            };
+            if repeated {
+                return Err(Error::Modality(format!(
+                    "analyzer `{spec}`: `{step}` repeats a step of its kind"
+                )));
+            }
+            let needs = match step {
+                "nfkc" | "word" => Some((cfg!(feature = "text"), "text")),
+                "confusable" => Some((cfg!(feature = "text-security"), "text-security")),
+                "cjk-jp" => Some((cfg!(feature = "text-cjk-japanese"), "text-cjk-japanese")),
+                "cjk-ko" => Some((cfg!(feature = "text-cjk-korean"), "text-cjk-korean")),
+                _ => None,
+            };
+            if let Some((false, feature)) = needs {
+                return Err(Error::Unsupported(format!(
+                    "analyzer step `{step}` needs the `{feature}` feature"
+                )));
+            }
+        }
+        let mut analyzer = Self {
+            canon: canon.unwrap_or(Canon::Lower),
+            split: split.unwrap_or(Split::Alnum),
+            stop: None,
+            stem,
+        };
+        if stop.is_some() {
+            analyzer.stop = Some(Stopwords {
+                name: "en".into(),
+                words: analyzer.stopword_set(STOP_EN),
+            });
+        }
+        Ok(analyzer)
+    }
+
+    /// Drop `words` instead of any list named in the spec. The words go
+    /// through this analyzer's canonicalizer and tokenizer first, so set
+    /// those before.
+    pub fn with_stopwords(mut self, words: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let words: Vec<String> = words.into_iter().map(|w| w.as_ref().to_string()).collect();
+        let words = self.stopword_set(&words);
+        let mut sorted: Vec<&str> = words.iter().map(String::as_str).collect();
+        sorted.sort_unstable();
+        self.stop = Some(Stopwords {
+            name: format!("{:016x}", fnv1a(sorted.join("\n").as_bytes())),
+            words,
+        });
+        self
+    }
+
+    /// Every step spelled out in a fixed order, e.g.
+    /// `lower+alnum` for the default. Two analyzers with the same
+    /// identity turn any text into the same terms.
+    pub fn identity(&self) -> String {
+        self.to_string()
+    }
+
+    /// Terms of `text`, in order: canonicalized, split, without
+    /// stopwords, stemmed.
+    pub(crate) fn analyze(&self, text: &str) -> Vec<String> {
+        let stemmer = self.stem.map(|(_, algorithm)| Stemmer::create(algorithm));
+        self.split(text)
+            .into_iter()
+            .filter(|t| self.stop.as_ref().is_none_or(|s| !s.words.contains(t)))
+            .map(|t| match &stemmer {
+                Some(stemmer) => stemmer.stem(&t).into_owned(),
+                None => t,
+            })
+            .collect()
+    }
+
+    /// Tokens of `text`, canonicalized but neither stopped nor stemmed:
+    /// the form prefix and fuzzy query terms are matched in.
+    pub(crate) fn split(&self, text: &str) -> Vec<String> {
+        let text = canonicalize(self.canon, text);
+        match self.split {
+            Split::Alnum => tokenize(&text),
+            split => segment(split, &text)
+                .into_iter()
+                .filter(|t| t.chars().any(char::is_alphanumeric))
+                .map(|t| match self.canon {
+                    Canon::Lower => t.to_lowercase(),
+                    _ => t,
+                })
+                .collect(),
+        }
+    }
+
+    /// Terms of `text` as [`Self::analyze`] finds them, each with the
+    /// byte range of `text` it was cut from, for highlighting. Tokens
+    /// are split from the text as written and analyzed one at a time,
+    /// so a canonicalizer that changes lengths still maps back.
+    pub(crate) fn spans(&self, text: &str) -> Vec<(Range<usize>, String)> {
+        let tokens: Vec<String> = match self.split {
+            Split::Alnum => text
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            split => segment(split, text),
+        };
+        let mut out = Vec::new();
+        let mut from = 0;
+        for token in tokens {
+            // Segmenters hand back owned tokens; find each after the last.
+            let Some(at) = text[from..].find(token.as_str()) else {
+                continue;
+            };
+            let range = from + at..from + at + token.len();
+            from = range.end;
+            for term in self.analyze(&token) {
+                out.push((range.clone(), term));
+            }
+        }
+        out
+    }
+
+    fn stopword_set(&self, words: &[impl AsRef<str>]) -> HashSet<String> {
+        words.iter().flat_map(|w| self.split(w.as_ref())).collect()
+    }
+}
+
+impl FromStr for Analyzer {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        Self::parse(spec)
+    }
+}
+
+impl fmt::Display for Analyzer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.canon {
+            Canon::Lower => "lower",
+            Canon::Nfkc => "nfkc",
+            Canon::Confusable => "confusable",
+        })?;
+        f.write_str(match self.split {
+            Split::Alnum => "+alnum",
+            Split::Word => "+word",
+            Split::CjkJp => "+cjk-jp",
+            Split::CjkKo => "+cjk-ko",
+        })?;
+        if let Some(stop) = &self.stop {
+            write!(f, "+stop-{}", stop.name)?;
+        }
+        if let Some((name, _)) = self.stem {
+            write!(f, "+stem-{name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Fill `slot` with `value`; true when a step had already filled it.
+fn once<T>(slot: &mut Option<T>, value: T) -> bool {
+    slot.replace(value).is_some()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(feature = "text")]
+fn canonicalize(canon: Canon, text: &str) -> Cow<'_, str> {
+    match canon {
+        Canon::Lower => Cow::Borrowed(text),
+        Canon::Nfkc => Cow::Owned(txtfp::Canonicalizer::default().canonicalize(text)),
+        Canon::Confusable => Cow::Owned(
+            txtfp::CanonicalizerBuilder {
+                apply_confusable: true,
+                ..Default::default()
+            }
+            .build()
+            .canonicalize(text),
+        ),
+    }
+}
+
+/// Without `text` only `lower` parses, and it leaves the text as is.
+#[cfg(not(feature = "text"))]
+fn canonicalize(_canon: Canon, text: &str) -> Cow<'_, str> {
+    Cow::Borrowed(text)
+}
+
+#[cfg(feature = "text")]
+fn segment(split: Split, text: &str) -> Vec<String> {
+    use txtfp::Tokenizer;
+    let mut out = Vec::new();
+    let mut push = |t: &str| out.push(t.to_string());
+    match split {
+        Split::Alnum | Split::Word => txtfp::WordTokenizer.for_each_token(text, &mut push),
+        #[cfg(feature = "text-cjk-japanese")]
+        Split::CjkJp => {
+            txtfp::CjkTokenizer::new(txtfp::CjkSegmenter::Lindera).for_each_token(text, &mut push)
+        }
+        #[cfg(feature = "text-cjk-korean")]
+        Split::CjkKo => txtfp::CjkTokenizer::new(txtfp::CjkSegmenter::LinderaKoDic)
+            .for_each_token(text, &mut push),
+        // `parse` refuses the segmenters this build lacks.
+        #[allow(unreachable_patterns)]
+        _ => txtfp::WordTokenizer.for_each_token(text, &mut push),
+    }
+    out
+}
+
+/// Without `text` only `alnum` parses, which never gets here.
+#[cfg(not(feature = "text"))]
+fn segment(_split: Split, text: &str) -> Vec<String> {
+    tokenize(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_the_original_tokenizer() {
+        let text = "Hello, World!  It's GREAT — naïve café 42";
+        assert_eq!(DEFAULT.analyze(text), tokenize(text));
+        assert_eq!(DEFAULT.identity(), "lower+alnum");
+        assert_eq!(Analyzer::parse("lower").unwrap().identity(), "lower+alnum");
+    }
+
+    #[test]
+    fn stops_and_stems() {
+        let a = Analyzer::parse("stem-english+stop-en").unwrap();
+        assert_eq!(a.identity(), "lower+alnum+stop-en+stem-english");
+        assert_eq!(
+            a.analyze("The contracts of the Contractor were signed"),
+            ["contract", "contractor", "were", "sign"]
+        );
+        assert_eq!(a.split("The contracts"), ["the", "contracts"]);
+
+        let custom = Analyzer::default().with_stopwords(["Were", "signed"]);
+        assert_eq!(custom.analyze("were signed today"), ["today"]);
+        assert!(custom.identity().starts_with("lower+alnum+stop-"));
+        let same = Analyzer::default().with_stopwords(["signed", "were"]);
+        assert_eq!(custom.identity(), same.identity());
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        for bad in ["lower+nope", "stem-klingon", "alnum+alnum", "lower+nfkc"] {
+            assert!(
+                matches!(Analyzer::parse(bad), Err(Error::Modality(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn canonicalizes_with_txtfp() {
+        let a = Analyzer::parse("nfkc+word").unwrap();
+        assert_eq!(a.identity(), "nfkc+word");
+        // Full-width letters fold to ASCII and zero-width spaces go.
+        assert_eq!(
+            a.analyze("ＦＵＬＬ wid\u{200B}th, v1.2"),
+            ["full", "width", "v1.2"]
+        );
+    }
+
+    #[test]
+    fn spans_map_terms_back_to_the_text() {
+        let a = Analyzer::parse("stem-english+stop-en").unwrap();
+        let text = "The Contracts, signed.";
+        let spans = a.spans(text);
+        let terms: Vec<&str> = spans.iter().map(|(_, t)| t.as_str()).collect();
+        assert_eq!(terms, a.analyze(text));
+        assert_eq!(&text[spans[0].0.clone()], "Contracts");
+        assert_eq!(&text[spans[1].0.clone()], "signed");
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn spans_survive_canonicalization() {
+        let a = Analyzer::parse("nfkc+word").unwrap();
+        let text = "ＦＵＬＬ width";
+        let spans = a.spans(text);
+        assert_eq!(spans[0], (0..12, "full".to_string()));
+        assert_eq!(&text[spans[1].0.clone()], "width");
+    }
+
+    #[cfg(feature = "text-cjk-japanese")]
+    #[test]
+    fn segments_japanese() {
+        let a = Analyzer::parse("nfkc+cjk-jp").unwrap();
+        let terms = a.analyze("東京都に住んでいる。");
+        assert!(terms.len() > 1, "{terms:?}");
+        assert!(!terms.iter().any(|t| t == "。"), "{terms:?}");
+        // The default analyzer keeps the run of Japanese as one token.
+        assert_eq!(DEFAULT.analyze("東京都に住んでいる。").len(), 1);
+    }
+}
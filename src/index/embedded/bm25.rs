@@ -2,37 +2,109 @@
 //!
 //! Per ARCHITECTURE §4 the day-one keyword search is BM25 backed by an
 //! `fst::Map<term, term_id>` for the dictionary plus redb tables for
-//! postings, doc lengths, and corpus stats. No tantivy — phrase / fuzzy
-//! / regex queries are explicit non-goals at this scale.
+//! postings, doc lengths, and corpus stats. No tantivy — regex queries
+//! are explicit non-goals at this scale; prefix and fuzzy terms expand
+//! over the dictionary (see [`super::vocab`]), and phrase and proximity
+//! queries run off an opt-in per-tenant positions table.
 //!
 //! All updates run inside the *same* redb write transaction as the
-//! fingerprint upsert, so the index never lags behind the catalog.
+//! fingerprint upsert, so the index never lags behind the catalog. A
+//! transaction collects its documents in an [`IndexBatch`] and applies
+//! them together: each touched term's rows are rewritten once and the
+//! dictionary at most once, however many documents the batch holds.
 //!
 //! ## Layout
 //!
 //! | Table                          | Key                | Value                                         |
 //! | ------------------------------ | ------------------ | --------------------------------------------- |
 //! | `ucfp/bm25/term_fst/v1`        | `tenant_id`        | serialized `fst::Map<term, term_id>`          |
-//! | `ucfp/bm25/postings/v1`        | `(tenant, term_id)`| serialized `RoaringTreemap<doc_id>`           |
-//! | `ucfp/bm25/scoring/v1`         | `(tenant, term_id)`| packed `[doc_id u64 le \|\| tf u32 le]*`      |
+//! | `ucfp/bm25/blocks/v1`          | `(tenant, term_id, last doc_id)` | posting block (see [`super::postings`]) |
+//! | `ucfp/bm25/positions/v1`       | `(tenant, term_id, doc_id)` | token positions (see [`super::positions`]) |
 //! | `ucfp/bm25/doc_lens/v1`        | `(tenant, doc_id)` | `u32` term count                              |
 //! | `ucfp/bm25/corpus/v1`          | `tenant_id`        | `CorpusStats` (doc_count, total_len, next_id) |
+//! | `ucfp/bm25/analyzer/v1`        | `tenant_id`        | identity of the analyzer the index was built with |
+//! | `ucfp/bm25/field_lens/v1`      | `(tenant, doc_id)` | JSON `{field: term count}` of the doc's named fields |
+//! | `ucfp/bm25/fields/v1`          | `tenant_id`        | JSON `{field: total term count}` over the tenant |
+//!
+//! A record's named text fields ([`Record::text_fields`](crate::core::Record::text_fields))
+//! share the tenant's dictionary: a term of field `title` is the key
+//! `title␟term` (U+001F between them), while `text` terms stay bare.
+//! `doc_lens` and `corpus` keep counting only `text`.
+//!
+//! Text becomes terms through the tenant's [`Analyzer`]; the same one
+//! runs over record text at index time and over query text here.
+//!
+//! ## Query syntax
+//!
+//! Bare words are OR'ed terms, each analyzed like record text (so a
+//! stemmed tenant finds `contracts` for `contract`). `"force majeure"` matches the tokens as
+//! an exact phrase, and `a NEAR/n b` matches `a` within `n` tokens of `b`
+//! on either side, where either operand may be a quoted phrase. A chain
+//! `a NEAR/2 b NEAR/3 c` is two clauses sharing `b`. Phrase and `NEAR`
+//! clauses need positions, so they are refused for tenants that do not
+//! store them.
+//!
+//! A word ending in `*` is a prefix, and one ending in `~`, `~1` or `~2`
+//! matches terms within that many edits (two for a bare `~`), matched
+//! against the dictionary unstemmed and with stopwords kept. Each term
+//! it expands to is searched as an OR'ed term, down-weighted, and named
+//! with its `expanded_from` query term in explain output. Expanded terms
+//! cannot be `NEAR` operands.
+//!
+//! `title:rust`, `title:"force majeure"` and `title:contr*` restrict a
+//! clause to one field; `text:` names the record text. Unqualified
+//! clauses search every field. A prefix that names no field the tenant
+//! has indexed or configured is read as part of the word. `NEAR`
+//! operands must both sit in the same field.
 //!
 //! ## Scoring
 //!
-//! Standard Okapi BM25 with `k1 = 1.2`, `b = 0.75`. The IDF uses the
-//! BM25+ smoothing `ln((N − n + 0.5) / (n + 0.5) + 1)` to keep scores
-//! non-negative.
-
+//! BM25F: a clause's frequency in each field it matches is
+//! length-normalised against that field's average length with the
+//! field's `b`, scaled by the field's boost (see [`Bm25Field`]), and the
+//! sum saturates once with `k1`. With only `text` this is standard Okapi
+//! BM25. `k1`, the default `b` and the model come from the tenant's
+//! [`Bm25Params`] (`k1 = 1.2`, `b = 0.75`, BM25 unless configured), or
+//! from the query itself:
+//!
+//! | Model       | Contribution of pseudo-frequency `t`        | IDF                             |
+//! | ----------- | ------------------------------------------- | ------------------------------- |
+//! | `bm25`      | `idf · t(k1 + 1) / (k1 + t)`                | `ln((N − n + 0.5) / (n + 0.5) + 1)` |
+//! | `bm25l`     | the same with `t + δ` for `t`               | `ln((N + 1) / (n + 0.5))`       |
+//! | `bm25-plus` | `idf · (t(k1 + 1) / (k1 + t) + δ)`          | `ln((N + 1) / n)`               |
+//! | `tf-idf`    | `idf · ln(1 + t)`, with `b = 0`             | `ln(1 + N / n)`                 |
+//!
+//! Every IDF is non-negative; `n` is the largest document frequency
+//! among the fields matched. Explain output splits a clause's
+//! contribution across its fields. A phrase or `NEAR` clause scores like
+//! one term whose frequency is its number of matches in the doc and
+//! whose IDF is the sum of its terms' IDFs; explain output lists each of
+//! its terms with its share.
+//!
+//! Top-k is evaluated with Block-Max WAND: the query terms' cursors walk
+//! their posting lists in doc order, and a document is only scored when
+//! the per-block score bounds of the terms it could match add up to more
+//! than the current k-th best score. Blocks below that are skipped
+//! without being decoded, so a query that pairs a rare term with a
+//! common one reads a few blocks of the common term, not all of it.
+//! Phrase and `NEAR` clauses are matched up front and join the walk as
+//! one block of matches each.
+
+use std::cmp::{Ordering, Reverse};
 use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::io::Cursor;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
 
 use fst::{IntoStreamer, Map as FstMap, MapBuilder, Streamer};
 use redb::{ReadableTable, TableDefinition, WriteTransaction};
-use roaring::RoaringTreemap;
 
-use crate::core::{Hit, HitSource};
+use super::analyzer::{self, Analyzer};
+use super::positions;
+use super::postings::{self, BlockKey, Posting, TermCursor};
+use super::vocab::{self, Expansion};
+use crate::core::{
+    Bm25Field, Bm25Model, Bm25Params, Correction, Hit, HitSource, TermHit, TermSuggestion,
+};
 use crate::error::{Error, Result};
 
 // ── Tables ──────────────────────────────────────────────────────────────
@@ -40,11 +112,11 @@ use crate::error::{Error, Result};
 pub(super) const BM25_TERM_FST: TableDefinition<'_, u32, &[u8]> =
     TableDefinition::new("ucfp/bm25/term_fst/v1");
 
-pub(super) const BM25_POSTINGS: TableDefinition<'_, (u32, u64), &[u8]> =
-    TableDefinition::new("ucfp/bm25/postings/v1");
+pub(super) const BM25_BLOCKS: TableDefinition<'_, BlockKey, &[u8]> =
+    TableDefinition::new("ucfp/bm25/blocks/v1");
 
-pub(super) const BM25_SCORING: TableDefinition<'_, (u32, u64), &[u8]> =
-    TableDefinition::new("ucfp/bm25/scoring/v1");
+pub(super) const BM25_POSITIONS: TableDefinition<'_, BlockKey, &[u8]> =
+    TableDefinition::new("ucfp/bm25/positions/v1");
 
 pub(super) const BM25_DOC_LENS: TableDefinition<'_, (u32, u64), u32> =
     TableDefinition::new("ucfp/bm25/doc_lens/v1");
@@ -55,7 +127,59 @@ pub(super) const BM25_CORPUS: TableDefinition<'_, u32, &[u8]> =
 pub(super) const BM25_DOC_TERMS: TableDefinition<'_, (u32, u64), &[u8]> =
     TableDefinition::new("ucfp/bm25/doc_terms/v1");
 
-fn pack_term_ids(tids: &[u64]) -> Vec<u8> {
+pub(super) const BM25_ANALYZER: TableDefinition<'_, u32, &str> =
+    TableDefinition::new("ucfp/bm25/analyzer/v1");
+
+pub(super) const BM25_FIELD_LENS: TableDefinition<'_, (u32, u64), &[u8]> =
+    TableDefinition::new("ucfp/bm25/field_lens/v1");
+
+pub(super) const BM25_FIELDS: TableDefinition<'_, u32, &[u8]> =
+    TableDefinition::new("ucfp/bm25/fields/v1");
+
+/// Name of the field [`Record::text`](crate::core::Record::text) is
+/// indexed as.
+pub(crate) const TEXT_FIELD: &str = "text";
+
+/// Separates a named field from its term in a dictionary key.
+pub(crate) const FIELD_SEP: char = '\u{1f}';
+
+/// Dictionary key of `term` in `field`: the term itself in
+/// [`TEXT_FIELD`], `field␟term` in a named field.
+pub(crate) fn field_key(field: &str, term: &str) -> String {
+    if field == TEXT_FIELD {
+        term.to_string()
+    } else {
+        format!("{field}{FIELD_SEP}{term}")
+    }
+}
+
+/// The field and term of a dictionary key; see [`field_key`].
+pub(crate) fn split_key(key: &str) -> (&str, &str) {
+    key.split_once(FIELD_SEP).unwrap_or((TEXT_FIELD, key))
+}
+
+/// Whether `name` can name a [`Record::text_fields`](crate::core::Record::text_fields)
+/// entry: lowercase ASCII letters, digits and `_`, starting with a
+/// letter, and not [`TEXT_FIELD`].
+pub(crate) fn is_field_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && name != TEXT_FIELD
+}
+
+/// [`Error::Modality`] naming the first of `fields` with an invalid name.
+pub(crate) fn check_field_names(fields: &BTreeMap<String, String>) -> Result<()> {
+    match fields.keys().find(|name| !is_field_name(name)) {
+        Some(name) => Err(Error::Modality(format!(
+            "text field `{name}`: names are lowercase letters, digits and `_`, \
+             start with a letter, and `{TEXT_FIELD}` is reserved"
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn pack_term_ids(tids: &[u64]) -> Vec<u8> {
     let mut out = Vec::with_capacity(tids.len() * 8);
     for t in tids {
         out.extend_from_slice(&t.to_le_bytes());
@@ -63,7 +187,7 @@ fn pack_term_ids(tids: &[u64]) -> Vec<u8> {
     out
 }
 
-fn unpack_term_ids(b: &[u8]) -> Vec<u64> {
+pub(crate) fn unpack_term_ids(b: &[u8]) -> Vec<u64> {
     let mut out = Vec::with_capacity(b.len() / 8);
     let mut i = 0;
     while i + 8 <= b.len() {
@@ -75,17 +199,20 @@ fn unpack_term_ids(b: &[u8]) -> Vec<u64> {
     out
 }
 
-// BM25 hyper-params (Robertson/Spärck Jones 1995 defaults).
+// BM25 hyper-params (Robertson/Spärck Jones 1995 defaults) for
+// FjallBackend, which has no per-tenant parameters.
+#[cfg_attr(not(feature = "fjall"), allow(dead_code))]
 const K1: f32 = 1.2;
+#[cfg_attr(not(feature = "fjall"), allow(dead_code))]
 const B: f32 = 0.75;
 
 // ── Tokenizer ───────────────────────────────────────────────────────────
 //
 // Lowercase + split on non-alphanumeric. Good enough for tags/titles per
-// ARCHITECTURE §4. Linguistic / phrase / fuzzy queries are out of scope
-// — promote to tantivy if those become product requirements.
+// ARCHITECTURE §4, and the default analyzer; tenants needing more get
+// one of their own (see `analyzer`).
 
-pub(super) fn tokenize(s: &str) -> Vec<String> {
+pub(crate) fn tokenize(s: &str) -> Vec<String> {
     let mut out = Vec::new();
     for chunk in s.split(|c: char| !c.is_alphanumeric()) {
         if chunk.is_empty() {
@@ -99,14 +226,14 @@ pub(super) fn tokenize(s: &str) -> Vec<String> {
 // ── Corpus stats ────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Copy, Default)]
-pub(super) struct CorpusStats {
+pub(crate) struct CorpusStats {
     pub doc_count: u64,
     pub total_doc_len: u64,
     pub next_term_id: u64,
 }
 
 impl CorpusStats {
-    fn pack(&self) -> [u8; 24] {
+    pub(crate) fn pack(&self) -> [u8; 24] {
         let mut buf = [0u8; 24];
         buf[..8].copy_from_slice(&self.doc_count.to_le_bytes());
         buf[8..16].copy_from_slice(&self.total_doc_len.to_le_bytes());
@@ -114,7 +241,7 @@ impl CorpusStats {
         buf
     }
 
-    fn unpack(b: &[u8]) -> Self {
+    pub(crate) fn unpack(b: &[u8]) -> Self {
         if b.len() != 24 {
             return Self::default();
         }
@@ -131,7 +258,7 @@ impl CorpusStats {
         }
     }
 
-    fn avgdl(&self) -> f32 {
+    pub(crate) fn avgdl(&self) -> f32 {
         if self.doc_count == 0 {
             return 0.0;
         }
@@ -139,31 +266,6 @@ impl CorpusStats {
     }
 }
 
-// ── Scoring postings: packed [doc_id u64 LE || tf u32 LE]* ──────────────
-
-fn pack_scoring(entries: &[(u64, u32)]) -> Vec<u8> {
-    let mut out = Vec::with_capacity(entries.len() * 12);
-    for (doc, tf) in entries {
-        out.extend_from_slice(&doc.to_le_bytes());
-        out.extend_from_slice(&tf.to_le_bytes());
-    }
-    out
-}
-
-fn unpack_scoring(b: &[u8]) -> Vec<(u64, u32)> {
-    let mut out = Vec::with_capacity(b.len() / 12);
-    let mut i = 0;
-    while i + 12 <= b.len() {
-        let mut d = [0u8; 8];
-        d.copy_from_slice(&b[i..i + 8]);
-        let mut t = [0u8; 4];
-        t.copy_from_slice(&b[i + 8..i + 12]);
-        out.push((u64::from_le_bytes(d), u32::from_le_bytes(t)));
-        i += 12;
-    }
-    out
-}
-
 // ── Read helpers ────────────────────────────────────────────────────────
 
 pub(super) fn read_corpus(txn: &WriteTransaction, tenant_id: u32) -> Result<CorpusStats> {
@@ -189,10 +291,20 @@ pub(super) fn read_term_dict(
     let row = table
         .get(tenant_id)
         .map_err(|e| Error::Index(e.to_string()))?;
-    let bytes = match row {
-        Some(v) => v.value().to_vec(),
-        None => return Ok(BTreeMap::new()),
-    };
+    match row {
+        Some(v) => decode_term_dict(v.value().to_vec()),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Number of terms in a serialized `fst::Map<term, term_id>`.
+pub(crate) fn vocabulary_size(bytes: Vec<u8>) -> Result<u64> {
+    let map = FstMap::new(bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
+    Ok(map.len() as u64)
+}
+
+/// Decode a serialized `fst::Map<term, term_id>` into an ordered dict.
+pub(crate) fn decode_term_dict(bytes: Vec<u8>) -> Result<BTreeMap<String, u64>> {
     let map = FstMap::new(bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
     let mut out = BTreeMap::new();
     let mut stream = map.into_stream();
@@ -204,43 +316,6 @@ pub(super) fn read_term_dict(
     Ok(out)
 }
 
-fn read_postings(txn: &WriteTransaction, tenant_id: u32, term_id: u64) -> Result<RoaringTreemap> {
-    let table = txn
-        .open_table(BM25_POSTINGS)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    let row = table
-        .get((tenant_id, term_id))
-        .map_err(|e| Error::Index(e.to_string()))?;
-    match row {
-        Some(v) => RoaringTreemap::deserialize_from(Cursor::new(v.value()))
-            .map_err(|e| Error::Index(format!("roaring deser: {e}"))),
-        None => Ok(RoaringTreemap::new()),
-    }
-}
-
-fn read_scoring(txn: &WriteTransaction, tenant_id: u32, term_id: u64) -> Result<Vec<(u64, u32)>> {
-    let table = txn
-        .open_table(BM25_SCORING)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    let row = table
-        .get((tenant_id, term_id))
-        .map_err(|e| Error::Index(e.to_string()))?;
-    Ok(match row {
-        Some(v) => unpack_scoring(v.value()),
-        None => Vec::new(),
-    })
-}
-
-fn read_doc_len(txn: &WriteTransaction, tenant_id: u32, doc_id: u64) -> Result<Option<u32>> {
-    let table = txn
-        .open_table(BM25_DOC_LENS)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    Ok(table
-        .get((tenant_id, doc_id))
-        .map_err(|e| Error::Index(e.to_string()))?
-        .map(|v| v.value()))
-}
-
 // ── Write helpers ───────────────────────────────────────────────────────
 
 fn write_term_dict(
@@ -248,6 +323,18 @@ fn write_term_dict(
     tenant_id: u32,
     dict: &BTreeMap<String, u64>,
 ) -> Result<()> {
+    let buf = encode_term_dict(dict)?;
+    let mut table = txn
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    table
+        .insert(tenant_id, buf.as_slice())
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
+
+/// Serialize `dict` as an `fst::Map<term, term_id>`.
+pub(crate) fn encode_term_dict(dict: &BTreeMap<String, u64>) -> Result<Vec<u8>> {
     let mut buf = Vec::new();
     let mut builder =
         MapBuilder::new(&mut buf).map_err(|e| Error::Index(format!("fst builder: {e}")))?;
@@ -260,319 +347,1696 @@ fn write_term_dict(
     builder
         .finish()
         .map_err(|e| Error::Index(format!("fst finish: {e}")))?;
+    Ok(buf)
+}
+
+fn write_corpus(txn: &WriteTransaction, tenant_id: u32, stats: &CorpusStats) -> Result<()> {
     let mut table = txn
-        .open_table(BM25_TERM_FST)
+        .open_table(BM25_CORPUS)
         .map_err(|e| Error::Index(e.to_string()))?;
     table
-        .insert(tenant_id, buf.as_slice())
+        .insert(tenant_id, stats.pack().as_slice())
         .map_err(|e| Error::Index(e.to_string()))?;
     Ok(())
 }
 
-fn write_postings(
+// ── Batched updates ─────────────────────────────────────────────────────
+
+/// Tenants whose term dictionary [`TermDictCache`] keeps at most.
+const DICT_CACHE_TENANTS: usize = 256;
+
+/// Term dictionaries of recently written tenants, kept across write
+/// transactions. An entry is only used while it is byte-for-byte the FST
+/// stored in the transaction at hand, so writers that go around the
+/// cache (fsck repair, reindex, pruning) cost a reload, never a stale
+/// term id.
+#[derive(Default)]
+pub(crate) struct TermDictCache(Mutex<HashMap<u32, Arc<FstMap<Vec<u8>>>>>);
+
+impl TermDictCache {
+    /// The dictionary of `tenant_id` whose serialized form is `stored`,
+    /// decoded again only when the cached one differs.
+    pub(crate) fn get(&self, tenant_id: u32, stored: &[u8]) -> Result<Arc<FstMap<Vec<u8>>>> {
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dict) = cache.get(&tenant_id)
+            && dict.as_fst().as_bytes() == stored
+        {
+            return Ok(dict.clone());
+        }
+        let dict = Arc::new(
+            FstMap::new(stored.to_vec()).map_err(|e| Error::Index(format!("fst load: {e}")))?,
+        );
+        Self::insert(&mut cache, tenant_id, dict.clone());
+        Ok(dict)
+    }
+
+    /// Remember `bytes` as the dictionary just written for `tenant_id`.
+    pub(crate) fn store(&self, tenant_id: u32, bytes: Vec<u8>) -> Result<()> {
+        let dict = FstMap::new(bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        Self::insert(&mut cache, tenant_id, Arc::new(dict));
+        Ok(())
+    }
+
+    fn insert(
+        cache: &mut HashMap<u32, Arc<FstMap<Vec<u8>>>>,
+        tenant_id: u32,
+        dict: Arc<FstMap<Vec<u8>>>,
+    ) {
+        if cache.len() >= DICT_CACHE_TENANTS && !cache.contains_key(&tenant_id) {
+            cache.clear();
+        }
+        cache.insert(tenant_id, dict);
+    }
+}
+
+/// Term frequencies and lengths of one document's text and named
+/// fields, plus each term's token positions when its tenant stores them.
+pub(crate) struct DocTerms {
+    /// Keyed by dictionary key (see [`field_key`]).
+    pub tf: BTreeMap<String, u32>,
+    /// Token count of the text.
+    pub len: u32,
+    /// Token count of each named field that has tokens.
+    pub field_lens: BTreeMap<String, u32>,
+    pub positions: Option<BTreeMap<String, Vec<u32>>>,
+}
+
+impl DocTerms {
+    /// Analyze `text` and `fields`, keeping positions when `positional`
+    /// is set.
+    pub(crate) fn new(
+        text: &str,
+        fields: &BTreeMap<String, String>,
+        analyzer: &Analyzer,
+        positional: bool,
+    ) -> Self {
+        let mut out = Self {
+            tf: BTreeMap::new(),
+            len: 0,
+            field_lens: BTreeMap::new(),
+            positions: positional.then(BTreeMap::new),
+        };
+        out.len = out.add(TEXT_FIELD, text, analyzer);
+        for (name, value) in fields {
+            let len = out.add(name, value, analyzer);
+            if len > 0 {
+                out.field_lens.insert(name.clone(), len);
+            }
+        }
+        out
+    }
+
+    /// Count the tokens of `text` under `field`; returns their number.
+    fn add(&mut self, field: &str, text: &str, analyzer: &Analyzer) -> u32 {
+        let tokens = analyzer.analyze(text);
+        let len = u32::try_from(tokens.len()).unwrap_or(u32::MAX);
+        for (at, tok) in tokens.into_iter().enumerate() {
+            let key = field_key(field, &tok);
+            if let Some(positions) = &mut self.positions {
+                let at = u32::try_from(at).unwrap_or(u32::MAX);
+                positions.entry(key.clone()).or_default().push(at);
+            }
+            *self.tf.entry(key).or_insert(0u32) += 1;
+        }
+        len
+    }
+
+    /// Token count of the field the dictionary key `key` belongs to.
+    pub(crate) fn dl(&self, key: &str) -> u32 {
+        match split_key(key) {
+            (TEXT_FIELD, _) => self.len,
+            (field, _) => self.field_lens.get(field).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// BM25 changes collected over one write transaction. Documents are
+/// queued with [`IndexBatch::index`] / [`IndexBatch::clear`] (the last
+/// call per document wins) and [`IndexBatch::apply`] then reads and
+/// writes every touched row once: the posting blocks holding the batch's
+/// documents, once per term, one dictionary rewrite when new terms
+/// appeared, one corpus stats row per tenant.
+#[derive(Default)]
+pub(crate) struct IndexBatch {
+    docs: BTreeMap<u32, BTreeMap<u64, Option<DocTerms>>>,
+    positional: Arc<BTreeSet<u32>>,
+    analyzers: Arc<BTreeMap<u32, Analyzer>>,
+}
+
+impl IndexBatch {
+    /// A batch that also records token positions for `tenants`.
+    pub(crate) fn with_positions(tenants: Arc<BTreeSet<u32>>) -> Self {
+        Self {
+            positional: tenants,
+            ..Self::default()
+        }
+    }
+
+    /// Analyze the text of the tenants in `analyzers` with theirs rather
+    /// than [`analyzer::DEFAULT`].
+    pub(crate) fn with_analyzers(mut self, analyzers: Arc<BTreeMap<u32, Analyzer>>) -> Self {
+        self.analyzers = analyzers;
+        self
+    }
+
+    /// Index (or re-index) `record_id` with `text`. Re-indexing replaces
+    /// the prior tf contribution rather than double-counting.
+    #[cfg_attr(not(feature = "fjall"), allow(dead_code))] // FjallBackend has no fields
+    pub(crate) fn index(&mut self, tenant_id: u32, record_id: u64, text: &str) {
+        self.index_fields(tenant_id, record_id, text, &BTreeMap::new());
+    }
+
+    /// [`Self::index`] with named text fields beside `text`.
+    pub(crate) fn index_fields(
+        &mut self,
+        tenant_id: u32,
+        record_id: u64,
+        text: &str,
+        fields: &BTreeMap<String, String>,
+    ) {
+        let terms = DocTerms::new(
+            text,
+            fields,
+            tenant_analyzer(&self.analyzers, tenant_id),
+            self.positional.contains(&tenant_id),
+        );
+        self.docs
+            .entry(tenant_id)
+            .or_default()
+            .insert(record_id, Some(terms));
+    }
+
+    /// Remove `record_id`'s contribution, if it has one.
+    pub(crate) fn clear(&mut self, tenant_id: u32, record_id: u64) {
+        self.docs
+            .entry(tenant_id)
+            .or_default()
+            .insert(record_id, None);
+    }
+
+    /// The queued documents, per tenant: new terms, or `None` to clear.
+    pub(crate) fn into_tenants(self) -> BTreeMap<u32, BTreeMap<u64, Option<DocTerms>>> {
+        self.docs
+    }
+
+    /// Write the queued changes into `txn`. Caller commits.
+    pub(super) fn apply(self, txn: &WriteTransaction, dicts: &TermDictCache) -> Result<()> {
+        let analyzers = self.analyzers.clone();
+        for (tenant_id, docs) in self.into_tenants() {
+            let analyzer = tenant_analyzer(&analyzers, tenant_id);
+            apply_tenant(txn, dicts, tenant_id, docs, analyzer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The analyzer `tenant_id` is configured with in `analyzers`.
+pub(crate) fn tenant_analyzer(analyzers: &BTreeMap<u32, Analyzer>, tenant_id: u32) -> &Analyzer {
+    analyzers.get(&tenant_id).unwrap_or(&analyzer::DEFAULT)
+}
+
+/// Field weights of a tenant configured with none.
+static NO_FIELDS: BTreeMap<String, Bm25Field> = BTreeMap::new();
+
+/// The BM25F field weights `tenant_id` is configured with in `fields`.
+pub(crate) fn tenant_fields(
+    fields: &BTreeMap<u32, BTreeMap<String, Bm25Field>>,
+    tenant_id: u32,
+) -> &BTreeMap<String, Bm25Field> {
+    fields.get(&tenant_id).unwrap_or(&NO_FIELDS)
+}
+
+fn apply_tenant(
     txn: &WriteTransaction,
+    dicts: &TermDictCache,
     tenant_id: u32,
-    term_id: u64,
-    bm: &RoaringTreemap,
+    docs: BTreeMap<u64, Option<DocTerms>>,
+    analyzer: &Analyzer,
 ) -> Result<()> {
-    let mut buf = Vec::with_capacity(bm.serialized_size());
-    bm.serialize_into(&mut buf)
-        .map_err(|e| Error::Index(format!("roaring ser: {e}")))?;
-    let mut table = txn
-        .open_table(BM25_POSTINGS)
+    let dict = match txn
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => Some(dicts.get(tenant_id, v.value())?),
+        None => None,
+    };
+    let mut corpus = read_corpus(txn, tenant_id)?;
+    // An empty index takes on the analyzer of its first documents.
+    let fresh = corpus.doc_count == 0;
+    let mut corpus_changed = false;
+    let mut totals = read_field_totals(txn, tenant_id)?;
+    let mut totals_changed = false;
+    let mut new_terms: BTreeMap<String, u64> = BTreeMap::new();
+    let mut removals: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+    let mut additions: BTreeMap<u64, Vec<Posting>> = BTreeMap::new();
+    {
+        let mut doc_terms = txn
+            .open_table(BM25_DOC_TERMS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut doc_lens = txn
+            .open_table(BM25_DOC_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut doc_positions = txn
+            .open_table(BM25_POSITIONS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut field_lens = txn
+            .open_table(BM25_FIELD_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        // Old positions go with their doc even once a tenant stops
+        // storing them; a tenant that never did skips the deletes.
+        let positional = docs.values().flatten().any(|d| d.positions.is_some())
+            || positions::any(&doc_positions, tenant_id)?;
+        for (doc, next) in docs {
+            let prev_len = doc_lens
+                .get((tenant_id, doc))
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|v| v.value());
+            if let Some(prev_len) = prev_len {
+                let tids = match doc_terms
+                    .get((tenant_id, doc))
+                    .map_err(|e| Error::Index(e.to_string()))?
+                {
+                    Some(v) => unpack_term_ids(v.value()),
+                    // Indexed before doc_terms existed: look everywhere.
+                    None => dict.as_deref().map_or_else(Vec::new, all_term_ids),
+                };
+                for tid in tids {
+                    removals.entry(tid).or_default().insert(doc);
+                    if positional {
+                        doc_positions
+                            .remove((tenant_id, tid, doc))
+                            .map_err(|e| Error::Index(e.to_string()))?;
+                    }
+                }
+                corpus.doc_count = corpus.doc_count.saturating_sub(1);
+                corpus.total_doc_len = corpus.total_doc_len.saturating_sub(u64::from(prev_len));
+                corpus_changed = true;
+                let prev_fields = field_lens
+                    .remove((tenant_id, doc))
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map(|v| decode_lens::<u32>(v.value()))
+                    .transpose()?;
+                for (name, len) in prev_fields.into_iter().flatten() {
+                    if let Some(total) = totals.get_mut(&name) {
+                        *total = total.saturating_sub(u64::from(len));
+                        if *total == 0 {
+                            totals.remove(&name);
+                        }
+                    }
+                    totals_changed = true;
+                }
+            }
+            let Some(mut next) = next else {
+                if prev_len.is_some() {
+                    doc_terms
+                        .remove((tenant_id, doc))
+                        .map_err(|e| Error::Index(e.to_string()))?;
+                    doc_lens
+                        .remove((tenant_id, doc))
+                        .map_err(|e| Error::Index(e.to_string()))?;
+                }
+                continue;
+            };
+            let mut tids = Vec::with_capacity(next.tf.len());
+            for (term, tf) in std::mem::take(&mut next.tf) {
+                let at = next.positions.as_mut().and_then(|p| p.remove(&term));
+                let dl = next.dl(&term);
+                let tid = term_id(dict.as_deref(), &mut new_terms, &mut corpus, term)?;
+                tids.push(tid);
+                additions
+                    .entry(tid)
+                    .or_default()
+                    .push(Posting { doc, tf, dl });
+                if let Some(at) = at {
+                    doc_positions
+                        .insert((tenant_id, tid, doc), positions::encode(&at).as_slice())
+                        .map_err(|e| Error::Index(e.to_string()))?;
+                }
+            }
+            doc_terms
+                .insert((tenant_id, doc), pack_term_ids(&tids).as_slice())
+                .map_err(|e| Error::Index(e.to_string()))?;
+            doc_lens
+                .insert((tenant_id, doc), next.len)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            corpus.doc_count = corpus.doc_count.saturating_add(1);
+            corpus.total_doc_len = corpus.total_doc_len.saturating_add(u64::from(next.len));
+            corpus_changed = true;
+            if !next.field_lens.is_empty() {
+                field_lens
+                    .insert((tenant_id, doc), encode_lens(&next.field_lens)?.as_slice())
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                for (name, &len) in &next.field_lens {
+                    *totals.entry(name.clone()).or_default() += u64::from(len);
+                }
+                totals_changed = true;
+            }
+        }
+    }
+
+    let touched: BTreeSet<u64> = removals.keys().chain(additions.keys()).copied().collect();
+    {
+        let mut blocks = txn
+            .open_table(BM25_BLOCKS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for tid in touched {
+            let added = additions.remove(&tid).unwrap_or_default();
+            postings::update_term(&mut blocks, tenant_id, tid, removals.get(&tid), added)?;
+        }
+    }
+
+    if !new_terms.is_empty() {
+        let merged = merge_term_dict(dict.as_deref(), &new_terms)?;
+        txn.open_table(BM25_TERM_FST)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .insert(tenant_id, merged.as_slice())
+            .map_err(|e| Error::Index(e.to_string()))?;
+        dicts.store(tenant_id, merged)?;
+    }
+    if corpus_changed {
+        write_corpus(txn, tenant_id, &corpus)?;
+    }
+    if totals_changed {
+        write_field_totals(txn, tenant_id, &totals)?;
+    }
+    if fresh && corpus.doc_count > 0 {
+        write_analyzer(txn, tenant_id, analyzer)?;
+    }
+    Ok(())
+}
+
+/// Encode a doc's field lengths or a tenant's field totals.
+pub(crate) fn encode_lens<N: serde::Serialize>(lens: &BTreeMap<String, N>) -> Result<Vec<u8>> {
+    serde_json::to_vec(lens).map_err(|e| Error::Index(format!("field lengths encode: {e}")))
+}
+
+/// Decode what [`encode_lens`] wrote.
+pub(crate) fn decode_lens<N: serde::de::DeserializeOwned>(
+    raw: &[u8],
+) -> Result<BTreeMap<String, N>> {
+    serde_json::from_slice(raw).map_err(|e| Error::Index(format!("field lengths decode: {e}")))
+}
+
+/// Summed token count of each named field over `tenant_id`'s docs.
+pub(super) fn read_field_totals(
+    txn: &WriteTransaction,
+    tenant_id: u32,
+) -> Result<BTreeMap<String, u64>> {
+    let table = txn
+        .open_table(BM25_FIELDS)
         .map_err(|e| Error::Index(e.to_string()))?;
-    table
-        .insert((tenant_id, term_id), buf.as_slice())
+    let row = table
+        .get(tenant_id)
         .map_err(|e| Error::Index(e.to_string()))?;
-    Ok(())
+    match row {
+        Some(v) => decode_lens(v.value()),
+        None => Ok(BTreeMap::new()),
+    }
 }
 
-fn write_scoring(
+/// Store `totals` as `tenant_id`'s field totals; none removes the row.
+pub(super) fn write_field_totals(
     txn: &WriteTransaction,
     tenant_id: u32,
-    term_id: u64,
-    entries: &[(u64, u32)],
+    totals: &BTreeMap<String, u64>,
 ) -> Result<()> {
-    let buf = pack_scoring(entries);
     let mut table = txn
-        .open_table(BM25_SCORING)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    table
-        .insert((tenant_id, term_id), buf.as_slice())
+        .open_table(BM25_FIELDS)
         .map_err(|e| Error::Index(e.to_string()))?;
+    if totals.is_empty() {
+        table
+            .remove(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    } else {
+        table
+            .insert(tenant_id, encode_lens(totals)?.as_slice())
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
     Ok(())
 }
 
-fn write_doc_len(txn: &WriteTransaction, tenant_id: u32, doc_id: u64, len: u32) -> Result<()> {
-    let mut table = txn
-        .open_table(BM25_DOC_LENS)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    table
-        .insert((tenant_id, doc_id), len)
+/// Record `analyzer` as the one `tenant_id`'s index is built with.
+pub(super) fn write_analyzer(
+    txn: &WriteTransaction,
+    tenant_id: u32,
+    analyzer: &Analyzer,
+) -> Result<()> {
+    txn.open_table(BM25_ANALYZER)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .insert(tenant_id, analyzer.identity().as_str())
         .map_err(|e| Error::Index(e.to_string()))?;
     Ok(())
 }
 
-fn write_corpus(txn: &WriteTransaction, tenant_id: u32, stats: &CorpusStats) -> Result<()> {
-    let mut table = txn
+/// Identity of the analyzer each tenant with indexed documents was
+/// built with. Indexes from before the identity was stored used the
+/// default one.
+pub(super) fn stored_analyzers(db: &redb::Database) -> Result<BTreeMap<u32, String>> {
+    use redb::ReadableDatabase;
+    let read = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let corpus = read
         .open_table(BM25_CORPUS)
         .map_err(|e| Error::Index(e.to_string()))?;
-    table
-        .insert(tenant_id, stats.pack().as_slice())
+    let stored = read
+        .open_table(BM25_ANALYZER)
         .map_err(|e| Error::Index(e.to_string()))?;
-    Ok(())
+    let mut out = BTreeMap::new();
+    for entry in corpus.iter().map_err(|e| Error::Index(e.to_string()))? {
+        let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        if CorpusStats::unpack(v.value()).doc_count == 0 {
+            continue;
+        }
+        let tenant_id = k.value();
+        let identity = match stored
+            .get(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?
+        {
+            Some(v) => v.value().to_string(),
+            None => analyzer::DEFAULT.identity(),
+        };
+        out.insert(tenant_id, identity);
+    }
+    Ok(out)
 }
 
-// ── Upsert ──────────────────────────────────────────────────────────────
-
-/// Index (or re-index) a single document inside an in-flight write txn.
-/// Caller is responsible for committing the transaction.
-///
-/// Idempotent: re-indexing the same `(tenant_id, record_id)` replaces
-/// the prior tf contribution rather than double-counting.
-pub(super) fn upsert_one(
-    txn: &WriteTransaction,
-    tenant_id: u32,
-    record_id: u64,
-    text: &str,
-) -> Result<()> {
-    // First clear any prior contribution for this doc — keeps re-ingest
-    // semantically clean.
-    let prev_len = clear_one(txn, tenant_id, record_id)?;
+/// Id of `term`: from `dict`, from earlier in the batch, or the next
+/// free id (recorded in `new_terms`).
+pub(crate) fn term_id(
+    dict: Option<&FstMap<Vec<u8>>>,
+    new_terms: &mut BTreeMap<String, u64>,
+    corpus: &mut CorpusStats,
+    term: String,
+) -> Result<u64> {
+    if let Some(tid) = dict.and_then(|d| d.get(term.as_bytes())) {
+        return Ok(tid);
+    }
+    if let Some(&tid) = new_terms.get(&term) {
+        return Ok(tid);
+    }
+    let tid = corpus.next_term_id;
+    corpus.next_term_id = tid
+        .checked_add(1)
+        .ok_or_else(|| Error::Index("BM25 term_id overflow".into()))?;
+    new_terms.insert(term, tid);
+    Ok(tid)
+}
 
-    let tokens = tokenize(text);
-    let doc_len = u32::try_from(tokens.len()).unwrap_or(u32::MAX);
+/// Every term id in `dict`.
+pub(crate) fn all_term_ids(dict: &FstMap<Vec<u8>>) -> Vec<u64> {
+    let mut out = Vec::with_capacity(dict.len());
+    let mut stream = dict.stream();
+    while let Some((_, tid)) = stream.next() {
+        out.push(tid);
+    }
+    out
+}
 
-    let mut tf: HashMap<String, u32> = HashMap::new();
-    for tok in tokens {
-        *tf.entry(tok).or_insert(0) += 1;
+/// Serialize `dict` plus `new_terms` (none of which it holds) as one
+/// `fst::Map`, merging the two sorted streams without decoding `dict`.
+pub(crate) fn merge_term_dict(
+    dict: Option<&FstMap<Vec<u8>>>,
+    new_terms: &BTreeMap<String, u64>,
+) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut builder =
+        MapBuilder::new(&mut buf).map_err(|e| Error::Index(format!("fst builder: {e}")))?;
+    let mut fresh = new_terms.iter().peekable();
+    if let Some(dict) = dict {
+        let mut stream = dict.stream();
+        while let Some((term, tid)) = stream.next() {
+            while let Some((new, &new_tid)) = fresh.next_if(|(new, _)| new.as_bytes() < term) {
+                builder
+                    .insert(new.as_bytes(), new_tid)
+                    .map_err(|e| Error::Index(format!("fst insert: {e}")))?;
+            }
+            builder
+                .insert(term, tid)
+                .map_err(|e| Error::Index(format!("fst insert: {e}")))?;
+        }
+    }
+    for (new, &new_tid) in fresh {
+        builder
+            .insert(new.as_bytes(), new_tid)
+            .map_err(|e| Error::Index(format!("fst insert: {e}")))?;
     }
+    builder
+        .finish()
+        .map_err(|e| Error::Index(format!("fst finish: {e}")))?;
+    Ok(buf)
+}
 
-    let mut corpus = read_corpus(txn, tenant_id)?;
-    let mut term_to_id = read_term_dict(txn, tenant_id)?;
+// ── Bulk maintenance ────────────────────────────────────────────────────
 
-    let mut new_term_added = false;
-    for term in tf.keys() {
-        if !term_to_id.contains_key(term) {
-            let tid = corpus.next_term_id;
-            corpus.next_term_id = tid
-                .checked_add(1)
-                .ok_or_else(|| Error::Index("BM25 term_id overflow".into()))?;
-            term_to_id.insert(term.clone(), tid);
-            new_term_added = true;
+/// Drop dictionary terms with no posting blocks left. Per-doc deletes
+/// leave these behind; bulk deletes call this once at the end so the FST
+/// shrinks with the corpus. Returns the number of terms dropped.
+pub(super) fn prune_dead_terms(txn: &WriteTransaction, tenant_id: u32) -> Result<usize> {
+    let mut dict = read_term_dict(txn, tenant_id)?;
+    let mut dead = Vec::new();
+    {
+        let blocks = txn
+            .open_table(BM25_BLOCKS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for (term, tid) in &dict {
+            if !postings::has_postings(&blocks, tenant_id, *tid)? {
+                dead.push(term.clone());
+            }
         }
     }
-
-    let mut term_ids = Vec::with_capacity(tf.len());
-    for (term, tf_in_doc) in &tf {
-        let tid = term_to_id[term];
-        term_ids.push(tid);
-        let mut bm = read_postings(txn, tenant_id, tid)?;
-        bm.insert(record_id);
-        write_postings(txn, tenant_id, tid, &bm)?;
-        let mut entries = read_scoring(txn, tenant_id, tid)?;
-        entries.push((record_id, *tf_in_doc));
-        write_scoring(txn, tenant_id, tid, &entries)?;
+    if dead.is_empty() {
+        return Ok(0);
+    }
+    for term in &dead {
+        dict.remove(term);
     }
+    if dict.is_empty() {
+        txn.open_table(BM25_TERM_FST)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .remove(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    } else {
+        write_term_dict(txn, tenant_id, &dict)?;
+    }
+    Ok(dead.len())
+}
 
-    let mut doc_terms_table = txn
-        .open_table(BM25_DOC_TERMS)
+/// Remove a tenant's single-row BM25 state (term FST, corpus stats,
+/// field totals, analyzer identity).
+/// Run last during a purge, once the per-term / per-doc tables are
+/// drained, so term ids stay assigned until nothing refers to them.
+pub(super) fn drop_tenant_heads(txn: &WriteTransaction, tenant_id: u32) -> Result<()> {
+    txn.open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .remove(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    txn.open_table(BM25_CORPUS)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .remove(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    txn.open_table(BM25_FIELDS)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .remove(tenant_id)
         .map_err(|e| Error::Index(e.to_string()))?;
-    doc_terms_table
-        .insert((tenant_id, record_id), pack_term_ids(&term_ids).as_slice())
+    txn.open_table(BM25_ANALYZER)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .remove(tenant_id)
         .map_err(|e| Error::Index(e.to_string()))?;
-    drop(doc_terms_table);
+    Ok(())
+}
 
-    write_doc_len(txn, tenant_id, record_id, doc_len)?;
+// ── Query ───────────────────────────────────────────────────────────────
 
-    // Doc count: clear_one already decremented if the doc existed; we
-    // unconditionally re-add since the doc is being indexed now.
-    if prev_len.is_none() {
-        // truly new doc
-    }
-    corpus.doc_count = corpus.doc_count.saturating_add(1);
-    corpus.total_doc_len = corpus.total_doc_len.saturating_add(doc_len as u64);
+/// Top-N matched terms reported per hit when `explain=true`. Caps the
+/// JSON payload to a sensible default; callers wanting more can clone +
+/// modify.
+const TERM_HITS_PER_DOC: usize = 16;
 
-    if new_term_added {
-        write_term_dict(txn, tenant_id, &term_to_id)?;
-    }
-    write_corpus(txn, tenant_id, &corpus)?;
-    Ok(())
+/// How a tenant's text is searched.
+#[derive(Clone, Copy)]
+pub(crate) struct Search<'a> {
+    pub analyzer: &'a Analyzer,
+    /// Whether the tenant stores token positions, which phrase and
+    /// `NEAR` clauses need.
+    pub positional: bool,
+    /// BM25F weights per field; unlisted fields get
+    /// [`Bm25Field::default`].
+    pub fields: &'a BTreeMap<String, Bm25Field>,
+    pub params: &'a Bm25Params,
 }
 
-/// Remove a doc's contribution from postings, scoring, doc_lens, and
-/// corpus stats. Returns the previous doc_len if one existed.
-pub(super) fn clear_one(
-    txn: &WriteTransaction,
+/// BM25 top-k search inside `tenant_id`. `terms` is the query text in
+/// the syntax of the module docs, split anywhere (the pieces are joined
+/// with spaces). When `explain` is true each returned hit carries the
+/// per-term contributions in [`Hit::term_hits`] (top-N by contribution).
+/// Phrase and `NEAR` clauses are [`Error::Modality`] unless `search`
+/// says the tenant stores positions.
+pub(super) fn search_explain(
+    db: &redb::Database,
     tenant_id: u32,
-    record_id: u64,
-) -> Result<Option<u32>> {
-    let prev_len = read_doc_len(txn, tenant_id, record_id)?;
-    let Some(prev_len_v) = prev_len else {
-        return Ok(None);
+    terms: &[&str],
+    k: usize,
+    explain: bool,
+    search: &Search<'_>,
+) -> Result<Vec<Hit>> {
+    use redb::ReadableDatabase;
+    if k == 0 || terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let read = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let totals: BTreeMap<String, u64> = match read
+        .open_table(BM25_FIELDS)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => decode_lens(v.value())?,
+        None => BTreeMap::new(),
     };
+    let names: BTreeSet<String> = totals.keys().chain(search.fields.keys()).cloned().collect();
+    let clauses = parse_query(terms, search.analyzer, &names)?;
+    if !search.positional
+        && let Some(c) = clauses.iter().find(|c| c.is_positional())
+    {
+        return Err(Error::Modality(format!(
+            "`{c}` needs token positions, which tenant {tenant_id} does not store"
+        )));
+    }
 
-    let mut doc_terms_table = txn
-        .open_table(BM25_DOC_TERMS)
-        .map_err(|e| Error::Index(e.to_string()))?;
+    // Corpus stats
+    let corpus = match read
+        .open_table(BM25_CORPUS)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => CorpusStats::unpack(v.value()),
+        None => return Ok(Vec::new()),
+    };
+    if corpus.doc_count == 0 {
+        return Ok(Vec::new());
+    }
+    let n = corpus.doc_count as f32;
+    let scorer = Scorer::new(search.params, n);
+    // The text, then every named field with indexed tokens.
+    let fields: Vec<Field> = std::iter::once((TEXT_FIELD, corpus.total_doc_len))
+        .chain(totals.iter().map(|(name, &total)| (name.as_str(), total)))
+        .map(|(name, total)| {
+            let weights = search.fields.get(name).copied().unwrap_or_default();
+            Field {
+                name: Arc::from(name),
+                norm: FieldNorm {
+                    boost: weights.boost,
+                    b: match search.params.model {
+                        Bm25Model::TfIdf => 0.0,
+                        _ => weights.b.unwrap_or(search.params.b),
+                    },
+                    avgdl: total as f32 / n,
+                },
+            }
+        })
+        .collect();
 
-    let tids = if let Some(v) = doc_terms_table
-        .get((tenant_id, record_id))
+    // FST term dict
+    let fst_table = read
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let dict_bytes = match fst_table
+        .get(tenant_id)
         .map_err(|e| Error::Index(e.to_string()))?
     {
-        unpack_term_ids(v.value())
-    } else {
-        let term_to_id = read_term_dict(txn, tenant_id)?;
-        term_to_id.values().copied().collect()
+        Some(v) => v.value().to_vec(),
+        None => return Ok(Vec::new()),
     };
+    let fst_map = FstMap::new(dict_bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
 
-    for tid in tids {
-        let mut entries = read_scoring(txn, tenant_id, tid)?;
-        let before = entries.len();
-        entries.retain(|(d, _)| *d != record_id);
-        if entries.len() != before {
-            write_scoring(txn, tenant_id, tid, &entries)?;
-            let mut bm = read_postings(txn, tenant_id, tid)?;
-            bm.remove(record_id);
-            write_postings(txn, tenant_id, tid, &bm)?;
+    let blocks = read
+        .open_table(BM25_BLOCKS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let doc_positions = read
+        .open_table(BM25_POSITIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let hidden = super::expiry::Expired::open(&read, tenant_id, crate::index::now_ms())?;
+    let lookup = Lookup {
+        dict: &fst_map,
+        blocks: &blocks,
+        positions: &doc_positions,
+        tenant_id,
+    };
+
+    // A clause repeated in the query counts once per occurrence.
+    let mut weights: Vec<(Scoped, u32)> = Vec::new();
+    for clause in clauses {
+        match weights.iter_mut().find(|(c, _)| *c == clause) {
+            Some((_, w)) => *w += 1,
+            None => weights.push((clause, 1)),
+        }
+    }
+    let mut query = Vec::with_capacity(weights.len());
+    for (order, (scoped, weight)) in weights.into_iter().enumerate() {
+        // What the clause matched in each field it targets, grouped by
+        // the dictionary term when it expands.
+        let mut groups: Vec<Group<'_>> = Vec::new();
+        for field in fields
+            .iter()
+            .filter(|f| scoped.field.as_deref().is_none_or(|name| *f.name == *name))
+        {
+            for found in lookup.find(&scoped.clause, field)? {
+                match groups.iter_mut().find(|g| g.key == found.key) {
+                    Some(group) => group.add(found),
+                    None => groups.push(Group::from(found)),
+                }
+            }
+        }
+        let label = match scoped.clause {
+            Clause::Term(_) => Label::Term,
+            Clause::Expand { .. } => Label::Expanded(scoped.to_string()),
+            _ => Label::Phrase(scoped.to_string()),
+        };
+        for group in groups {
+            let terms = group
+                .terms
+                .iter()
+                .map(|(term, df)| (term.clone(), scorer.idf(*df as f32)))
+                .collect();
+            let weight = weight as f32 * group.weight;
+            if let Some(source) = Source::new(group.parts)? {
+                query.push(QueryTerm::new(
+                    terms,
+                    label.clone(),
+                    order,
+                    weight,
+                    scorer,
+                    source,
+                ));
+            }
         }
     }
 
-    doc_terms_table
-        .remove((tenant_id, record_id))
-        .map_err(|e| Error::Index(e.to_string()))?;
-    drop(doc_terms_table);
+    let (accum, explain_hits) = block_max_wand(query, &hidden, k, explain)?;
+    Ok(collect_hits(tenant_id, accum, explain_hits, k, explain))
+}
 
-    let mut doc_lens = txn
-        .open_table(BM25_DOC_LENS)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    doc_lens
-        .remove((tenant_id, record_id))
+/// Completions of the last token of `prefix` in `tenant_id`'s
+/// dictionary, most documents first; see [`vocab::complete`].
+pub(super) fn suggest(
+    db: &redb::Database,
+    tenant_id: u32,
+    prefix: &str,
+    limit: usize,
+    analyzer: &Analyzer,
+) -> Result<Vec<TermSuggestion>> {
+    let (dict, blocks) = vocabulary(db, tenant_id)?;
+    vocab::complete(&dict, prefix, limit, analyzer, |tid| {
+        doc_freq(&blocks, tenant_id, tid)
+    })
+}
+
+/// The closest terms in `tenant_id`'s dictionary to each token of
+/// `text`; see [`vocab::corrections`].
+pub(super) fn did_you_mean(
+    db: &redb::Database,
+    tenant_id: u32,
+    text: &str,
+    limit: usize,
+    analyzer: &Analyzer,
+) -> Result<Vec<Correction>> {
+    let (dict, blocks) = vocabulary(db, tenant_id)?;
+    vocab::corrections(&dict, text, limit, analyzer, |tid| {
+        doc_freq(&blocks, tenant_id, tid)
+    })
+}
+
+/// A tenant's term dictionary and the postings of its terms.
+type Vocabulary = (
+    FstMap<Vec<u8>>,
+    redb::ReadOnlyTable<BlockKey, &'static [u8]>,
+);
+
+/// `tenant_id`'s term dictionary (empty when it has none) and the
+/// postings to count its terms' docs in.
+fn vocabulary(db: &redb::Database, tenant_id: u32) -> Result<Vocabulary> {
+    use redb::ReadableDatabase;
+    let read = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let dict = match read
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => {
+            FstMap::new(v.value().to_vec()).map_err(|e| Error::Index(format!("fst load: {e}")))?
+        }
+        None => FstMap::default(),
+    };
+    let blocks = read
+        .open_table(BM25_BLOCKS)
         .map_err(|e| Error::Index(e.to_string()))?;
-    drop(doc_lens);
+    Ok((dict, blocks))
+}
 
-    let mut corpus = read_corpus(txn, tenant_id)?;
-    corpus.doc_count = corpus.doc_count.saturating_sub(1);
-    corpus.total_doc_len = corpus.total_doc_len.saturating_sub(prev_len_v as u64);
-    write_corpus(txn, tenant_id, &corpus)?;
+/// Docs holding `term_id`, from its block headers.
+fn doc_freq(
+    blocks: &impl ReadableTable<BlockKey, &'static [u8]>,
+    tenant_id: u32,
+    term_id: u64,
+) -> Result<u64> {
+    Ok(postings::headers(blocks, tenant_id, term_id)?
+        .iter()
+        .map(|(_, h)| u64::from(h.count))
+        .sum())
+}
 
-    Ok(Some(prev_len_v))
+/// A text field as a query weighs it.
+#[derive(Clone)]
+struct Field {
+    name: Arc<str>,
+    norm: FieldNorm,
 }
 
-// ── Query ───────────────────────────────────────────────────────────────
+/// BM25F normalization of one field.
+#[derive(Clone, Copy)]
+struct FieldNorm {
+    boost: f32,
+    b: f32,
+    avgdl: f32,
+}
 
-/// Top-N matched terms reported per hit when `explain=true`. Caps the
-/// JSON payload to a sensible default; callers wanting more can clone +
-/// modify.
-const TERM_HITS_PER_DOC: usize = 16;
+impl FieldNorm {
+    /// A term's pseudo-frequency from `tf` occurrences in a field of
+    /// `dl` tokens: boosted, and divided by the field's relative length.
+    fn tf(&self, tf: u32, dl: u32) -> f32 {
+        let norm = 1.0 - self.b + self.b * dl as f32 / self.avgdl.max(1.0);
+        self.boost * tf as f32 / norm.max(1e-6)
+    }
+}
 
-/// BM25 top-k search inside `tenant_id`. `terms` is the already-
-/// tokenized query (caller is responsible for matching the index-time
-/// tokenizer). When `explain` is true each returned hit carries the
-/// per-term contributions in [`Hit::term_hits`] (top-N by contribution).
-pub(super) fn search_explain(
-    db: &redb::Database,
+/// The tables a query reads its clauses' matches from.
+struct Lookup<'a, 't> {
+    dict: &'a FstMap<Vec<u8>>,
+    blocks: &'t redb::ReadOnlyTable<BlockKey, &'static [u8]>,
+    positions: &'a redb::ReadOnlyTable<BlockKey, &'static [u8]>,
     tenant_id: u32,
-    terms: &[&str],
+}
+
+impl<'t> Lookup<'_, 't> {
+    /// What `clause` matched in `field`: one entry per dictionary term
+    /// for an expansion, at most one otherwise.
+    fn find(&self, clause: &Clause, field: &Field) -> Result<Vec<Found<'t>>> {
+        if let Clause::Expand { term, how } = clause {
+            let mut out = Vec::new();
+            let live = |tid| postings::has_postings(self.blocks, self.tenant_id, tid);
+            for e in vocab::expand(self.dict, &field.name, term, *how, live)? {
+                let Some(cursor) = TermCursor::open(self.blocks, self.tenant_id, e.term_id)? else {
+                    continue;
+                };
+                out.push(Found {
+                    key: e.term.clone(),
+                    weight: e.weight,
+                    terms: vec![(e.term, cursor.doc_freq())],
+                    field: field.clone(),
+                    part: Part::Postings(cursor),
+                });
+            }
+            return Ok(out);
+        }
+        let mut opened = Vec::new();
+        for term in clause.terms() {
+            let key = field_key(&field.name, term);
+            let Some(term_id) = self.dict.get(key.as_bytes()) else {
+                return Ok(Vec::new());
+            };
+            let Some(cursor) = TermCursor::open(self.blocks, self.tenant_id, term_id)? else {
+                return Ok(Vec::new());
+            };
+            opened.push((term.to_string(), term_id, cursor));
+        }
+        let terms = opened
+            .iter()
+            .map(|(term, _, cursor)| (term.clone(), cursor.doc_freq()))
+            .collect();
+        let part = if let Clause::Term(_) = clause {
+            let (_, _, cursor) = opened.pop().expect("a term clause has one term");
+            Part::Postings(cursor)
+        } else {
+            let cursors = opened.into_iter().map(|(_, tid, c)| (tid, c)).collect();
+            let matches = positions::matches(self.positions, self.tenant_id, clause, cursors)?;
+            if matches.is_empty() {
+                return Ok(Vec::new());
+            }
+            Part::Matches(matches)
+        };
+        Ok(vec![Found {
+            key: String::new(),
+            weight: 1.0,
+            terms,
+            field: field.clone(),
+            part,
+        }])
+    }
+}
+
+/// What one clause matched in one field.
+struct Found<'t> {
+    /// The dictionary term an expansion reached; empty otherwise.
+    key: String,
+    /// Multiplier on the contribution; below 1 for expansions.
+    weight: f32,
+    /// The clause's terms with their doc frequency in the field.
+    terms: Vec<(String, u64)>,
+    field: Field,
+    part: Part<'t>,
+}
+
+/// A term's posting list, or the docs a phrase or `NEAR` clause matched
+/// as postings whose tf is the match count.
+enum Part<'t> {
+    Postings(TermCursor<'t>),
+    Matches(Vec<Posting>),
+}
+
+/// What one clause (or one term of an expansion) matched over its
+/// fields. Each term's IDF comes from its largest per-field doc
+/// frequency.
+struct Group<'t> {
+    key: String,
+    weight: f32,
+    terms: Vec<(String, u64)>,
+    parts: Vec<(Field, Part<'t>)>,
+}
+
+impl<'t> From<Found<'t>> for Group<'t> {
+    fn from(found: Found<'t>) -> Self {
+        Self {
+            key: found.key,
+            weight: found.weight,
+            terms: found.terms,
+            parts: vec![(found.field, found.part)],
+        }
+    }
+}
+
+impl<'t> Group<'t> {
+    fn add(&mut self, found: Found<'t>) {
+        for ((_, df), (_, more)) in self.terms.iter_mut().zip(&found.terms) {
+            *df = (*df).max(*more);
+        }
+        self.parts.push((found.field, found.part));
+    }
+}
+
+/// One distinct query clause and its cursor. A term clause scores with
+/// its term's IDF; a phrase or `NEAR` clause with the sum of its terms'.
+struct QueryTerm<'t> {
+    /// The clause's terms with their IDFs, in clause order.
+    terms: Vec<(String, f32)>,
+    /// Which kind of clause this is, for explain output.
+    label: Label,
+    /// Position in the query; breaks ties so contributions are summed
+    /// in query order.
+    order: usize,
+    weight: f32,
+    scorer: Scorer,
+    idf: f32,
+    /// Bound on the clause's contribution to any doc.
+    max_score: f32,
+    cursor: Source<'t>,
+}
+
+impl<'t> QueryTerm<'t> {
+    fn new(
+        terms: Vec<(String, f32)>,
+        label: Label,
+        order: usize,
+        weight: f32,
+        scorer: Scorer,
+        cursor: Source<'t>,
+    ) -> Self {
+        let idf = terms.iter().map(|(_, idf)| idf).sum();
+        Self {
+            max_score: weight * scorer.score(idf, cursor.max_tf()),
+            scorer,
+            idf,
+            terms,
+            label,
+            order,
+            weight,
+            cursor,
+        }
+    }
+
+    /// Doc under the cursor; `u64::MAX` sorts exhausted cursors last.
+    fn doc(&self) -> u64 {
+        self.cursor.doc().unwrap_or(u64::MAX)
+    }
+
+    /// The last doc of the block holding `doc`, and a bound on the
+    /// contribution of any entry in it.
+    fn block_bound(&self, doc: u64) -> Option<(u64, f32)> {
+        let (last, tf) = self.cursor.block_at(doc)?;
+        Some((last, self.weight * self.scorer.score(self.idf, tf)))
+    }
+
+    /// Contribution to the doc under the cursor.
+    fn score(&self) -> f32 {
+        self.weight * self.scorer.score(self.idf, self.cursor.tf())
+    }
+
+    /// The contribution of each of the clause's terms to the doc under
+    /// the cursor, split over the fields it matched in.
+    fn term_hits(&self) -> Vec<TermHit> {
+        let (phrase, expanded_from) = match &self.label {
+            Label::Term => (None, None),
+            Label::Phrase(c) => (Some(c), None),
+            Label::Expanded(c) => (None, Some(c)),
+        };
+        let tf = self.cursor.tf();
+        let parts = self.cursor.parts();
+        let mut out = Vec::with_capacity(self.terms.len() * parts.len());
+        for (term, idf) in &self.terms {
+            let contribution = self.weight * self.scorer.score(*idf, tf);
+            for part in &parts {
+                out.push(TermHit {
+                    term: term.clone(),
+                    idf: *idf,
+                    tf: part.tf,
+                    contribution: match parts.len() {
+                        1 => contribution,
+                        _ => contribution * part.share / tf,
+                    },
+                    phrase: phrase.cloned(),
+                    expanded_from: expanded_from.cloned(),
+                    field: part.field.to_string(),
+                });
+            }
+        }
+        out
+    }
+}
+
+/// Where a [`QueryTerm`] came from, as written in the query.
+#[derive(Clone)]
+enum Label {
+    Term,
+    /// A phrase or `NEAR` clause.
+    Phrase(String),
+    /// One dictionary term a `term*` or `term~n` clause expanded to.
+    Expanded(String),
+}
+
+/// A doc a clause matched ahead of the walk.
+struct Match {
+    doc: u64,
+    /// Pseudo-frequency: the sum of the fields' shares.
+    tf: f32,
+    fields: Vec<FieldTf>,
+}
+
+/// A clause's frequency in one field of a doc.
+struct FieldTf {
+    field: Arc<str>,
+    tf: u32,
+    /// Its share of the pseudo-frequency.
+    share: f32,
+}
+
+/// What a [`QueryTerm`] walks: a term's posting list in one field, or
+/// the docs matched up front (a phrase or `NEAR` clause, or a clause
+/// merged over several fields) bounded as one block.
+enum Source<'t> {
+    Postings(TermCursor<'t>, Field),
+    Matches {
+        entries: Vec<Match>,
+        pos: usize,
+        last: u64,
+        max_tf: f32,
+    },
+}
+
+impl<'t> Source<'t> {
+    /// The source of what a clause matched in `parts`, merged by doc
+    /// unless it is a single posting list; `None` when nothing matched.
+    fn new(mut parts: Vec<(Field, Part<'t>)>) -> Result<Option<Self>> {
+        if let [(_, Part::Postings(_))] = parts.as_slice() {
+            let Some((field, Part::Postings(cursor))) = parts.pop() else {
+                unreachable!("checked above");
+            };
+            return Ok(Some(Self::Postings(cursor, field)));
+        }
+        let mut by_doc: BTreeMap<u64, Match> = BTreeMap::new();
+        for (field, part) in parts {
+            let mut add = |p: Posting| {
+                let share = field.norm.tf(p.tf, p.dl);
+                let m = by_doc.entry(p.doc).or_insert_with(|| Match {
+                    doc: p.doc,
+                    tf: 0.0,
+                    fields: Vec::new(),
+                });
+                m.tf += share;
+                m.fields.push(FieldTf {
+                    field: field.name.clone(),
+                    tf: p.tf,
+                    share,
+                });
+            };
+            match part {
+                Part::Postings(mut cursor) => {
+                    while let Some(p) = cursor.current() {
+                        add(p);
+                        cursor.next()?;
+                    }
+                }
+                Part::Matches(entries) => entries.into_iter().for_each(add),
+            }
+        }
+        let Some((&last, _)) = by_doc.last_key_value() else {
+            return Ok(None);
+        };
+        let entries: Vec<Match> = by_doc.into_values().collect();
+        Ok(Some(Self::Matches {
+            max_tf: entries.iter().map(|m| m.tf).fold(0.0, f32::max),
+            entries,
+            pos: 0,
+            last,
+        }))
+    }
+
+    /// Bound on the pseudo-frequency of any doc.
+    fn max_tf(&self) -> f32 {
+        match self {
+            Self::Postings(c, field) => c
+                .blocks()
+                .iter()
+                .map(|(_, h)| field.norm.tf(h.max_tf, h.min_dl))
+                .fold(0.0, f32::max),
+            Self::Matches { max_tf, .. } => *max_tf,
+        }
+    }
+
+    fn doc(&self) -> Option<u64> {
+        match self {
+            Self::Postings(c, _) => c.current().map(|p| p.doc),
+            Self::Matches { entries, pos, .. } => entries.get(*pos).map(|m| m.doc),
+        }
+    }
+
+    /// Pseudo-frequency of the doc under the cursor.
+    fn tf(&self) -> f32 {
+        match self {
+            Self::Postings(c, field) => c.current().map_or(0.0, |p| field.norm.tf(p.tf, p.dl)),
+            Self::Matches { entries, pos, .. } => entries.get(*pos).map_or(0.0, |m| m.tf),
+        }
+    }
+
+    /// The fields the doc under the cursor matched in.
+    fn parts(&self) -> Vec<FieldTf> {
+        match self {
+            Self::Postings(c, field) => c
+                .current()
+                .map(|p| FieldTf {
+                    field: field.name.clone(),
+                    tf: p.tf,
+                    share: field.norm.tf(p.tf, p.dl),
+                })
+                .into_iter()
+                .collect(),
+            Self::Matches { entries, pos, .. } => entries.get(*pos).map_or_else(Vec::new, |m| {
+                m.fields
+                    .iter()
+                    .map(|f| FieldTf {
+                        field: f.field.clone(),
+                        tf: f.tf,
+                        share: f.share,
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// The last doc of the block holding `doc`, and a bound on the
+    /// pseudo-frequency of its entries.
+    fn block_at(&self, doc: u64) -> Option<(u64, f32)> {
+        match self {
+            Self::Postings(c, field) => c
+                .block_at(doc)
+                .map(|(last, h)| (last, field.norm.tf(h.max_tf, h.min_dl))),
+            Self::Matches { last, max_tf, .. } => (*last >= doc).then_some((*last, *max_tf)),
+        }
+    }
+
+    fn next(&mut self) -> Result<()> {
+        match self {
+            Self::Postings(c, _) => c.next(),
+            Self::Matches { pos, .. } => {
+                *pos += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn seek(&mut self, target: u64) -> Result<()> {
+        match self {
+            Self::Postings(c, _) => c.seek(target),
+            Self::Matches { entries, pos, .. } => {
+                *pos += entries[(*pos).min(entries.len())..].partition_point(|m| m.doc < target);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A scored doc, ordered by score for the top-k heap.
+struct Scored(f32, u64);
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+/// Scores of the top-k docs, and their term contributions.
+type TopK = (HashMap<u64, f32>, HashMap<u64, Vec<TermHit>>);
+
+/// Block-Max WAND over `query`: the top `k` docs not in `hidden` with
+/// their scores, and their term contributions when `explain` is set.
+/// Expiry is only looked up for a doc that would make the cut, and an
+/// expired one leaves its place to the next, so `k` stays filled.
+///
+/// Cursors are kept sorted by doc. The pivot is the first cursor at which
+/// the terms' whole-list bounds exceed the k-th best score θ; no doc
+/// before the pivot's can make the cut. The pivot doc is then checked
+/// against the bounds of the blocks it falls in: above θ it is scored
+/// (or the lagging cursors are moved up to it), otherwise every cursor
+/// up to the pivot jumps past the end of the nearest of those blocks.
+fn block_max_wand(
+    mut query: Vec<QueryTerm<'_>>,
+    hidden: &super::expiry::Expired,
     k: usize,
     explain: bool,
-) -> Result<Vec<Hit>> {
-    use redb::ReadableDatabase;
-    if k == 0 || terms.is_empty() {
-        return Ok(Vec::new());
+) -> Result<TopK> {
+    let mut top: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+    let mut explain_hits: HashMap<u64, Vec<TermHit>> = HashMap::new();
+    loop {
+        query.retain(|q| q.cursor.doc().is_some());
+        query.sort_by_key(|q| (q.doc(), q.order));
+        let theta = match top.peek() {
+            Some(Reverse(Scored(score, _))) if top.len() >= k => *score,
+            _ => 0.0,
+        };
+
+        let mut bound = 0.0;
+        let Some(mut pivot) = query.iter().position(|q| {
+            bound += q.max_score;
+            bound > theta
+        }) else {
+            break;
+        };
+        let doc = query[pivot].doc();
+        while query.get(pivot + 1).is_some_and(|q| q.doc() == doc) {
+            pivot += 1;
+        }
+
+        let mut block_bound = 0.0;
+        let mut block_end = u64::MAX;
+        for q in &query[..=pivot] {
+            if let Some((last, bound)) = q.block_bound(doc) {
+                block_bound += bound;
+                block_end = block_end.min(last);
+            }
+        }
+        if block_bound <= theta {
+            // Nothing up to the end of these blocks can make the cut.
+            let next = query.get(pivot + 1).map(|q| q.doc());
+            let Some(target) = block_end.checked_add(1).into_iter().chain(next).min() else {
+                break;
+            };
+            for q in &mut query[..=pivot] {
+                q.cursor.seek(target)?;
+            }
+            continue;
+        }
+        if query[0].doc() != doc {
+            for q in &mut query[..pivot] {
+                q.cursor.seek(doc)?;
+            }
+            continue;
+        }
+
+        let mut score = 0.0;
+        let mut term_hits = Vec::new();
+        for q in &query[..=pivot] {
+            score += q.score();
+            if explain {
+                term_hits.extend(q.term_hits());
+            }
+        }
+        if (top.len() < k || score > theta) && !hidden.contains(doc)? {
+            top.push(Reverse(Scored(score, doc)));
+            if explain {
+                explain_hits.insert(doc, term_hits);
+            }
+            if top.len() > k
+                && let Some(Reverse(Scored(_, evicted))) = top.pop()
+            {
+                explain_hits.remove(&evicted);
+            }
+        }
+        for q in &mut query[..=pivot] {
+            q.cursor.next()?;
+        }
+    }
+    let accum = top
+        .into_iter()
+        .map(|Reverse(Scored(score, doc))| (doc, score))
+        .collect();
+    Ok((accum, explain_hits))
+}
+
+/// One clause of a parsed query; see the module docs for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Clause {
+    Term(String),
+    /// Two or more tokens in a row.
+    Phrase(Vec<String>),
+    /// An occurrence of `left` within `within` tokens of one of `right`.
+    /// Either side is a single term or a phrase.
+    Near {
+        left: Vec<String>,
+        right: Vec<String>,
+        within: u32,
+    },
+    /// `term*` or `term~n`: the dictionary terms `term` expands to.
+    Expand {
+        term: String,
+        how: Expansion,
+    },
+}
+
+impl Clause {
+    fn operand(mut tokens: Vec<String>) -> Self {
+        if tokens.len() == 1 {
+            Self::Term(tokens.remove(0))
+        } else {
+            Self::Phrase(tokens)
+        }
+    }
+
+    /// Whether matching the clause needs token positions.
+    pub(crate) fn is_positional(&self) -> bool {
+        matches!(self, Self::Phrase(_) | Self::Near { .. })
+    }
+
+    /// The clause's terms in order, repeats included.
+    pub(crate) fn terms(&self) -> Vec<&str> {
+        match self {
+            Self::Term(t) | Self::Expand { term: t, .. } => vec![t.as_str()],
+            Self::Phrase(ts) => ts.iter().map(String::as_str).collect(),
+            Self::Near { left, right, .. } => {
+                left.iter().chain(right).map(String::as_str).collect()
+            }
+        }
     }
-    let read = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+}
 
-    // Corpus stats
-    let corpus = match read
-        .open_table(BM25_CORPUS)
-        .map_err(|e| Error::Index(e.to_string()))?
-        .get(tenant_id)
-        .map_err(|e| Error::Index(e.to_string()))?
-    {
-        Some(v) => CorpusStats::unpack(v.value()),
-        None => return Ok(Vec::new()),
-    };
-    if corpus.doc_count == 0 {
-        return Ok(Vec::new());
+/// A `NEAR` operand as written: a term, or a quoted phrase.
+fn write_operand(f: &mut std::fmt::Formatter<'_>, tokens: &[String]) -> std::fmt::Result {
+    match tokens {
+        [one] => f.write_str(one),
+        _ => write!(f, "\"{}\"", tokens.join(" ")),
     }
-    let avgdl = corpus.avgdl();
-    let n = corpus.doc_count as f32;
+}
 
-    // FST term dict
-    let fst_table = read
-        .open_table(BM25_TERM_FST)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    let dict_bytes = match fst_table
-        .get(tenant_id)
-        .map_err(|e| Error::Index(e.to_string()))?
-    {
-        Some(v) => v.value().to_vec(),
-        None => return Ok(Vec::new()),
-    };
-    let fst_map = FstMap::new(dict_bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
+impl std::fmt::Display for Clause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Term(t) => f.write_str(t),
+            Self::Phrase(ts) => write_operand(f, ts),
+            Self::Near {
+                left,
+                right,
+                within,
+            } => {
+                write_operand(f, left)?;
+                write!(f, " NEAR/{within} ")?;
+                write_operand(f, right)
+            }
+            Self::Expand { term, how } => match how {
+                Expansion::Prefix => write!(f, "{term}*"),
+                Expansion::Fuzzy(n) => write!(f, "{term}~{n}"),
+            },
+        }
+    }
+}
 
-    let scoring_table = read
-        .open_table(BM25_SCORING)
-        .map_err(|e| Error::Index(e.to_string()))?;
-    let doc_lens_table = read
-        .open_table(BM25_DOC_LENS)
-        .map_err(|e| Error::Index(e.to_string()))?;
+/// A [`Clause`] and the field it is confined to; `None` matches it in
+/// every field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Scoped {
+    pub field: Option<String>,
+    pub clause: Clause,
+}
+
+impl Scoped {
+    /// Whether matching the clause needs token positions.
+    pub(crate) fn is_positional(&self) -> bool {
+        self.clause.is_positional()
+    }
+}
 
-    let mut accum: HashMap<u64, f32> = HashMap::new();
-    // (doc_id) → Vec<TermHit>. Only populated when `explain` is true, so
-    // the hot search path stays a single-map walk for non-explain calls.
-    let mut explain_hits: HashMap<u64, Vec<crate::core::TermHit>> = HashMap::new();
-    // Tokenize each provided query term again so single-token args like
-    // "Hello, World" still split sensibly.
-    let mut query_terms: Vec<String> = Vec::new();
-    for raw in terms {
-        for tok in tokenize(raw) {
-            query_terms.push(tok);
+impl std::fmt::Display for Scoped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.field, &self.clause) {
+            (None, clause) => clause.fmt(f),
+            (
+                Some(name),
+                Clause::Near {
+                    left,
+                    right,
+                    within,
+                },
+            ) => {
+                write!(f, "{name}:")?;
+                write_operand(f, left)?;
+                write!(f, " NEAR/{within} {name}:")?;
+                write_operand(f, right)
+            }
+            (Some(name), clause) => write!(f, "{name}:{clause}"),
         }
     }
+}
 
-    for term in &query_terms {
-        let Some(term_id) = fst_map.get(term.as_bytes()) else {
+/// Parse query text into clauses. Words and quoted text go through
+/// `analyzer`; a quote left open runs to the end. A word or quote
+/// prefixed `name:`, where `name` is `text` or one of `fields`, is
+/// confined to that field; any other `x:` is plain text. A word ending
+/// in `*`, `~`, `~1` or `~2` expands its last token, split by `analyzer`
+/// but neither stopped nor stemmed. `NEAR/n` must be upper case with
+/// `n ≥ 1` and sit between two phrases or unexpanded terms of the same
+/// field; that and any other fuzzy distance are [`Error::Modality`].
+pub(crate) fn parse_query(
+    terms: &[&str],
+    analyzer: &Analyzer,
+    fields: &BTreeSet<String>,
+) -> Result<Vec<Scoped>> {
+    enum Item {
+        Operand(Option<String>, Vec<String>),
+        Expand(Option<String>, String, Expansion),
+        Near(u32),
+    }
+    let text = terms.join(" ");
+    let mut items = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let mut field = None;
+        if let Some((name, after)) = rest.split_once(':')
+            && (name == TEXT_FIELD || fields.contains(name))
+            && after.starts_with(|c: char| !c.is_whitespace())
+        {
+            field = Some(name.to_string());
+            rest = after;
+        }
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let (inner, after) = quoted.split_once('"').unwrap_or((quoted, ""));
+            let tokens = analyzer.analyze(inner);
+            if !tokens.is_empty() {
+                items.push(Item::Operand(field, tokens));
+            }
+            rest = after.trim_start();
             continue;
-        };
-        let row = scoring_table
-            .get((tenant_id, term_id))
-            .map_err(|e| Error::Index(e.to_string()))?;
-        let entries = match row {
-            Some(v) => unpack_scoring(v.value()),
-            None => continue,
-        };
-        if entries.is_empty() {
+        }
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        rest = after.trim_start();
+        if field.is_none()
+            && let Some(n) = word.strip_prefix("NEAR/")
+        {
+            match n.parse::<u32>() {
+                Ok(within) if within > 0 => items.push(Item::Near(within)),
+                _ => {
+                    return Err(Error::Modality(format!(
+                        "`{word}`: expected NEAR/n with n ≥ 1"
+                    )));
+                }
+            }
             continue;
         }
-        let n_with_term = entries.len() as f32;
-        // BM25+ smoothed IDF — non-negative.
-        let idf = ((n - n_with_term + 0.5) / (n_with_term + 0.5) + 1.0).ln();
+        let (stem, how) = match word.rsplit_once('~') {
+            Some((stem, n)) if n.bytes().all(|b| b.is_ascii_digit()) => {
+                let distance = if n.is_empty() {
+                    vocab::MAX_FUZZY_DISTANCE
+                } else {
+                    n.parse().unwrap_or(0)
+                };
+                if !(1..=vocab::MAX_FUZZY_DISTANCE).contains(&distance) {
+                    return Err(Error::Modality(format!(
+                        "`{word}`: expected term~n with n of 1 or 2"
+                    )));
+                }
+                (stem, Some(Expansion::Fuzzy(distance)))
+            }
+            _ => match word.strip_suffix('*') {
+                Some(stem) => (stem, Some(Expansion::Prefix)),
+                None => (word, None),
+            },
+        };
+        let (tokens, last) = match how {
+            Some(how) => {
+                let mut tokens = analyzer.split(stem);
+                let last = tokens.pop().map(|t| Item::Expand(field.clone(), t, how));
+                (analyzer.analyze(&tokens.join(" ")), last)
+            }
+            None => (analyzer.analyze(stem), None),
+        };
+        items.extend(
+            tokens
+                .into_iter()
+                .map(|t| Item::Operand(field.clone(), vec![t])),
+        );
+        items.extend(last);
+    }
 
-        for (doc_id, tf) in entries {
-            let dl = doc_lens_table
-                .get((tenant_id, doc_id))
-                .map_err(|e| Error::Index(e.to_string()))?
-                .map(|v| v.value())
-                .unwrap_or(0) as f32;
-            let denom = (tf as f32) + K1 * (1.0 - B + B * dl / avgdl.max(1.0));
-            let contribution = idf * ((tf as f32) * (K1 + 1.0)) / denom.max(1e-6);
-            *accum.entry(doc_id).or_insert(0.0) += contribution;
-            if explain {
-                explain_hits
-                    .entry(doc_id)
-                    .or_default()
-                    .push(crate::core::TermHit {
-                        term: term.clone(),
-                        idf,
-                        tf,
-                        contribution,
+    let mut out = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let before = i.checked_sub(1).and_then(|j| items.get(j));
+        let after = items.get(i + 1);
+        match item {
+            Item::Near(within) => match (before, after) {
+                (Some(Item::Operand(field, left)), Some(Item::Operand(other, right)))
+                    if field == other =>
+                {
+                    out.push(Scoped {
+                        field: field.clone(),
+                        clause: Clause::Near {
+                            left: left.clone(),
+                            right: right.clone(),
+                            within: *within,
+                        },
+                    });
+                }
+                (Some(Item::Operand(..)), Some(Item::Operand(..))) => {
+                    return Err(Error::Modality(format!(
+                        "NEAR/{within} needs both sides in the same field"
+                    )));
+                }
+                _ => {
+                    return Err(Error::Modality(format!(
+                        "NEAR/{within} needs a plain term or phrase on each side"
+                    )));
+                }
+            },
+            Item::Expand(field, term, how) => out.push(Scoped {
+                field: field.clone(),
+                clause: Clause::Expand {
+                    term: term.clone(),
+                    how: *how,
+                },
+            }),
+            Item::Operand(field, tokens) => {
+                let bound =
+                    matches!(before, Some(Item::Near(_))) || matches!(after, Some(Item::Near(_)));
+                if !bound {
+                    out.push(Scoped {
+                        field: field.clone(),
+                        clause: Clause::operand(tokens.clone()),
                     });
+                }
             }
         }
     }
+    Ok(out)
+}
+
+/// BM25+ smoothed IDF — non-negative.
+#[inline]
+pub(crate) fn idf(n: f32, n_with_term: f32) -> f32 {
+    ((n - n_with_term + 0.5) / (n_with_term + 0.5) + 1.0).ln()
+}
+
+/// One term's Okapi contribution to a document's score.
+#[inline]
+#[cfg_attr(not(feature = "fjall"), allow(dead_code))] // FjallBackend scores one field
+pub(crate) fn term_score(idf: f32, tf: u32, dl: f32, avgdl: f32) -> f32 {
+    let denom = (tf as f32) + K1 * (1.0 - B + B * dl / avgdl.max(1.0));
+    idf * ((tf as f32) * (K1 + 1.0)) / denom.max(1e-6)
+}
+
+/// A query's scoring function, resolved against the tenant's corpus.
+#[derive(Clone, Copy)]
+struct Scorer {
+    model: Bm25Model,
+    k1: f32,
+    delta: f32,
+    /// Documents in the tenant.
+    n: f32,
+}
+
+impl Scorer {
+    fn new(params: &Bm25Params, n: f32) -> Self {
+        Self {
+            model: params.model,
+            k1: params.k1,
+            delta: params.delta(),
+            n,
+        }
+    }
+
+    /// The IDF of a term held by `df` documents.
+    fn idf(&self, df: f32) -> f32 {
+        let n = self.n;
+        match self.model {
+            Bm25Model::Bm25 => idf(n, df),
+            Bm25Model::Bm25l => ((n + 1.0) / (df + 0.5)).ln(),
+            Bm25Model::Bm25Plus => ((n + 1.0) / df.max(1.0)).ln(),
+            Bm25Model::TfIdf => (n / df.max(1.0)).ln_1p(),
+        }
+    }
+
+    /// A clause's contribution from its pseudo-frequency `tf` (see
+    /// `FieldNorm::tf`). Rises with `tf`, so a bound on `tf` bounds it,
+    /// and is linear in `idf`, so a phrase's splits over its terms. For
+    /// BM25 over one field of boost 1 this is [`term_score`].
+    fn score(&self, idf: f32, tf: f32) -> f32 {
+        if tf <= 0.0 {
+            return 0.0;
+        }
+        let saturate = |tf: f32| tf * (self.k1 + 1.0) / (self.k1 + tf).max(1e-6);
+        idf * match self.model {
+            Bm25Model::Bm25 => saturate(tf),
+            Bm25Model::Bm25l => saturate(tf + self.delta),
+            Bm25Model::Bm25Plus => saturate(tf) + self.delta,
+            Bm25Model::TfIdf => tf.ln_1p(),
+        }
+    }
+}
 
+/// Turn the per-doc accumulators into the top-k hit list, attaching the
+/// top-N term contributions when `explain` is set. Expired records must
+/// be gone from `accum` already.
+pub(crate) fn collect_hits(
+    tenant_id: u32,
+    accum: HashMap<u64, f32>,
+    mut explain_hits: HashMap<u64, Vec<crate::core::TermHit>>,
+    k: usize,
+    explain: bool,
+) -> Vec<Hit> {
     let mut hits: Vec<Hit> = accum
         .into_iter()
         .map(|(record_id, score)| {
@@ -600,6 +2064,7 @@ pub(super) fn search_explain(
                 vector_rank: None,
                 bm25_rank: None,
                 term_hits,
+                snippets: Vec::new(),
             }
         })
         .collect();
@@ -619,7 +2084,7 @@ pub(super) fn search_explain(
             .unwrap_or(std::cmp::Ordering::Equal)
     });
 
-    Ok(hits)
+    hits
 }
 
 // ── Schema bootstrap ────────────────────────────────────────────────────
@@ -631,10 +2096,10 @@ pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
         .open_table(BM25_TERM_FST)
         .map_err(|e| Error::Index(e.to_string()))?;
     let _ = txn
-        .open_table(BM25_POSTINGS)
+        .open_table(BM25_BLOCKS)
         .map_err(|e| Error::Index(e.to_string()))?;
     let _ = txn
-        .open_table(BM25_SCORING)
+        .open_table(BM25_POSITIONS)
         .map_err(|e| Error::Index(e.to_string()))?;
     let _ = txn
         .open_table(BM25_DOC_LENS)
@@ -645,6 +2110,15 @@ pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
     let _ = txn
         .open_table(BM25_DOC_TERMS)
         .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(BM25_ANALYZER)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(BM25_FIELD_LENS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(BM25_FIELDS)
+        .map_err(|e| Error::Index(e.to_string()))?;
     Ok(())
 }
 
@@ -653,9 +2127,34 @@ pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::embedded::analyzer::DEFAULT;
     use redb::Database;
     use tempfile::tempdir;
 
+    /// Query settings for a tenant with the default analyzer and no
+    /// configured fields.
+    fn plain(positional: bool) -> Search<'static> {
+        Search {
+            analyzer: &DEFAULT,
+            positional,
+            fields: &NO_FIELDS,
+            params: &BM25,
+        }
+    }
+
+    const BM25: Bm25Params = Bm25Params {
+        model: Bm25Model::Bm25,
+        k1: 1.2,
+        b: 0.75,
+        delta: None,
+    };
+
+    /// Parse with no known fields, dropping the (always unscoped) field.
+    fn clauses(terms: &[&str]) -> Result<Vec<Clause>> {
+        let parsed = parse_query(terms, &DEFAULT, &BTreeSet::new())?;
+        Ok(parsed.into_iter().map(|s| s.clause).collect())
+    }
+
     fn open_db(path: &std::path::Path) -> Database {
         Database::create(path).unwrap()
     }
@@ -663,7 +2162,10 @@ mod tests {
     fn upsert(db: &Database, tenant: u32, rid: u64, text: &str) {
         let txn = db.begin_write().unwrap();
         bootstrap_tables(&txn).unwrap();
-        upsert_one(&txn, tenant, rid, text).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        batch.index(tenant, rid, text);
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
         txn.commit().unwrap();
     }
 
@@ -681,7 +2183,7 @@ mod tests {
         let db = open_db(&dir.path().join("u.redb"));
         upsert(&db, 1, 100, "the quick brown fox");
 
-        let hits = search_explain(&db, 1, &["fox"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["fox"], 10, false, &plain(false)).unwrap();
         assert_eq!(hits.len(), 1);
         assert_eq!(hits[0].record_id, 100);
         assert!(hits[0].score > 0.0);
@@ -696,7 +2198,7 @@ mod tests {
         upsert(&db, 1, 102, "go language");
 
         // "rust" scoring: doc 100 has tf=3, doc 101 tf=1. Doc 102 absent.
-        let hits = search_explain(&db, 1, &["rust"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["rust"], 10, false, &plain(false)).unwrap();
         let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
         assert_eq!(ids, vec![100, 101]);
     }
@@ -709,7 +2211,7 @@ mod tests {
         upsert(&db, 1, 2, "go async language");
         upsert(&db, 1, 3, "rust safety");
 
-        let hits = search_explain(&db, 1, &["rust", "async"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["rust", "async"], 10, false, &plain(false)).unwrap();
         // Doc 1 hits both terms; should outrank docs that hit only one.
         assert_eq!(hits[0].record_id, 1);
     }
@@ -721,11 +2223,11 @@ mod tests {
         upsert(&db, 1, 100, "tenant one document");
         upsert(&db, 2, 200, "tenant two document");
 
-        let hits1 = search_explain(&db, 1, &["document"], 10, false).unwrap();
+        let hits1 = search_explain(&db, 1, &["document"], 10, false, &plain(false)).unwrap();
         assert_eq!(hits1.len(), 1);
         assert_eq!(hits1[0].record_id, 100);
 
-        let hits2 = search_explain(&db, 2, &["document"], 10, false).unwrap();
+        let hits2 = search_explain(&db, 2, &["document"], 10, false, &plain(false)).unwrap();
         assert_eq!(hits2.len(), 1);
         assert_eq!(hits2[0].record_id, 200);
     }
@@ -735,7 +2237,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let db = open_db(&dir.path().join("u.redb"));
         upsert(&db, 1, 1, "the quick brown fox");
-        let hits = search_explain(&db, 1, &["zebra"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["zebra"], 10, false, &plain(false)).unwrap();
         assert!(hits.is_empty());
     }
 
@@ -748,10 +2250,12 @@ mod tests {
 
         // Clear doc 100.
         let txn = db.begin_write().unwrap();
-        clear_one(&txn, 1, 100).unwrap();
+        let mut batch = IndexBatch::default();
+        batch.clear(1, 100);
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
         txn.commit().unwrap();
 
-        let hits = search_explain(&db, 1, &["rust"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["rust"], 10, false, &plain(false)).unwrap();
         assert_eq!(hits.len(), 1);
         assert_eq!(hits[0].record_id, 101);
     }
@@ -768,18 +2272,557 @@ mod tests {
         // Compare to a fresh competing doc.
         upsert(&db, 1, 101, "rust rust rust rust rust");
 
-        let hits = search_explain(&db, 1, &["rust"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["rust"], 10, false, &plain(false)).unwrap();
         // After re-ingest, doc 101 (5x rust) should outrank doc 100 (1x rust).
         assert_eq!(hits[0].record_id, 101);
     }
 
+    fn corpus(db: &Database, tenant: u32) -> CorpusStats {
+        let txn = db.begin_write().unwrap();
+        let stats = read_corpus(&txn, tenant).unwrap();
+        txn.abort().unwrap();
+        stats
+    }
+
+    #[test]
+    fn batch_matches_one_doc_at_a_time() {
+        let dir = tempdir().unwrap();
+        let one_by_one = open_db(&dir.path().join("a.redb"));
+        let batched = open_db(&dir.path().join("b.redb"));
+        let docs = [
+            (1, "rust async language"),
+            (2, "go async"),
+            (3, "rust rust safety"),
+            (2, "go generics language"),
+        ];
+        for (rid, text) in docs {
+            upsert(&one_by_one, 1, rid, text);
+        }
+        upsert(&batched, 1, 9, "gone soon");
+        let txn = batched.begin_write().unwrap();
+        let mut batch = IndexBatch::default();
+        for (rid, text) in docs {
+            batch.index(1, rid, text);
+        }
+        batch.clear(1, 9);
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        let (a, b) = (corpus(&one_by_one, 1), corpus(&batched, 1));
+        assert_eq!((a.doc_count, a.total_doc_len), (3, 9));
+        assert_eq!((b.doc_count, b.total_doc_len), (3, 9));
+        for term in ["rust", "async", "go", "language", "safety", "gone"] {
+            let a = search_explain(&one_by_one, 1, &[term], 10, false, &plain(false)).unwrap();
+            let b = search_explain(&batched, 1, &[term], 10, false, &plain(false)).unwrap();
+            // Equal scores come back in no particular order.
+            let scores = |hits: &[Hit]| -> Vec<(u64, f32)> {
+                let mut out: Vec<_> = hits.iter().map(|h| (h.record_id, h.score)).collect();
+                out.sort_by_key(|&(id, _)| id);
+                out
+            };
+            assert_eq!(scores(&a), scores(&b), "term {term}");
+        }
+    }
+
+    #[test]
+    fn stale_dict_cache_is_reloaded() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let cache = TermDictCache::default();
+        let apply = |batch: IndexBatch, cache: &TermDictCache| {
+            let txn = db.begin_write().unwrap();
+            bootstrap_tables(&txn).unwrap();
+            super::super::expiry::bootstrap_tables(&txn).unwrap();
+            batch.apply(&txn, cache).unwrap();
+            txn.commit().unwrap();
+        };
+        let mut batch = IndexBatch::default();
+        batch.index(1, 1, "alpha beta");
+        batch.index(1, 2, "gamma");
+        apply(batch, &cache);
+
+        // Drop doc 1 and prune its terms around the cache.
+        let mut batch = IndexBatch::default();
+        batch.clear(1, 1);
+        apply(batch, &TermDictCache::default());
+        let txn = db.begin_write().unwrap();
+        assert_eq!(prune_dead_terms(&txn, 1).unwrap(), 2);
+        txn.commit().unwrap();
+
+        let mut batch = IndexBatch::default();
+        batch.index(1, 3, "alpha");
+        apply(batch, &cache);
+        let txn = db.begin_write().unwrap();
+        let dict = read_term_dict(&txn, 1).unwrap();
+        txn.abort().unwrap();
+        assert_eq!(dict.len(), 2);
+        assert_eq!(dict["alpha"], 3, "fresh id, not the pruned one");
+        let hits = search_explain(&db, 1, &["alpha"], 10, false, &plain(false)).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].record_id, 3);
+    }
+
+    #[test]
+    fn pruned_top_k_matches_scoring_every_doc() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let txn = db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        for i in 0..1500u64 {
+            let mut text = "common ".repeat(i as usize % 3 + 1);
+            text += &format!("w{} ", i % 13).repeat(i as usize % 7);
+            if i % 5 == 0 {
+                text += " mid";
+            }
+            if i % 97 == 0 {
+                text += " rare rare";
+            }
+            batch.index(1, i, &text);
+        }
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        for query in [
+            &["common", "rare"][..],
+            &["mid", "common", "w3"],
+            &["rare", "mid", "mid"],
+            &["w1", "w2", "w4", "common"],
+        ] {
+            // With k past the corpus size nothing is pruned.
+            let every = search_explain(&db, 1, query, 10_000, false, &plain(false)).unwrap();
+            let top = search_explain(&db, 1, query, 10, true, &plain(false)).unwrap();
+            assert_eq!(top.len(), 10, "{query:?}");
+            for (got, want) in top.iter().zip(&every) {
+                assert!((got.score - want.score).abs() < 1e-4, "{query:?}");
+                let explained: f32 = got.term_hits.iter().map(|t| t.contribution).sum();
+                assert!((explained - got.score).abs() < 1e-4, "{query:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn parses_phrases_and_near() {
+        let words = |ws: &[&str]| ws.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            clauses(&["\"Force  Majeure\" clause", "\"act of"]).unwrap(),
+            vec![
+                Clause::Phrase(words(&["force", "majeure"])),
+                Clause::Term("clause".into()),
+                Clause::Phrase(words(&["act", "of"])),
+            ]
+        );
+        assert_eq!(
+            clauses(&["a NEAR/2 \"b c\" NEAR/5 d near/3"]).unwrap(),
+            vec![
+                Clause::Near {
+                    left: words(&["a"]),
+                    right: words(&["b", "c"]),
+                    within: 2,
+                },
+                Clause::Near {
+                    left: words(&["b", "c"]),
+                    right: words(&["d"]),
+                    within: 5,
+                },
+                Clause::Term("near".into()),
+                Clause::Term("3".into()),
+            ]
+        );
+        assert_eq!(
+            clauses(&["\"one\""]).unwrap(),
+            vec![Clause::Term("one".into())]
+        );
+        for bad in [
+            "a NEAR/0 b",
+            "a NEAR/x b",
+            "NEAR/2 b",
+            "a NEAR/2",
+            "a NEAR/2 NEAR/2 b",
+        ] {
+            assert!(matches!(clauses(&[bad]), Err(Error::Modality(_))), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parses_expansions() {
+        let expand = |term: &str, how| Clause::Expand {
+            term: term.into(),
+            how,
+        };
+        assert_eq!(
+            clauses(&["Contract* colour~ colr~1 x-ray* a~b"]).unwrap(),
+            vec![
+                expand("contract", Expansion::Prefix),
+                expand("colour", Expansion::Fuzzy(2)),
+                expand("colr", Expansion::Fuzzy(1)),
+                Clause::Term("x".into()),
+                expand("ray", Expansion::Prefix),
+                Clause::Term("a".into()),
+                Clause::Term("b".into()),
+            ]
+        );
+        assert_eq!(expand("colr", Expansion::Fuzzy(1)).to_string(), "colr~1");
+        assert!(clauses(&["* ~"]).unwrap().is_empty());
+        for bad in ["colour~3", "colour~0", "contr* NEAR/2 b"] {
+            assert!(matches!(clauses(&[bad]), Err(Error::Modality(_))), "{bad}");
+        }
+    }
+
+    #[test]
+    fn expanded_terms_are_down_weighted() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        upsert(&db, 1, 1, "contract signed");
+        upsert(&db, 1, 2, "contracts signed");
+        upsert(&db, 1, 3, "contractor hired");
+        upsert(&db, 1, 4, "unrelated words");
+
+        let hits = search_explain(&db, 1, &["contract*"], 10, true, &plain(false)).unwrap();
+        let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids[0], 1, "the exact term outranks its completions");
+        assert_eq!(ids.len(), 3);
+        let exact = search_explain(&db, 1, &["contracts"], 10, false, &plain(false)).unwrap();
+        let hit = hits.iter().find(|h| h.record_id == 2).unwrap();
+        assert!((hit.score - vocab::EXPANSION_WEIGHT * exact[0].score).abs() < 1e-4);
+        let th = &hit.term_hits[0];
+        assert_eq!(th.term, "contracts");
+        assert_eq!(th.expanded_from.as_deref(), Some("contract*"));
+        assert_eq!(th.phrase, None);
+
+        let fuzzy = search_explain(&db, 1, &["contrcat~2"], 10, true, &plain(false)).unwrap();
+        let ids: Vec<u64> = fuzzy.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(
+            fuzzy[0].term_hits[0].expanded_from.as_deref(),
+            Some("contrcat~2")
+        );
+        assert!(
+            search_explain(&db, 1, &["contrcat"], 10, false, &plain(false))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn phrase_clauses_score_their_matches() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let txn = db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::with_positions(Arc::new(BTreeSet::from([1])));
+        batch.index(1, 1, "force majeure excuses force majeure events");
+        batch.index(1, 2, "force majeure clause");
+        batch.index(1, 3, "majeure force clause");
+        for i in 10..60 {
+            batch.index(1, i, &format!("clause {i} filler"));
+        }
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        let hits = search_explain(&db, 1, &["\"force majeure\""], 10, true, &plain(true)).unwrap();
+        let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![1, 2], "two matches outrank one");
+        let th = &hits[1].term_hits;
+        let terms: Vec<&str> = th.iter().map(|t| t.term.as_str()).collect();
+        assert_eq!(terms.len(), 2);
+        assert!(terms.contains(&"force") && terms.contains(&"majeure"));
+        for t in th {
+            assert_eq!(t.phrase.as_deref(), Some("\"force majeure\""));
+            assert_eq!(t.tf, 1);
+        }
+        let explained: f32 = th.iter().map(|t| t.contribution).sum();
+        assert!((explained - hits[1].score).abs() < 1e-4);
+
+        let near =
+            search_explain(&db, 1, &["clause NEAR/1 force"], 10, false, &plain(true)).unwrap();
+        let ids: Vec<u64> = near.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![3]);
+
+        // Mixed with a common term, the pruned top-k agrees with the
+        // full ranking.
+        let query = &["clause", "\"force majeure\""][..];
+        let every = search_explain(&db, 1, query, 1000, false, &plain(true)).unwrap();
+        let top = search_explain(&db, 1, query, 3, false, &plain(true)).unwrap();
+        for (got, want) in top.iter().zip(&every) {
+            assert!((got.score - want.score).abs() < 1e-4);
+        }
+        let mut best: Vec<u64> = top[..2].iter().map(|h| h.record_id).collect();
+        best.sort_unstable();
+        assert_eq!(best, vec![1, 2], "phrase matches outrank the term alone");
+
+        assert!(matches!(
+            search_explain(&db, 1, &["\"force majeure\""], 10, false, &plain(false)),
+            Err(Error::Modality(_))
+        ));
+    }
+
     #[test]
     fn empty_text_records_doc_len_zero() {
         let dir = tempdir().unwrap();
         let db = open_db(&dir.path().join("u.redb"));
         upsert(&db, 1, 100, "   ,,, ...   ");
         // No terms, so query returns nothing.
-        let hits = search_explain(&db, 1, &["anything"], 10, false).unwrap();
+        let hits = search_explain(&db, 1, &["anything"], 10, false, &plain(false)).unwrap();
         assert!(hits.is_empty());
     }
+
+    fn fields(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_field_prefixes() {
+        let known = BTreeSet::from(["title".to_string()]);
+        let parsed = parse_query(
+            &["title:Rust title:\"force majeure\" text:contr* tag:x body"],
+            &DEFAULT,
+            &known,
+        )
+        .unwrap();
+        let shown: Vec<String> = parsed.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            shown,
+            vec![
+                "title:rust",
+                "title:\"force majeure\"",
+                "text:contr*",
+                "tag",
+                "x",
+                "body",
+            ],
+            "unknown `tag:` is read as part of the text"
+        );
+        assert!(parsed[3].field.is_none());
+        assert!(matches!(
+            parse_query(&["title:a NEAR/2 b"], &DEFAULT, &known),
+            Err(Error::Modality(_))
+        ));
+        assert_eq!(
+            parse_query(&["title:a NEAR/2 title:b"], &DEFAULT, &known).unwrap()[0].to_string(),
+            "title:a NEAR/2 title:b"
+        );
+    }
+
+    #[test]
+    fn bm25f_weights_fields() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let txn = db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        batch.index_fields(
+            1,
+            1,
+            "a long body about many things",
+            &fields(&[("title", "rust")]),
+        );
+        batch.index_fields(
+            1,
+            2,
+            "rust appears in the body once",
+            &fields(&[("title", "notes")]),
+        );
+        for i in 10..30 {
+            batch.index_fields(1, i, "filler body", &fields(&[("title", "filler")]));
+        }
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        // Equal weights: both docs match `rust` once.
+        let hits = search_explain(&db, 1, &["rust"], 10, true, &plain(false)).unwrap();
+        let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![1, 2], "the shorter title field wins on length");
+        assert_eq!(hits[0].term_hits[0].field, "title");
+        assert_eq!(hits[1].term_hits[0].field, TEXT_FIELD);
+
+        // Boosting the body flips the order.
+        let boosted = BTreeMap::from([(
+            TEXT_FIELD.to_string(),
+            Bm25Field {
+                boost: 10.0,
+                ..Bm25Field::default()
+            },
+        )]);
+        let search = Search {
+            fields: &boosted,
+            ..plain(false)
+        };
+        let hits = search_explain(&db, 1, &["rust"], 10, false, &search).unwrap();
+        assert_eq!(hits[0].record_id, 2);
+
+        // Qualified clauses only look in their field.
+        let hits = search_explain(&db, 1, &["title:rust"], 10, false, &plain(false)).unwrap();
+        let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![1]);
+        let hits = search_explain(&db, 1, &["text:rust"], 10, false, &plain(false)).unwrap();
+        let ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        assert_eq!(ids, vec![2]);
+        let hits = search_explain(&db, 1, &["title:filler"], 10, false, &plain(false)).unwrap();
+        assert_eq!(hits.len(), 10);
+        let hits = search_explain(&db, 1, &["title:fil*"], 100, false, &plain(false)).unwrap();
+        assert_eq!(hits.len(), 20);
+    }
+
+    #[test]
+    fn bm25f_explain_splits_across_fields() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let txn = db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        batch.index_fields(1, 1, "rust in the body", &fields(&[("title", "rust rust")]));
+        batch.index(1, 2, "nothing here");
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        let hits = search_explain(&db, 1, &["rust"], 10, true, &plain(false)).unwrap();
+        assert_eq!(hits.len(), 1);
+        let th = &hits[0].term_hits;
+        let mut named: Vec<(&str, u32)> = th.iter().map(|t| (t.field.as_str(), t.tf)).collect();
+        named.sort_unstable();
+        assert_eq!(named, vec![(TEXT_FIELD, 1), ("title", 2)]);
+        let explained: f32 = th.iter().map(|t| t.contribution).sum();
+        assert!((explained - hits[0].score).abs() < 1e-4);
+    }
+
+    #[test]
+    fn text_only_scores_match_fieldless_index() {
+        let dir = tempdir().unwrap();
+        let plain_db = open_db(&dir.path().join("a.redb"));
+        let mixed_db = open_db(&dir.path().join("b.redb"));
+        let docs = ["rust is fast", "rust and go", "python is slow", "go go go"];
+        for (i, d) in docs.iter().enumerate() {
+            upsert(&plain_db, 1, i as u64, d);
+        }
+        let txn = mixed_db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        for (i, d) in docs.iter().enumerate() {
+            batch.index_fields(1, i as u64, d, &fields(&[("tags", "unrelated")]));
+        }
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        for q in ["rust", "go", "text:rust is"] {
+            let a = search_explain(&plain_db, 1, &[q], 10, false, &plain(false)).unwrap();
+            let b = search_explain(&mixed_db, 1, &[q], 10, false, &plain(false)).unwrap();
+            assert_eq!(a.len(), b.len(), "{q}");
+            let scores: HashMap<u64, f32> = b.iter().map(|h| (h.record_id, h.score)).collect();
+            for x in &a {
+                assert!((x.score - scores[&x.record_id]).abs() < 1e-5, "{q}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_field_names() {
+        for bad in ["", "Title", "1st", "text", "a-b", "a\u{1f}b"] {
+            assert!(
+                matches!(
+                    check_field_names(&fields(&[(bad, "x")])),
+                    Err(Error::Modality(_))
+                ),
+                "{bad:?}"
+            );
+        }
+        assert!(check_field_names(&fields(&[("title", "x"), ("tag_2", "y")])).is_ok());
+    }
+
+    #[test]
+    fn models_differ_on_long_documents() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let long = format!("rust rust {}", "filler ".repeat(200));
+        upsert(&db, 1, 1, "rust");
+        upsert(&db, 1, 2, &long);
+        for i in 10..20 {
+            upsert(&db, 1, i, "other words");
+        }
+        let top = |params: Bm25Params| {
+            let search = Search {
+                params: &params,
+                ..plain(false)
+            };
+            let hits = search_explain(&db, 1, &["rust"], 10, true, &search).unwrap();
+            for h in &hits {
+                let explained: f32 = h.term_hits.iter().map(|t| t.contribution).sum();
+                assert!((explained - h.score).abs() < 1e-4, "{params:?}");
+            }
+            hits.iter()
+                .map(|h| (h.record_id, h.score))
+                .collect::<Vec<_>>()
+        };
+        let bm25 = top(BM25);
+        assert_eq!(bm25[0].0, 1, "length normalization favours the short doc");
+        // The same ranking as the fixed-parameter scorer fjall uses.
+        let df = 2.0;
+        let want = term_score(idf(12.0, df), 1, 1.0, (1.0 + 202.0 + 20.0) / 12.0);
+        assert!((bm25[0].1 - want).abs() < 1e-4, "{bm25:?} {want}");
+
+        let no_length = top(Bm25Params { b: 0.0, ..BM25 });
+        assert_eq!(no_length[0].0, 2, "b = 0 lets two matches win");
+        let tf_idf = top(Bm25Params {
+            model: Bm25Model::TfIdf,
+            ..BM25
+        });
+        assert_eq!(tf_idf[0].0, 2, "TF-IDF ignores length");
+        let gap = |hits: &[(u64, f32)]| {
+            let score = |id| hits.iter().find(|h| h.0 == id).unwrap().1;
+            score(2) / score(1)
+        };
+        let plus = top(Bm25Params {
+            model: Bm25Model::Bm25Plus,
+            ..BM25
+        });
+        let l = top(Bm25Params {
+            model: Bm25Model::Bm25l,
+            ..BM25
+        });
+        assert!(gap(&plus) > gap(&bm25), "BM25+ floors the long doc");
+        assert!(gap(&l) > gap(&bm25), "BM25L penalizes it less");
+    }
+
+    #[test]
+    fn pruned_top_k_matches_full_ranking_for_every_model() {
+        let dir = tempdir().unwrap();
+        let db = open_db(&dir.path().join("u.redb"));
+        let txn = db.begin_write().unwrap();
+        bootstrap_tables(&txn).unwrap();
+        super::super::expiry::bootstrap_tables(&txn).unwrap();
+        let mut batch = IndexBatch::default();
+        for i in 0..600u64 {
+            let mut text = "common ".repeat(1 + (i % 7) as usize);
+            if i % 13 == 0 {
+                text.push_str("rare rare ");
+            }
+            text.push_str(&"pad ".repeat((i % 31) as usize));
+            batch.index(1, i, &text);
+        }
+        batch.apply(&txn, &TermDictCache::default()).unwrap();
+        txn.commit().unwrap();
+
+        for model in Bm25Model::ALL {
+            let params = Bm25Params { model, ..BM25 };
+            let search = Search {
+                params: &params,
+                ..plain(false)
+            };
+            let query = &["rare", "common"][..];
+            let every = search_explain(&db, 1, query, 10_000, false, &search).unwrap();
+            let top = search_explain(&db, 1, query, 10, false, &search).unwrap();
+            assert_eq!(top.len(), 10);
+            for (got, want) in top.iter().zip(&every) {
+                assert!((got.score - want.score).abs() < 1e-4, "{model:?}");
+            }
+        }
+    }
 }
@@ -0,0 +1,165 @@
+//! Change log for the embedded backend.
+//!
+//! | Table                  | Key    | Value                              |
+//! | ---------------------- | ------ | ---------------------------------- |
+//! | `ucfp/changes/v1`      | `seq`  | [`ChangeEvent`] as JSON            |
+//! | `ucfp/change_head/v1`  | `()`   | last sequence number handed out    |
+//!
+//! Writers append through a [`ChangeLog`] opened on their own write
+//! transaction, so an entry commits (or rolls back) with the change it
+//! describes. The head is kept separately from the log so sequence
+//! numbers keep rising after retention empties the log.
+
+use redb::{ReadTransaction, ReadableTable, Table, TableDefinition, WriteTransaction};
+
+use crate::core::{ChangeEvent, ChangeKind};
+use crate::error::{Error, Result};
+
+pub(super) const CHANGES: TableDefinition<'_, u64, &[u8]> = TableDefinition::new("ucfp/changes/v1");
+
+pub(super) const CHANGE_HEAD: TableDefinition<'_, (), u64> =
+    TableDefinition::new("ucfp/change_head/v1");
+
+pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
+    let _ = txn
+        .open_table(CHANGES)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(CHANGE_HEAD)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
+
+/// Appender bound to one write transaction. Call [`Self::finish`]
+/// before committing to persist the new head.
+pub(super) struct ChangeLog<'txn> {
+    log: Table<'txn, u64, &'static [u8]>,
+    head: u64,
+    start: u64,
+    at_ms: u64,
+}
+
+impl<'txn> ChangeLog<'txn> {
+    pub fn open(txn: &'txn WriteTransaction, at_ms: u64) -> Result<Self> {
+        let head = txn
+            .open_table(CHANGE_HEAD)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .get(())
+            .map_err(|e| Error::Index(e.to_string()))?
+            .map_or(0, |v| v.value());
+        let log = txn
+            .open_table(CHANGES)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        Ok(Self {
+            log,
+            head,
+            start: head,
+            at_ms,
+        })
+    }
+
+    /// Time every event of this transaction is stamped with.
+    pub fn at_ms(&self) -> u64 {
+        self.at_ms
+    }
+
+    pub fn push(
+        &mut self,
+        kind: ChangeKind,
+        tenant_id: u32,
+        record_id: Option<u64>,
+        version: Option<u64>,
+    ) -> Result<()> {
+        self.head += 1;
+        let event = ChangeEvent {
+            seq: self.head,
+            at_ms: self.at_ms,
+            kind,
+            tenant_id,
+            record_id,
+            version,
+        };
+        let row =
+            serde_json::to_vec(&event).map_err(|e| Error::Index(format!("change encode: {e}")))?;
+        self.log
+            .insert(self.head, row.as_slice())
+            .map_err(|e| Error::Index(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn finish(self, txn: &WriteTransaction) -> Result<()> {
+        drop(self.log);
+        if self.head != self.start {
+            txn.open_table(CHANGE_HEAD)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .insert((), self.head)
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn decode(row: &[u8]) -> Result<ChangeEvent> {
+    serde_json::from_slice(row).map_err(|e| Error::Index(format!("change decode: {e}")))
+}
+
+/// Last sequence number handed out, 0 before the first change.
+pub(super) fn head(txn: &ReadTransaction) -> Result<u64> {
+    Ok(txn
+        .open_table(CHANGE_HEAD)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(())
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map_or(0, |v| v.value()))
+}
+
+/// Up to `limit` entries after `seq`, oldest first. Fails with
+/// [`Error::ChangesPruned`] when retention dropped `seq + 1`; `seq == 0`
+/// reads from the oldest retained entry instead.
+pub(super) fn since(txn: &ReadTransaction, seq: u64, limit: usize) -> Result<Vec<ChangeEvent>> {
+    let Some(from) = seq.checked_add(1) else {
+        return Ok(Vec::new());
+    };
+    let log = txn
+        .open_table(CHANGES)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    if seq > 0 {
+        let oldest = match log.first().map_err(|e| Error::Index(e.to_string()))? {
+            Some((k, _)) => k.value(),
+            None => head(txn)? + 1,
+        };
+        if from < oldest {
+            return Err(Error::ChangesPruned { since: seq, oldest });
+        }
+    }
+    log.range(from..)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .take(limit)
+        .map(|entry| {
+            let (_, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            decode(v.value())
+        })
+        .collect()
+}
+
+/// Drop up to `limit` of the oldest entries committed before `cutoff_ms`.
+pub(super) fn prune(txn: &WriteTransaction, cutoff_ms: u64, limit: usize) -> Result<u64> {
+    let mut log = txn
+        .open_table(CHANGES)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut doomed = Vec::new();
+    for entry in log.iter().map_err(|e| Error::Index(e.to_string()))? {
+        if doomed.len() == limit {
+            break;
+        }
+        let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        if decode(v.value())?.at_ms >= cutoff_ms {
+            break;
+        }
+        doomed.push(k.value());
+    }
+    for seq in &doomed {
+        log.remove(*seq).map_err(|e| Error::Index(e.to_string()))?;
+    }
+    Ok(doomed.len() as u64)
+}
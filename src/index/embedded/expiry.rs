@@ -0,0 +1,143 @@
+//! Per-record TTL bookkeeping for the embedded backend.
+//!
+//! Two tables, both written in the same redb transaction as the record:
+//!
+//! | Table                    | Key                          | Value        |
+//! | ------------------------ | ---------------------------- | ------------ |
+//! | `ucfp/expiry/v1`         | `(tenant, record_id)`        | `expires_at` |
+//! | `ucfp/expiry_due/v1`     | `(expires_at, tenant, rid)`  | `()`         |
+//!
+//! `expiry` answers "is this record dead right now" for the query paths
+//! with a point lookup per candidate ([`Expired`]), so a selective query
+//! stays cheap however many of the tenant's records carry a TTL. `expiry_due`
+//! is the sweep queue in deadline order. Re-upserting or deleting a
+//! record only touches `expiry`; stale queue entries are recognised
+//! (and dropped) by the sweeper when their deadline comes round.
+
+use redb::{ReadOnlyTable, ReadTransaction, ReadableTable, TableDefinition, WriteTransaction};
+
+use crate::core::Record;
+use crate::error::{Error, Result};
+use crate::index::Purging;
+
+pub(super) const EXPIRY: TableDefinition<'_, (u32, u64), u64> =
+    TableDefinition::new("ucfp/expiry/v1");
+
+pub(super) const EXPIRY_DUE: TableDefinition<'_, (u64, u32, u64), ()> =
+    TableDefinition::new("ucfp/expiry_due/v1");
+
+pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
+    let _ = txn
+        .open_table(EXPIRY)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(EXPIRY_DUE)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
+
+/// Track (or stop tracking) `rec`'s expiry as part of its upsert.
+pub(super) fn record(txn: &WriteTransaction, rec: &Record) -> Result<()> {
+    let key = (rec.tenant_id, rec.record_id);
+    let mut expiry = txn
+        .open_table(EXPIRY)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    match rec.expires_at {
+        Some(at) => {
+            expiry
+                .insert(key, at)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            txn.open_table(EXPIRY_DUE)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .insert((at, rec.tenant_id, rec.record_id), ())
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+        None => {
+            expiry
+                .remove(key)
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Expiry of one tenant's records as of one instant, looked up record by
+/// record in an open read transaction.
+pub(super) struct Expired {
+    table: ReadOnlyTable<(u32, u64), u64>,
+    tenant_id: u32,
+    now_ms: u64,
+}
+
+impl Expired {
+    pub(super) fn open(txn: &ReadTransaction, tenant_id: u32, now_ms: u64) -> Result<Self> {
+        let table = txn
+            .open_table(EXPIRY)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        Ok(Self {
+            table,
+            tenant_id,
+            now_ms,
+        })
+    }
+
+    /// `true` if `record_id` is past its expiry, though not yet swept.
+    pub(super) fn contains(&self, record_id: u64) -> Result<bool> {
+        Ok(self
+            .table
+            .get((self.tenant_id, record_id))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .is_some_and(|at| at.value() <= self.now_ms))
+    }
+}
+
+/// Pop sweep-queue entries due at `now_ms` until `limit` really-expired
+/// `(tenant, record_id)` pairs are collected or the due range is empty.
+/// Entries whose record was since deleted or re-upserted with another
+/// expiry are dropped on the way without counting, as are those of a
+/// tenant being purged: the purge removes the record itself.
+pub(super) fn take_due(
+    txn: &WriteTransaction,
+    now_ms: u64,
+    limit: usize,
+    purging: &Purging,
+) -> Result<Vec<(u32, u64)>> {
+    let mut due_table = txn
+        .open_table(EXPIRY_DUE)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut expiry = txn
+        .open_table(EXPIRY)
+        .map_err(|e| Error::Index(e.to_string()))?;
+
+    let mut out = Vec::new();
+    while out.len() < limit {
+        let want = limit - out.len();
+        let due: Vec<(u64, u32, u64)> = due_table
+            .range(..=(now_ms, u32::MAX, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .take(want)
+            .map(|entry| entry.map(|(k, _)| k.value()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let exhausted = due.len() < want;
+        for (at, tenant_id, record_id) in due {
+            due_table
+                .remove((at, tenant_id, record_id))
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let current = expiry
+                .get((tenant_id, record_id))
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|v| v.value());
+            if current.is_some_and(|t| t <= now_ms) && !purging.is_purging(tenant_id) {
+                expiry
+                    .remove((tenant_id, record_id))
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                out.push((tenant_id, record_id));
+            }
+        }
+        if exhausted {
+            break;
+        }
+    }
+    Ok(out)
+}
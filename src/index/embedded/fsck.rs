@@ -0,0 +1,1201 @@
+//! Integrity checker for the embedded backend.
+//!
+//! Invariants that otherwise live only in the write paths, checked one
+//! tenant at a time:
+//!
+//! | Check                      | Invariant                                                   |
+//! | -------------------------- | ----------------------------------------------------------- |
+//! | `catalog.decode`           | every catalog row decodes                                   |
+//! | `catalog.fingerprint`      | every catalog row has a fingerprint blob                    |
+//! | `catalog.fingerprint_len`  | `fingerprint_len` = fingerprint blob length                 |
+//! | `catalog.embedding_dim`    | `embedding_dim` × 4 = vector blob length (0 = no vector)    |
+//! | `catalog.metadata_len`     | `metadata_len` = metadata blob length                       |
+//! | `expiry.index`             | `ucfp/expiry/v1` holds exactly the catalog's `expires_at`   |
+//! | `orphan.*`                 | no fingerprint / vector / metadata / expiry / text / text fields row without a catalog row |
+//! | `orphan.versions`          | history without a catalog row ends in a tombstone           |
+//! | `bm25.term_fst`            | the term dictionary decodes and maps terms to distinct ids  |
+//! | `bm25.postings`            | posting lists belong to dictionary terms, one entry per doc |
+//! | `bm25.blocks`              | posting blocks decode, ascend keyed by their last doc, with exact max tf / min length and the stored length of each entry's field |
+//! | `bm25.positions`           | positions rows decode and hold one position per occurrence of a posting's tf |
+//! | `bm25.doc_terms`           | a doc's term ids = the terms whose posting list has it      |
+//! | `bm25.doc_lens`            | doc length = the sum of its text's term frequencies         |
+//! | `bm25.field_lens`          | a doc's named field lengths = the sums of their term frequencies |
+//! | `bm25.orphan_doc`          | every indexed doc has a catalog row                         |
+//! | `bm25.corpus`              | `CorpusStats` = doc count / summed doc lengths; ids below `next_term_id` |
+//! | `bm25.fields`              | the tenant's field totals = the summed field lengths        |
+//! | `stats.counters`           | `ucfp/stats/v1` = the counters recounted from the catalog   |
+//!
+//! Repair treats the blobs and the docs and term frequencies in the BM25
+//! posting blocks as primary. Catalog lengths, the expiry index, the
+//! blocks themselves, `doc_terms`, `doc_lens`, `field_lens`, corpus and
+//! field stats and tenant counters are rebuilt from them and orphans
+//! dropped, one write transaction per tenant; a block that does not
+//! decode is dropped with its entries, and so is a positions row that
+//! disagrees with the rebuilt postings. With text retention on, a
+//! repaired BM25 index is then rebuilt once more from the retained text
+//! the way `reindex` does, positions included; a tenant with records
+//! indexed before retention keeps the one rebuilt from the blocks. A
+//! missing fingerprint blob or an undecodable row, term dictionary or
+//! newest history row is reported but left alone.
+//!
+//! Checking takes no lock. Only a tenant with something to repair is
+//! read again under the write lock, so repair rewrites exactly the state
+//! it checked and a clean tenant never holds up writers.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use redb::{
+    Database, Key, ReadOnlyTable, ReadTransaction, ReadableDatabase, ReadableTable,
+    TableDefinition, Value, WriteTransaction,
+};
+
+use super::analyzer::Analyzer;
+use super::bm25::{
+    self, BM25_BLOCKS, BM25_CORPUS, BM25_DOC_LENS, BM25_DOC_TERMS, BM25_FIELD_LENS, BM25_FIELDS,
+    BM25_POSITIONS, BM25_TERM_FST, CorpusStats, TEXT_FIELD,
+};
+use super::expiry::{EXPIRY, EXPIRY_DUE};
+use super::positions;
+use super::postings::{self, BlockHeader, BlockKey, Posting};
+use super::reindex;
+use super::stats::{self, Delta};
+use super::versions::{self, VERSIONS};
+use super::{CATALOG, CatalogEntry, FINGERPRINTS, METADATA, TEXT, TEXT_FIELDS, VECTORS};
+use crate::core::{FsckReport, FsckViolation};
+use crate::error::{Error, Result};
+use crate::index::Purging;
+
+fn redb_err(e: impl std::fmt::Display) -> Error {
+    Error::Index(e.to_string())
+}
+
+fn open<K: Key + 'static, V: Value + 'static>(
+    txn: &ReadTransaction,
+    def: TableDefinition<'_, K, V>,
+) -> Result<ReadOnlyTable<K, V>> {
+    txn.open_table(def).map_err(redb_err)
+}
+
+/// What repair needs to rebuild BM25 from retained text.
+pub(super) struct TextRebuild {
+    pub analyzers: Arc<BTreeMap<u32, Analyzer>>,
+    pub positional: Arc<BTreeSet<u32>>,
+    pub purging: Arc<Purging>,
+}
+
+impl TextRebuild {
+    /// Reindex `tenant_id` from its retained text, tokenizing off the
+    /// write lock. Leaves the index alone when records indexed without
+    /// retention stand in the way.
+    fn run(&self, db: &Database, tenant_id: u32) -> Result<()> {
+        let positional = self.positional.contains(&tenant_id);
+        let analyzer = bm25::tenant_analyzer(&self.analyzers, tenant_id);
+        let mut docs = BTreeMap::new();
+        let rebuilt = loop {
+            let after = docs.keys().next_back().copied();
+            match reindex::tokenize_page(db, tenant_id, after, positional, analyzer) {
+                Ok(page) if page.is_empty() => {
+                    break reindex::swap(db, tenant_id, docs, positional, analyzer, &self.purging);
+                }
+                Ok(page) => docs.extend(page),
+                Err(e) => break Err(e),
+            }
+        };
+        match rebuilt {
+            Ok(_) | Err(Error::Incompatible(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Check `tenant` (every tenant when `None`); with `repair`, fix what is
+/// derivable, rebuilding BM25 from retained text when `text` is given.
+pub(super) fn run(
+    db: &Database,
+    tenant: Option<u32>,
+    repair: bool,
+    text: Option<&TextRebuild>,
+) -> Result<FsckReport> {
+    let tenants = match tenant {
+        Some(t) => BTreeSet::from([t]),
+        None => tenants(&db.begin_read().map_err(redb_err)?)?,
+    };
+    let mut report = FsckReport {
+        repair,
+        ..FsckReport::default()
+    };
+    for tenant_id in tenants {
+        report.tenants += 1;
+        let state = TenantState::load(&db.begin_read().map_err(redb_err)?, tenant_id)?;
+        let mut findings = state.check(tenant_id);
+        let mut records = state.catalog.len() as u64;
+        if repair && findings.iter().any(|f| f.derivable) {
+            // Holding the write lock, a read transaction sees exactly the
+            // state this write transaction starts from. Writers may have
+            // moved on since the check, so look again.
+            let txn = db.begin_write().map_err(redb_err)?;
+            let state = TenantState::load(&db.begin_read().map_err(redb_err)?, tenant_id)?;
+            findings = state.check(tenant_id);
+            records = state.catalog.len() as u64;
+            if findings.iter().any(|f| f.derivable) {
+                state.repair(&txn, tenant_id, &findings)?;
+                txn.commit().map_err(redb_err)?;
+            } else {
+                txn.abort().map_err(redb_err)?;
+            }
+            let bm25 = findings
+                .iter()
+                .any(|f| f.derivable && f.violation.check.starts_with("bm25."));
+            if let Some(text) = text.filter(|_| bm25) {
+                text.run(db, tenant_id)?;
+            }
+        }
+        report.records += records;
+        report.violations.extend(findings.into_iter().map(|mut f| {
+            f.violation.repaired = repair && f.derivable;
+            f.violation
+        }));
+    }
+    Ok(report)
+}
+
+/// Every tenant with a row in any per-tenant table.
+fn tenants(txn: &ReadTransaction) -> Result<BTreeSet<u32>> {
+    let mut out = BTreeSet::new();
+    for def in [
+        CATALOG,
+        FINGERPRINTS,
+        METADATA,
+        VECTORS,
+        TEXT_FIELDS,
+        BM25_DOC_TERMS,
+    ] {
+        tenants_in(&open(txn, def)?, &mut out)?;
+    }
+    tenants_in(&open(txn, TEXT)?, &mut out)?;
+    for def in [BM25_BLOCKS, BM25_POSITIONS, VERSIONS] {
+        let table: ReadOnlyTable<BlockKey, &[u8]> = open(txn, def)?;
+        let mut from = Some(0u32);
+        while let Some(t) = from {
+            let Some(first) = table.range((t, 0, 0)..).map_err(redb_err)?.next() else {
+                break;
+            };
+            let tenant = first.map_err(redb_err)?.0.value().0;
+            out.insert(tenant);
+            from = tenant.checked_add(1);
+        }
+    }
+    tenants_in(&open(txn, BM25_DOC_LENS)?, &mut out)?;
+    tenants_in(&open(txn, BM25_FIELD_LENS)?, &mut out)?;
+    tenants_in(&open(txn, EXPIRY)?, &mut out)?;
+    out.extend(stats::tenants(txn)?);
+    for def in [BM25_TERM_FST, BM25_CORPUS, BM25_FIELDS] {
+        for entry in open(txn, def)?.iter().map_err(redb_err)? {
+            out.insert(entry.map_err(redb_err)?.0.value());
+        }
+    }
+    Ok(out)
+}
+
+/// Tenants of a `(tenant, id)` table, one range probe per tenant.
+fn tenants_in<V: Value + 'static>(
+    table: &ReadOnlyTable<(u32, u64), V>,
+    out: &mut BTreeSet<u32>,
+) -> Result<()> {
+    let mut from = 0u32;
+    loop {
+        let Some(first) = table.range((from, 0)..).map_err(redb_err)?.next() else {
+            return Ok(());
+        };
+        let tenant = first.map_err(redb_err)?.0.value().0;
+        out.insert(tenant);
+        match tenant.checked_add(1) {
+            Some(next) => from = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+fn blobs(
+    txn: &ReadTransaction,
+    def: TableDefinition<'_, (u32, u64), &'static [u8]>,
+    tenant: u32,
+) -> Result<BTreeMap<u64, Vec<u8>>> {
+    let mut out = BTreeMap::new();
+    for entry in open(txn, def)?
+        .range((tenant, 0)..=(tenant, u64::MAX))
+        .map_err(redb_err)?
+    {
+        let (k, v) = entry.map_err(redb_err)?;
+        out.insert(k.value().1, v.value().to_vec());
+    }
+    Ok(out)
+}
+
+fn blob_lens(
+    txn: &ReadTransaction,
+    def: TableDefinition<'_, (u32, u64), &'static [u8]>,
+    tenant: u32,
+) -> Result<BTreeMap<u64, usize>> {
+    let mut out = BTreeMap::new();
+    for entry in open(txn, def)?
+        .range((tenant, 0)..=(tenant, u64::MAX))
+        .map_err(redb_err)?
+    {
+        let (k, v) = entry.map_err(redb_err)?;
+        out.insert(k.value().1, v.value().len());
+    }
+    Ok(out)
+}
+
+fn keys<V: Value + 'static>(
+    txn: &ReadTransaction,
+    def: TableDefinition<'_, (u32, u64), V>,
+    tenant: u32,
+) -> Result<BTreeSet<u64>> {
+    let mut out = BTreeSet::new();
+    for entry in open(txn, def)?
+        .range((tenant, 0)..=(tenant, u64::MAX))
+        .map_err(redb_err)?
+    {
+        out.insert(entry.map_err(redb_err)?.0.value().1);
+    }
+    Ok(out)
+}
+
+/// Records of `tenant` with history but no catalog row, and whether
+/// their newest history row is a tombstone (`None` when it does not
+/// decode).
+fn uncatalogued_history(
+    txn: &ReadTransaction,
+    tenant: u32,
+    catalog: &BTreeMap<u64, Option<CatalogEntry>>,
+) -> Result<BTreeMap<u64, Option<bool>>> {
+    let table = open(txn, VERSIONS)?;
+    let mut out = BTreeMap::new();
+    let mut from = 0u64;
+    loop {
+        let Some(first) = table
+            .range((tenant, from, 0)..=(tenant, u64::MAX, u64::MAX))
+            .map_err(redb_err)?
+            .next()
+        else {
+            return Ok(out);
+        };
+        let rid = first.map_err(redb_err)?.0.value().1;
+        if !catalog.contains_key(&rid) {
+            let newest = table
+                .range((tenant, rid, 0)..=(tenant, rid, u64::MAX))
+                .map_err(redb_err)?
+                .next_back()
+                .transpose()
+                .map_err(redb_err)?;
+            let tombstone = newest.and_then(|(_, v)| {
+                versions::decode(v.value())
+                    .ok()
+                    .map(|snapshot| snapshot.catalog.deleted)
+            });
+            out.insert(rid, tombstone);
+        }
+        match rid.checked_add(1) {
+            Some(next) => from = next,
+            None => return Ok(out),
+        }
+    }
+}
+
+fn scalars<V>(
+    txn: &ReadTransaction,
+    def: TableDefinition<'_, (u32, u64), V>,
+    tenant: u32,
+) -> Result<BTreeMap<u64, V::SelfType<'static>>>
+where
+    V: Value + 'static,
+    for<'a> V: Value<SelfType<'a> = V>,
+{
+    let mut out = BTreeMap::new();
+    for entry in open(txn, def)?
+        .range((tenant, 0)..=(tenant, u64::MAX))
+        .map_err(redb_err)?
+    {
+        let (k, v) = entry.map_err(redb_err)?;
+        out.insert(k.value().1, v.value());
+    }
+    Ok(out)
+}
+
+/// One violation plus whether repair can rebuild it.
+struct Finding {
+    violation: FsckViolation,
+    derivable: bool,
+}
+
+/// One stored posting block.
+struct StoredBlock {
+    /// Last doc id, from the key.
+    last: u64,
+    header: Option<BlockHeader>,
+    entries: std::result::Result<Vec<Posting>, String>,
+}
+
+/// Everything stored for one tenant.
+struct TenantState {
+    catalog: BTreeMap<u64, Option<CatalogEntry>>,
+    fingerprints: BTreeMap<u64, usize>,
+    vectors: BTreeMap<u64, usize>,
+    metadata: BTreeMap<u64, usize>,
+    expiry: BTreeMap<u64, u64>,
+    text: BTreeSet<u64>,
+    text_fields: BTreeSet<u64>,
+    /// See [`uncatalogued_history`].
+    history: BTreeMap<u64, Option<bool>>,
+    dict: Option<BTreeMap<String, u64>>,
+    dict_error: Option<String>,
+    blocks: BTreeMap<u64, Vec<StoredBlock>>,
+    /// `(doc, tf)` per term, from the blocks that decode.
+    scoring: BTreeMap<u64, Vec<(u64, u32)>>,
+    /// Positions per `(term, doc)`.
+    positions: BTreeMap<(u64, u64), std::result::Result<Vec<u32>, String>>,
+    doc_lens: BTreeMap<u64, u32>,
+    /// Named field lengths per doc; `None` when the row does not decode.
+    field_lens: BTreeMap<u64, Option<BTreeMap<String, u32>>>,
+    /// The named field of each dictionary term outside the text.
+    term_fields: BTreeMap<u64, String>,
+    doc_terms: BTreeMap<u64, Vec<u64>>,
+    corpus: Option<Vec<u8>>,
+    /// Field totals; `Some(None)` when the row does not decode.
+    fields: Option<Option<BTreeMap<String, u64>>>,
+    counters: BTreeMap<String, u64>,
+}
+
+impl TenantState {
+    fn load(txn: &ReadTransaction, tenant: u32) -> Result<Self> {
+        let catalog = blobs(txn, CATALOG, tenant)?
+            .into_iter()
+            .map(|(rid, row)| (rid, CatalogEntry::decode(&row).ok()))
+            .collect();
+        let history = uncatalogued_history(txn, tenant, &catalog)?;
+        let (dict, dict_error) = match open(txn, BM25_TERM_FST)?.get(tenant).map_err(redb_err)? {
+            None => (Some(BTreeMap::new()), None),
+            Some(v) => match bm25::decode_term_dict(v.value().to_vec()) {
+                Ok(d) => (Some(d), None),
+                Err(e) => (None, Some(e.to_string())),
+            },
+        };
+        let mut blocks: BTreeMap<u64, Vec<StoredBlock>> = BTreeMap::new();
+        for entry in open(txn, BM25_BLOCKS)?
+            .range(postings::tenant_range(tenant))
+            .map_err(redb_err)?
+        {
+            let (k, v) = entry.map_err(redb_err)?;
+            let (_, tid, last) = k.value();
+            blocks.entry(tid).or_default().push(StoredBlock {
+                last,
+                header: postings::header(v.value()).ok(),
+                entries: postings::decode(v.value()).map_err(|e| e.to_string()),
+            });
+        }
+        let scoring = blocks
+            .iter()
+            .map(|(&tid, blocks)| {
+                let entries = blocks
+                    .iter()
+                    .filter_map(|b| b.entries.as_ref().ok())
+                    .flatten()
+                    .map(|p| (p.doc, p.tf))
+                    .collect();
+                (tid, entries)
+            })
+            .collect();
+        let mut positions = BTreeMap::new();
+        for entry in open(txn, BM25_POSITIONS)?
+            .range(postings::tenant_range(tenant))
+            .map_err(redb_err)?
+        {
+            let (k, v) = entry.map_err(redb_err)?;
+            let (_, tid, doc) = k.value();
+            let decoded = positions::decode(v.value()).map_err(|e| e.to_string());
+            positions.insert((tid, doc), decoded);
+        }
+        let doc_terms = blobs(txn, BM25_DOC_TERMS, tenant)?
+            .into_iter()
+            .map(|(doc, raw)| (doc, bm25::unpack_term_ids(&raw)))
+            .collect();
+        let corpus = open(txn, BM25_CORPUS)?
+            .get(tenant)
+            .map_err(redb_err)?
+            .map(|v| v.value().to_vec());
+        let field_lens = blobs(txn, BM25_FIELD_LENS, tenant)?
+            .into_iter()
+            .map(|(doc, raw)| (doc, bm25::decode_lens(&raw).ok()))
+            .collect();
+        let fields = open(txn, BM25_FIELDS)?
+            .get(tenant)
+            .map_err(redb_err)?
+            .map(|v| bm25::decode_lens(v.value()).ok());
+        let term_fields = dict
+            .iter()
+            .flatten()
+            .filter_map(|(key, &tid)| match bm25::split_key(key) {
+                (TEXT_FIELD, _) => None,
+                (field, _) => Some((tid, field.to_string())),
+            })
+            .collect();
+        Ok(Self {
+            catalog,
+            fingerprints: blob_lens(txn, FINGERPRINTS, tenant)?,
+            vectors: blob_lens(txn, VECTORS, tenant)?,
+            metadata: blob_lens(txn, METADATA, tenant)?,
+            expiry: scalars(txn, EXPIRY, tenant)?,
+            text: keys(txn, TEXT, tenant)?,
+            text_fields: keys(txn, TEXT_FIELDS, tenant)?,
+            history,
+            dict,
+            dict_error,
+            blocks,
+            scoring,
+            positions,
+            doc_lens: scalars(txn, BM25_DOC_LENS, tenant)?,
+            field_lens,
+            term_fields,
+            doc_terms,
+            corpus,
+            fields,
+            counters: stats::read(txn, tenant)?,
+        })
+    }
+
+    /// The catalog row with its lengths taken from the blobs, or `None`
+    /// when it already agrees.
+    fn fixed_row(&self, rid: u64, row: &CatalogEntry) -> Option<CatalogEntry> {
+        let mut fixed = row.clone();
+        if let Some(&len) = self.fingerprints.get(&rid) {
+            fixed.fingerprint_len = len as u32;
+        }
+        match self.vectors.get(&rid) {
+            Some(&len) if len % 4 == 0 => fixed.embedding_dim = (len / 4) as u32,
+            Some(_) => {}
+            None => fixed.embedding_dim = 0,
+        }
+        fixed.metadata_len = self.metadata.get(&rid).copied().unwrap_or(0) as u32;
+        let changed = (
+            fixed.fingerprint_len,
+            fixed.embedding_dim,
+            fixed.metadata_len,
+        ) != (row.fingerprint_len, row.embedding_dim, row.metadata_len);
+        changed.then_some(fixed)
+    }
+
+    /// Counters as the catalog, with lengths fixed, says they should be.
+    fn expected_counters(&self) -> BTreeMap<String, u64> {
+        let mut delta = Delta::default();
+        for (&rid, row) in &self.catalog {
+            if let Some(row) = row {
+                delta.add(0, &self.fixed_row(rid, row).unwrap_or_else(|| row.clone()));
+            }
+        }
+        delta
+            .into_counts()
+            .into_iter()
+            .map(|((_, name), n)| (name, n))
+            .collect()
+    }
+
+    /// Posting lists as repair rebuilds them: dictionary terms only, one
+    /// entry per doc (the latest), catalogued docs only.
+    fn canonical_scoring(&self) -> BTreeMap<u64, Vec<(u64, u32)>> {
+        let known: Option<BTreeSet<u64>> =
+            self.dict.as_ref().map(|d| d.values().copied().collect());
+        self.scoring
+            .iter()
+            .filter(|(tid, _)| known.as_ref().is_none_or(|k| k.contains(tid)))
+            .map(|(tid, entries)| {
+                let mut by_doc = BTreeMap::new();
+                for &(doc, tf) in entries {
+                    if self.catalog.contains_key(&doc) {
+                        by_doc.insert(doc, tf);
+                    }
+                }
+                (*tid, by_doc.into_iter().collect())
+            })
+            .collect()
+    }
+
+    /// The field `tid`'s term belongs to.
+    fn field_of(&self, tid: u64) -> &str {
+        self.term_fields
+            .get(&tid)
+            .map_or(TEXT_FIELD, String::as_str)
+    }
+
+    /// The stored length of `field` in `doc`, when `doc` is indexed.
+    fn stored_len(&self, doc: u64, field: &str) -> Option<u32> {
+        if field == TEXT_FIELD {
+            return self.doc_lens.get(&doc).copied();
+        }
+        self.doc_lens.contains_key(&doc).then(|| {
+            self.field_lens
+                .get(&doc)
+                .and_then(Option::as_ref)
+                .and_then(|lens| lens.get(field))
+                .copied()
+                .unwrap_or(0)
+        })
+    }
+
+    /// The tf of every `(term, doc)` in `scoring`.
+    fn tfs(scoring: &BTreeMap<u64, Vec<(u64, u32)>>) -> BTreeMap<(u64, u64), u32> {
+        scoring
+            .iter()
+            .flat_map(|(&tid, entries)| entries.iter().map(move |&(doc, tf)| ((tid, doc), tf)))
+            .collect()
+    }
+
+    fn check(&self, tenant: u32) -> Vec<Finding> {
+        let mut out = Vec::new();
+        let mut found = |check: &str,
+                         record_id: Option<u64>,
+                         term_id: Option<u64>,
+                         detail: String,
+                         derivable: bool| {
+            out.push(Finding {
+                violation: FsckViolation {
+                    tenant_id: tenant,
+                    check: check.to_string(),
+                    record_id,
+                    term_id,
+                    detail,
+                    repaired: false,
+                },
+                derivable,
+            });
+        };
+
+        // ── Catalog against its blobs ──
+        for (&rid, row) in &self.catalog {
+            let Some(row) = row else {
+                found(
+                    "catalog.decode",
+                    Some(rid),
+                    None,
+                    "row does not decode".into(),
+                    false,
+                );
+                continue;
+            };
+            match self.fingerprints.get(&rid) {
+                None => found(
+                    "catalog.fingerprint",
+                    Some(rid),
+                    None,
+                    "fingerprint blob missing".into(),
+                    false,
+                ),
+                Some(&len) if len != row.fingerprint_len as usize => found(
+                    "catalog.fingerprint_len",
+                    Some(rid),
+                    None,
+                    format!("catalog says {}, blob has {len}", row.fingerprint_len),
+                    true,
+                ),
+                Some(_) => {}
+            }
+            let vector = self.vectors.get(&rid).copied();
+            if vector.unwrap_or(0) != row.embedding_dim as usize * 4
+                || (vector.is_some() && row.embedding_dim == 0)
+            {
+                found(
+                    "catalog.embedding_dim",
+                    Some(rid),
+                    None,
+                    format!(
+                        "catalog says {} dims, vector blob has {} bytes",
+                        row.embedding_dim,
+                        vector.map_or("no".into(), |n| n.to_string())
+                    ),
+                    vector.is_none_or(|n| n % 4 == 0),
+                );
+            }
+            let metadata = self.metadata.get(&rid).copied().unwrap_or(0);
+            if metadata != row.metadata_len as usize {
+                found(
+                    "catalog.metadata_len",
+                    Some(rid),
+                    None,
+                    format!("catalog says {}, blob has {metadata}", row.metadata_len),
+                    true,
+                );
+            }
+            if self.expiry.get(&rid).copied() != row.expires_at {
+                found(
+                    "expiry.index",
+                    Some(rid),
+                    None,
+                    format!(
+                        "catalog expires_at {:?}, expiry index {:?}",
+                        row.expires_at,
+                        self.expiry.get(&rid)
+                    ),
+                    true,
+                );
+            }
+        }
+        for (check, rows) in [
+            (
+                "orphan.fingerprint",
+                self.fingerprints.keys().collect::<Vec<_>>(),
+            ),
+            ("orphan.vector", self.vectors.keys().collect()),
+            ("orphan.metadata", self.metadata.keys().collect()),
+            ("orphan.expiry", self.expiry.keys().collect()),
+            ("orphan.text", self.text.iter().collect()),
+            ("orphan.text_fields", self.text_fields.iter().collect()),
+        ] {
+            for &rid in rows
+                .into_iter()
+                .filter(|rid| !self.catalog.contains_key(rid))
+            {
+                found(check, Some(rid), None, "no catalog row".into(), true);
+            }
+        }
+        // A delete under versioning leaves history ending in a tombstone.
+        for (&rid, &tombstone) in &self.history {
+            match tombstone {
+                Some(true) => {}
+                Some(false) => found(
+                    "orphan.versions",
+                    Some(rid),
+                    None,
+                    "history without a catalog row or a tombstone".into(),
+                    true,
+                ),
+                None => found(
+                    "orphan.versions",
+                    Some(rid),
+                    None,
+                    "newest history row does not decode".into(),
+                    false,
+                ),
+            }
+        }
+
+        // ── Counters against the catalog ──
+        let expected = self.expected_counters();
+        let names: BTreeSet<&String> = expected.keys().chain(self.counters.keys()).collect();
+        for name in names {
+            let want = expected.get(name).copied().unwrap_or(0);
+            let have = self.counters.get(name).copied().unwrap_or(0);
+            if want != have {
+                found(
+                    "stats.counters",
+                    None,
+                    None,
+                    format!("counter `{name}` is {have}, the catalog says {want}"),
+                    true,
+                );
+            }
+        }
+
+        // ── BM25 ──
+        if let Some(e) = &self.dict_error {
+            found("bm25.term_fst", None, None, e.clone(), false);
+        }
+        let dict_ids: BTreeMap<u64, usize> =
+            self.dict
+                .iter()
+                .flatten()
+                .fold(BTreeMap::new(), |mut m, (_, &id)| {
+                    *m.entry(id).or_default() += 1;
+                    m
+                });
+        for (&tid, &n) in dict_ids.iter().filter(|(_, n)| **n > 1) {
+            found(
+                "bm25.term_fst",
+                None,
+                Some(tid),
+                format!("{n} terms share the id"),
+                false,
+            );
+        }
+        let mut expected_terms: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+        let mut expected_lens: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut expected_fields: BTreeMap<u64, BTreeMap<String, u32>> = BTreeMap::new();
+        for (&tid, entries) in &self.scoring {
+            if self.dict.is_some() && !dict_ids.contains_key(&tid) {
+                found(
+                    "bm25.postings",
+                    None,
+                    Some(tid),
+                    "posting list for a term not in the dictionary".into(),
+                    true,
+                );
+                continue;
+            }
+            let mut docs = BTreeSet::new();
+            for &(doc, tf) in entries {
+                if !docs.insert(doc) {
+                    found(
+                        "bm25.postings",
+                        Some(doc),
+                        Some(tid),
+                        "doc listed more than once".into(),
+                        true,
+                    );
+                    continue;
+                }
+                expected_terms.entry(doc).or_default().insert(tid);
+                match self.field_of(tid) {
+                    TEXT_FIELD => *expected_lens.entry(doc).or_default() += u64::from(tf),
+                    field => {
+                        let len = expected_fields
+                            .entry(doc)
+                            .or_default()
+                            .entry(field.to_string())
+                            .or_default();
+                        *len = len.saturating_add(tf);
+                    }
+                }
+            }
+        }
+        for (&tid, blocks) in &self.blocks {
+            let mut prev_last = None;
+            for block in blocks {
+                let mut broken = |record_id: Option<u64>, detail: String| {
+                    found("bm25.blocks", record_id, Some(tid), detail, true);
+                };
+                let entries = match &block.entries {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        broken(
+                            None,
+                            format!("block ending at doc {} does not decode: {e}", block.last),
+                        );
+                        continue;
+                    }
+                };
+                let (first, last) = (entries[0].doc, entries[entries.len() - 1].doc);
+                if last != block.last {
+                    broken(
+                        None,
+                        format!("block keyed by doc {} ends at doc {last}", block.last),
+                    );
+                }
+                if prev_last.is_some_and(|prev| first <= prev) {
+                    broken(
+                        None,
+                        format!("block starting at doc {first} overlaps the one before"),
+                    );
+                }
+                prev_last = Some(last);
+                let max_tf = entries.iter().map(|p| p.tf).max().unwrap_or(0);
+                let min_dl = entries.iter().map(|p| p.dl).min().unwrap_or(0);
+                if let Some(h) = block.header
+                    && (h.max_tf, h.min_dl) != (max_tf, min_dl)
+                {
+                    broken(
+                        None,
+                        format!(
+                            "header says max tf {} / min length {}, entries have {max_tf} / {min_dl}",
+                            h.max_tf, h.min_dl
+                        ),
+                    );
+                }
+                let field = self.field_of(tid);
+                for p in entries {
+                    match self.stored_len(p.doc, field) {
+                        Some(len) if len != p.dl && field == TEXT_FIELD => broken(
+                            Some(p.doc),
+                            format!("entry has doc length {}, doc_lens {len}", p.dl),
+                        ),
+                        Some(len) if len != p.dl => broken(
+                            Some(p.doc),
+                            format!("entry has `{field}` length {}, field_lens {len}", p.dl),
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let tfs = Self::tfs(&self.scoring);
+        for (&(tid, doc), stored) in &self.positions {
+            let detail = match (stored, tfs.get(&(tid, doc))) {
+                (Err(e), _) => format!("positions row does not decode: {e}"),
+                (Ok(_), None) => "positions for a doc the posting list lacks".into(),
+                (Ok(at), Some(&tf)) if at.len() != tf as usize => {
+                    format!("{} positions for tf {tf}", at.len())
+                }
+                _ => continue,
+            };
+            found("bm25.positions", Some(doc), Some(tid), detail, true);
+        }
+
+        let indexed: BTreeSet<u64> = self
+            .doc_lens
+            .keys()
+            .chain(self.doc_terms.keys())
+            .chain(self.field_lens.keys())
+            .chain(expected_terms.keys())
+            .copied()
+            .collect();
+        for &doc in &indexed {
+            if !self.catalog.contains_key(&doc) {
+                found(
+                    "bm25.orphan_doc",
+                    Some(doc),
+                    None,
+                    "indexed doc has no catalog row".into(),
+                    true,
+                );
+                continue;
+            }
+            let stored: Option<BTreeSet<u64>> = self
+                .doc_terms
+                .get(&doc)
+                .map(|t| t.iter().copied().collect());
+            let expected = expected_terms.get(&doc).cloned().unwrap_or_default();
+            if stored.as_ref() != Some(&expected) {
+                found(
+                    "bm25.doc_terms",
+                    Some(doc),
+                    None,
+                    match stored {
+                        None => "no doc_terms row".into(),
+                        Some(s) => format!(
+                            "lists {} terms, posting lists have it under {}",
+                            s.len(),
+                            expected.len()
+                        ),
+                    },
+                    true,
+                );
+            }
+            let expected_len = expected_lens.get(&doc).copied().unwrap_or(0);
+            let stored_len = self.doc_lens.get(&doc).copied();
+            if stored_len.map(u64::from) != Some(expected_len) {
+                found(
+                    "bm25.doc_lens",
+                    Some(doc),
+                    None,
+                    format!("stored {stored_len:?}, term frequencies sum to {expected_len}"),
+                    true,
+                );
+            }
+            let expected_lens = expected_fields.get(&doc).cloned().unwrap_or_default();
+            let detail = match self.field_lens.get(&doc) {
+                Some(None) => Some("field_lens row does not decode".to_string()),
+                Some(Some(stored)) if *stored != expected_lens => Some(format!(
+                    "stored {stored:?}, term frequencies sum to {expected_lens:?}"
+                )),
+                None if !expected_lens.is_empty() => Some(format!(
+                    "no field_lens row, term frequencies sum to {expected_lens:?}"
+                )),
+                _ => None,
+            };
+            if let Some(detail) = detail {
+                found("bm25.field_lens", Some(doc), None, detail, true);
+            }
+        }
+
+        let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+        for lens in self.field_lens.values().flatten() {
+            for (name, &len) in lens {
+                *totals.entry(name).or_default() += u64::from(len);
+            }
+        }
+        let stored: Option<BTreeMap<&str, u64>> = match &self.fields {
+            None => Some(BTreeMap::new()),
+            Some(Some(fields)) => Some(fields.iter().map(|(n, &t)| (n.as_str(), t)).collect()),
+            Some(None) => None,
+        };
+        match stored {
+            None => found(
+                "bm25.fields",
+                None,
+                None,
+                "field stats row does not decode".into(),
+                true,
+            ),
+            Some(stored) if stored != totals => found(
+                "bm25.fields",
+                None,
+                None,
+                format!("stats say {stored:?}, field_lens hold {totals:?}"),
+                true,
+            ),
+            Some(_) => {}
+        }
+
+        let docs = self.doc_lens.len() as u64;
+        let total: u64 = self.doc_lens.values().map(|&l| u64::from(l)).sum();
+        match &self.corpus {
+            None if docs > 0 => found(
+                "bm25.corpus",
+                None,
+                None,
+                format!("no corpus stats for {docs} indexed docs"),
+                true,
+            ),
+            None => {}
+            Some(raw) if raw.len() != 24 => found(
+                "bm25.corpus",
+                None,
+                None,
+                format!("stats row is {} bytes, expected 24", raw.len()),
+                true,
+            ),
+            Some(raw) => {
+                let stats = CorpusStats::unpack(raw);
+                if (stats.doc_count, stats.total_doc_len) != (docs, total) {
+                    found(
+                        "bm25.corpus",
+                        None,
+                        None,
+                        format!(
+                            "stats say {} docs / {} terms, doc_lens hold {docs} / {total}",
+                            stats.doc_count, stats.total_doc_len
+                        ),
+                        true,
+                    );
+                }
+                let max_id = dict_ids.keys().chain(self.scoring.keys()).max();
+                if let Some(&max_id) = max_id
+                    && max_id >= stats.next_term_id
+                {
+                    found(
+                        "bm25.corpus",
+                        None,
+                        Some(max_id),
+                        format!(
+                            "term id in use at or past next_term_id {}",
+                            stats.next_term_id
+                        ),
+                        true,
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    fn repair(&self, txn: &WriteTransaction, tenant: u32, findings: &[Finding]) -> Result<()> {
+        let derivable = |prefix: &str| {
+            findings
+                .iter()
+                .any(|f| f.derivable && f.violation.check.starts_with(prefix))
+        };
+
+        if derivable("catalog.") || derivable("expiry.") {
+            let mut catalog = txn.open_table(CATALOG).map_err(redb_err)?;
+            let mut expiry = txn.open_table(EXPIRY).map_err(redb_err)?;
+            let mut due = txn.open_table(EXPIRY_DUE).map_err(redb_err)?;
+            for (&rid, row) in &self.catalog {
+                let Some(row) = row else { continue };
+                if let Some(fixed) = self.fixed_row(rid, row) {
+                    catalog
+                        .insert((tenant, rid), fixed.encode()?.as_slice())
+                        .map_err(redb_err)?;
+                }
+                let stored = self.expiry.get(&rid).copied();
+                if stored != row.expires_at {
+                    if let Some(at) = stored {
+                        due.remove((at, tenant, rid)).map_err(redb_err)?;
+                    }
+                    match row.expires_at {
+                        Some(at) => {
+                            expiry.insert((tenant, rid), at).map_err(redb_err)?;
+                            due.insert((at, tenant, rid), ()).map_err(redb_err)?;
+                        }
+                        None => {
+                            expiry.remove((tenant, rid)).map_err(redb_err)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        if derivable("orphan.") {
+            for (def, rows) in [
+                (FINGERPRINTS, &self.fingerprints),
+                (VECTORS, &self.vectors),
+                (METADATA, &self.metadata),
+            ] {
+                let mut table = txn.open_table(def).map_err(redb_err)?;
+                for &rid in rows.keys().filter(|rid| !self.catalog.contains_key(rid)) {
+                    table.remove((tenant, rid)).map_err(redb_err)?;
+                }
+            }
+            let mut expiry = txn.open_table(EXPIRY).map_err(redb_err)?;
+            let mut due = txn.open_table(EXPIRY_DUE).map_err(redb_err)?;
+            for (&rid, &at) in self
+                .expiry
+                .iter()
+                .filter(|(rid, _)| !self.catalog.contains_key(rid))
+            {
+                expiry.remove((tenant, rid)).map_err(redb_err)?;
+                due.remove((at, tenant, rid)).map_err(redb_err)?;
+            }
+            let mut text = txn.open_table(TEXT).map_err(redb_err)?;
+            for &rid in self
+                .text
+                .iter()
+                .filter(|rid| !self.catalog.contains_key(rid))
+            {
+                text.remove((tenant, rid)).map_err(redb_err)?;
+            }
+            let mut text_fields = txn.open_table(TEXT_FIELDS).map_err(redb_err)?;
+            for &rid in self
+                .text_fields
+                .iter()
+                .filter(|rid| !self.catalog.contains_key(rid))
+            {
+                text_fields.remove((tenant, rid)).map_err(redb_err)?;
+            }
+            // Their `version_due` entries are skipped once the rows are
+            // gone.
+            for (&rid, _) in self.history.iter().filter(|(_, t)| **t == Some(false)) {
+                versions::drop_history(txn, tenant, rid)?;
+            }
+        }
+
+        if derivable("bm25.") {
+            self.rebuild_bm25(txn, tenant)?;
+        }
+        if derivable("stats.") {
+            stats::write_tenant(txn, tenant, &self.expected_counters())?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite every derived BM25 table of `tenant`, and the blocks, from
+    /// the canonical posting lists.
+    fn rebuild_bm25(&self, txn: &WriteTransaction, tenant: u32) -> Result<()> {
+        let scoring = self.canonical_scoring();
+        let mut doc_terms: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        let mut doc_lens: BTreeMap<u64, u32> = BTreeMap::new();
+        // Docs indexed with no tokens keep their zero-length entry.
+        for &doc in self.doc_lens.keys().chain(self.doc_terms.keys()) {
+            if self.catalog.contains_key(&doc) {
+                doc_terms.entry(doc).or_default();
+                doc_lens.entry(doc).or_default();
+            }
+        }
+        let mut field_lens: BTreeMap<u64, BTreeMap<String, u32>> = BTreeMap::new();
+        for (&tid, entries) in &scoring {
+            let field = self.field_of(tid);
+            for &(doc, tf) in entries {
+                doc_terms.entry(doc).or_default().push(tid);
+                let text_len = doc_lens.entry(doc).or_default();
+                let len = match field {
+                    TEXT_FIELD => text_len,
+                    field => field_lens
+                        .entry(doc)
+                        .or_default()
+                        .entry(field.to_string())
+                        .or_default(),
+                };
+                *len = len.saturating_add(tf);
+            }
+        }
+        let dl = |tid: u64, doc: u64| match self.field_of(tid) {
+            TEXT_FIELD => doc_lens.get(&doc).copied().unwrap_or(0),
+            field => field_lens
+                .get(&doc)
+                .and_then(|lens| lens.get(field))
+                .copied()
+                .unwrap_or(0),
+        };
+
+        let range = (tenant, 0)..=(tenant, u64::MAX);
+        {
+            let mut blocks_t = txn.open_table(BM25_BLOCKS).map_err(redb_err)?;
+            let mut terms_t = txn.open_table(BM25_DOC_TERMS).map_err(redb_err)?;
+            let mut lens_t = txn.open_table(BM25_DOC_LENS).map_err(redb_err)?;
+            let mut fields_t = txn.open_table(BM25_FIELD_LENS).map_err(redb_err)?;
+            blocks_t
+                .retain_in(postings::tenant_range(tenant), |_, _| false)
+                .map_err(redb_err)?;
+            fields_t
+                .retain_in(range.clone(), |_, _| false)
+                .map_err(redb_err)?;
+            terms_t
+                .retain_in(range.clone(), |_, _| false)
+                .map_err(redb_err)?;
+            lens_t.retain_in(range, |_, _| false).map_err(redb_err)?;
+            for (&tid, entries) in &scoring {
+                let list: Vec<Posting> = entries
+                    .iter()
+                    .map(|&(doc, tf)| Posting {
+                        doc,
+                        tf,
+                        dl: dl(tid, doc),
+                    })
+                    .collect();
+                postings::write_term(&mut blocks_t, tenant, tid, &list)?;
+            }
+            for (&doc, tids) in &doc_terms {
+                terms_t
+                    .insert((tenant, doc), bm25::pack_term_ids(tids).as_slice())
+                    .map_err(redb_err)?;
+            }
+            for (&doc, &len) in &doc_lens {
+                lens_t.insert((tenant, doc), len).map_err(redb_err)?;
+            }
+            for (doc, lens) in &field_lens {
+                fields_t
+                    .insert((tenant, *doc), bm25::encode_lens(lens)?.as_slice())
+                    .map_err(redb_err)?;
+            }
+        }
+        let mut totals: BTreeMap<String, u64> = BTreeMap::new();
+        for (name, &len) in field_lens.values().flatten() {
+            *totals.entry(name.clone()).or_default() += u64::from(len);
+        }
+        bm25::write_field_totals(txn, tenant, &totals)?;
+        {
+            // Positions can't be rebuilt without the text; keep the rows
+            // that still fit a posting.
+            let tfs = Self::tfs(&scoring);
+            let mut positions_t = txn.open_table(BM25_POSITIONS).map_err(redb_err)?;
+            for (&(tid, doc), stored) in &self.positions {
+                let fits = stored
+                    .as_ref()
+                    .is_ok_and(|at| tfs.get(&(tid, doc)) == Some(&(at.len() as u32)));
+                if !fits {
+                    positions_t.remove((tenant, tid, doc)).map_err(redb_err)?;
+                }
+            }
+        }
+
+        let stored_next = self
+            .corpus
+            .as_deref()
+            .map_or(0, |raw| CorpusStats::unpack(raw).next_term_id);
+        let max_id = self
+            .dict
+            .iter()
+            .flat_map(|d| d.values())
+            .chain(scoring.keys())
+            .max()
+            .map_or(0, |&id| id + 1);
+        let stats = CorpusStats {
+            doc_count: doc_lens.len() as u64,
+            total_doc_len: doc_lens.values().map(|&l| u64::from(l)).sum(),
+            next_term_id: stored_next.max(max_id),
+        };
+        let mut corpus = txn.open_table(BM25_CORPUS).map_err(redb_err)?;
+        if stats.doc_count == 0 && self.dict.as_ref().is_some_and(|d| d.is_empty()) {
+            corpus.remove(tenant).map_err(redb_err)?;
+        } else {
+            corpus
+                .insert(tenant, stats.pack().as_slice())
+                .map_err(redb_err)?;
+        }
+        drop(corpus);
+        if self.dict.is_some() {
+            bm25::prune_dead_terms(txn, tenant)?;
+        }
+        Ok(())
+    }
+}
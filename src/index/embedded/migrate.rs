@@ -0,0 +1,288 @@
+//! Schema migrations for the embedded backend.
+//!
+//! Every table name carries its layout version (`ucfp/catalog/v2`); a
+//! layout change gets a new name plus an entry in [`MIGRATIONS`] that
+//! rewrites the old table into the new one. The database records which
+//! schema it is on:
+//!
+//! | Table             | Key              | Value                        |
+//! | ----------------- | ---------------- | ---------------------------- |
+//! | `ucfp/schema/v1`  | `schema_version` | [`SCHEMA_VERSION`] applied   |
+//! |                   | `format_version` | [`crate::FORMAT_VERSION`]    |
+//!
+//! [`run`] is called by [`super::EmbeddedBackend::open`]: it refuses a
+//! database written by a newer build (higher schema or `FORMAT_VERSION`,
+//! or a table name this build does not know), then applies every pending
+//! migration in order inside one write transaction, so a failure leaves
+//! the database as it was. Databases from before the meta table existed
+//! are placed by the tables they hold.
+//!
+//! Schema versions:
+//!
+//! 1. `ucfp/catalog/v1` — 24-byte little-endian rows: modality `u32`,
+//!    format_version `u32`, config_hash `u64`, fingerprint_len `u32`,
+//!    embedding_dim `u32`.
+//! 2. `ucfp/catalog/v2` — JSON [`CatalogEntry`] rows.
+//! 3. `ucfp/stats/v1` — per-tenant counters, backfilled from the catalog.
+//! 4. `ucfp/bm25/blocks/v1` — posting lists cut into compressed blocks,
+//!    replacing the whole-list `ucfp/bm25/scoring/v1` (packed
+//!    `[doc_id u64 || tf u32]*`) and `ucfp/bm25/postings/v1` (roaring
+//!    bitmaps) rows (current).
+
+use std::collections::BTreeMap;
+
+use redb::{
+    Database, ReadTransaction, ReadableDatabase, ReadableTable, TableDefinition, TableHandle,
+    WriteTransaction,
+};
+
+use super::bm25::{BM25_BLOCKS, BM25_DOC_LENS};
+use super::postings::{self, Posting};
+use super::{CATALOG, CatalogEntry, METADATA, snapshot, stats};
+use crate::core::{MigrationReport, MigrationStep};
+use crate::error::{Error, Result};
+
+pub(super) const SCHEMA: TableDefinition<'_, &str, u64> = TableDefinition::new("ucfp/schema/v1");
+
+/// Schema this build reads and writes.
+pub(super) const SCHEMA_VERSION: u64 = 4;
+
+pub(super) const CATALOG_V1: TableDefinition<'_, (u32, u64), &[u8]> =
+    TableDefinition::new("ucfp/catalog/v1");
+
+pub(super) const BM25_SCORING_V1: TableDefinition<'_, (u32, u64), &[u8]> =
+    TableDefinition::new("ucfp/bm25/scoring/v1");
+
+pub(super) const BM25_POSTINGS_V1: TableDefinition<'_, (u32, u64), &[u8]> =
+    TableDefinition::new("ucfp/bm25/postings/v1");
+
+/// One step from `from` to `from + 1`. `run` returns the rows it rewrote.
+struct Migration {
+    from: u64,
+    name: &'static str,
+    run: fn(&WriteTransaction) -> Result<u64>,
+}
+
+/// Ordered by `from`; one entry per schema version below
+/// [`SCHEMA_VERSION`].
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        name: "ucfp/catalog/v1 -> ucfp/catalog/v2",
+        run: catalog_v1_to_v2,
+    },
+    Migration {
+        from: 2,
+        name: "backfill ucfp/stats/v1",
+        run: stats::backfill,
+    },
+    Migration {
+        from: 3,
+        name: "ucfp/bm25/scoring/v1 -> ucfp/bm25/blocks/v1",
+        run: bm25_scoring_to_blocks,
+    },
+];
+
+/// Tables only older schemas have; known, but migrated away.
+pub(super) fn legacy_tables() -> [&'static str; 3] {
+    [
+        CATALOG_V1.name(),
+        BM25_SCORING_V1.name(),
+        BM25_POSTINGS_V1.name(),
+    ]
+}
+
+/// Bring `db` to [`SCHEMA_VERSION`]. With `dry_run` the migrations run
+/// but the transaction is aborted, so the report shows what would change.
+pub(super) fn run(db: &Database, dry_run: bool) -> Result<MigrationReport> {
+    let from = {
+        let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+        current_version(&txn)?
+    };
+    let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    let mut steps = Vec::new();
+    for m in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        let rows = (m.run)(&txn)?;
+        steps.push(MigrationStep {
+            from_version: m.from,
+            to_version: m.from + 1,
+            name: m.name.to_string(),
+            rows,
+        });
+    }
+    {
+        let mut meta = txn
+            .open_table(SCHEMA)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        meta.insert("schema_version", SCHEMA_VERSION)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        meta.insert("format_version", u64::from(crate::FORMAT_VERSION))
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
+    if dry_run {
+        txn.abort().map_err(|e| Error::Index(e.to_string()))?;
+    } else {
+        txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    }
+    Ok(MigrationReport {
+        from_version: from,
+        to_version: SCHEMA_VERSION,
+        steps,
+        dry_run,
+    })
+}
+
+/// Schema `txn` is on, or [`Error::Incompatible`] when a newer build
+/// wrote it.
+fn current_version(txn: &ReadTransaction) -> Result<u64> {
+    let mut known: Vec<&str> = snapshot::known_tables();
+    known.extend(legacy_tables());
+    known.extend(snapshot::own_tables());
+    let tables = txn
+        .list_tables()
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map(|t| t.name().to_string())
+        .collect::<Vec<_>>();
+    if let Some(unknown) = tables
+        .iter()
+        .find(|t| t.starts_with("ucfp/") && !known.contains(&t.as_str()))
+    {
+        return Err(Error::Incompatible(format!(
+            "table `{unknown}` is not a table version this build reads; \
+             the database was written by a newer ucfp"
+        )));
+    }
+
+    let stored = |key: &str| -> Result<Option<u64>> {
+        match txn.open_table(SCHEMA) {
+            Ok(meta) => Ok(meta
+                .get(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|v| v.value())),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+            Err(e) => Err(Error::Index(e.to_string())),
+        }
+    };
+    if let Some(format) = stored("format_version")?
+        && format > u64::from(crate::FORMAT_VERSION)
+    {
+        return Err(Error::Incompatible(format!(
+            "database FORMAT_VERSION {format}, this build reads up to {}",
+            crate::FORMAT_VERSION
+        )));
+    }
+    match stored("schema_version")? {
+        Some(v) if v > SCHEMA_VERSION => Err(Error::Incompatible(format!(
+            "database schema version {v}, this build reads up to {SCHEMA_VERSION}"
+        ))),
+        Some(v) => Ok(v),
+        // No meta table yet: older than the migrations framework, or new.
+        None if tables.iter().any(|t| t == CATALOG_V1.name()) => Ok(1),
+        None if tables.iter().any(|t| t == CATALOG.name()) => Ok(2),
+        None => Ok(SCHEMA_VERSION),
+    }
+}
+
+/// Decode the packed v1 rows into JSON v2 rows. A key that already has
+/// a v2 row was rewritten by a build that ignored the v1 table, and that
+/// row is the newer one.
+fn catalog_v1_to_v2(txn: &WriteTransaction) -> Result<u64> {
+    let mut rows = 0;
+    {
+        let old = txn
+            .open_table(CATALOG_V1)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let metadata = txn
+            .open_table(METADATA)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut new = txn
+            .open_table(CATALOG)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for entry in old.iter().map_err(|e| Error::Index(e.to_string()))? {
+            let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            let key = k.value();
+            if new
+                .get(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .is_some()
+            {
+                continue;
+            }
+            let metadata_len = metadata
+                .get(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map_or(0, |m| m.value().len() as u32);
+            let row = decode_v1(v.value(), metadata_len)?;
+            new.insert(key, row.encode()?.as_slice())
+                .map_err(|e| Error::Index(e.to_string()))?;
+            rows += 1;
+        }
+    }
+    txn.delete_table(CATALOG_V1)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(rows)
+}
+
+fn decode_v1(row: &[u8], metadata_len: u32) -> Result<CatalogEntry> {
+    let row: &[u8; 24] = row.try_into().map_err(|_| {
+        Error::Index(format!(
+            "ucfp/catalog/v1: expected 24-byte rows, found {}",
+            row.len()
+        ))
+    })?;
+    let u32_at = |i: usize| u32::from_le_bytes(row[i..i + 4].try_into().expect("4 bytes"));
+    Ok(CatalogEntry {
+        modality: u32_at(0),
+        format_version: u32_at(4),
+        config_hash: u64::from_le_bytes(row[8..16].try_into().expect("8 bytes")),
+        fingerprint_len: u32_at(16),
+        embedding_dim: u32_at(20),
+        algorithm: String::new(),
+        model_id: None,
+        metadata_len,
+        expires_at: None,
+        version: 0,
+        written_at: None,
+        deleted: false,
+    })
+}
+
+/// Cut every whole-list scoring row into posting blocks, with doc
+/// lengths from `doc_lens`, then drop the scoring rows and the postings
+/// bitmaps they duplicated. A doc listed twice keeps its later entry.
+fn bm25_scoring_to_blocks(txn: &WriteTransaction) -> Result<u64> {
+    let mut rows = 0;
+    {
+        let scoring = txn
+            .open_table(BM25_SCORING_V1)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let doc_lens = txn
+            .open_table(BM25_DOC_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut blocks = txn
+            .open_table(BM25_BLOCKS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for entry in scoring.iter().map_err(|e| Error::Index(e.to_string()))? {
+            let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            let (tenant_id, term_id) = k.value();
+            let mut list = BTreeMap::new();
+            for chunk in v.value().chunks_exact(12) {
+                let doc = u64::from_le_bytes(chunk[..8].try_into().expect("8 bytes"));
+                let tf = u32::from_le_bytes(chunk[8..].try_into().expect("4 bytes"));
+                let dl = doc_lens
+                    .get((tenant_id, doc))
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map_or(0, |v| v.value());
+                list.insert(doc, Posting { doc, tf, dl });
+            }
+            let list: Vec<Posting> = list.into_values().collect();
+            postings::write_term(&mut blocks, tenant_id, term_id, &list)?;
+            rows += 1;
+        }
+    }
+    txn.delete_table(BM25_SCORING_V1)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    txn.delete_table(BM25_POSTINGS_V1)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(rows)
+}
@@ -6,8 +6,13 @@
 //! metadata      (tenant_id: u32, record_id: u64) → application metadata
 //! vectors       (tenant_id: u32, record_id: u64) → f32 array (raw little-endian)
 //! catalog       (tenant_id: u32, record_id: u64) → CatalogEntry (algorithm, fmt_ver, ...)
+//! text          (tenant_id: u32, record_id: u64) → indexed text, with text retention on
 //! ```
 //!
+//! plus the BM25 tables in `bm25`, the TTL tables in `expiry`, the
+//! change log in `changes`, per-tenant counters in `stats` and, with
+//! versioning on, record history in `versions`.
+//!
 //! Per ARCHITECTURE §3, this implementation uses **brute-force cosine**
 //! over the `vectors` table. That's the correct path below ~1M vectors;
 //! HNSW lands as a follow-up under the same trait.
@@ -15,19 +20,44 @@
 //! `bm25` is not yet implemented — returns [`Error::Index`] with a clear
 //! message until the FST + roaring postings layout from §4 is wired.
 
-mod bm25;
+pub mod analyzer;
+pub(crate) mod bm25;
+mod changes;
+mod expiry;
+mod fsck;
+mod migrate;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod positions;
+pub(crate) mod postings;
+mod reindex;
+mod snapshot;
+mod snippet;
+mod stats;
+mod versions;
+pub(crate) mod vocab;
 
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use bytes::Bytes;
 use rayon::prelude::*;
-use redb::{Database, ReadableDatabase, TableDefinition};
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
 
-use crate::core::{FingerprintMeta, Hit, HitSource, Modality, Record};
+use self::analyzer::{Analyzer, AnalyzerMismatch};
+use crate::core::{
+    Bm25Field, Bm25Params, ChangeEvent, ChangeKind, Correction, FingerprintMeta, FsckReport,
+    Highlight, Hit, HitSource, MigrationReport, Modality, Progress, Record, RecordFilter, ScanPage,
+    SnapshotInfo, Snippet, TenantStats, TermSuggestion, VersionPolicy, VersionSpec,
+    VersionedRecord, WriteCondition, WriteOutcome,
+};
 use crate::error::{Error, Result};
-use crate::index::IndexBackend;
+use crate::index::{
+    DELETE_BATCH, IndexBackend, Purging, SCAN_BUDGET, check_conditions, decode_cursor,
+    delete_matching, encode_cursor, is_expired, now_ms,
+};
 
 // ── Schema ──────────────────────────────────────────────────────────────
 //
@@ -41,26 +71,51 @@ const VECTORS: TableDefinition<'_, (u32, u64), &[u8]> = TableDefinition::new("uc
 // v2 carries algorithm + model_id alongside the original Pod fields. The
 // row is serde_json so the schema can grow without another bump.
 const CATALOG: TableDefinition<'_, (u32, u64), &[u8]> = TableDefinition::new("ucfp/catalog/v2");
+// Source text of BM25-indexed records, kept only with text retention on
+// so the index can be rebuilt (see `reindex`).
+const TEXT: TableDefinition<'_, (u32, u64), &str> = TableDefinition::new("ucfp/text/v1");
+// Named text fields of the same records, as a JSON object.
+const TEXT_FIELDS: TableDefinition<'_, (u32, u64), &[u8]> =
+    TableDefinition::new("ucfp/text_fields/v1");
 
 /// Single-file embedded backend.
 ///
 /// One redb database, multiple tables, MVCC-snapshotted reads. Back up
-/// with `cp` while the writer is open — redb's COW guarantees a
-/// consistent snapshot (see ARCHITECTURE §8.2).
+/// online with [`IndexBackend::snapshot`] and bring a copy back with
+/// [`EmbeddedBackend::restore`] (see ARCHITECTURE §8.2).
 pub struct EmbeddedBackend {
     db: Arc<Database>,
     path: PathBuf,
+    versioning: Option<VersionPolicy>,
+    retain_text: bool,
+    positional: Arc<BTreeSet<u32>>,
+    analyzers: Arc<BTreeMap<u32, Analyzer>>,
+    bm25_fields: Arc<BTreeMap<u32, BTreeMap<String, Bm25Field>>>,
+    bm25_params: Arc<BTreeMap<u32, Bm25Params>>,
+    term_dicts: Arc<bm25::TermDictCache>,
+    purging: Arc<Purging>,
 }
 
 impl EmbeddedBackend {
     /// Open or create a UCFP database at `path`. Creates the parent
-    /// directory if it doesn't exist.
+    /// directory if it doesn't exist. Databases on an older schema are
+    /// migrated first; one written by a newer build is refused with
+    /// [`Error::Incompatible`].
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         let db = Database::create(&path).map_err(|e| Error::Index(e.to_string()))?;
+        let report = migrate::run(&db, false)?;
+        for step in &report.steps {
+            tracing::info!(
+                path = %path.display(),
+                migration = %step.name,
+                rows = step.rows,
+                "schema migrated"
+            );
+        }
 
         // Touch every table so range scans on a fresh DB don't TableDoesNotExist.
         let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
@@ -77,20 +132,175 @@ impl EmbeddedBackend {
             let _ = txn
                 .open_table(CATALOG)
                 .map_err(|e| Error::Index(e.to_string()))?;
+            let _ = txn
+                .open_table(TEXT)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let _ = txn
+                .open_table(TEXT_FIELDS)
+                .map_err(|e| Error::Index(e.to_string()))?;
             bm25::bootstrap_tables(&txn)?;
+            expiry::bootstrap_tables(&txn)?;
+            versions::bootstrap_tables(&txn)?;
+            changes::bootstrap_tables(&txn)?;
+            let _ = txn
+                .open_table(stats::STATS)
+                .map_err(|e| Error::Index(e.to_string()))?;
         }
         txn.commit().map_err(|e| Error::Index(e.to_string()))?;
 
         Ok(Self {
             db: Arc::new(db),
             path,
+            versioning: None,
+            retain_text: false,
+            positional: Arc::default(),
+            analyzers: Arc::default(),
+            bm25_fields: Arc::default(),
+            bm25_params: Arc::default(),
+            term_dicts: Arc::default(),
+            purging: Arc::default(),
         })
     }
 
+    /// Replace the database at `db_path` with `snapshot`, after checking
+    /// the snapshot's `FORMAT_VERSION` and table versions against this
+    /// build ([`Error::Incompatible`] otherwise). Offline: fails while
+    /// anything has `db_path` open. The replaced database is kept as
+    /// `<db_path>.pre-restore`.
+    pub fn restore(snapshot: impl AsRef<Path>, db_path: impl AsRef<Path>) -> Result<SnapshotInfo> {
+        snapshot::restore(snapshot.as_ref(), db_path.as_ref())
+    }
+
+    /// Bring the existing database at `path` to this build's schema, as
+    /// [`Self::open`] does, and report the steps. With `dry_run` the
+    /// migrations run inside a transaction that is then aborted.
+    pub fn migrate(path: impl AsRef<Path>, dry_run: bool) -> Result<MigrationReport> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(Error::NotFound(format!("database {}", path.display())));
+        }
+        let db = Database::open(path).map_err(|e| Error::Index(e.to_string()))?;
+        migrate::run(&db, dry_run)
+    }
+
+    /// Run the checks of [`Self::restore`] on `snapshot` only.
+    pub fn check_snapshot(snapshot: impl AsRef<Path>) -> Result<SnapshotInfo> {
+        snapshot::validate(snapshot.as_ref())
+    }
+
+    /// Keep superseded versions of every record, retained per `policy`.
+    /// Off by default: an upsert then replaces the row in place, though
+    /// the version counter still advances.
+    pub fn with_versioning(mut self, policy: VersionPolicy) -> Self {
+        self.versioning = Some(policy);
+        self
+    }
+
+    /// Keep the text and text fields of every BM25-indexed record so
+    /// [`IndexBackend::reindex_bm25`] can rebuild the index after a
+    /// tokenizer change or drift, and [`IndexBackend::highlight`] can cut
+    /// snippets from it. Off by default: text is tokenized and
+    /// dropped. Records written while it was off stay unrebuildable
+    /// until they are upserted again.
+    pub fn with_retained_text(mut self) -> Self {
+        self.retain_text = true;
+        self
+    }
+
+    /// Store token positions for the BM25-indexed records of `tenants`,
+    /// so their queries can use quoted phrases and `NEAR/n` (see
+    /// [`IndexBackend::bm25`]). Off by default. Records written before a tenant was
+    /// listed have no positions and match no phrase until they are
+    /// upserted again or the tenant is reindexed.
+    pub fn with_positions(mut self, tenants: impl IntoIterator<Item = u32>) -> Self {
+        self.positional = Arc::new(tenants.into_iter().collect());
+        self
+    }
+
+    /// Analyze the BM25 text of each listed tenant with its own
+    /// [`Analyzer`], at index and query time; the rest keep
+    /// [`Analyzer::default`]. An index built under another analyzer
+    /// stays as it is, listed by [`Self::analyzer_mismatches`], until the
+    /// tenant is reindexed.
+    pub fn with_analyzers(mut self, analyzers: impl IntoIterator<Item = (u32, Analyzer)>) -> Self {
+        self.analyzers = Arc::new(analyzers.into_iter().collect());
+        self
+    }
+
+    /// Weigh matches in the listed text fields of each tenant (`text`
+    /// for [`Record::text`], or a name from [`Record::text_fields`]) by
+    /// their [`Bm25Field`] when scoring that tenant's BM25F queries; the
+    /// rest get [`Bm25Field::default`]. Applies at query time, so
+    /// changing it needs no reindex.
+    pub fn with_bm25_fields(
+        mut self,
+        fields: impl IntoIterator<Item = (u32, String, Bm25Field)>,
+    ) -> Self {
+        let mut by_tenant: BTreeMap<u32, BTreeMap<String, Bm25Field>> = BTreeMap::new();
+        for (tenant_id, name, field) in fields {
+            by_tenant.entry(tenant_id).or_default().insert(name, field);
+        }
+        self.bm25_fields = Arc::new(by_tenant);
+        self
+    }
+
+    /// Score the BM25 queries of each listed tenant with its own
+    /// [`Bm25Params`] (model, `k1`, `b`); the rest get
+    /// [`Bm25Params::default`]. Applies at query time, so changing it
+    /// needs no reindex.
+    pub fn with_bm25_params(mut self, params: impl IntoIterator<Item = (u32, Bm25Params)>) -> Self {
+        self.bm25_params = Arc::new(params.into_iter().collect());
+        self
+    }
+
+    /// Tenants whose BM25 index was built with an analyzer other than
+    /// the one they are configured with. Their queries are analyzed the
+    /// new way and miss terms indexed the old way until
+    /// [`IndexBackend::reindex_bm25`] rebuilds them.
+    pub fn analyzer_mismatches(&self) -> Result<Vec<AnalyzerMismatch>> {
+        Ok(bm25::stored_analyzers(&self.db)?
+            .into_iter()
+            .filter_map(|(tenant_id, indexed)| {
+                let configured = self.analyzer(tenant_id).identity();
+                (indexed != configured).then_some(AnalyzerMismatch {
+                    tenant_id,
+                    indexed,
+                    configured,
+                })
+            })
+            .collect())
+    }
+
+    /// The analyzer `tenant_id`'s text goes through.
+    fn analyzer(&self, tenant_id: u32) -> Analyzer {
+        bm25::tenant_analyzer(&self.analyzers, tenant_id).clone()
+    }
+
     /// On-disk path of the database file.
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Write the catalog as Parquet under `out_dir` for offline
+    /// analytics (ARCHITECTURE §7), plus daily usage rollups of
+    /// `usage_log` when given. Brings the previous run's output forward
+    /// from its change-log watermark; `full` rewrites the catalog.
+    #[cfg(feature = "parquet")]
+    pub async fn export_parquet(
+        &self,
+        out_dir: impl AsRef<Path>,
+        usage_log: Option<&Path>,
+        full: bool,
+    ) -> Result<crate::core::ParquetReport> {
+        let db = self.db.clone();
+        let out_dir = out_dir.as_ref().to_path_buf();
+        let usage_log = usage_log.map(Path::to_path_buf);
+        tokio::task::spawn_blocking(move || {
+            parquet::export(&db, &out_dir, usage_log.as_deref(), full)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
 }
 
 /// Catalog row carried in the `catalog` table — lets the matcher and
@@ -102,7 +312,7 @@ impl EmbeddedBackend {
 /// schemas. The performance hit is negligible — catalog reads happen
 /// per record, not per inner-loop iteration.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-struct CatalogEntry {
+pub(crate) struct CatalogEntry {
     /// Modality discriminator: 0 = audio, 1 = image, 2 = text.
     modality: u32,
     /// Producing SDK's FORMAT_VERSION at ingest time.
@@ -122,9 +332,167 @@ struct CatalogEntry {
     /// Length of the application metadata blob in bytes.
     #[serde(default)]
     metadata_len: u32,
+    /// Absolute expiry, unix milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// Per-record write sequence; 0 on rows that predate it (read as 1).
+    #[serde(default)]
+    version: u64,
+    /// Write time of this version, unix milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    written_at: Option<u64>,
+    /// Set on the history row a delete leaves behind (see `versions`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deleted: bool,
+}
+
+impl CatalogEntry {
+    /// Catalog row for `rec` as it is about to be written.
+    pub(crate) fn from_record(rec: &Record) -> Self {
+        Self {
+            modality: rec.modality as u32,
+            format_version: rec.format_version,
+            config_hash: rec.config_hash,
+            fingerprint_len: rec.fingerprint.len() as u32,
+            embedding_dim: rec.embedding.as_ref().map(|v| v.len()).unwrap_or(0) as u32,
+            algorithm: rec.algorithm.clone(),
+            model_id: rec.model_id.clone(),
+            metadata_len: rec.metadata.len() as u32,
+            expires_at: rec.expires_at,
+            version: 1,
+            written_at: None,
+            deleted: false,
+        }
+    }
+
+    /// Stamp the write sequence and time onto a row about to be stored.
+    pub(crate) fn stamped(mut self, version: u64, written_at: u64) -> Self {
+        self.version = version;
+        self.written_at = Some(written_at);
+        self
+    }
+
+    /// Tombstone following this row in history: the record was deleted
+    /// at `deleted_at`, which takes version `version`.
+    pub(crate) fn tombstone(mut self, version: u64, deleted_at: u64) -> Self {
+        self.deleted = true;
+        self.stamped(version, deleted_at)
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.version.max(1)
+    }
+
+    /// `true` once the record must no longer be served.
+    pub(crate) fn is_expired(&self, now_ms: u64) -> bool {
+        is_expired(self.expires_at, now_ms)
+    }
+
+    /// `(version, config_hash)` for [`WriteCondition::evaluate`]; `None`
+    /// once expired, so an expired row counts as absent.
+    pub(crate) fn condition_state(&self, now_ms: u64) -> Option<(u64, u64)> {
+        (!self.is_expired(now_ms)).then(|| (self.version(), self.config_hash))
+    }
+
+    pub(crate) fn encode(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| Error::Index(format!("catalog encode: {e}")))
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|e| Error::Index(format!("catalog decode: {e}")))
+    }
+
+    pub(crate) fn modality(&self) -> Result<Modality> {
+        match self.modality {
+            0 => Ok(Modality::Audio),
+            1 => Ok(Modality::Image),
+            2 => Ok(Modality::Text),
+            other => Err(Error::Index(format!(
+                "catalog: unknown modality discriminator {other}"
+            ))),
+        }
+    }
+
+    /// Reassemble a full [`Record`] from this row plus the blobs read
+    /// alongside it. `vector` is the raw little-endian f32 bytes.
+    pub(crate) fn into_record(
+        self,
+        tenant_id: u32,
+        record_id: u64,
+        fingerprint: &[u8],
+        metadata: &[u8],
+        vector: Option<&[u8]>,
+    ) -> Result<Record> {
+        Ok(Record {
+            tenant_id,
+            record_id,
+            modality: self.modality()?,
+            format_version: self.format_version,
+            algorithm: self.algorithm,
+            config_hash: self.config_hash,
+            fingerprint: Bytes::copy_from_slice(fingerprint),
+            embedding: vector.map(decode_vector),
+            model_id: self.model_id,
+            metadata: Bytes::copy_from_slice(metadata),
+            text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: self.expires_at,
+        })
+    }
+
+    /// Project the stored row into the public describe shape.
+    pub(crate) fn into_meta(self, tenant_id: u32, record_id: u64) -> Result<FingerprintMeta> {
+        let modality = self.modality()?;
+        let embedding_dim = if self.embedding_dim == 0 {
+            None
+        } else {
+            Some(self.embedding_dim as usize)
+        };
+        Ok(FingerprintMeta {
+            tenant_id,
+            record_id,
+            modality,
+            algorithm: self.algorithm,
+            format_version: self.format_version,
+            config_hash: self.config_hash,
+            fingerprint_bytes: self.fingerprint_len as usize,
+            has_embedding: embedding_dim.is_some(),
+            embedding_dim,
+            model_id: self.model_id,
+            metadata_bytes: self.metadata_len as usize,
+            expires_at: self.expires_at,
+            version: self.version.max(1),
+            written_at: self.written_at,
+            deleted: self.deleted,
+        })
+    }
 }
 
 impl EmbeddedBackend {
+    /// Drain every `(tenant_id, *)` row of `table`, one bounded txn per
+    /// batch, bumping `progress` after each commit when given.
+    async fn drain_tenant<V: redb::Value + Send + 'static>(
+        &self,
+        table: TableDefinition<'static, (u32, u64), V>,
+        tenant_id: u32,
+        progress: Option<&Progress>,
+    ) -> Result<u64> {
+        let mut removed = 0u64;
+        loop {
+            let db = self.db.clone();
+            let n = tokio::task::spawn_blocking(move || drain_batch(&db, table, tenant_id))
+                .await
+                .map_err(|e| Error::Index(format!("join error: {e}")))??;
+            if n == 0 {
+                return Ok(removed);
+            }
+            removed += n;
+            if let Some(p) = progress {
+                p.add_batch(n);
+            }
+        }
+    }
+
     async fn bm25_inner(
         &self,
         tenant_id: u32,
@@ -132,6 +500,7 @@ impl EmbeddedBackend {
         k: usize,
         filter: Option<&Bytes>,
         explain: bool,
+        params: Option<Bm25Params>,
     ) -> Result<Vec<Hit>> {
         // v1 ignores `filter` — metadata pre-filter for BM25 is documented
         // as a follow-up. Surfacing here so callers don't silently get
@@ -141,11 +510,32 @@ impl EmbeddedBackend {
                 "BM25 filter pre-filtering is not yet supported on EmbeddedBackend".into(),
             ));
         }
+        // Postings are half drained mid-purge and never checked against
+        // the catalog, so the tenant has no hits until the purge is done.
+        if self.purging.is_purging(tenant_id) {
+            return Ok(Vec::new());
+        }
         let db = self.db.clone();
         let owned_terms: Vec<String> = terms.iter().map(|s| (*s).to_string()).collect();
+        let positional = self.positional.contains(&tenant_id);
+        let analyzer = self.analyzer(tenant_id);
+        let fields = self.bm25_fields.clone();
+        let params = params.unwrap_or_else(|| {
+            self.bm25_params
+                .get(&tenant_id)
+                .copied()
+                .unwrap_or_default()
+        });
+        params.check()?;
         tokio::task::spawn_blocking(move || -> Result<Vec<Hit>> {
             let term_refs: Vec<&str> = owned_terms.iter().map(String::as_str).collect();
-            bm25::search_explain(&db, tenant_id, &term_refs, k, explain)
+            let search = bm25::Search {
+                analyzer: &analyzer,
+                positional,
+                fields: bm25::tenant_fields(&fields, tenant_id),
+                params: &params,
+            };
+            bm25::search_explain(&db, tenant_id, &term_refs, k, explain, &search)
         })
         .await
         .map_err(|e| Error::Index(format!("join error: {e}")))?
@@ -155,11 +545,39 @@ impl EmbeddedBackend {
 #[async_trait::async_trait]
 impl IndexBackend for EmbeddedBackend {
     async fn upsert(&self, batch: &[Record]) -> Result<()> {
+        self.upsert_if(batch, &vec![WriteCondition::Always; batch.len()])
+            .await
+            .map(drop)
+    }
+
+    async fn upsert_if(
+        &self,
+        batch: &[Record],
+        conditions: &[WriteCondition],
+    ) -> Result<Vec<WriteOutcome>> {
+        check_conditions(batch, conditions)?;
+        for rec in batch {
+            bm25::check_field_names(&rec.text_fields)?;
+        }
         let db = self.db.clone();
         let batch: Vec<Record> = batch.to_vec();
+        let conditions = conditions.to_vec();
+        let versioning = self.versioning;
+        let retain_text = self.retain_text;
+        let positional = self.positional.clone();
+        let analyzers = self.analyzers.clone();
+        let term_dicts = self.term_dicts.clone();
+        let purging = self.purging.clone();
 
-        tokio::task::spawn_blocking(move || -> Result<()> {
+        tokio::task::spawn_blocking(move || -> Result<Vec<WriteOutcome>> {
+            let now = now_ms();
+            let mut outcomes = Vec::with_capacity(batch.len());
             let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            for tenant_id in batch.iter().map(|r| r.tenant_id).collect::<BTreeSet<_>>() {
+                purging.check(tenant_id)?;
+            }
+            let mut log = changes::ChangeLog::open(&txn, now)?;
+            let mut counters = stats::Delta::default();
             {
                 let mut fps = txn
                     .open_table(FINGERPRINTS)
@@ -173,15 +591,50 @@ impl IndexBackend for EmbeddedBackend {
                 let mut cat = txn
                     .open_table(CATALOG)
                     .map_err(|e| Error::Index(e.to_string()))?;
+                let mut texts = txn
+                    .open_table(TEXT)
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                let mut text_fields = txn
+                    .open_table(TEXT_FIELDS)
+                    .map_err(|e| Error::Index(e.to_string()))?;
 
-                for rec in &batch {
+                for (rec, condition) in batch.iter().zip(&conditions) {
                     let key = (rec.tenant_id, rec.record_id);
+                    let prev = cat
+                        .get(key)
+                        .map_err(|e| Error::Index(e.to_string()))?
+                        .map(|row| row.value().to_vec());
+                    let prev_entry = prev.as_deref().map(CatalogEntry::decode).transpose()?;
+                    let prev_version = match &prev_entry {
+                        Some(entry) => entry.version(),
+                        // Numbering carries on past a delete kept in history.
+                        None if versioning.is_some() => {
+                            versions::last_version(&txn, key)?.unwrap_or(0)
+                        }
+                        None => 0,
+                    };
+                    let outcome = condition
+                        .evaluate(prev_entry.as_ref().and_then(|e| e.condition_state(now)));
+                    outcomes.push(outcome);
+                    if outcome != WriteOutcome::Written {
+                        continue;
+                    }
+                    if let (Some(policy), Some(row)) = (&versioning, &prev) {
+                        let blob = |t: &redb::Table<'_, (u32, u64), &[u8]>| -> Result<Vec<u8>> {
+                            Ok(t.get(key)
+                                .map_err(|e| Error::Index(e.to_string()))?
+                                .map(|v| v.value().to_vec())
+                                .unwrap_or_default())
+                        };
+                        let snapshot =
+                            versions::encode(row, &blob(&fps)?, &blob(&meta)?, &blob(&vecs)?);
+                        versions::archive(&txn, key, prev_version, &snapshot, now, policy)?;
+                    }
                     fps.insert(key, rec.fingerprint.as_ref())
                         .map_err(|e| Error::Index(e.to_string()))?;
                     meta.insert(key, rec.metadata.as_ref())
                         .map_err(|e| Error::Index(e.to_string()))?;
 
-                    let embedding_dim = rec.embedding.as_ref().map(|v| v.len()).unwrap_or(0) as u32;
                     if let Some(v) = rec.embedding.as_ref() {
                         vecs.insert(key, bytemuck::cast_slice::<f32, u8>(v))
                             .map_err(|e| Error::Index(e.to_string()))?;
@@ -189,38 +642,75 @@ impl IndexBackend for EmbeddedBackend {
                         // Drop any stale vector for this key.
                         vecs.remove(key).map_err(|e| Error::Index(e.to_string()))?;
                     }
+                    // Text retained while the option was on must not
+                    // outlive the record version it came with.
+                    match rec.text.as_deref() {
+                        Some(t) if retain_text => {
+                            texts
+                                .insert(key, t)
+                                .map_err(|e| Error::Index(e.to_string()))?;
+                        }
+                        _ => {
+                            texts.remove(key).map_err(|e| Error::Index(e.to_string()))?;
+                        }
+                    }
+                    if retain_text && !rec.text_fields.is_empty() {
+                        text_fields
+                            .insert(key, encode_text_fields(&rec.text_fields)?.as_slice())
+                            .map_err(|e| Error::Index(e.to_string()))?;
+                    } else {
+                        text_fields
+                            .remove(key)
+                            .map_err(|e| Error::Index(e.to_string()))?;
+                    }
 
-                    let entry = CatalogEntry {
-                        modality: rec.modality as u32,
-                        format_version: rec.format_version,
-                        config_hash: rec.config_hash,
-                        fingerprint_len: rec.fingerprint.len() as u32,
-                        embedding_dim,
-                        algorithm: rec.algorithm.clone(),
-                        model_id: rec.model_id.clone(),
-                        metadata_len: rec.metadata.len() as u32,
-                    };
-                    let row = serde_json::to_vec(&entry)
-                        .map_err(|e| Error::Index(format!("catalog encode: {e}")))?;
+                    let entry = CatalogEntry::from_record(rec).stamped(prev_version + 1, now);
+                    if let Some(prev) = &prev_entry {
+                        counters.remove(rec.tenant_id, prev);
+                    }
+                    counters.add(rec.tenant_id, &entry);
+                    let row = entry.encode()?;
                     cat.insert(key, row.as_slice())
                         .map_err(|e| Error::Index(e.to_string()))?;
+                    log.push(
+                        ChangeKind::Upsert,
+                        rec.tenant_id,
+                        Some(rec.record_id),
+                        Some(prev_version + 1),
+                    )?;
                 }
 
-                // BM25 index update — same txn as the fingerprint write.
-                // Records without text are skipped; clear_one keeps the
-                // index in step if a text record is re-ingested without
-                // text (e.g. modality change).
-                for rec in &batch {
+                // BM25 index update — same txn as the fingerprint write,
+                // applied once for the whole batch. Records without text
+                // or text fields are cleared, which keeps the index in step if a text
+                // record is re-ingested without text (e.g. modality
+                // change).
+                let written = batch
+                    .iter()
+                    .zip(&outcomes)
+                    .filter(|(_, o)| **o == WriteOutcome::Written);
+                let mut index =
+                    bm25::IndexBatch::with_positions(positional).with_analyzers(analyzers);
+                for (rec, _) in written {
                     match rec.text.as_deref() {
-                        Some(t) => bm25::upsert_one(&txn, rec.tenant_id, rec.record_id, t)?,
-                        None => {
-                            bm25::clear_one(&txn, rec.tenant_id, rec.record_id)?;
+                        None if rec.text_fields.is_empty() => {
+                            index.clear(rec.tenant_id, rec.record_id)
                         }
+                        text => index.index_fields(
+                            rec.tenant_id,
+                            rec.record_id,
+                            text.unwrap_or_default(),
+                            &rec.text_fields,
+                        ),
                     }
+                    expiry::record(&txn, rec)?;
                 }
+                index.apply(&txn, &term_dicts)?;
             }
+            counters.apply(&txn)?;
+            log.finish(&txn)?;
             txn.commit().map_err(|e| Error::Index(e.to_string()))?;
-            Ok(())
+            Ok(outcomes)
         })
         .await
         .map_err(|e| Error::Index(format!("join error: {e}")))?
@@ -228,36 +718,25 @@ impl IndexBackend for EmbeddedBackend {
 
     async fn delete(&self, tenant_id: u32, ids: &[u64]) -> Result<()> {
         let db = self.db.clone();
+        let term_dicts = self.term_dicts.clone();
+        let purging = self.purging.clone();
+        let versioning = self.versioning;
         let ids = ids.to_vec();
 
         tokio::task::spawn_blocking(move || -> Result<()> {
             let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
-            {
-                let mut fps = txn
-                    .open_table(FINGERPRINTS)
-                    .map_err(|e| Error::Index(e.to_string()))?;
-                let mut meta = txn
-                    .open_table(METADATA)
-                    .map_err(|e| Error::Index(e.to_string()))?;
-                let mut vecs = txn
-                    .open_table(VECTORS)
-                    .map_err(|e| Error::Index(e.to_string()))?;
-                let mut cat = txn
-                    .open_table(CATALOG)
-                    .map_err(|e| Error::Index(e.to_string()))?;
-                for id in &ids {
-                    let key = (tenant_id, *id);
-                    fps.remove(key).map_err(|e| Error::Index(e.to_string()))?;
-                    meta.remove(key).map_err(|e| Error::Index(e.to_string()))?;
-                    vecs.remove(key).map_err(|e| Error::Index(e.to_string()))?;
-                    cat.remove(key).map_err(|e| Error::Index(e.to_string()))?;
-                }
-                // Pull doc out of the BM25 index too — otherwise a deleted
-                // record keeps polluting term postings + corpus stats.
-                for id in &ids {
-                    bm25::clear_one(&txn, tenant_id, *id)?;
-                }
-            }
+            purging.check(tenant_id)?;
+            let mut log = changes::ChangeLog::open(&txn, now_ms())?;
+            delete_rows(
+                &txn,
+                &term_dicts,
+                &mut log,
+                ChangeKind::Delete,
+                versioning.as_ref(),
+                tenant_id,
+                &ids,
+            )?;
+            log.finish(&txn)?;
             txn.commit().map_err(|e| Error::Index(e.to_string()))?;
             Ok(())
         })
@@ -270,9 +749,16 @@ impl IndexBackend for EmbeddedBackend {
         tenant_id: u32,
         query: &[f32],
         k: usize,
-        _filter: Option<&Bytes>,
+        filter: Option<&Bytes>,
     ) -> Result<Vec<Hit>> {
-        if query.is_empty() || k == 0 {
+        // Same contract as BM25: refuse rather than silently return
+        // unfiltered hits until the roaring pre-filter from §4 lands.
+        if filter.is_some() {
+            return Err(Error::Unsupported(
+                "knn filter pre-filtering is not yet supported on EmbeddedBackend".into(),
+            ));
+        }
+        if query.is_empty() || k == 0 || self.purging.is_purging(tenant_id) {
             return Ok(Vec::new());
         }
 
@@ -287,12 +773,10 @@ impl IndexBackend for EmbeddedBackend {
 
             // ── Phase 1: collect candidates from redb ─────────────────────
             //
-            // Parse stored f32 vectors via `from_le_bytes` rather than
-            // `bytemuck::cast_slice`. redb returns `&[u8]` slices into its
-            // mmap; their alignment is not guaranteed to be 4 bytes, so a
-            // direct cast would panic on architectures that enforce it.
+            // redb returns `&[u8]` slices into its mmap with no alignment
+            // guarantee — `decode_vector` copies rather than casts.
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
             let candidates: Vec<(u64, Vec<f32>)> = {
-                let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
                 let table = txn
                     .open_table(VECTORS)
                     .map_err(|e| Error::Index(e.to_string()))?;
@@ -307,53 +791,32 @@ impl IndexBackend for EmbeddedBackend {
                     if bytes.len() % 4 != 0 || bytes.len() / 4 != query.len() {
                         continue;
                     }
-                    let v: Vec<f32> = bytes
-                        .chunks_exact(4)
-                        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-                        .collect();
-                    out.push((rid, v));
+                    out.push((rid, decode_vector(bytes)));
                 }
                 out
             };
 
-            // ── Phase 2: rayon parallel cosine + top-k merge ──────────────
+            // ── Phase 2: rank, dropping expired records ──────────────────
             //
-            // Per-thread fold builds a local top-k descending; reduce
-            // merges with a bounded insert. Final sort is on a vec of size
-            // ≤ k, so it's free relative to the scan.
-            let mut merged: Vec<(u64, f32)> = candidates
-                .par_iter()
-                .fold(Vec::<(u64, f32)>::new, |mut local, (rid, v)| {
-                    let v_norm = l2_norm(v);
-                    if v_norm == 0.0 {
-                        return local;
-                    }
-                    let score = dot_product(&query, v) / (q_norm * v_norm);
-                    insert_topk(&mut local, *rid, score, k);
-                    local
-                })
-                .reduce(Vec::<(u64, f32)>::new, |mut a, b| {
-                    for (rid, score) in b {
-                        insert_topk(&mut a, rid, score, k);
+            // Expiry is looked up for the ranked hits only; when some are
+            // expired, rank twice as deep until `k` live ones are found.
+            let hidden = expiry::Expired::open(&txn, tenant_id, now_ms())?;
+            let mut depth = k;
+            loop {
+                let ranked = rank_cosine(tenant_id, &query, q_norm, &candidates, depth);
+                let exhausted = ranked.len() < depth;
+                let mut live = Vec::with_capacity(k);
+                for hit in ranked {
+                    if !hidden.contains(hit.record_id)? {
+                        live.push(hit);
                     }
-                    a
-                });
-
-            merged.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
-            Ok(merged
-                .into_iter()
-                .map(|(rid, score)| Hit {
-                    tenant_id,
-                    record_id: rid,
-                    score,
-                    source: HitSource::Vector,
-                    vector_score: None,
-                    bm25_score: None,
-                    vector_rank: None,
-                    bm25_rank: None,
-                    term_hits: Vec::new(),
-                })
-                .collect())
+                }
+                if live.len() >= k || exhausted {
+                    live.truncate(k);
+                    return Ok(live);
+                }
+                depth = depth.saturating_mul(2);
+            }
         })
         .await
         .map_err(|e| Error::Index(format!("join error: {e}")))?
@@ -366,7 +829,8 @@ impl IndexBackend for EmbeddedBackend {
         k: usize,
         filter: Option<&Bytes>,
     ) -> Result<Vec<Hit>> {
-        self.bm25_inner(tenant_id, terms, k, filter, false).await
+        self.bm25_inner(tenant_id, terms, k, filter, false, None)
+            .await
     }
 
     async fn bm25_explain(
@@ -376,7 +840,93 @@ impl IndexBackend for EmbeddedBackend {
         k: usize,
         filter: Option<&Bytes>,
     ) -> Result<Vec<Hit>> {
-        self.bm25_inner(tenant_id, terms, k, filter, true).await
+        self.bm25_inner(tenant_id, terms, k, filter, true, None)
+            .await
+    }
+
+    async fn bm25_with(
+        &self,
+        tenant_id: u32,
+        terms: &[&str],
+        k: usize,
+        filter: Option<&Bytes>,
+        explain: bool,
+        params: &Bm25Params,
+    ) -> Result<Vec<Hit>> {
+        self.bm25_inner(tenant_id, terms, k, filter, explain, Some(*params))
+            .await
+    }
+
+    async fn highlight(&self, tenant_id: u32, hits: &mut [Hit], opts: &Highlight) -> Result<()> {
+        if !self.retain_text {
+            return Err(Error::Incompatible(
+                "highlighting needs retained text; open with with_retained_text (UCFP_RETAIN_TEXT=1)"
+                    .into(),
+            ));
+        }
+        let db = self.db.clone();
+        let analyzer = self.analyzer(tenant_id);
+        let opts = *opts;
+        let wanted: Vec<(u64, Vec<(String, String)>)> = hits
+            .iter()
+            .map(|h| {
+                let matched = h
+                    .term_hits
+                    .iter()
+                    .map(|t| (t.field.clone(), t.term.clone()));
+                (h.record_id, matched.collect())
+            })
+            .collect();
+        let snippets = tokio::task::spawn_blocking(move || -> Result<Vec<Vec<Snippet>>> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let texts = txn
+                .open_table(TEXT)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let text_fields = txn
+                .open_table(TEXT_FIELDS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let mut out = Vec::with_capacity(wanted.len());
+            for (id, matched) in &wanted {
+                if matched.is_empty() {
+                    out.push(Vec::new());
+                    continue;
+                }
+                let key = (tenant_id, *id);
+                let text = texts
+                    .get(key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map(|v| v.value().to_string());
+                let named = match text_fields
+                    .get(key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                {
+                    Some(v) => decode_text_fields(v.value())?,
+                    None => BTreeMap::new(),
+                };
+                let fields: Vec<(&str, &str)> = text
+                    .as_deref()
+                    .map(|t| (bm25::TEXT_FIELD, t))
+                    .into_iter()
+                    .chain(named.iter().map(|(f, t)| (f.as_str(), t.as_str())))
+                    .collect();
+                let matched: HashSet<(&str, &str)> = matched
+                    .iter()
+                    .map(|(f, t)| (f.as_str(), t.as_str()))
+                    .collect();
+                out.push(snippet::snippets(&analyzer, &fields, &matched, &opts));
+            }
+            Ok(out)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))??;
+        for (hit, snippets) in hits.iter_mut().zip(snippets) {
+            hit.snippets = snippets;
+        }
+        Ok(())
+    }
+
+    fn retains_text(&self) -> bool {
+        self.retain_text
     }
 
     async fn flush(&self) -> Result<()> {
@@ -396,89 +946,774 @@ impl IndexBackend for EmbeddedBackend {
         let db = self.db.clone();
         tokio::task::spawn_blocking(move || -> Result<FingerprintMeta> {
             let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
-            let table = txn
+            read_current(&txn, tenant_id, record_id)?.into_meta(tenant_id, record_id)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn scan(
+        &self,
+        tenant_id: u32,
+        cursor: Option<&str>,
+        limit: usize,
+        filter: &RecordFilter,
+    ) -> Result<ScanPage> {
+        let Some(start) = decode_cursor(cursor)? else {
+            return Ok(ScanPage::default());
+        };
+        let db = self.db.clone();
+        let filter = filter.clone();
+        tokio::task::spawn_blocking(move || -> Result<ScanPage> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let cat = txn
                 .open_table(CATALOG)
                 .map_err(|e| Error::Index(e.to_string()))?;
-            let row = table
-                .get((tenant_id, record_id))
+            let rows = cat
+                .range((tenant_id, start)..=(tenant_id, u64::MAX))
                 .map_err(|e| Error::Index(e.to_string()))?
-                .ok_or(Error::RecordNotFound {
-                    tenant_id,
-                    record_id,
-                })?;
-            let entry: CatalogEntry = serde_json::from_slice(row.value())
-                .map_err(|e| Error::Index(format!("catalog decode: {e}")))?;
-            let modality = match entry.modality {
-                0 => Modality::Audio,
-                1 => Modality::Image,
-                2 => Modality::Text,
-                other => {
-                    return Err(Error::Index(format!(
-                        "catalog: unknown modality discriminator {other}"
-                    )));
-                }
-            };
-            let embedding_dim = if entry.embedding_dim == 0 {
-                None
-            } else {
-                Some(entry.embedding_dim as usize)
-            };
-            Ok(FingerprintMeta {
-                tenant_id,
-                record_id,
-                modality,
-                algorithm: entry.algorithm,
-                format_version: entry.format_version,
-                config_hash: entry.config_hash,
-                fingerprint_bytes: entry.fingerprint_len as usize,
-                has_embedding: embedding_dim.is_some(),
-                embedding_dim,
-                model_id: entry.model_id,
-                metadata_bytes: entry.metadata_len as usize,
-            })
+                .map(|entry| {
+                    let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+                    Ok((k.value().1, v.value().to_vec()))
+                });
+            scan_rows(tenant_id, rows, limit, &filter)
         })
         .await
         .map_err(|e| Error::Index(format!("join error: {e}")))?
     }
-}
-
-// ── helpers ─────────────────────────────────────────────────────────────
 
-/// Dot product with chunked independent accumulators.
-///
-/// Eight parallel f32 lanes break the dependency chain so LLVM emits a
-/// SIMD-friendly inner loop (AVX2 on x86_64, NEON on aarch64) at
-/// `opt-level >= 2`. The scalar `iter().zip().map().sum()` form depends
-/// on a single accumulator and stalls vectorization.
-#[inline]
-fn dot_product(a: &[f32], b: &[f32]) -> f32 {
-    debug_assert_eq!(a.len(), b.len());
-    const LANES: usize = 8;
-    let mut accs = [0f32; LANES];
-    let a_chunks = a.chunks_exact(LANES);
-    let b_chunks = b.chunks_exact(LANES);
-    let a_rem = a_chunks.remainder();
-    let b_rem = b_chunks.remainder();
-    for (ac, bc) in a_chunks.zip(b_chunks) {
-        for j in 0..LANES {
-            accs[j] += ac[j] * bc[j];
+    async fn delete_where(
+        &self,
+        tenant_id: u32,
+        filter: &RecordFilter,
+        progress: &Progress,
+    ) -> Result<u64> {
+        let removed = delete_matching(self, tenant_id, filter, progress).await?;
+        if removed > 0 {
+            let db = self.db.clone();
+            let purging = self.purging.clone();
+            tokio::task::spawn_blocking(move || -> Result<()> {
+                let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+                purging.check(tenant_id)?;
+                bm25::prune_dead_terms(&txn, tenant_id)?;
+                txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+                Ok(())
+            })
+            .await
+            .map_err(|e| Error::Index(format!("join error: {e}")))??;
         }
+        Ok(removed)
     }
-    let mut sum: f32 = accs.iter().sum();
-    for (x, y) in a_rem.iter().zip(b_rem.iter()) {
-        sum += x * y;
+
+    async fn purge_tenant(&self, tenant_id: u32, progress: &Progress) -> Result<u64> {
+        // Whole-tenant drop skips the per-doc BM25 bookkeeping `delete`
+        // does: every BM25 row for the tenant goes anyway, so each table
+        // is drained by key range instead. Writes to the tenant are
+        // refused and its searches answer empty meanwhile; the sweeper
+        // leaves its records to the purge. The dictionary, corpus stats
+        // and counters go in its last commit.
+        let _fence = self.purging.begin(tenant_id)?;
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            let mut log = changes::ChangeLog::open(&txn, now_ms())?;
+            log.push(ChangeKind::Purge, tenant_id, None, None)?;
+            log.finish(&txn)?;
+            txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))??;
+
+        // Catalog first and counted: once a record's catalog row is gone
+        // it no longer lists, describes or scans.
+        let removed = self
+            .drain_tenant(CATALOG, tenant_id, Some(progress))
+            .await?;
+        self.drain_tenant(FINGERPRINTS, tenant_id, None).await?;
+        self.drain_tenant(METADATA, tenant_id, None).await?;
+        self.drain_tenant(VECTORS, tenant_id, None).await?;
+        self.drain_tenant(TEXT, tenant_id, None).await?;
+        self.drain_tenant(TEXT_FIELDS, tenant_id, None).await?;
+        for table in [bm25::BM25_BLOCKS, bm25::BM25_POSITIONS] {
+            loop {
+                let db = self.db.clone();
+                let n = tokio::task::spawn_blocking(move || {
+                    postings::drain_tenant_batch(&db, table, tenant_id)
+                })
+                .await
+                .map_err(|e| Error::Index(format!("join error: {e}")))??;
+                if n == 0 {
+                    break;
+                }
+            }
+        }
+        self.drain_tenant(bm25::BM25_DOC_LENS, tenant_id, None)
+            .await?;
+        self.drain_tenant(bm25::BM25_DOC_TERMS, tenant_id, None)
+            .await?;
+        self.drain_tenant(bm25::BM25_FIELD_LENS, tenant_id, None)
+            .await?;
+        // Sweep-queue entries stay behind; the sweeper drops them as
+        // stale once their `expiry` row is gone.
+        self.drain_tenant(expiry::EXPIRY, tenant_id, None).await?;
+        loop {
+            let db = self.db.clone();
+            let n =
+                tokio::task::spawn_blocking(move || versions::drain_tenant_batch(&db, tenant_id))
+                    .await
+                    .map_err(|e| Error::Index(format!("join error: {e}")))??;
+            if n == 0 {
+                break;
+            }
+        }
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            bm25::drop_tenant_heads(&txn, tenant_id)?;
+            stats::drop_tenant(&txn, tenant_id)?;
+            txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))??;
+        Ok(removed)
     }
-    sum
-}
 
-#[inline]
-fn l2_norm(v: &[f32]) -> f32 {
-    dot_product(v, v).sqrt()
-}
+    async fn sweep_expired(&self, now_ms: u64, limit: usize) -> Result<u64> {
+        let db = self.db.clone();
+        let term_dicts = self.term_dicts.clone();
+        let purging = self.purging.clone();
+        let versioning = self.versioning;
+        tokio::task::spawn_blocking(move || -> Result<u64> {
+            let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            let due = expiry::take_due(&txn, now_ms, limit, &purging)?;
+            let mut log = changes::ChangeLog::open(&txn, now_ms)?;
+            let mut by_tenant: std::collections::BTreeMap<u32, Vec<u64>> = Default::default();
+            for (tenant_id, record_id) in &due {
+                by_tenant.entry(*tenant_id).or_default().push(*record_id);
+            }
+            for (tenant_id, ids) in &by_tenant {
+                delete_rows(
+                    &txn,
+                    &term_dicts,
+                    &mut log,
+                    ChangeKind::Expire,
+                    versioning.as_ref(),
+                    *tenant_id,
+                    ids,
+                )?;
+            }
+            log.finish(&txn)?;
+            txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(due.len() as u64)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
 
-/// Maintain a sorted-descending top-k buffer in place. O(k) per call —
-/// fine for k ≤ a few hundred. For larger k, a min-heap would amortize
-/// better, but the partition_point + insert pattern compiles to tight,
+    async fn list_versions(&self, tenant_id: u32, record_id: u64) -> Result<Vec<FingerprintMeta>> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<Vec<FingerprintMeta>> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let current = current_if_any(&txn, tenant_id, record_id)?;
+            let history = txn
+                .open_table(versions::VERSIONS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let mut out = Vec::new();
+            for entry in history
+                .range((tenant_id, record_id, 0)..=(tenant_id, record_id, u64::MAX))
+                .map_err(|e| Error::Index(e.to_string()))?
+            {
+                let (_, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+                let snap = versions::decode(v.value())?;
+                out.push(snap.catalog.into_meta(tenant_id, record_id)?);
+            }
+            if let Some(current) = current {
+                out.push(current.into_meta(tenant_id, record_id)?);
+            }
+            if out.is_empty() {
+                return Err(Error::RecordNotFound {
+                    tenant_id,
+                    record_id,
+                });
+            }
+            Ok(out)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn get_version(
+        &self,
+        tenant_id: u32,
+        record_id: u64,
+        spec: VersionSpec,
+    ) -> Result<VersionedRecord> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<VersionedRecord> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let not_found = Error::RecordNotFound {
+                tenant_id,
+                record_id,
+            };
+            let current = current_if_any(&txn, tenant_id, record_id)?;
+            let is_current = current.as_ref().is_some_and(|current| match spec {
+                VersionSpec::Latest => true,
+                VersionSpec::Number(n) => n == current.version(),
+                VersionSpec::AsOf(at) => current.written_at.unwrap_or(0) <= at,
+            });
+            if let Some(current) = current.filter(|_| is_current) {
+                let key = (tenant_id, record_id);
+                let blob =
+                    |def: TableDefinition<'_, (u32, u64), &[u8]>| -> Result<Option<Vec<u8>>> {
+                        Ok(txn
+                            .open_table(def)
+                            .map_err(|e| Error::Index(e.to_string()))?
+                            .get(key)
+                            .map_err(|e| Error::Index(e.to_string()))?
+                            .map(|v| v.value().to_vec()))
+                    };
+                let fp = blob(FINGERPRINTS)?.unwrap_or_default();
+                let md = blob(METADATA)?.unwrap_or_default();
+                let vec = blob(VECTORS)?;
+                let meta = current.clone().into_meta(tenant_id, record_id)?;
+                let record = current.into_record(tenant_id, record_id, &fp, &md, vec.as_deref())?;
+                return Ok(VersionedRecord { meta, record });
+            }
+
+            let history = txn
+                .open_table(versions::VERSIONS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let row = match spec {
+                VersionSpec::Number(n) => history
+                    .get((tenant_id, record_id, n))
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map(|v| v.value().to_vec()),
+                // Newest retained version written at or before `at`.
+                VersionSpec::AsOf(at) => {
+                    let mut found = None;
+                    for entry in history
+                        .range((tenant_id, record_id, 0)..=(tenant_id, record_id, u64::MAX))
+                        .map_err(|e| Error::Index(e.to_string()))?
+                        .rev()
+                    {
+                        let (_, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+                        let snap = versions::decode(v.value())?;
+                        if snap.catalog.written_at.unwrap_or(0) <= at {
+                            found = Some(v.value().to_vec());
+                            break;
+                        }
+                    }
+                    found
+                }
+                VersionSpec::Latest => None,
+            };
+            let row = row.ok_or(not_found)?;
+            let snap = versions::decode(&row)?;
+            // A tombstone: the record did not exist at that version / time.
+            if snap.catalog.deleted {
+                return Err(Error::RecordNotFound {
+                    tenant_id,
+                    record_id,
+                });
+            }
+            let meta = snap.catalog.clone().into_meta(tenant_id, record_id)?;
+            let record = snap.catalog.into_record(
+                tenant_id,
+                record_id,
+                snap.fingerprint,
+                snap.metadata,
+                snap.vector,
+            )?;
+            Ok(VersionedRecord { meta, record })
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn prune_versions(&self, now_ms: u64, limit: usize) -> Result<u64> {
+        let Some(keep_for) = self.versioning.and_then(|p| p.keep_for_ms) else {
+            return Ok(0);
+        };
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<u64> {
+            let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            let pruned = versions::prune_due(&txn, now_ms.saturating_sub(keep_for), limit)?;
+            txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(pruned)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn changes_since(&self, seq: u64, limit: usize) -> Result<Vec<ChangeEvent>> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<Vec<ChangeEvent>> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            changes::since(&txn, seq, limit)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn snapshot(&self, dest: &Path) -> Result<SnapshotInfo> {
+        let db = self.db.clone();
+        let db_path = self.path.clone();
+        let dest = dest.to_path_buf();
+        tokio::task::spawn_blocking(move || snapshot::write(&db, &db_path, &dest))
+            .await
+            .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn tenant_stats(&self, tenant_id: Option<u32>) -> Result<Vec<TenantStats>> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<Vec<TenantStats>> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let tenants = match tenant_id {
+                Some(t) => vec![t],
+                None => stats::tenants(&txn)?,
+            };
+            tenants
+                .into_iter()
+                .map(|t| stats::tenant_stats(&txn, t))
+                .collect()
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn suggest_terms(
+        &self,
+        tenant_id: u32,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<TermSuggestion>> {
+        let db = self.db.clone();
+        let prefix = prefix.to_string();
+        let analyzer = self.analyzer(tenant_id);
+        tokio::task::spawn_blocking(move || {
+            bm25::suggest(&db, tenant_id, &prefix, limit, &analyzer)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn did_you_mean(
+        &self,
+        tenant_id: u32,
+        text: &str,
+        limit: usize,
+    ) -> Result<Vec<Correction>> {
+        let db = self.db.clone();
+        let text = text.to_string();
+        let analyzer = self.analyzer(tenant_id);
+        tokio::task::spawn_blocking(move || {
+            bm25::did_you_mean(&db, tenant_id, &text, limit, &analyzer)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn reindex_bm25(&self, tenant_id: u32, progress: &Progress) -> Result<u64> {
+        // Tokenize page by page off the write lock, then swap the whole
+        // index in one transaction; see `reindex`.
+        let positional = self.positional.contains(&tenant_id);
+        let analyzer = Arc::new(self.analyzer(tenant_id));
+        let mut docs = BTreeMap::new();
+        loop {
+            let db = self.db.clone();
+            let after = docs.keys().next_back().copied();
+            let page_analyzer = analyzer.clone();
+            let page = tokio::task::spawn_blocking(move || {
+                reindex::tokenize_page(&db, tenant_id, after, positional, &page_analyzer)
+            })
+            .await
+            .map_err(|e| Error::Index(format!("join error: {e}")))??;
+            if page.is_empty() {
+                break;
+            }
+            progress.add_batch(page.len() as u64);
+            docs.extend(page);
+        }
+        let db = self.db.clone();
+        let purging = self.purging.clone();
+        tokio::task::spawn_blocking(move || {
+            reindex::swap(&db, tenant_id, docs, positional, &analyzer, &purging)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn fsck(&self, tenant_id: Option<u32>, repair: bool) -> Result<FsckReport> {
+        let db = self.db.clone();
+        let text = self.retain_text.then(|| fsck::TextRebuild {
+            analyzers: self.analyzers.clone(),
+            positional: self.positional.clone(),
+            purging: self.purging.clone(),
+        });
+        tokio::task::spawn_blocking(move || fsck::run(&db, tenant_id, repair, text.as_ref()))
+            .await
+            .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn prune_changes(&self, cutoff_ms: u64, limit: usize) -> Result<u64> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || -> Result<u64> {
+            let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+            let pruned = changes::prune(&txn, cutoff_ms, limit)?;
+            txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(pruned)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn get_records(&self, tenant_id: u32, ids: &[u64]) -> Result<Vec<Record>> {
+        let db = self.db.clone();
+        let ids = ids.to_vec();
+        tokio::task::spawn_blocking(move || -> Result<Vec<Record>> {
+            let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+            let cat = txn
+                .open_table(CATALOG)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let fps = txn
+                .open_table(FINGERPRINTS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let meta = txn
+                .open_table(METADATA)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let vecs = txn
+                .open_table(VECTORS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let texts = txn
+                .open_table(TEXT)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let text_fields = txn
+                .open_table(TEXT_FIELDS)
+                .map_err(|e| Error::Index(e.to_string()))?;
+
+            let now = now_ms();
+            let mut out = Vec::with_capacity(ids.len());
+            for id in ids {
+                let key = (tenant_id, id);
+                let Some(row) = cat.get(key).map_err(|e| Error::Index(e.to_string()))? else {
+                    continue;
+                };
+                let entry = CatalogEntry::decode(row.value())?;
+                if entry.is_expired(now) {
+                    continue;
+                }
+                let fp = fps.get(key).map_err(|e| Error::Index(e.to_string()))?;
+                let md = meta.get(key).map_err(|e| Error::Index(e.to_string()))?;
+                let vec = vecs.get(key).map_err(|e| Error::Index(e.to_string()))?;
+                let mut record = entry.into_record(
+                    tenant_id,
+                    id,
+                    fp.as_ref().map(|v| v.value()).unwrap_or_default(),
+                    md.as_ref().map(|v| v.value()).unwrap_or_default(),
+                    vec.as_ref().map(|v| v.value()),
+                )?;
+                record.text = texts
+                    .get(key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map(|v| v.value().to_string());
+                if let Some(v) = text_fields
+                    .get(key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                {
+                    record.text_fields = decode_text_fields(v.value())?;
+                }
+                out.push(record);
+            }
+            Ok(out)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+}
+
+// ── helpers ─────────────────────────────────────────────────────────────
+
+/// A `ucfp/text_fields/v1` row.
+fn encode_text_fields(fields: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    serde_json::to_vec(fields).map_err(|e| Error::Index(format!("text fields encode: {e}")))
+}
+
+/// Decode what [`encode_text_fields`] wrote.
+pub(super) fn decode_text_fields(raw: &[u8]) -> Result<BTreeMap<String, String>> {
+    serde_json::from_slice(raw).map_err(|e| Error::Index(format!("text fields decode: {e}")))
+}
+
+/// Current catalog row of a live (present, unexpired) record.
+fn read_current(
+    txn: &redb::ReadTransaction,
+    tenant_id: u32,
+    record_id: u64,
+) -> Result<CatalogEntry> {
+    txn.open_table(CATALOG)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get((tenant_id, record_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map(|row| CatalogEntry::decode(row.value()))
+        .transpose()?
+        .filter(|entry| !entry.is_expired(now_ms()))
+        .ok_or(Error::RecordNotFound {
+            tenant_id,
+            record_id,
+        })
+}
+
+/// [`read_current`], `None` where that is [`Error::RecordNotFound`].
+fn current_if_any(
+    txn: &redb::ReadTransaction,
+    tenant_id: u32,
+    record_id: u64,
+) -> Result<Option<CatalogEntry>> {
+    match read_current(txn, tenant_id, record_id) {
+        Ok(entry) => Ok(Some(entry)),
+        Err(Error::RecordNotFound { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove `ids` of `tenant_id` from every table inside `txn`. With
+/// `versioning` on, each removed row goes to history followed by a
+/// tombstone; otherwise its history goes too.
+fn delete_rows(
+    txn: &redb::WriteTransaction,
+    term_dicts: &bm25::TermDictCache,
+    log: &mut changes::ChangeLog<'_>,
+    kind: ChangeKind,
+    versioning: Option<&VersionPolicy>,
+    tenant_id: u32,
+    ids: &[u64],
+) -> Result<()> {
+    let now = log.at_ms();
+    let mut counters = stats::Delta::default();
+    {
+        let mut fps = txn
+            .open_table(FINGERPRINTS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut meta = txn
+            .open_table(METADATA)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut vecs = txn
+            .open_table(VECTORS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut cat = txn
+            .open_table(CATALOG)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut exp = txn
+            .open_table(expiry::EXPIRY)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut texts = txn
+            .open_table(TEXT)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut text_fields = txn
+            .open_table(TEXT_FIELDS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let take = |t: &mut redb::Table<'_, (u32, u64), &[u8]>, key| -> Result<Vec<u8>> {
+            Ok(t.remove(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|v| v.value().to_vec())
+                .unwrap_or_default())
+        };
+        for id in ids {
+            let key = (tenant_id, *id);
+            let fp = take(&mut fps, key)?;
+            texts.remove(key).map_err(|e| Error::Index(e.to_string()))?;
+            text_fields
+                .remove(key)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            let md = take(&mut meta, key)?;
+            let vector = take(&mut vecs, key)?;
+            let row = cat
+                .remove(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|row| row.value().to_vec());
+            exp.remove(key).map_err(|e| Error::Index(e.to_string()))?;
+            let Some(row) = row else {
+                continue;
+            };
+            let entry = CatalogEntry::decode(&row)?;
+            counters.remove(tenant_id, &entry);
+            log.push(kind, tenant_id, Some(*id), None)?;
+            match versioning {
+                Some(policy) => {
+                    let version = entry.version();
+                    let snapshot = versions::encode(&row, &fp, &md, &vector);
+                    versions::archive(txn, key, version, &snapshot, now, policy)?;
+                    let tombstone = entry.tombstone(version + 1, now).encode()?;
+                    let snapshot = versions::encode(&tombstone, &[], &[], &[]);
+                    versions::archive(txn, key, version + 1, &snapshot, now, policy)?;
+                }
+                None => versions::drop_history(txn, tenant_id, *id)?,
+            }
+        }
+    }
+    counters.apply(txn)?;
+    // Pull doc out of the BM25 index too — otherwise a deleted
+    // record keeps polluting term postings + corpus stats.
+    let mut index = bm25::IndexBatch::default();
+    for id in ids {
+        index.clear(tenant_id, *id);
+    }
+    index.apply(txn, term_dicts)
+}
+
+/// Delete up to [`DELETE_BATCH`] `(tenant_id, *)` rows of `table` in one
+/// write transaction. Returns how many went; 0 means the range is empty.
+fn drain_batch<V: redb::Value + 'static>(
+    db: &Database,
+    table: TableDefinition<'_, (u32, u64), V>,
+    tenant_id: u32,
+) -> Result<u64> {
+    let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    let n = {
+        let mut t = txn
+            .open_table(table)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let keys: Vec<(u32, u64)> = t
+            .range((tenant_id, 0u64)..=(tenant_id, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .take(DELETE_BATCH)
+            .map(|entry| entry.map(|(k, _)| k.value()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for key in &keys {
+            t.remove(*key).map_err(|e| Error::Index(e.to_string()))?;
+        }
+        keys.len() as u64
+    };
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    Ok(n)
+}
+
+/// Shared page builder for [`IndexBackend::scan`]. `rows` yields
+/// `(record_id, catalog row)` in ascending order from the cursor
+/// position. Expired rows are skipped. Stops at `limit` matches (0 is
+/// treated as 1) or after
+/// [`SCAN_BUDGET`] rows, handing back a cursor at the last row examined.
+pub(crate) fn scan_rows(
+    tenant_id: u32,
+    rows: impl Iterator<Item = Result<(u64, Vec<u8>)>>,
+    limit: usize,
+    filter: &RecordFilter,
+) -> Result<ScanPage> {
+    let limit = limit.max(1);
+    let now = now_ms();
+    let mut page = ScanPage::default();
+    for (examined, row) in rows.enumerate() {
+        let (record_id, bytes) = row?;
+        let meta = CatalogEntry::decode(&bytes)?.into_meta(tenant_id, record_id)?;
+        if !is_expired(meta.expires_at, now) && filter.matches(&meta) {
+            page.records.push(meta);
+        }
+        if page.records.len() >= limit || examined + 1 >= SCAN_BUDGET {
+            page.next_cursor = Some(encode_cursor(record_id));
+            break;
+        }
+    }
+    Ok(page)
+}
+
+/// Parse a stored f32 vector via `from_le_bytes` rather than
+/// `bytemuck::cast_slice`: storage engines hand back `&[u8]` slices with
+/// no alignment guarantee, so a direct cast would panic on architectures
+/// that enforce it. Trailing bytes short of a full `f32` are ignored.
+pub(crate) fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Phase 2 of brute-force k-NN: rayon parallel cosine + top-k merge
+/// over candidates already pulled from storage. Shared with the fjall
+/// backend so both rank identically.
+///
+/// Per-thread fold builds a local top-k descending; reduce merges with
+/// a bounded insert. Final sort is on a vec of size ≤ k, so it's free
+/// relative to the scan.
+pub(crate) fn rank_cosine(
+    tenant_id: u32,
+    query: &[f32],
+    q_norm: f32,
+    candidates: &[(u64, Vec<f32>)],
+    k: usize,
+) -> Vec<Hit> {
+    let mut merged: Vec<(u64, f32)> = candidates
+        .par_iter()
+        .fold(Vec::<(u64, f32)>::new, |mut local, (rid, v)| {
+            let v_norm = l2_norm(v);
+            if v_norm == 0.0 {
+                return local;
+            }
+            let score = dot_product(query, v) / (q_norm * v_norm);
+            insert_topk(&mut local, *rid, score, k);
+            local
+        })
+        .reduce(Vec::<(u64, f32)>::new, |mut a, b| {
+            for (rid, score) in b {
+                insert_topk(&mut a, rid, score, k);
+            }
+            a
+        });
+
+    merged.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    merged
+        .into_iter()
+        .map(|(rid, score)| Hit {
+            tenant_id,
+            record_id: rid,
+            score,
+            source: HitSource::Vector,
+            vector_score: None,
+            bm25_score: None,
+            vector_rank: None,
+            bm25_rank: None,
+            term_hits: Vec::new(),
+            snippets: Vec::new(),
+        })
+        .collect()
+}
+
+/// Dot product with chunked independent accumulators.
+///
+/// Eight parallel f32 lanes break the dependency chain so LLVM emits a
+/// SIMD-friendly inner loop (AVX2 on x86_64, NEON on aarch64) at
+/// `opt-level >= 2`. The scalar `iter().zip().map().sum()` form depends
+/// on a single accumulator and stalls vectorization.
+#[inline]
+fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    const LANES: usize = 8;
+    let mut accs = [0f32; LANES];
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let a_rem = a_chunks.remainder();
+    let b_rem = b_chunks.remainder();
+    for (ac, bc) in a_chunks.zip(b_chunks) {
+        for j in 0..LANES {
+            accs[j] += ac[j] * bc[j];
+        }
+    }
+    let mut sum: f32 = accs.iter().sum();
+    for (x, y) in a_rem.iter().zip(b_rem.iter()) {
+        sum += x * y;
+    }
+    sum
+}
+
+#[inline]
+pub(crate) fn l2_norm(v: &[f32]) -> f32 {
+    dot_product(v, v).sqrt()
+}
+
+/// Maintain a sorted-descending top-k buffer in place. O(k) per call —
+/// fine for k ≤ a few hundred. For larger k, a min-heap would amortize
+/// better, but the partition_point + insert pattern compiles to tight,
 /// branch-predictable code at small k.
 #[inline]
 fn insert_topk(local: &mut Vec<(u64, f32)>, rid: u64, score: f32, k: usize) {
@@ -496,6 +1731,8 @@ fn insert_topk(local: &mut Vec<(u64, f32)>, rid: u64, score: f32, k: usize) {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::*;
     use crate::core::{Modality, Record};
 
@@ -516,9 +1753,1086 @@ mod tests {
             model_id: Some("test-model".into()),
             metadata: Bytes::new(),
             text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: None,
         }
     }
 
+    /// Rows of `table` under `tenant`, read straight from redb.
+    fn tenant_rows<V: redb::Value + 'static>(
+        db: &EmbeddedBackend,
+        table: TableDefinition<'_, (u32, u64), V>,
+        tenant: u32,
+    ) -> usize {
+        let txn = db.db.begin_read().unwrap();
+        let t = txn.open_table(table).unwrap();
+        t.range((tenant, 0u64)..=(tenant, u64::MAX))
+            .unwrap()
+            .count()
+    }
+
+    /// Posting blocks under `tenant`.
+    fn term_rows(
+        db: &EmbeddedBackend,
+        table: TableDefinition<'_, postings::BlockKey, &[u8]>,
+        tenant: u32,
+    ) -> usize {
+        let txn = db.db.begin_read().unwrap();
+        let t = txn.open_table(table).unwrap();
+        t.range(postings::tenant_range(tenant)).unwrap().count()
+    }
+
+    #[tokio::test]
+    async fn purge_tenant_leaves_no_rows_in_any_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"))
+            .with_positions([1])
+            .with_retained_text();
+        let mut records: Vec<Record> = (0..2500).map(|i| rec(1, i, vec![1.0, 0.0])).collect();
+        for r in &mut records {
+            r.text = Some(format!("doc {} shared", r.record_id));
+            r.text_fields.insert("title".into(), "title words".into());
+        }
+        records.push(rec(2, 0, vec![1.0, 0.0]));
+        db.upsert(&records).await.unwrap();
+
+        let progress = Progress::default();
+        assert_eq!(db.purge_tenant(1, &progress).await.unwrap(), 2500);
+        assert_eq!(progress.batches(), 3, "1000-row batches");
+
+        assert_eq!(tenant_rows(&db, CATALOG, 1), 0);
+        assert_eq!(tenant_rows(&db, FINGERPRINTS, 1), 0);
+        assert_eq!(tenant_rows(&db, METADATA, 1), 0);
+        assert_eq!(tenant_rows(&db, VECTORS, 1), 0);
+        assert_eq!(term_rows(&db, bm25::BM25_BLOCKS, 1), 0);
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 1), 0);
+        assert_eq!(tenant_rows(&db, bm25::BM25_DOC_LENS, 1), 0);
+        assert_eq!(tenant_rows(&db, bm25::BM25_DOC_TERMS, 1), 0);
+        assert_eq!(tenant_rows(&db, bm25::BM25_FIELD_LENS, 1), 0);
+        assert_eq!(tenant_rows(&db, TEXT, 1), 0);
+        assert_eq!(tenant_rows(&db, TEXT_FIELDS, 1), 0);
+        let txn = db.db.begin_read().unwrap();
+        for head in [bm25::BM25_TERM_FST, bm25::BM25_CORPUS, bm25::BM25_FIELDS] {
+            assert!(txn.open_table(head).unwrap().get(1).unwrap().is_none());
+        }
+        let analyzers = txn.open_table(bm25::BM25_ANALYZER).unwrap();
+        assert!(analyzers.get(1).unwrap().is_none());
+        assert_eq!(tenant_rows(&db, CATALOG, 2), 1);
+    }
+
+    #[tokio::test]
+    async fn writes_to_a_tenant_wait_out_its_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb")).with_retained_text();
+        db.upsert(&[text_rec(1, 1, "red fox")]).await.unwrap();
+        let expired = Record {
+            expires_at: Some(1),
+            ..text_rec(1, 9, "old fox")
+        };
+        db.upsert(&[expired]).await.unwrap();
+
+        let fence = db.purging.begin(1).unwrap();
+        assert!(matches!(
+            db.upsert(&[text_rec(1, 2, "blue fox")]).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert!(matches!(
+            db.delete(1, &[1]).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert!(matches!(
+            db.delete_where(1, &RecordFilter::default(), &Progress::default())
+                .await,
+            Err(Error::Incompatible(_))
+        ));
+        assert_eq!(db.sweep_expired(now_ms(), 100).await.unwrap(), 0);
+        assert!(db.bm25(1, &["fox"], 10, None).await.unwrap().is_empty());
+        assert!(matches!(
+            db.purge_tenant(1, &Progress::default()).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert!(matches!(
+            db.reindex_bm25(1, &Progress::default()).await,
+            Err(Error::Incompatible(_))
+        ));
+        db.upsert(&[text_rec(2, 1, "other tenant")]).await.unwrap();
+        drop(fence);
+
+        db.upsert(&[text_rec(1, 2, "blue fox")]).await.unwrap();
+        assert_eq!(db.bm25(1, &["fox"], 10, None).await.unwrap().len(), 2);
+        assert_eq!(db.purge_tenant(1, &Progress::default()).await.unwrap(), 3);
+        db.upsert(&[text_rec(1, 3, "green fox")]).await.unwrap();
+        let hits = db.bm25(1, &["fox"], 10, None).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.record_id).collect::<Vec<_>>(), [3]);
+    }
+
+    #[tokio::test]
+    async fn versioning_keeps_bounded_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb")).with_versioning(VersionPolicy {
+            keep_versions: Some(3),
+            keep_for_ms: Some(1),
+        });
+        for fp in [b"v1", b"v2", b"v3", b"v4"] {
+            let mut r = rec(1, 7, vec![1.0, 0.0]);
+            r.fingerprint = Bytes::from_static(fp);
+            db.upsert(&[r]).await.unwrap();
+        }
+
+        let listed: Vec<u64> = db
+            .list_versions(1, 7)
+            .await
+            .unwrap()
+            .iter()
+            .map(|m| m.version)
+            .collect();
+        assert_eq!(listed, vec![2, 3, 4], "count bound drops v1");
+        let v2 = db.get_version(1, 7, VersionSpec::Number(2)).await.unwrap();
+        assert_eq!(v2.meta.version, 2);
+        assert_eq!(v2.record.fingerprint.as_ref(), b"v2");
+        assert!(matches!(
+            db.get_version(1, 7, VersionSpec::Number(1)).await,
+            Err(Error::RecordNotFound { .. })
+        ));
+        let latest = db.get_version(1, 7, VersionSpec::Latest).await.unwrap();
+        assert_eq!(latest.record.fingerprint.as_ref(), b"v4");
+        let as_of = db
+            .get_version(1, 7, VersionSpec::AsOf(u64::MAX))
+            .await
+            .unwrap();
+        assert_eq!(as_of.meta.version, 4);
+
+        // Age bound: everything superseded is past `keep_for_ms` by now.
+        assert_eq!(db.prune_versions(u64::MAX, 100).await.unwrap(), 2);
+        assert_eq!(db.list_versions(1, 7).await.unwrap().len(), 1);
+
+        let mut r = rec(1, 7, vec![1.0, 0.0]);
+        r.fingerprint = Bytes::from_static(b"v5");
+        db.upsert(&[r]).await.unwrap();
+        db.delete(1, &[7]).await.unwrap();
+
+        // The delete keeps v5 and leaves a tombstone after it.
+        let listed = db.list_versions(1, 7).await.unwrap();
+        let shape: Vec<(u64, bool)> = listed.iter().map(|m| (m.version, m.deleted)).collect();
+        assert_eq!(shape, vec![(5, false), (6, true)]);
+        let v5 = db.get_version(1, 7, VersionSpec::Number(5)).await.unwrap();
+        assert_eq!(v5.record.fingerprint.as_ref(), b"v5");
+        for spec in [
+            VersionSpec::Latest,
+            VersionSpec::Number(6),
+            VersionSpec::AsOf(u64::MAX),
+        ] {
+            assert!(matches!(
+                db.get_version(1, 7, spec).await,
+                Err(Error::RecordNotFound { .. })
+            ));
+        }
+
+        // A re-insert numbers on from the tombstone.
+        db.upsert(&[rec(1, 7, vec![1.0, 0.0])]).await.unwrap();
+        assert_eq!(db.get_record_metadata(1, 7).await.unwrap().version, 7);
+        assert_eq!(db.list_versions(1, 7).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn change_log_retention_keeps_sequence_rising() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        db.upsert(&[rec(1, 1, vec![1.0]), rec(1, 2, vec![1.0])])
+            .await
+            .unwrap();
+        db.purge_tenant(1, &Progress::default()).await.unwrap();
+        let events = db.changes_since(0, 10).await.unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].kind, ChangeKind::Purge);
+        assert_eq!(events[2].record_id, None);
+
+        assert_eq!(
+            db.prune_changes(0, 10).await.unwrap(),
+            0,
+            "nothing that old"
+        );
+        assert_eq!(db.prune_changes(u64::MAX, 2).await.unwrap(), 2, "limit");
+        assert_eq!(db.prune_changes(u64::MAX, 10).await.unwrap(), 1);
+        assert!(db.changes_since(0, 10).await.unwrap().is_empty());
+        assert!(
+            db.changes_since(3, 10).await.unwrap().is_empty(),
+            "caught up"
+        );
+        assert!(matches!(
+            db.changes_since(2, 10).await,
+            Err(Error::ChangesPruned {
+                since: 2,
+                oldest: 4
+            })
+        ));
+
+        db.upsert(&[rec(1, 3, vec![1.0])]).await.unwrap();
+        let events = db.changes_since(0, 10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 4, "head survives an empty log");
+        assert_eq!(events[0].version, Some(1));
+        assert_eq!(db.changes_since(3, 10).await.unwrap().len(), 1);
+        assert!(matches!(
+            db.changes_since(1, 10).await,
+            Err(Error::ChangesPruned { oldest: 4, .. })
+        ));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[tokio::test]
+    async fn parquet_export_is_incremental_from_the_watermark() {
+        use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use arrow_array::{Array, StringArray, UInt64Array};
+
+        let read = |path: &Path| -> Vec<arrow_array::RecordBatch> {
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap()
+                .map(|b| b.unwrap())
+                .collect()
+        };
+        let files = |dir: &Path| std::fs::read_dir(dir).unwrap().count();
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        let mut tagged = rec(1, 1, vec![1.0]);
+        tagged.metadata = Bytes::from_static(br#"{"source":"cam"}"#);
+        let mut text = rec(2, 1, vec![1.0]);
+        text.modality = Modality::Text;
+        db.upsert(&[tagged, rec(1, 2, vec![1.0]), text])
+            .await
+            .unwrap();
+
+        let usage = dir.path().join("usage.ndjson");
+        std::fs::write(
+            &usage,
+            concat!(
+                r#"{"tenant_id":1,"op":"query","modality":"Image","units":3,"status":200,"ts":86400000}"#,
+                "\n",
+                r#"{"tenant_id":1,"op":"query","modality":"Image","units":2,"status":500,"ts":90000000}"#,
+                "\nnot json\n",
+                r#"{"tenant_id":1,"op":"upsert""#,
+            ),
+        )
+        .unwrap();
+
+        let out = dir.path().join("parquet");
+        let first = db.export_parquet(&out, Some(&usage), false).await.unwrap();
+        assert!(first.full);
+        assert_eq!(first.catalog_rows, 3);
+        assert_eq!((first.usage_rows, first.usage_skipped), (1, 1));
+        let image = out.join("catalog/tenant_id=1/modality=image");
+        let batch = &read(&image.join(format!("part-{:020}.parquet", first.watermark)))[0];
+        assert_eq!(batch.num_rows(), 2);
+        let metadata = batch
+            .column_by_name("metadata")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(metadata.value(0), r#"{"source":"cam"}"#);
+        assert!(metadata.is_null(1));
+        let usage_part = std::fs::read_dir(out.join("usage/tenant_id=1"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let requests = read(&usage_part)[0]
+            .column_by_name("requests")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(requests, 2);
+
+        db.upsert(&[rec(1, 3, vec![1.0])]).await.unwrap();
+        db.delete(1, &[2]).await.unwrap();
+        let second = db.export_parquet(&out, Some(&usage), false).await.unwrap();
+        assert!(!second.full);
+        assert_eq!((second.catalog_rows, second.deletes), (1, 1));
+        assert_eq!(second.usage_rows, 0);
+        assert_eq!(files(&image), 2);
+        let deleted = &read(&out.join(format!("deletes/part-{:020}.parquet", second.watermark)))[0];
+        let seq = deleted
+            .column_by_name("seq")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(seq.value(0), second.watermark);
+
+        let third = db.export_parquet(&out, None, true).await.unwrap();
+        assert!(third.full);
+        assert_eq!(third.catalog_rows, 3);
+        assert_eq!(files(&image), 1);
+
+        // More changes than one page of the log.
+        let many: Vec<Record> = (10..10 + DELETE_BATCH as u64 + 5)
+            .map(|rid| rec(1, rid, vec![1.0]))
+            .collect();
+        db.upsert(&many).await.unwrap();
+        db.delete(1, &[10]).await.unwrap();
+        let fourth = db.export_parquet(&out, None, false).await.unwrap();
+        assert!(!fourth.full);
+        assert_eq!(
+            (fourth.catalog_rows, fourth.deletes),
+            (DELETE_BATCH as u64 + 4, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshot_restores_point_in_time_with_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let live = dir.path().join("live/ucfp.redb");
+        let db = fixture(&live);
+        let mut r = rec(1, 1, vec![1.0, 0.0]);
+        r.text = Some("kept across restore".into());
+        db.upsert(&[r]).await.unwrap();
+        std::fs::create_dir_all(snapshot::sidecar_dir(&live)).unwrap();
+        std::fs::write(snapshot::sidecar_dir(&live).join("ann.bin"), b"graph").unwrap();
+
+        let snap = dir.path().join("snaps/one.redb");
+        let info = db.snapshot(&snap).await.unwrap();
+        assert_eq!(info.format_version, crate::FORMAT_VERSION);
+        assert_eq!(info.sidecars, 1);
+        assert!(info.tables.iter().any(|t| t == "ucfp/catalog/v2"));
+        assert!(db.snapshot(&snap).await.is_err(), "never overwrites");
+        db.upsert(&[rec(1, 2, vec![0.0, 1.0])]).await.unwrap();
+
+        assert!(
+            EmbeddedBackend::restore(&snap, &live).is_err(),
+            "live database is open"
+        );
+        let target = dir.path().join("restored/ucfp.redb");
+        EmbeddedBackend::restore(&snap, &target).unwrap();
+        let restored = fixture(&target);
+        let ids: Vec<u64> = restored
+            .get_records(1, &[1, 2])
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.record_id)
+            .collect();
+        assert_eq!(ids, vec![1], "snapshot predates record 2");
+        let hits = restored.bm25(1, &["restore"], 10, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            std::fs::read(snapshot::sidecar_dir(&target).join("ann.bin")).unwrap(),
+            b"graph"
+        );
+    }
+
+    #[test]
+    fn restore_refuses_unknown_table_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let snap = dir.path().join("snap.redb");
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let db = fixture(&dir.path().join("ucfp.redb"));
+            db.snapshot(&snap).await.unwrap();
+        });
+        {
+            let raw = Database::open(&snap).unwrap();
+            let txn = raw.begin_write().unwrap();
+            let future: TableDefinition<'_, (u32, u64), &[u8]> =
+                TableDefinition::new("ucfp/catalog/v3");
+            drop(txn.open_table(future).unwrap());
+            txn.commit().unwrap();
+        }
+        let target = dir.path().join("target.redb");
+        assert!(matches!(
+            EmbeddedBackend::check_snapshot(&snap),
+            Err(Error::Incompatible(_))
+        ));
+        assert!(matches!(
+            EmbeddedBackend::restore(&snap, &target),
+            Err(Error::Incompatible(_))
+        ));
+        assert!(!target.exists(), "nothing swapped in");
+    }
+
+    #[tokio::test]
+    async fn open_migrates_catalog_v1_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ucfp.redb");
+        let v1: TableDefinition<'_, (u32, u64), &[u8]> = TableDefinition::new("ucfp/catalog/v1");
+        {
+            let raw = Database::create(&path).unwrap();
+            let txn = raw.begin_write().unwrap();
+            let mut row = Vec::new();
+            row.extend(2u32.to_le_bytes()); // text
+            row.extend(1u32.to_le_bytes());
+            row.extend(42u64.to_le_bytes());
+            row.extend(2u32.to_le_bytes());
+            row.extend(0u32.to_le_bytes());
+            txn.open_table(v1)
+                .unwrap()
+                .insert((1, 7), row.as_slice())
+                .unwrap();
+            txn.open_table(FINGERPRINTS)
+                .unwrap()
+                .insert((1, 7), b"fp".as_slice())
+                .unwrap();
+            txn.open_table(METADATA)
+                .unwrap()
+                .insert((1, 7), b"{}".as_slice())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+
+        let plan = EmbeddedBackend::migrate(&path, true).unwrap();
+        assert_eq!((plan.from_version, plan.to_version), (1, 4));
+        assert_eq!(plan.steps.len(), 3);
+        assert_eq!(plan.steps[0].rows, 1);
+        assert_eq!(plan.steps[1].rows, 1, "stats backfilled");
+        assert_eq!(plan.steps[2].rows, 0, "no scoring lists");
+        {
+            let raw = Database::open(&path).unwrap();
+            let txn = raw.begin_read().unwrap();
+            assert!(txn.open_table(v1).is_ok(), "dry run commits nothing");
+            assert!(txn.open_table(migrate::SCHEMA).is_err());
+        }
+
+        let db = fixture(&path);
+        let meta = db.get_record_metadata(1, 7).await.unwrap();
+        assert_eq!(meta.modality, Modality::Text);
+        assert_eq!(meta.config_hash, 42);
+        assert_eq!(meta.fingerprint_bytes, 2);
+        assert_eq!(meta.metadata_bytes, 2);
+        assert!(!meta.has_embedding);
+        {
+            let txn = db.db.begin_read().unwrap();
+            assert!(txn.open_table(v1).is_err());
+            let schema = txn.open_table(migrate::SCHEMA).unwrap();
+            let version = schema.get("schema_version").unwrap().unwrap().value();
+            assert_eq!(version, migrate::SCHEMA_VERSION);
+        }
+        drop(db);
+        assert!(
+            EmbeddedBackend::migrate(&path, false)
+                .unwrap()
+                .steps
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn open_migrates_scoring_lists_to_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ucfp.redb");
+        let db = fixture(&path);
+        db.upsert(&[
+            text_rec(1, 1, "red fox"),
+            text_rec(1, 2, "red red dog"),
+            text_rec(2, 1, "red"),
+        ])
+        .await
+        .unwrap();
+        let before = db.bm25(1, &["red", "dog"], 10, None).await.unwrap();
+        drop(db);
+        {
+            // Rewrite the blocks as a schema-3 database stored them.
+            let raw = Database::open(&path).unwrap();
+            let txn = raw.begin_write().unwrap();
+            let mut lists: BTreeMap<(u32, u64), Vec<u8>> = BTreeMap::new();
+            for entry in txn.open_table(bm25::BM25_BLOCKS).unwrap().iter().unwrap() {
+                let (k, v) = entry.unwrap();
+                let (tenant, tid, _) = k.value();
+                let list = lists.entry((tenant, tid)).or_default();
+                for p in postings::decode(v.value()).unwrap() {
+                    list.extend(p.doc.to_le_bytes());
+                    list.extend(p.tf.to_le_bytes());
+                }
+            }
+            txn.delete_table(bm25::BM25_BLOCKS).unwrap();
+            {
+                let mut scoring = txn.open_table(migrate::BM25_SCORING_V1).unwrap();
+                let mut bitmaps = txn.open_table(migrate::BM25_POSTINGS_V1).unwrap();
+                for (key, list) in &lists {
+                    scoring.insert(*key, list.as_slice()).unwrap();
+                    bitmaps.insert(*key, [0u8; 8].as_slice()).unwrap();
+                }
+                txn.open_table(migrate::SCHEMA)
+                    .unwrap()
+                    .insert("schema_version", 3)
+                    .unwrap();
+            }
+            txn.commit().unwrap();
+        }
+
+        let report = EmbeddedBackend::migrate(&path, false).unwrap();
+        assert_eq!((report.from_version, report.to_version), (3, 4));
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].rows, 4, "red, fox, dog + tenant 2's red");
+        let db = fixture(&path);
+        let after = db.bm25(1, &["red", "dog"], 10, None).await.unwrap();
+        let scores = |hits: &[Hit]| -> Vec<(u64, f32)> {
+            hits.iter().map(|h| (h.record_id, h.score)).collect()
+        };
+        assert_eq!(scores(&after), scores(&before));
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        let txn = db.db.begin_read().unwrap();
+        assert!(txn.open_table(migrate::BM25_SCORING_V1).is_err());
+        assert!(txn.open_table(migrate::BM25_POSTINGS_V1).is_err());
+    }
+
+    #[test]
+    fn open_refuses_databases_from_newer_builds() {
+        let dir = tempfile::tempdir().unwrap();
+        let newer_schema = dir.path().join("schema.redb");
+        drop(fixture(&newer_schema));
+        {
+            let raw = Database::open(&newer_schema).unwrap();
+            let txn = raw.begin_write().unwrap();
+            txn.open_table(migrate::SCHEMA)
+                .unwrap()
+                .insert("schema_version", migrate::SCHEMA_VERSION + 1)
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        assert!(matches!(
+            EmbeddedBackend::open(&newer_schema),
+            Err(Error::Incompatible(_))
+        ));
+
+        let newer_table = dir.path().join("table.redb");
+        {
+            let raw = Database::create(&newer_table).unwrap();
+            let txn = raw.begin_write().unwrap();
+            let future: TableDefinition<'_, (u32, u64), &[u8]> =
+                TableDefinition::new("ucfp/catalog/v3");
+            drop(txn.open_table(future).unwrap());
+            txn.commit().unwrap();
+        }
+        assert!(matches!(
+            EmbeddedBackend::migrate(&newer_table, true),
+            Err(Error::Incompatible(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn delete_where_prunes_dead_terms() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        let mut a = rec(1, 1, vec![1.0]);
+        a.text = Some("common unique".into());
+        let mut b = rec(1, 2, vec![1.0]);
+        b.text = Some("common".into());
+        b.algorithm = "gone".into();
+        db.upsert(&[a, b]).await.unwrap();
+        assert_eq!(term_rows(&db, bm25::BM25_BLOCKS, 1), 2);
+
+        let filter = RecordFilter {
+            algorithm: Some("test".into()),
+            ..RecordFilter::default()
+        };
+        db.delete_where(1, &filter, &Progress::default())
+            .await
+            .unwrap();
+        // "unique" only lived in record 1 — its rows and dict entry go.
+        assert_eq!(term_rows(&db, bm25::BM25_BLOCKS, 1), 1);
+        let hits = db.bm25(1, &["common"], 10, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].record_id, 2);
+    }
+
+    #[tokio::test]
+    async fn reindex_bm25_rebuilds_from_retained_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb")).with_retained_text();
+        db.upsert(&[
+            text_rec(1, 1, "red fox"),
+            text_rec(1, 2, "red dog"),
+            text_rec(1, 3, "gone soon"),
+            text_rec(2, 1, "other tenant"),
+        ])
+        .await
+        .unwrap();
+        db.upsert(&[text_rec(1, 2, "blue dog dog")]).await.unwrap();
+        db.delete(1, &[3]).await.unwrap();
+        assert_eq!(tenant_rows(&db, TEXT, 1), 2);
+        {
+            // Drift the index: lose a doc_terms row and the corpus totals.
+            let txn = db.db.begin_write().unwrap();
+            txn.open_table(bm25::BM25_DOC_TERMS)
+                .unwrap()
+                .remove((1, 1))
+                .unwrap();
+            txn.open_table(bm25::BM25_CORPUS)
+                .unwrap()
+                .insert(1, bm25::CorpusStats::default().pack().as_slice())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        assert!(!db.fsck(Some(1), false).await.unwrap().violations.is_empty());
+
+        let progress = Progress::default();
+        assert_eq!(db.reindex_bm25(1, &progress).await.unwrap(), 2);
+        assert_eq!(progress.records(), 2);
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        let hits = db.bm25(1, &["dog"], 10, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].record_id, 2);
+        assert!(db.bm25(1, &["gone"], 10, None).await.unwrap().is_empty());
+        let got = db.get_records(1, &[2]).await.unwrap();
+        assert_eq!(got[0].text.as_deref(), Some("blue dog dog"));
+        assert_eq!(db.bm25(2, &["tenant"], 10, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reindex_bm25_refuses_unretained_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        db.upsert(&[text_rec(1, 1, "red fox")]).await.unwrap();
+        assert!(matches!(
+            db.reindex_bm25(1, &Progress::default()).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert_eq!(db.bm25(1, &["fox"], 10, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn highlight_marks_analyzed_matches_and_needs_retained_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = fixture(&dir.path().join("plain.redb"));
+        plain.upsert(&[text_rec(1, 1, "red fox")]).await.unwrap();
+        let mut hits = plain.bm25_explain(1, &["fox"], 10, None).await.unwrap();
+        assert!(matches!(
+            plain.highlight(1, &mut hits, &Highlight::default()).await,
+            Err(Error::Incompatible(_))
+        ));
+
+        let db = fixture(&dir.path().join("ucfp.redb"))
+            .with_retained_text()
+            .with_analyzers([(1, Analyzer::parse("stem-english").unwrap())]);
+        let mut titled = text_rec(1, 1, "Signed contracts, and the Contractor's notes");
+        titled
+            .text_fields
+            .insert("title".into(), "Contract law".into());
+        db.upsert(&[titled]).await.unwrap();
+        let mut hits = db.bm25_explain(1, &["contract"], 10, None).await.unwrap();
+        db.highlight(1, &mut hits, &Highlight::default())
+            .await
+            .unwrap();
+        let fragments: Vec<(&str, &str)> = hits[0]
+            .snippets
+            .iter()
+            .map(|s| (s.field.as_str(), s.fragment.as_str()))
+            .collect();
+        assert_eq!(
+            fragments,
+            [
+                (
+                    "text",
+                    "Signed <mark>contracts</mark>, and the Contractor&#39;s notes"
+                ),
+                ("title", "<mark>Contract</mark> law"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn text_fields_are_scored_retained_and_reindexed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"))
+            .with_retained_text()
+            .with_bm25_fields([
+                (
+                    1,
+                    "title".to_string(),
+                    Bm25Field {
+                        boost: 3.0,
+                        b: Some(0.5),
+                    },
+                ),
+                (
+                    2,
+                    "title".to_string(),
+                    Bm25Field {
+                        boost: 0.0,
+                        b: None,
+                    },
+                ),
+            ]);
+        let mut titled = text_rec(1, 1, "a note on ownership");
+        titled.text_fields.insert("title".into(), "Rust".into());
+        let mut untitled = text_rec(1, 2, "rust mentioned in passing among many words");
+        untitled.text_fields.insert("tags".into(), "misc".into());
+        db.upsert(&[titled, untitled, text_rec(1, 3, "unrelated")])
+            .await
+            .unwrap();
+
+        let ids = |hits: Vec<Hit>| hits.iter().map(|h| h.record_id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(db.bm25(1, &["rust"], 10, None).await.unwrap()),
+            vec![1, 2]
+        );
+        assert_eq!(
+            ids(db.bm25(1, &["title:rust"], 10, None).await.unwrap()),
+            vec![1]
+        );
+        assert_eq!(
+            ids(db.bm25(1, &["tags:misc"], 10, None).await.unwrap()),
+            vec![2]
+        );
+        let got = db.get_records(1, &[1]).await.unwrap();
+        assert_eq!(got[0].text_fields["title"], "Rust");
+
+        // Weights are per tenant: tenant 2 ignores title matches.
+        let mut titled = text_rec(2, 1, "a note on ownership");
+        titled.text_fields.insert("title".into(), "Rust".into());
+        db.upsert(&[
+            titled,
+            text_rec(2, 2, "rust mentioned in passing among many words"),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(ids(db.bm25(2, &["rust"], 10, None).await.unwrap())[0], 2);
+
+        // Dropping the fields on update drops their terms.
+        db.upsert(&[text_rec(1, 2, "rust mentioned in passing among many words")])
+            .await
+            .unwrap();
+        assert!(db.bm25(1, &["misc"], 10, None).await.unwrap().is_empty());
+        assert_eq!(tenant_rows(&db, TEXT_FIELDS, 1), 1);
+        assert_eq!(tenant_rows(&db, bm25::BM25_FIELD_LENS, 1), 1);
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+
+        let before = db.bm25(1, &["rust"], 10, None).await.unwrap();
+        db.reindex_bm25(1, &Progress::default()).await.unwrap();
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        let after = db.bm25(1, &["rust"], 10, None).await.unwrap();
+        assert_eq!(ids(before.clone()), ids(after.clone()));
+        for (a, b) in before.iter().zip(&after) {
+            assert!((a.score - b.score).abs() < 1e-5);
+        }
+
+        {
+            let txn = db.db.begin_write().unwrap();
+            txn.open_table(bm25::BM25_FIELDS)
+                .unwrap()
+                .remove(1)
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        let report = db.fsck(Some(1), true).await.unwrap();
+        assert!(report.violations.iter().any(|v| v.check == "bm25.fields"));
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+
+        let mut bad = text_rec(1, 4, "x");
+        bad.text_fields.insert("Title".into(), "y".into());
+        assert!(matches!(db.upsert(&[bad]).await, Err(Error::Modality(_))));
+    }
+
+    #[tokio::test]
+    async fn bm25_params_apply_per_tenant_and_per_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let flat = Bm25Params {
+            b: 0.0,
+            ..Bm25Params::default()
+        };
+        let db = fixture(&dir.path().join("ucfp.redb")).with_bm25_params([(2, flat)]);
+        let long = format!("rust rust {}", "filler ".repeat(50));
+        for tenant in [1, 2] {
+            db.upsert(&[
+                text_rec(tenant, 1, "rust"),
+                text_rec(tenant, 2, &long),
+                text_rec(tenant, 3, "other"),
+            ])
+            .await
+            .unwrap();
+        }
+        let first = |hits: Vec<Hit>| hits[0].record_id;
+        assert_eq!(first(db.bm25(1, &["rust"], 10, None).await.unwrap()), 1);
+        assert_eq!(first(db.bm25(2, &["rust"], 10, None).await.unwrap()), 2);
+
+        // A per-query override beats the tenant's configuration.
+        let hits = db
+            .bm25_with(1, &["rust"], 10, None, true, &flat)
+            .await
+            .unwrap();
+        assert_eq!(first(hits.clone()), 2);
+        assert!(!hits[0].term_hits.is_empty());
+        let tenant_default = db
+            .bm25_with(2, &["rust"], 10, None, false, &Bm25Params::default())
+            .await
+            .unwrap();
+        assert_eq!(first(tenant_default), 1);
+
+        let bad = Bm25Params {
+            b: 1.5,
+            ..Bm25Params::default()
+        };
+        assert!(matches!(
+            db.bm25_with(1, &["rust"], 10, None, false, &bad).await,
+            Err(Error::Modality(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn analyzer_change_is_flagged_until_reindex() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ucfp.redb");
+        let db = fixture(&path).with_retained_text();
+        db.upsert(&[
+            text_rec(1, 1, "The contracts were signed"),
+            text_rec(2, 1, "other tenant"),
+        ])
+        .await
+        .unwrap();
+        assert!(db.analyzer_mismatches().unwrap().is_empty());
+        drop(db);
+
+        let stemmed = Analyzer::parse("stop-en+stem-english").unwrap();
+        let db = fixture(&path)
+            .with_retained_text()
+            .with_analyzers([(1, stemmed.clone())]);
+        assert_eq!(
+            db.analyzer_mismatches().unwrap(),
+            vec![AnalyzerMismatch {
+                tenant_id: 1,
+                indexed: "lower+alnum".into(),
+                configured: stemmed.identity(),
+            }]
+        );
+        // Queries are stemmed already; the index is not yet.
+        assert!(
+            db.bm25(1, &["contract"], 10, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        db.reindex_bm25(1, &Progress::default()).await.unwrap();
+        assert!(db.analyzer_mismatches().unwrap().is_empty());
+        for query in ["contract", "contracts", "sign"] {
+            assert_eq!(db.bm25(1, &[query], 10, None).await.unwrap().len(), 1);
+        }
+        assert!(db.bm25(1, &["the"], 10, None).await.unwrap().is_empty());
+        // New records of the tenant go through the same analyzer.
+        db.upsert(&[text_rec(1, 2, "Signing the contract")])
+            .await
+            .unwrap();
+        assert_eq!(db.bm25(1, &["signed"], 10, None).await.unwrap().len(), 2);
+        assert_eq!(db.bm25(2, &["tenant"], 10, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fsck_reports_then_repairs_derived_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        db.upsert(&[
+            text_rec(1, 1, "common unique"),
+            text_rec(1, 2, "common"),
+            text_rec(2, 1, "other tenant"),
+        ])
+        .await
+        .unwrap();
+        {
+            let txn = db.db.begin_write().unwrap();
+            {
+                let mut catalog = txn.open_table(CATALOG).unwrap();
+                let mut row =
+                    CatalogEntry::decode(catalog.get((1, 1)).unwrap().unwrap().value()).unwrap();
+                row.fingerprint_len = 99;
+                catalog
+                    .insert((1, 1), row.encode().unwrap().as_slice())
+                    .unwrap();
+                txn.open_table(VECTORS)
+                    .unwrap()
+                    .insert((1, 50), [0u8; 8].as_slice())
+                    .unwrap();
+                let mut lens = txn.open_table(bm25::BM25_DOC_LENS).unwrap();
+                lens.insert((1, 2), 42).unwrap();
+                lens.insert((1, 77), 3).unwrap();
+            }
+            txn.commit().unwrap();
+        }
+
+        let report = db.fsck(None, false).await.unwrap();
+        assert_eq!((report.tenants, report.records), (2, 3));
+        let found: Vec<(&str, Option<u64>)> = report
+            .violations
+            .iter()
+            .map(|v| (v.check.as_str(), v.record_id))
+            .collect();
+        for want in [
+            ("catalog.fingerprint_len", Some(1)),
+            ("orphan.vector", Some(50)),
+            ("bm25.doc_lens", Some(2)),
+            ("bm25.orphan_doc", Some(77)),
+            ("bm25.corpus", None),
+        ] {
+            assert!(found.contains(&want), "missing {want:?} in {found:?}");
+        }
+        assert!(report.violations.iter().all(|v| v.tenant_id == 1));
+        assert_eq!(report.unrepaired(), report.violations.len());
+
+        let fixed = db.fsck(Some(1), true).await.unwrap();
+        assert_eq!(fixed.violations.len(), report.violations.len());
+        assert_eq!(fixed.unrepaired(), 0);
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        assert_eq!(tenant_rows(&db, VECTORS, 1), 0);
+        assert_eq!(
+            db.get_record_metadata(1, 1)
+                .await
+                .unwrap()
+                .fingerprint_bytes,
+            2
+        );
+        let hits = db.bm25(1, &["common"], 10, None).await.unwrap();
+        let mut got: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+        got.sort_unstable();
+        assert_eq!(got, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn fsck_drops_orphan_text_and_history_and_reindexes_retained_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"))
+            .with_retained_text()
+            .with_positions([1])
+            .with_versioning(VersionPolicy::default());
+        db.upsert(&[text_rec(1, 1, "red fox"), text_rec(1, 2, "old dog")])
+            .await
+            .unwrap();
+        db.delete(1, &[2]).await.unwrap();
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        {
+            let txn = db.db.begin_write().unwrap();
+            {
+                let snapshot = {
+                    let catalog = txn.open_table(CATALOG).unwrap();
+                    let row = catalog.get((1, 1)).unwrap().unwrap();
+                    versions::encode(row.value(), b"fp", b"", b"")
+                };
+                txn.open_table(versions::VERSIONS)
+                    .unwrap()
+                    .insert((1, 62, 1), snapshot.as_slice())
+                    .unwrap();
+                txn.open_table(TEXT)
+                    .unwrap()
+                    .insert((1, 60), "stray")
+                    .unwrap();
+                txn.open_table(TEXT_FIELDS)
+                    .unwrap()
+                    .insert((1, 61), b"{}".as_slice())
+                    .unwrap();
+                // One position too many for its posting.
+                let mut positions_t = txn.open_table(bm25::BM25_POSITIONS).unwrap();
+                let key = positions_t
+                    .range((1, 0, 1)..=(1, u64::MAX, 1))
+                    .unwrap()
+                    .find(|e| e.as_ref().unwrap().0.value().2 == 1)
+                    .unwrap()
+                    .unwrap()
+                    .0
+                    .value();
+                positions_t
+                    .insert(key, positions::encode(&[0, 5]).as_slice())
+                    .unwrap();
+            }
+            txn.commit().unwrap();
+        }
+
+        let report = db.fsck(Some(1), true).await.unwrap();
+        let mut found: Vec<(&str, Option<u64>)> = report
+            .violations
+            .iter()
+            .map(|v| (v.check.as_str(), v.record_id))
+            .collect();
+        found.sort_unstable();
+        assert_eq!(
+            found,
+            vec![
+                ("bm25.positions", Some(1)),
+                ("orphan.text", Some(60)),
+                ("orphan.text_fields", Some(61)),
+                ("orphan.versions", Some(62)),
+            ]
+        );
+        assert_eq!(report.unrepaired(), 0);
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        assert_eq!(tenant_rows(&db, TEXT, 1), 1);
+        assert_eq!(tenant_rows(&db, TEXT_FIELDS, 1), 0);
+        assert_eq!(db.list_versions(1, 2).await.unwrap().len(), 2);
+        // Repair from the blocks alone drops the positions; the retained
+        // text brings them back.
+        let phrase = db.bm25(1, &["\"red fox\""], 10, None).await.unwrap();
+        assert_eq!(phrase.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn tenant_stats_follow_every_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        db.upsert(&[
+            rec(1, 1, vec![0.0; 3]),
+            rec(1, 2, vec![0.0; 3]),
+            text_rec(1, 3, "red fox"),
+            text_rec(2, 1, "other tenant"),
+        ])
+        .await
+        .unwrap();
+        let mut replaced = rec(1, 2, vec![]);
+        replaced.embedding = None;
+        replaced.model_id = None;
+        db.upsert(&[replaced]).await.unwrap();
+        db.delete(1, &[3]).await.unwrap();
+
+        let all = db.tenant_stats(None).await.unwrap();
+        assert_eq!(all.iter().map(|s| s.tenant_id).collect::<Vec<_>>(), [1, 2]);
+        let s = &all[0];
+        assert_eq!(s.records, 2);
+        assert_eq!(s.by_modality, BTreeMap::from([("image".into(), 2)]));
+        assert_eq!(s.by_algorithm, BTreeMap::from([("test".into(), 2)]));
+        assert_eq!(s.by_model, BTreeMap::from([("test-model".into(), 1)]));
+        assert_eq!(s.fingerprint_bytes, 4);
+        assert_eq!(s.vector_bytes, 12);
+        assert_eq!((s.ann.kind.as_str(), s.ann.vectors), ("brute_force", 1));
+        assert_eq!(s.bm25.doc_count, 0);
+        let t2 = &all[1];
+        assert_eq!(
+            (t2.records, t2.bm25.doc_count, t2.bm25.vocabulary),
+            (1, 1, 2)
+        );
+        assert_eq!(t2.bm25.avgdl, 2.0);
+        let empty = db.tenant_stats(Some(9)).await.unwrap();
+        assert_eq!(empty[0].records, 0);
+
+        // Drifted counters are an fsck finding and repair recounts them.
+        {
+            let txn = db.db.begin_write().unwrap();
+            txn.open_table(stats::STATS)
+                .unwrap()
+                .insert((1, "records"), 7)
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        let report = db.fsck(Some(1), true).await.unwrap();
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].check, "stats.counters");
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+        assert_eq!(db.tenant_stats(Some(1)).await.unwrap()[0].records, 2);
+
+        db.purge_tenant(1, &Progress::default()).await.unwrap();
+        assert_eq!(
+            db.tenant_stats(None)
+                .await
+                .unwrap()
+                .iter()
+                .map(|s| s.tenant_id)
+                .collect::<Vec<_>>(),
+            [2]
+        );
+    }
+
     #[tokio::test]
     async fn upsert_and_knn_round_trip() {
         let dir = tempfile::tempdir().unwrap();
@@ -601,6 +2915,8 @@ mod tests {
             model_id: None,
             metadata: Bytes::new(),
             text: Some(text.to_string()),
+            text_fields: BTreeMap::new(),
+            expires_at: None,
         }
     }
 
@@ -621,6 +2937,69 @@ mod tests {
         assert_eq!(hits[0].source, HitSource::Bm25);
     }
 
+    #[tokio::test]
+    async fn phrase_queries_follow_writes_and_reindex() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"))
+            .with_retained_text()
+            .with_positions([1]);
+        db.upsert(&[
+            text_rec(1, 100, "the force majeure clause"),
+            text_rec(1, 101, "force of nature majeure"),
+            text_rec(2, 100, "force majeure"),
+        ])
+        .await
+        .unwrap();
+        let ids = |hits: Vec<Hit>| {
+            let mut ids: Vec<u64> = hits.iter().map(|h| h.record_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        let phrase = db.bm25(1, &["\"force majeure\""], 10, None).await.unwrap();
+        assert_eq!(ids(phrase), vec![100]);
+        let near = db.bm25(1, &["force NEAR/3 majeure"], 10, None).await;
+        assert_eq!(ids(near.unwrap()), vec![100, 101]);
+        assert!(matches!(
+            db.bm25(2, &["\"force majeure\""], 10, None).await,
+            Err(Error::Modality(_))
+        ));
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 1), 8);
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 2), 0);
+
+        db.upsert(&[text_rec(1, 100, "majeure force")])
+            .await
+            .unwrap();
+        db.delete(1, &[101]).await.unwrap();
+        let phrase = db.bm25(1, &["\"force majeure\""], 10, None).await.unwrap();
+        assert!(phrase.is_empty());
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 1), 2);
+
+        db.reindex_bm25(1, &Progress::default()).await.unwrap();
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 1), 2);
+        let phrase = db.bm25(1, &["\"majeure force\""], 10, None).await.unwrap();
+        assert_eq!(ids(phrase), vec![100]);
+        assert!(db.fsck(None, false).await.unwrap().violations.is_empty());
+
+        // A row no posting backs is reported, and dropped on repair.
+        {
+            let txn = db.db.begin_write().unwrap();
+            txn.open_table(bm25::BM25_POSITIONS)
+                .unwrap()
+                .insert((1, 0, 555), positions::encode(&[3]).as_slice())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        let report = db.fsck(Some(1), true).await.unwrap();
+        let found: Vec<(&str, Option<u64>)> = report
+            .violations
+            .iter()
+            .map(|v| (v.check.as_str(), v.record_id))
+            .collect();
+        assert_eq!(found, vec![("bm25.positions", Some(555))]);
+        assert_eq!(term_rows(&db, bm25::BM25_POSITIONS, 1), 2);
+    }
+
     #[tokio::test]
     async fn bm25_filter_param_is_unsupported() {
         let dir = tempfile::tempdir().unwrap();
@@ -630,4 +3009,25 @@ mod tests {
             .await;
         assert!(matches!(result, Err(Error::Unsupported(_))));
     }
+
+    #[tokio::test]
+    async fn knn_filter_param_is_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = fixture(&dir.path().join("ucfp.redb"));
+        let result = db
+            .knn(1, &[1.0], 10, Some(&Bytes::from_static(b"\x00")))
+            .await;
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn passes_conformance_suite() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut n = 0;
+        crate::index::conformance::run_all(|| {
+            n += 1;
+            fixture(&dir.path().join(format!("conformance-{n}.redb"))).with_retained_text()
+        })
+        .await;
+    }
 }
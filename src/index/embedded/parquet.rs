@@ -0,0 +1,687 @@
+//! Columnar export of the catalog for offline analytics (feature
+//! `parquet`).
+//!
+//! ARCHITECTURE §7 answers "SQL over metadata" with a read-only DuckDB
+//! view over Parquet files derived from redb. This module writes them:
+//!
+//! ```text
+//! <out>/catalog/tenant_id=7/modality=image/part-<seq>.parquet
+//! <out>/deletes/part-<seq>.parquet
+//! <out>/usage/tenant_id=7/part-<offset>.parquet
+//! <out>/_watermark.json
+//! ```
+//!
+//! Directories are Hive-style partitions, so `tenant_id` and `modality`
+//! come from the path rather than the files. Catalog rows are exported
+//! from one read transaction and stamped with the change-log sequence
+//! that transaction saw (`exported_seq`). The first run, and any run
+//! after the change log was pruned past the watermark, rewrites the
+//! whole catalog; later runs read the change log from the watermark and
+//! add one part per touched partition plus the deletes since. A record
+//! is live when its newest row has no delete after its `exported_seq`
+//! (a purge deletes with a null `record_id`).
+//!
+//! Usage rollups come from the NDJSON `LogUsageSink` file, read from the
+//! byte offset the previous run stopped at, summed per tenant, UTC day,
+//! op, modality and algorithm. Rollup rows are additive across parts.
+//!
+//! Every file is written as `*.parquet.partial` and renamed when
+//! complete, and the watermark moves last, so a crashed run is redone
+//! by the next one (same file names, same contents).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufRead, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use arrow_array::{
+    ArrayRef, Date32Array, RecordBatch, StringArray, TimestampMillisecondArray, UInt32Array,
+    UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use redb::{Database, ReadOnlyTable, ReadTransaction, ReadableDatabase, ReadableTable};
+use serde::{Deserialize, Serialize};
+
+use super::{CATALOG, CatalogEntry, METADATA, changes};
+use crate::core::{ChangeKind, Modality, ParquetReport};
+use crate::error::{Error, Result};
+use crate::index::DELETE_BATCH;
+
+/// Rows buffered per partition before a row group is flushed.
+const BATCH_ROWS: usize = 65_536;
+
+const WATERMARK_FILE: &str = "_watermark.json";
+
+/// Progress persisted between runs in `<out>/_watermark.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Watermark {
+    /// Change-log sequence the exported catalog reflects.
+    change_seq: u64,
+    /// Byte offset into the usage log already rolled up.
+    usage_offset: u64,
+}
+
+fn parquet_err(e: impl std::fmt::Display) -> Error {
+    Error::Index(format!("parquet: {e}"))
+}
+
+/// Export into `out`. `usage_log` is the `UCFP_USAGE_LOG_PATH` file to
+/// roll up, if any; `full` forces a catalog rewrite.
+pub(super) fn export(
+    db: &Database,
+    out: &Path,
+    usage_log: Option<&Path>,
+    full: bool,
+) -> Result<ParquetReport> {
+    std::fs::create_dir_all(out)?;
+    let previous = read_watermark(out)?;
+    let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let head = changes::head(&txn)?;
+
+    // Incremental only when the log still holds every event since the
+    // watermark; otherwise (first run, pruned log, restored database)
+    // start over.
+    let pending = match &previous {
+        Some(w) if !full && w.change_seq <= head => pending_since(&txn, w.change_seq, head)?,
+        _ => None,
+    };
+
+    let mut report = ParquetReport {
+        out_dir: out.to_path_buf(),
+        full: pending.is_none(),
+        watermark: head,
+        ..ParquetReport::default()
+    };
+    let metadata = txn
+        .open_table(METADATA)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let part = format!("part-{head:020}.parquet");
+    let mut catalog = Partitions::new(out.join("catalog"), &part, head);
+    let mut deletes = DeleteRows::default();
+    match pending {
+        None => {
+            for dir in ["catalog", "deletes"] {
+                match std::fs::remove_dir_all(out.join(dir)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            let table = txn
+                .open_table(CATALOG)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            for entry in table.iter().map_err(|e| Error::Index(e.to_string()))? {
+                let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+                let (tenant_id, record_id) = k.value();
+                let row = CatalogEntry::decode(v.value())?;
+                catalog.push(&metadata, tenant_id, record_id, row)?;
+            }
+        }
+        Some(Pending {
+            touched,
+            deletes: logged,
+        }) => {
+            deletes = logged;
+            let table = txn
+                .open_table(CATALOG)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            for (tenant_id, record_id) in touched {
+                // Gone again by `head`: the delete is already recorded.
+                let Some(v) = table
+                    .get((tenant_id, record_id))
+                    .map_err(|e| Error::Index(e.to_string()))?
+                else {
+                    continue;
+                };
+                let row = CatalogEntry::decode(v.value())?;
+                catalog.push(&metadata, tenant_id, record_id, row)?;
+            }
+        }
+    }
+    catalog.finish()?;
+    report.catalog_rows = catalog.rows;
+    report.files += catalog.files;
+
+    // After a rewrite there are no deletes to report, but an empty file
+    // keeps `read_parquet('deletes/*.parquet')` valid for the view.
+    if report.full || !deletes.is_empty() {
+        report.deletes = deletes.len() as u64;
+        let dir = out.join("deletes");
+        std::fs::create_dir_all(&dir)?;
+        write_file(
+            &dir.join(&part),
+            deletes_schema(),
+            std::iter::once(deletes.into_batch()?),
+        )?;
+        report.files += 1;
+    }
+
+    let mut usage_offset = previous.map_or(0, |w| w.usage_offset);
+    if let Some(log) = usage_log {
+        let rollup = usage::rollup(log, usage_offset)?;
+        usage_offset = rollup.end_offset;
+        report.usage_skipped = rollup.skipped;
+        report.usage_rows = rollup.rows.len() as u64;
+        report.files += usage::write(&out.join("usage"), rollup)?;
+    }
+
+    write_watermark(
+        out,
+        &Watermark {
+            change_seq: head,
+            usage_offset,
+        },
+    )?;
+    Ok(report)
+}
+
+/// What an incremental run has to export.
+struct Pending {
+    /// `(tenant, record)` upserted since the watermark.
+    touched: BTreeSet<(u32, u64)>,
+    deletes: DeleteRows,
+}
+
+/// The records upserted and the deletes logged after `seq`, read from
+/// the change log a page at a time; `None` when the log no longer
+/// reaches back to `seq`.
+fn pending_since(txn: &ReadTransaction, seq: u64, head: u64) -> Result<Option<Pending>> {
+    let mut touched = BTreeSet::new();
+    let mut deletes = DeleteRows::default();
+    let mut cursor = seq;
+    loop {
+        let page = match changes::since(txn, cursor, DELETE_BATCH) {
+            Err(Error::ChangesPruned { .. }) => return Ok(None),
+            page => page?,
+        };
+        // `since(0, ..)` reads whatever is retained, so check the start.
+        if cursor == seq && head != seq && page.first().map(|ev| ev.seq) != seq.checked_add(1) {
+            return Ok(None);
+        }
+        let full_page = page.len() == DELETE_BATCH;
+        for ev in page {
+            cursor = ev.seq;
+            match ev.kind {
+                ChangeKind::Upsert => {
+                    if let Some(rid) = ev.record_id {
+                        touched.insert((ev.tenant_id, rid));
+                    }
+                }
+                ChangeKind::Delete | ChangeKind::Expire | ChangeKind::Purge => deletes.push(&ev),
+            }
+        }
+        if !full_page {
+            return Ok(Some(Pending { touched, deletes }));
+        }
+    }
+}
+
+fn read_watermark(out: &Path) -> Result<Option<Watermark>> {
+    match std::fs::read(out.join(WATERMARK_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| Error::Index(format!("{WATERMARK_FILE}: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_watermark(out: &Path, w: &Watermark) -> Result<()> {
+    let tmp = out.join(format!("{WATERMARK_FILE}.partial"));
+    let bytes = serde_json::to_vec(w).map_err(|e| Error::Index(format!("watermark: {e}")))?;
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, out.join(WATERMARK_FILE))?;
+    Ok(())
+}
+
+/// Write `batches` to `path` via a `.partial` file renamed on success.
+fn write_file(
+    path: &Path,
+    schema: SchemaRef,
+    batches: impl IntoIterator<Item = RecordBatch>,
+) -> Result<()> {
+    let mut writer = FileWriter::create(path, schema)?;
+    for batch in batches {
+        writer.write(&batch)?;
+    }
+    writer.close()
+}
+
+/// A Parquet file being written under its `.partial` name.
+struct FileWriter {
+    writer: ArrowWriter<File>,
+    tmp: PathBuf,
+    path: PathBuf,
+}
+
+impl FileWriter {
+    fn create(path: &Path, schema: SchemaRef) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".partial");
+        let tmp = PathBuf::from(tmp);
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer =
+            ArrowWriter::try_new(File::create(&tmp)?, schema, Some(props)).map_err(parquet_err)?;
+        Ok(Self {
+            writer,
+            tmp,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.writer.write(batch).map_err(parquet_err)
+    }
+
+    fn close(self) -> Result<()> {
+        self.writer.close().map_err(parquet_err)?;
+        std::fs::rename(&self.tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn modality_dir(m: Modality) -> &'static str {
+    match m {
+        Modality::Audio => "audio",
+        Modality::Image => "image",
+        Modality::Text => "text",
+    }
+}
+
+fn ts_field(name: &str) -> Field {
+    Field::new(
+        name,
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        true,
+    )
+}
+
+fn ts_array(values: Vec<Option<u64>>) -> ArrayRef {
+    let values: Vec<Option<i64>> = values
+        .into_iter()
+        .map(|v| v.map(|ms| ms.min(i64::MAX as u64) as i64))
+        .collect();
+    Arc::new(TimestampMillisecondArray::from(values).with_timezone("UTC"))
+}
+
+// ── Catalog ────────────────────────────────────────────────────────────
+
+fn catalog_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("record_id", DataType::UInt64, false),
+        Field::new("algorithm", DataType::Utf8, false),
+        Field::new("format_version", DataType::UInt32, false),
+        Field::new("config_hash", DataType::UInt64, false),
+        Field::new("fingerprint_bytes", DataType::UInt32, false),
+        Field::new("embedding_dim", DataType::UInt32, true),
+        Field::new("model_id", DataType::Utf8, true),
+        Field::new("metadata_bytes", DataType::UInt32, false),
+        // The application metadata when it is a JSON object, for
+        // `metadata->>'source'` style queries; null otherwise.
+        Field::new("metadata", DataType::Utf8, true),
+        ts_field("expires_at"),
+        Field::new("version", DataType::UInt64, false),
+        ts_field("written_at"),
+        Field::new("exported_seq", DataType::UInt64, false),
+    ]))
+}
+
+#[derive(Default)]
+struct CatalogRows {
+    record_id: Vec<u64>,
+    algorithm: Vec<String>,
+    format_version: Vec<u32>,
+    config_hash: Vec<u64>,
+    fingerprint_bytes: Vec<u32>,
+    embedding_dim: Vec<Option<u32>>,
+    model_id: Vec<Option<String>>,
+    metadata_bytes: Vec<u32>,
+    metadata: Vec<Option<String>>,
+    expires_at: Vec<Option<u64>>,
+    version: Vec<u64>,
+    written_at: Vec<Option<u64>>,
+}
+
+impl CatalogRows {
+    fn push(&mut self, record_id: u64, row: CatalogEntry, metadata: Option<String>) {
+        self.record_id.push(record_id);
+        self.format_version.push(row.format_version);
+        self.config_hash.push(row.config_hash);
+        self.fingerprint_bytes.push(row.fingerprint_len);
+        self.embedding_dim
+            .push((row.embedding_dim != 0).then_some(row.embedding_dim));
+        self.metadata_bytes.push(row.metadata_len);
+        self.metadata.push(metadata);
+        self.expires_at.push(row.expires_at);
+        self.version.push(row.version.max(1));
+        self.written_at.push(row.written_at);
+        self.algorithm.push(row.algorithm);
+        self.model_id.push(row.model_id);
+    }
+
+    fn len(&self) -> usize {
+        self.record_id.len()
+    }
+
+    fn take_batch(&mut self, exported_seq: u64) -> Result<RecordBatch> {
+        let rows = std::mem::take(self);
+        let n = rows.len();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(rows.record_id)),
+            Arc::new(StringArray::from(rows.algorithm)),
+            Arc::new(UInt32Array::from(rows.format_version)),
+            Arc::new(UInt64Array::from(rows.config_hash)),
+            Arc::new(UInt32Array::from(rows.fingerprint_bytes)),
+            Arc::new(UInt32Array::from(rows.embedding_dim)),
+            Arc::new(StringArray::from(rows.model_id)),
+            Arc::new(UInt32Array::from(rows.metadata_bytes)),
+            Arc::new(StringArray::from(rows.metadata)),
+            ts_array(rows.expires_at),
+            Arc::new(UInt64Array::from(rows.version)),
+            ts_array(rows.written_at),
+            Arc::new(UInt64Array::from(vec![exported_seq; n])),
+        ];
+        RecordBatch::try_new(catalog_schema(), columns).map_err(parquet_err)
+    }
+}
+
+/// One open file per modality of the current tenant. Rows must arrive
+/// grouped by tenant, which `(tenant_id, record_id)` key order gives.
+struct Partitions {
+    root: PathBuf,
+    part: String,
+    exported_seq: u64,
+    tenant: Option<u32>,
+    open: BTreeMap<&'static str, (FileWriter, CatalogRows)>,
+    rows: u64,
+    files: usize,
+}
+
+impl Partitions {
+    fn new(root: PathBuf, part: &str, exported_seq: u64) -> Self {
+        Self {
+            root,
+            part: part.to_string(),
+            exported_seq,
+            tenant: None,
+            open: BTreeMap::new(),
+            rows: 0,
+            files: 0,
+        }
+    }
+
+    fn push(
+        &mut self,
+        metadata: &ReadOnlyTable<(u32, u64), &'static [u8]>,
+        tenant_id: u32,
+        record_id: u64,
+        row: CatalogEntry,
+    ) -> Result<()> {
+        if self.tenant != Some(tenant_id) {
+            self.finish()?;
+            self.tenant = Some(tenant_id);
+        }
+        let metadata = if row.metadata_len == 0 {
+            None
+        } else {
+            metadata
+                .get((tenant_id, record_id))
+                .map_err(|e| Error::Index(e.to_string()))?
+                .and_then(|v| json_object(v.value()))
+        };
+        let modality = modality_dir(row.modality()?);
+        let slot = match self.open.entry(modality) {
+            std::collections::btree_map::Entry::Occupied(slot) => slot.into_mut(),
+            std::collections::btree_map::Entry::Vacant(slot) => {
+                let path = self
+                    .root
+                    .join(format!("tenant_id={tenant_id}"))
+                    .join(format!("modality={modality}"))
+                    .join(&self.part);
+                let writer = FileWriter::create(&path, catalog_schema())?;
+                slot.insert((writer, CatalogRows::default()))
+            }
+        };
+        slot.1.push(record_id, row, metadata);
+        self.rows += 1;
+        if slot.1.len() >= BATCH_ROWS {
+            let batch = slot.1.take_batch(self.exported_seq)?;
+            slot.0.write(&batch)?;
+        }
+        Ok(())
+    }
+
+    /// Flush and close the current tenant's files.
+    fn finish(&mut self) -> Result<()> {
+        for (_, (mut writer, mut rows)) in std::mem::take(&mut self.open) {
+            if rows.len() > 0 {
+                writer.write(&rows.take_batch(self.exported_seq)?)?;
+            }
+            writer.close()?;
+            self.files += 1;
+        }
+        Ok(())
+    }
+}
+
+/// `bytes` as a string when they hold a JSON object.
+fn json_object(bytes: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()?
+        .is_object()
+        .then(|| text.to_string())
+}
+
+// ── Deletes ────────────────────────────────────────────────────────────
+
+fn deletes_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("seq", DataType::UInt64, false),
+        ts_field("at"),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("tenant_id", DataType::UInt32, false),
+        Field::new("record_id", DataType::UInt64, true),
+    ]))
+}
+
+#[derive(Default)]
+struct DeleteRows {
+    seq: Vec<u64>,
+    at: Vec<Option<u64>>,
+    kind: Vec<&'static str>,
+    tenant_id: Vec<u32>,
+    record_id: Vec<Option<u64>>,
+}
+
+impl DeleteRows {
+    fn push(&mut self, ev: &crate::core::ChangeEvent) {
+        self.seq.push(ev.seq);
+        self.at.push(Some(ev.at_ms));
+        self.kind.push(match ev.kind {
+            ChangeKind::Upsert => "upsert",
+            ChangeKind::Delete => "delete",
+            ChangeKind::Expire => "expire",
+            ChangeKind::Purge => "purge",
+        });
+        self.tenant_id.push(ev.tenant_id);
+        self.record_id.push(ev.record_id);
+    }
+
+    fn len(&self) -> usize {
+        self.seq.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.seq.is_empty()
+    }
+
+    fn into_batch(self) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(self.seq)),
+            ts_array(self.at),
+            Arc::new(StringArray::from(self.kind)),
+            Arc::new(UInt32Array::from(self.tenant_id)),
+            Arc::new(UInt64Array::from(self.record_id)),
+        ];
+        RecordBatch::try_new(deletes_schema(), columns).map_err(parquet_err)
+    }
+}
+
+// ── Usage rollups ──────────────────────────────────────────────────────
+
+mod usage {
+    use super::*;
+
+    const DAY_MS: u64 = 86_400_000;
+
+    /// The fields of a `UsageEvent` line the rollup needs.
+    #[derive(Deserialize)]
+    struct Line {
+        tenant_id: u32,
+        op: String,
+        #[serde(default)]
+        modality: Option<Modality>,
+        #[serde(default)]
+        algorithm: Option<String>,
+        #[serde(default)]
+        bytes_in: u64,
+        #[serde(default)]
+        units: u64,
+        #[serde(default)]
+        elapsed_ms: u64,
+        #[serde(default)]
+        status: u16,
+        ts: u64,
+    }
+
+    /// `(tenant, day, op, modality, algorithm)`.
+    type Key = (u32, u64, String, Option<&'static str>, Option<String>);
+
+    #[derive(Default)]
+    pub(super) struct Totals {
+        requests: u64,
+        errors: u64,
+        units: u64,
+        bytes_in: u64,
+        elapsed_ms: u64,
+    }
+
+    pub(super) struct Rollup {
+        pub rows: BTreeMap<Key, Totals>,
+        pub end_offset: u64,
+        pub skipped: u64,
+    }
+
+    /// Sum the complete lines of `log` after `offset`. A log shorter
+    /// than `offset` was rotated and is read from the start.
+    pub(super) fn rollup(log: &Path, offset: u64) -> Result<Rollup> {
+        let mut rollup = Rollup {
+            rows: BTreeMap::new(),
+            end_offset: offset,
+            skipped: 0,
+        };
+        let mut file = match File::open(log) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(rollup),
+            Err(e) => return Err(e.into()),
+        };
+        if file.metadata()?.len() < offset {
+            rollup.end_offset = 0;
+        }
+        file.seek(std::io::SeekFrom::Start(rollup.end_offset))?;
+        let mut reader = std::io::BufReader::new(file);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            // A line without its newline is still being appended.
+            if n == 0 || buf.last() != Some(&b'\n') {
+                break;
+            }
+            rollup.end_offset += n as u64;
+            let Ok(line) = serde_json::from_slice::<Line>(&buf) else {
+                rollup.skipped += 1;
+                continue;
+            };
+            let key = (
+                line.tenant_id,
+                line.ts / DAY_MS,
+                line.op,
+                line.modality.map(modality_dir),
+                line.algorithm,
+            );
+            let t = rollup.rows.entry(key).or_default();
+            t.requests += 1;
+            t.errors += u64::from(line.status >= 400);
+            t.units += line.units;
+            t.bytes_in += line.bytes_in;
+            t.elapsed_ms += line.elapsed_ms;
+        }
+        Ok(rollup)
+    }
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("day", DataType::Date32, false),
+            Field::new("op", DataType::Utf8, false),
+            Field::new("modality", DataType::Utf8, true),
+            Field::new("algorithm", DataType::Utf8, true),
+            Field::new("requests", DataType::UInt64, false),
+            Field::new("errors", DataType::UInt64, false),
+            Field::new("units", DataType::UInt64, false),
+            Field::new("bytes_in", DataType::UInt64, false),
+            Field::new("elapsed_ms", DataType::UInt64, false),
+        ]))
+    }
+
+    /// One file per tenant under `root`; returns how many were written.
+    pub(super) fn write(root: &Path, rollup: Rollup) -> Result<usize> {
+        let part = format!("part-{:020}.parquet", rollup.end_offset);
+        let mut by_tenant: BTreeMap<u32, Vec<(Key, Totals)>> = BTreeMap::new();
+        for (key, totals) in rollup.rows {
+            by_tenant.entry(key.0).or_default().push((key, totals));
+        }
+        let files = by_tenant.len();
+        for (tenant_id, rows) in by_tenant {
+            let mut day = Vec::with_capacity(rows.len());
+            let mut op = Vec::with_capacity(rows.len());
+            let mut modality = Vec::with_capacity(rows.len());
+            let mut algorithm = Vec::with_capacity(rows.len());
+            let mut totals = Vec::with_capacity(rows.len());
+            for ((_, d, o, m, a), t) in rows {
+                day.push(d.min(i32::MAX as u64) as i32);
+                op.push(o);
+                modality.push(m);
+                algorithm.push(a);
+                totals.push(t);
+            }
+            let sum = |f: fn(&Totals) -> u64| -> ArrayRef {
+                Arc::new(UInt64Array::from(totals.iter().map(f).collect::<Vec<_>>()))
+            };
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(Date32Array::from(day)),
+                Arc::new(StringArray::from(op)),
+                Arc::new(StringArray::from(modality)),
+                Arc::new(StringArray::from(algorithm)),
+                sum(|t| t.requests),
+                sum(|t| t.errors),
+                sum(|t| t.units),
+                sum(|t| t.bytes_in),
+                sum(|t| t.elapsed_ms),
+            ];
+            let batch = RecordBatch::try_new(schema(), columns).map_err(parquet_err)?;
+            let path = root.join(format!("tenant_id={tenant_id}")).join(&part);
+            write_file(&path, schema(), std::iter::once(batch))?;
+        }
+        Ok(files)
+    }
+}
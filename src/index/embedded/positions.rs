@@ -0,0 +1,216 @@
+//! Positional postings for phrase and `NEAR/n` queries.
+//!
+//! For tenants given to [`super::EmbeddedBackend::with_positions`] the
+//! BM25 write path also stores where each term occurs: one
+//! `ucfp/bm25/positions/v1` row per term and doc, keyed
+//! `(tenant, term_id, doc)`. The value is the position count as a `u32`
+//! followed by the gaps between positions (the first counted from 0) as
+//! one bitpacked run of the [`super::postings`] codec. Positions come from
+//! the same token stream as the term frequencies, so a row holds exactly
+//! `tf` positions.
+//!
+//! A phrase or `NEAR/n` clause is matched by walking the posting lists of
+//! its terms in step and reading positions only for the docs that hold all
+//! of them. Docs indexed before positions were turned on for their tenant
+//! have no rows and never match a phrase until they are written again.
+
+use redb::{ReadOnlyTable, ReadableTable};
+
+use super::bm25::Clause;
+use super::postings::{self, BlockKey, Posting, TermCursor};
+use crate::error::{Error, Result};
+
+/// Encode `positions` (ascending) as one row.
+pub(crate) fn encode(positions: &[u32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(5 + positions.len() * 2);
+    out.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+    let mut prev = 0;
+    let gaps: Vec<u64> = positions
+        .iter()
+        .map(|&p| {
+            let gap = p - prev;
+            prev = p;
+            u64::from(gap)
+        })
+        .collect();
+    postings::pack_run(&gaps, &mut out);
+    out
+}
+
+/// Decode a row written by [`encode`].
+pub(crate) fn decode(raw: &[u8]) -> Result<Vec<u32>> {
+    let count = raw
+        .get(..4)
+        .ok_or_else(|| Error::Index("positions row is truncated".into()))?;
+    let n = u32::from_le_bytes(count.try_into().expect("4 bytes")) as usize;
+    let mut pos = 4;
+    let gaps = postings::unpack_run(raw, &mut pos, n)?;
+    let mut out = Vec::with_capacity(n);
+    let mut at = 0u32;
+    for gap in gaps {
+        at = u32::try_from(gap)
+            .ok()
+            .and_then(|gap| at.checked_add(gap))
+            .ok_or_else(|| Error::Index("position overflows u32".into()))?;
+        out.push(at);
+    }
+    Ok(out)
+}
+
+/// Whether `tenant_id` has any positions stored.
+pub(super) fn any(
+    table: &impl ReadableTable<BlockKey, &'static [u8]>,
+    tenant_id: u32,
+) -> Result<bool> {
+    Ok(table
+        .range(postings::tenant_range(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .next()
+        .is_some())
+}
+
+/// Docs matching `clause` (a phrase or `NEAR/n`), as postings whose tf
+/// is the number of matches in the doc. `terms` holds the id and a fresh
+/// cursor of each of the clause's terms, in clause order.
+pub(super) fn matches(
+    table: &ReadOnlyTable<BlockKey, &'static [u8]>,
+    tenant_id: u32,
+    clause: &Clause,
+    mut terms: Vec<(u64, TermCursor<'_>)>,
+) -> Result<Vec<Posting>> {
+    let mut out = Vec::new();
+    if terms.is_empty() {
+        return Ok(out);
+    }
+    loop {
+        let mut target = 0;
+        for (_, cursor) in &terms {
+            match cursor.current() {
+                Some(p) => target = target.max(p.doc),
+                None => return Ok(out),
+            }
+        }
+        for (_, cursor) in &mut terms {
+            cursor.seek(target)?;
+        }
+        let Some(p) = terms[0].1.current() else {
+            return Ok(out);
+        };
+        if terms
+            .iter()
+            .any(|(_, c)| c.current().is_none_or(|q| q.doc != p.doc))
+        {
+            continue;
+        }
+        let mut lists = Vec::with_capacity(terms.len());
+        for (tid, _) in &terms {
+            match table
+                .get((tenant_id, *tid, p.doc))
+                .map_err(|e| Error::Index(e.to_string()))?
+            {
+                Some(raw) => lists.push(decode(raw.value())?),
+                None => break,
+            }
+        }
+        if lists.len() == terms.len() {
+            let tf = count(clause, &lists);
+            if tf > 0 {
+                out.push(Posting { tf, ..p });
+            }
+        }
+        terms[0].1.next()?;
+    }
+}
+
+/// Matches of `clause` in one doc, given the positions of each of its
+/// terms in clause order. A `NEAR/n` match is an occurrence of the left
+/// operand with one of the right operand at most `n` tokens away, before
+/// or after it.
+pub(crate) fn count(clause: &Clause, lists: &[Vec<u32>]) -> u32 {
+    let n = match clause {
+        Clause::Term(_) | Clause::Expand { .. } => lists.first().map_or(0, Vec::len),
+        Clause::Phrase(_) => starts(lists).len(),
+        Clause::Near {
+            left,
+            right,
+            within,
+        } => {
+            let (l, r) = lists.split_at(left.len().min(lists.len()));
+            let (l_len, r_len) = (left.len() as u64, right.len() as u64);
+            let rights = starts(r);
+            starts(l)
+                .into_iter()
+                .filter(|&s| {
+                    let s = u64::from(s);
+                    // Right starts whose span ends within reach before
+                    // `s`, up to those beginning within reach after it.
+                    let lo = s.saturating_sub(u64::from(*within) + r_len - 1);
+                    let hi = s + l_len - 1 + u64::from(*within);
+                    let from = rights.partition_point(|&r| u64::from(r) < lo);
+                    rights[from..]
+                        .iter()
+                        .map(|&r| u64::from(r))
+                        .take_while(|&r| r <= hi)
+                        .any(|r| r >= s + l_len || r + r_len <= s)
+                })
+                .count()
+        }
+    };
+    u32::try_from(n).unwrap_or(u32::MAX)
+}
+
+/// Start positions of the phrase whose `i`-th term occurs at `lists[i]`.
+fn starts(lists: &[Vec<u32>]) -> Vec<u32> {
+    let Some((first, rest)) = lists.split_first() else {
+        return Vec::new();
+    };
+    first
+        .iter()
+        .copied()
+        .filter(|&s| {
+            rest.iter().enumerate().all(|(i, list)| {
+                s.checked_add(i as u32 + 1)
+                    .is_some_and(|p| list.binary_search(&p).is_ok())
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(left: &[&str], right: &[&str], within: u32) -> Clause {
+        Clause::Near {
+            left: left.iter().map(|s| s.to_string()).collect(),
+            right: right.iter().map(|s| s.to_string()).collect(),
+            within,
+        }
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        for positions in [vec![], vec![0], vec![3, 4, 90, 70_000]] {
+            assert_eq!(decode(&encode(&positions)).unwrap(), positions);
+        }
+        assert!(decode(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn counts_phrase_and_near_matches() {
+        let phrase = Clause::Phrase(vec!["force".into(), "majeure".into()]);
+        // "force majeure ... force ... majeure force majeure"
+        let lists = [vec![0, 5, 9], vec![1, 8, 10]];
+        assert_eq!(count(&phrase, &lists), 2);
+
+        // Left occurrences 0, 5, 9 against right ones 1, 8, 10.
+        assert_eq!(count(&near(&["force"], &["majeure"], 1), &lists), 2);
+        assert_eq!(count(&near(&["force"], &["majeure"], 3), &lists), 3);
+        // A phrase operand is measured from its ends.
+        let lists = [vec![0], vec![1], vec![4]];
+        assert_eq!(count(&near(&["a", "b"], &["c"], 2), &lists), 0);
+        assert_eq!(count(&near(&["a", "b"], &["c"], 3), &lists), 1);
+        // An occurrence is never near itself.
+        assert_eq!(count(&near(&["a"], &["a"], 5), &[vec![2], vec![2]]), 0);
+    }
+}
@@ -0,0 +1,607 @@
+//! Block-encoded BM25 posting lists.
+//!
+//! A term's postings are cut into blocks in doc-id order, one
+//! `ucfp/bm25/blocks/v1` row each, keyed `(tenant, term_id, last doc)`:
+//! the block that holds (or would hold) doc `d` is the first key at or
+//! after `d`, or the term's last block when `d` is past all of them.
+//!
+//! | Bytes   | Field                                                  |
+//! | ------- | ------------------------------------------------------ |
+//! | `0..4`  | entry count `u32`                                      |
+//! | `4..12` | first doc id `u64`                                     |
+//! | `12..16`| max term frequency `u32`                               |
+//! | `16..20`| min doc length `u32`                                   |
+//! | `20..`  | doc-id gaps, term frequencies, doc lengths             |
+//!
+//! Each of the three trailing runs is one width byte followed by its
+//! values bitpacked at that width, least significant bit first. A BM25
+//! contribution grows with tf and shrinks with doc length, so the header
+//! alone bounds every entry of the block: queries skip blocks whose bound
+//! cannot reach the current top-k without decoding them.
+//!
+//! Writes decode and rewrite only the blocks holding the touched docs.
+//! A block grows to at most `2 × BLOCK_LEN` entries before it is split
+//! into `BLOCK_LEN`-entry blocks; blocks emptied by deletes are dropped,
+//! and a rebuild (reindex or fsck repair) packs them full again.
+//!
+//! [`update_term`] and [`TermCursor`] reach the rows through
+//! [`BlockRead`] / [`BlockWrite`], implemented here for redb tables; the
+//! fjall backend implements them over a partition to share the layout.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
+
+use redb::{Database, ReadOnlyTable, ReadableTable, Table, TableDefinition};
+
+use crate::error::{Error, Result};
+
+/// Entries per block as written by a rebuild or a split.
+pub(crate) const BLOCK_LEN: usize = 128;
+
+const HEADER_LEN: usize = 20;
+
+/// Rows removed per transaction by [`drain_tenant_batch`].
+const DRAIN_BATCH: usize = 1000;
+
+pub(crate) type BlockKey = (u32, u64, u64);
+
+/// One document in a term's posting list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Posting {
+    pub doc: u64,
+    pub tf: u32,
+    pub dl: u32,
+}
+
+/// The fixed-size head of a block, readable without decoding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlockHeader {
+    pub count: u32,
+    pub first_doc: u64,
+    pub max_tf: u32,
+    pub min_dl: u32,
+}
+
+/// Encode `postings` (non-empty, ascending by doc) as one block.
+pub(crate) fn encode(postings: &[Posting]) -> Vec<u8> {
+    let first = postings.first().map_or(0, |p| p.doc);
+    let header = BlockHeader {
+        count: postings.len() as u32,
+        first_doc: first,
+        max_tf: postings.iter().map(|p| p.tf).max().unwrap_or(0),
+        min_dl: postings.iter().map(|p| p.dl).min().unwrap_or(0),
+    };
+    let mut out = Vec::with_capacity(HEADER_LEN + 3 + postings.len() * 4);
+    out.extend_from_slice(&header.count.to_le_bytes());
+    out.extend_from_slice(&header.first_doc.to_le_bytes());
+    out.extend_from_slice(&header.max_tf.to_le_bytes());
+    out.extend_from_slice(&header.min_dl.to_le_bytes());
+    let gaps: Vec<u64> = postings.windows(2).map(|w| w[1].doc - w[0].doc).collect();
+    pack_run(&gaps, &mut out);
+    pack_run(
+        &postings.iter().map(|p| u64::from(p.tf)).collect::<Vec<_>>(),
+        &mut out,
+    );
+    pack_run(
+        &postings.iter().map(|p| u64::from(p.dl)).collect::<Vec<_>>(),
+        &mut out,
+    );
+    out
+}
+
+/// The header of an encoded block.
+pub(crate) fn header(raw: &[u8]) -> Result<BlockHeader> {
+    let Some(head) = raw.get(..HEADER_LEN) else {
+        return Err(Error::Index(format!(
+            "posting block is {} bytes, shorter than its header",
+            raw.len()
+        )));
+    };
+    let u32_at = |i: usize| u32::from_le_bytes(head[i..i + 4].try_into().expect("4 bytes"));
+    Ok(BlockHeader {
+        count: u32_at(0),
+        first_doc: u64::from_le_bytes(head[4..12].try_into().expect("8 bytes")),
+        max_tf: u32_at(12),
+        min_dl: u32_at(16),
+    })
+}
+
+/// Decode every entry of an encoded block.
+pub(crate) fn decode(raw: &[u8]) -> Result<Vec<Posting>> {
+    let head = header(raw)?;
+    let n = head.count as usize;
+    if n == 0 {
+        return Err(Error::Index("empty posting block".into()));
+    }
+    let mut pos = HEADER_LEN;
+    let gaps = unpack_run(raw, &mut pos, n - 1)?;
+    let tfs = unpack_run(raw, &mut pos, n)?;
+    let dls = unpack_run(raw, &mut pos, n)?;
+    if pos != raw.len() {
+        return Err(Error::Index(format!(
+            "posting block has {} trailing bytes",
+            raw.len() - pos
+        )));
+    }
+    let mut doc = head.first_doc;
+    let mut out = Vec::with_capacity(n);
+    for i in 0..n {
+        if i > 0 {
+            doc = doc
+                .checked_add(gaps[i - 1])
+                .ok_or_else(|| Error::Index("posting block doc id overflow".into()))?;
+        }
+        out.push(Posting {
+            doc,
+            tf: u32::try_from(tfs[i]).map_err(|e| Error::Index(e.to_string()))?,
+            dl: u32::try_from(dls[i]).map_err(|e| Error::Index(e.to_string()))?,
+        });
+    }
+    Ok(out)
+}
+
+/// Append `values` as one run: a width byte, then the values bitpacked
+/// at that width.
+pub(super) fn pack_run(values: &[u64], out: &mut Vec<u8>) {
+    let width = values
+        .iter()
+        .map(|v| 64 - v.leading_zeros())
+        .max()
+        .unwrap_or(0);
+    out.push(width as u8);
+    let mut acc: u128 = 0;
+    let mut bits = 0u32;
+    for &v in values {
+        acc |= u128::from(v) << bits;
+        bits += width;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        out.push(acc as u8);
+    }
+}
+
+/// Read the `n`-value run at `*pos` and move `pos` past it.
+pub(super) fn unpack_run(raw: &[u8], pos: &mut usize, n: usize) -> Result<Vec<u64>> {
+    let truncated = || Error::Index("posting block is truncated".into());
+    let width = u32::from(*raw.get(*pos).ok_or_else(truncated)?);
+    if width > 64 {
+        return Err(Error::Index(format!("posting block run width {width}")));
+    }
+    *pos += 1;
+    let len = (n * width as usize).div_ceil(8);
+    let bytes = raw.get(*pos..*pos + len).ok_or_else(truncated)?;
+    *pos += len;
+    let mask = if width == 64 {
+        u128::from(u64::MAX)
+    } else {
+        (1u128 << width) - 1
+    };
+    let mut out = Vec::with_capacity(n);
+    let mut acc: u128 = 0;
+    let mut bits = 0u32;
+    let mut next = bytes.iter();
+    for _ in 0..n {
+        while bits < width {
+            acc |= u128::from(*next.next().ok_or_else(truncated)?) << bits;
+            bits += 8;
+        }
+        out.push((acc & mask) as u64);
+        acc >>= width;
+        bits -= width;
+    }
+    Ok(out)
+}
+
+// ── Table access ────────────────────────────────────────────────────────
+
+/// Key range of every block of `term_id`.
+pub(super) fn term_range(tenant_id: u32, term_id: u64) -> RangeInclusive<BlockKey> {
+    (tenant_id, term_id, 0)..=(tenant_id, term_id, u64::MAX)
+}
+
+/// Key range of every row of `tenant_id` in a `(tenant, term_id, doc)`
+/// table: its blocks, or its positions.
+pub(super) fn tenant_range(tenant_id: u32) -> RangeInclusive<BlockKey> {
+    (tenant_id, 0, 0)..=(tenant_id, u64::MAX, u64::MAX)
+}
+
+/// `(last doc, header)` of every block of `term_id`, in doc order.
+pub(super) fn headers(
+    table: &impl ReadableTable<BlockKey, &'static [u8]>,
+    tenant_id: u32,
+    term_id: u64,
+) -> Result<Vec<(u64, BlockHeader)>> {
+    let mut out = Vec::new();
+    for entry in table
+        .range(term_range(tenant_id, term_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        out.push((k.value().2, header(v.value())?));
+    }
+    Ok(out)
+}
+
+/// Whether `term_id` has any postings left.
+pub(super) fn has_postings(
+    table: &impl ReadableTable<BlockKey, &'static [u8]>,
+    tenant_id: u32,
+    term_id: u64,
+) -> Result<bool> {
+    Ok(table
+        .range(term_range(tenant_id, term_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .next()
+        .is_some())
+}
+
+/// Read access to block rows keyed [`BlockKey`], in key order.
+pub(crate) trait BlockRead {
+    /// `(last doc, header)` of every block of `term_id`, in doc order.
+    fn headers(&self, tenant_id: u32, term_id: u64) -> Result<Vec<(u64, BlockHeader)>>;
+
+    /// Last doc of the block `doc` falls in: the first block of `term_id`
+    /// keyed at or after `doc`, else its last block. `None` when the term
+    /// has no blocks.
+    fn locate(&self, tenant_id: u32, term_id: u64, doc: u64) -> Result<Option<u64>>;
+
+    /// The decoded entries of the block keyed `(tenant_id, term_id, last)`.
+    fn entries(&self, tenant_id: u32, term_id: u64, last: u64) -> Result<Option<Vec<Posting>>>;
+}
+
+/// Write access to block rows, for [`write_term`] and [`update_term`].
+pub(crate) trait BlockWrite: BlockRead {
+    fn put(&mut self, key: BlockKey, block: &[u8]) -> Result<()>;
+
+    fn remove(&mut self, key: BlockKey) -> Result<()>;
+}
+
+impl<T: ReadableTable<BlockKey, &'static [u8]>> BlockRead for T {
+    fn headers(&self, tenant_id: u32, term_id: u64) -> Result<Vec<(u64, BlockHeader)>> {
+        headers(self, tenant_id, term_id)
+    }
+
+    fn locate(&self, tenant_id: u32, term_id: u64, doc: u64) -> Result<Option<u64>> {
+        let at_or_after = self
+            .range((tenant_id, term_id, doc)..=(tenant_id, term_id, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .next();
+        let entry = match at_or_after {
+            Some(entry) => Some(entry),
+            None => self
+                .range(term_range(tenant_id, term_id))
+                .map_err(|e| Error::Index(e.to_string()))?
+                .next_back(),
+        };
+        match entry {
+            Some(entry) => Ok(Some(
+                entry.map_err(|e| Error::Index(e.to_string()))?.0.value().2,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn entries(&self, tenant_id: u32, term_id: u64, last: u64) -> Result<Option<Vec<Posting>>> {
+        self.get((tenant_id, term_id, last))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .map(|raw| decode(raw.value()))
+            .transpose()
+    }
+}
+
+impl BlockWrite for Table<'_, BlockKey, &'static [u8]> {
+    fn put(&mut self, key: BlockKey, block: &[u8]) -> Result<()> {
+        self.insert(key, block)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: BlockKey) -> Result<()> {
+        Table::remove(self, key).map_err(|e| Error::Index(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Write `postings` (ascending by doc) as the blocks of a term that has
+/// none yet.
+pub(crate) fn write_term(
+    table: &mut (impl BlockWrite + ?Sized),
+    tenant_id: u32,
+    term_id: u64,
+    postings: &[Posting],
+) -> Result<()> {
+    let chunk = if postings.len() <= 2 * BLOCK_LEN {
+        postings.len().max(1)
+    } else {
+        BLOCK_LEN
+    };
+    for block in postings.chunks(chunk) {
+        let last = block.last().expect("chunks are non-empty").doc;
+        table.put((tenant_id, term_id, last), &encode(block))?;
+    }
+    Ok(())
+}
+
+/// Drop the docs in `gone` from `term_id` and add `added`, rewriting only
+/// the blocks they fall in. A doc in both is replaced.
+pub(crate) fn update_term(
+    table: &mut (impl BlockWrite + ?Sized),
+    tenant_id: u32,
+    term_id: u64,
+    gone: Option<&BTreeSet<u64>>,
+    added: Vec<Posting>,
+) -> Result<()> {
+    let mut blocks: BTreeMap<Option<u64>, BTreeMap<u64, Posting>> = BTreeMap::new();
+    let docs = gone
+        .into_iter()
+        .flatten()
+        .copied()
+        .chain(added.iter().map(|p| p.doc));
+    let mut placed = Vec::with_capacity(added.len());
+    for doc in docs {
+        let last = table.locate(tenant_id, term_id, doc)?;
+        if let Some(key) = last
+            && !blocks.contains_key(&last)
+        {
+            let entries = table
+                .entries(tenant_id, term_id, key)?
+                .ok_or_else(|| Error::Index("posting block vanished".into()))?;
+            blocks.insert(last, entries.into_iter().map(|p| (p.doc, p)).collect());
+        }
+        blocks.entry(last).or_default();
+        placed.push(last);
+    }
+    if let Some(gone) = gone {
+        for entries in blocks.values_mut() {
+            entries.retain(|doc, _| !gone.contains(doc));
+        }
+    }
+    let added_from = placed.len() - added.len();
+    for (p, last) in added.into_iter().zip(&placed[added_from..]) {
+        blocks.entry(*last).or_default().insert(p.doc, p);
+    }
+
+    for (last, entries) in blocks {
+        if let Some(key) = last {
+            table.remove((tenant_id, term_id, key))?;
+        }
+        let entries: Vec<Posting> = entries.into_values().collect();
+        if !entries.is_empty() {
+            write_term(table, tenant_id, term_id, &entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete up to [`DRAIN_BATCH`] rows of `tenant_id` from `table` (the
+/// blocks or the positions) in one transaction; returns how many went.
+/// Purge calls it until it returns 0.
+pub(super) fn drain_tenant_batch(
+    db: &Database,
+    table: TableDefinition<'_, BlockKey, &[u8]>,
+    tenant_id: u32,
+) -> Result<u64> {
+    let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    let n = {
+        let mut table = txn
+            .open_table(table)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let keys: Vec<BlockKey> = table
+            .range(tenant_range(tenant_id))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .take(DRAIN_BATCH)
+            .map(|entry| entry.map(|(k, _)| k.value()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for key in &keys {
+            table
+                .remove(*key)
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+        keys.len() as u64
+    };
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    Ok(n)
+}
+
+// ── Query cursor ────────────────────────────────────────────────────────
+
+/// Forward cursor over one term's postings. Block headers are read when
+/// the cursor opens; a block's entries are decoded the first time the
+/// cursor lands in it, so blocks skipped over are never decoded.
+pub(crate) struct TermCursor<'t, R: ?Sized = ReadOnlyTable<BlockKey, &'static [u8]>> {
+    table: &'t R,
+    tenant_id: u32,
+    term_id: u64,
+    blocks: Vec<(u64, BlockHeader)>,
+    block: usize,
+    entries: Vec<Posting>,
+    pos: usize,
+}
+
+impl<'t, R: BlockRead + ?Sized> TermCursor<'t, R> {
+    /// Cursor on the first posting of `term_id`, or `None` when it has
+    /// none.
+    pub(crate) fn open(table: &'t R, tenant_id: u32, term_id: u64) -> Result<Option<Self>> {
+        let blocks = table.headers(tenant_id, term_id)?;
+        if blocks.is_empty() {
+            return Ok(None);
+        }
+        let mut cursor = Self {
+            table,
+            tenant_id,
+            term_id,
+            blocks,
+            block: 0,
+            entries: Vec::new(),
+            pos: 0,
+        };
+        cursor.load(0)?;
+        Ok(Some(cursor))
+    }
+
+    /// Headers of every block, in doc order.
+    pub(super) fn blocks(&self) -> &[(u64, BlockHeader)] {
+        &self.blocks
+    }
+
+    /// Number of docs holding the term.
+    pub(crate) fn doc_freq(&self) -> u64 {
+        self.blocks.iter().map(|(_, h)| u64::from(h.count)).sum()
+    }
+
+    /// The posting under the cursor; `None` once exhausted.
+    pub(crate) fn current(&self) -> Option<Posting> {
+        self.entries.get(self.pos).copied()
+    }
+
+    /// `(last doc, header)` of the block that would hold `doc`, looking
+    /// at headers only. `None` when every block ends before `doc`.
+    pub(super) fn block_at(&self, doc: u64) -> Option<(u64, BlockHeader)> {
+        self.blocks[self.block.min(self.blocks.len())..]
+            .iter()
+            .find(|(last, _)| *last >= doc)
+            .copied()
+    }
+
+    /// Step to the next posting.
+    pub(crate) fn next(&mut self) -> Result<()> {
+        self.pos += 1;
+        if self.pos >= self.entries.len() {
+            self.load(self.block + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Move to the first posting with a doc id of at least `target`,
+    /// decoding only the block it lands in.
+    pub(super) fn seek(&mut self, target: u64) -> Result<()> {
+        if self.current().is_none_or(|p| p.doc >= target) {
+            return Ok(());
+        }
+        if self.blocks[self.block].0 < target {
+            let skip = self.blocks[self.block..]
+                .iter()
+                .position(|(last, _)| *last >= target)
+                .unwrap_or(self.blocks.len() - self.block);
+            self.load(self.block + skip)?;
+        }
+        loop {
+            self.pos += self.entries[self.pos..].partition_point(|p| p.doc < target);
+            if self.pos < self.entries.len() {
+                return Ok(());
+            }
+            // Only a block whose key overstates its last doc gets here.
+            self.load(self.block + 1)?;
+            if self.entries.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn load(&mut self, block: usize) -> Result<()> {
+        self.block = block;
+        self.pos = 0;
+        self.entries.clear();
+        let Some(&(last, _)) = self.blocks.get(block) else {
+            return Ok(());
+        };
+        if let Some(entries) = self.table.entries(self.tenant_id, self.term_id, last)? {
+            self.entries = entries;
+        }
+        if self.entries.is_empty() {
+            return self.load(block + 1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::embedded::bm25::BM25_BLOCKS;
+    use redb::ReadableDatabase;
+
+    fn postings(docs: impl IntoIterator<Item = u64>) -> Vec<Posting> {
+        docs.into_iter()
+            .map(|doc| Posting {
+                doc,
+                tf: (doc % 7) as u32 + 1,
+                dl: (doc % 31) as u32 * 3,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn block_round_trips() {
+        for docs in [
+            postings([5]),
+            postings((0..BLOCK_LEN as u64).map(|i| i * 3 + 1)),
+            postings([0, 1, u64::MAX / 2, u64::MAX]),
+        ] {
+            let raw = encode(&docs);
+            assert_eq!(decode(&raw).unwrap(), docs);
+            let head = header(&raw).unwrap();
+            assert_eq!(head.count as usize, docs.len());
+            assert_eq!(head.first_doc, docs[0].doc);
+            assert_eq!(head.max_tf, docs.iter().map(|p| p.tf).max().unwrap());
+            assert_eq!(head.min_dl, docs.iter().map(|p| p.dl).min().unwrap());
+        }
+        let dense = encode(&postings(1000..1000 + BLOCK_LEN as u64));
+        assert!(dense.len() < BLOCK_LEN * 3, "{} bytes", dense.len());
+        assert!(decode(&dense[..dense.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn updates_touch_only_their_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::create(dir.path().join("p.redb")).unwrap();
+        let all = postings((0..1000).map(|i| i * 2));
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(BM25_BLOCKS).unwrap();
+            write_term(&mut table, 1, 7, &all).unwrap();
+            let gone = BTreeSet::from([0, 500, 1998]);
+            let added = postings([1, 501, 5000]);
+            update_term(&mut table, 1, 7, Some(&gone), added).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let mut want: BTreeMap<u64, Posting> = all.iter().map(|p| (p.doc, *p)).collect();
+        for doc in [0, 500, 1998] {
+            want.remove(&doc);
+        }
+        for p in postings([1, 501, 5000]) {
+            want.insert(p.doc, p);
+        }
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(BM25_BLOCKS).unwrap();
+        let blocks = headers(&table, 1, 7).unwrap();
+        assert!(
+            blocks
+                .iter()
+                .all(|(_, h)| h.count as usize <= 2 * BLOCK_LEN)
+        );
+        let mut cursor = TermCursor::open(&table, 1, 7).unwrap().unwrap();
+        assert_eq!(cursor.doc_freq(), want.len() as u64);
+        let mut seen = Vec::new();
+        while let Some(p) = cursor.current() {
+            seen.push(p);
+            cursor.next().unwrap();
+        }
+        assert_eq!(seen, want.into_values().collect::<Vec<_>>());
+
+        let mut cursor = TermCursor::open(&table, 1, 7).unwrap().unwrap();
+        cursor.seek(1201).unwrap();
+        assert_eq!(cursor.current().unwrap().doc, 1202);
+        cursor.seek(4000).unwrap();
+        assert_eq!(cursor.current().unwrap().doc, 5000);
+        cursor.seek(5001).unwrap();
+        assert!(cursor.current().is_none());
+    }
+}
@@ -0,0 +1,351 @@
+//! Rebuild a tenant's BM25 index from retained text.
+//!
+//! With [`super::EmbeddedBackend::with_retained_text`] on, every indexed
+//! record keeps its text in `ucfp/text/v1` and its named text fields in
+//! `ucfp/text_fields/v1`. A rebuild runs in two phases:
+//!
+//! 1. [`tokenize_page`] walks the indexed records in pages, each in its
+//!    own read transaction, and tokenizes their retained text off the
+//!    write lock. Writers and readers carry on against the current index.
+//! 2. [`swap`] takes the write lock, re-reads the retained text, re-tokenizes
+//!    whatever changed since its page was read, then replaces every
+//!    `ucfp/bm25/*` row of the tenant in one transaction, positions
+//!    included when the tenant stores them. Queries see the
+//!    old index until that commit and the new one after.
+//!
+//! Text goes through the tenant's configured analyzer, which the
+//! rebuilt index then records as its own: this is how a tenant moves to
+//! a new [`Analyzer`].
+//!
+//! Term ids are reassigned densely from 0 in term order. A tenant with
+//! indexed records whose text was never retained is refused rather than
+//! rebuilt without them.
+
+use std::collections::BTreeMap;
+
+use redb::{Database, ReadOnlyTable, ReadTransaction, ReadableDatabase, WriteTransaction};
+
+use super::analyzer::Analyzer;
+use super::bm25::{
+    self, BM25_ANALYZER, BM25_BLOCKS, BM25_CORPUS, BM25_DOC_LENS, BM25_DOC_TERMS, BM25_FIELD_LENS,
+    BM25_POSITIONS, BM25_TERM_FST, CorpusStats, DocTerms,
+};
+use super::positions;
+use super::postings::{self, Posting};
+use super::{TEXT, TEXT_FIELDS, decode_text_fields};
+use crate::error::{Error, Result};
+use crate::index::Purging;
+
+/// Records tokenized per [`tokenize_page`] call.
+pub(super) const REINDEX_BATCH: usize = 1000;
+
+/// One record's retained text and fields.
+type Retained = (Option<String>, BTreeMap<String, String>);
+
+/// One record's text and fields and their term frequencies.
+pub(super) struct Doc {
+    retained: Retained,
+    terms: DocTerms,
+}
+
+impl Doc {
+    fn new(retained: Retained, analyzer: &Analyzer, positional: bool) -> Self {
+        let (text, fields) = &retained;
+        let terms = DocTerms::new(
+            text.as_deref().unwrap_or_default(),
+            fields,
+            analyzer,
+            positional,
+        );
+        Self { retained, terms }
+    }
+}
+
+/// The retained text tables, open in one read transaction.
+struct Texts {
+    text: ReadOnlyTable<(u32, u64), &'static str>,
+    fields: ReadOnlyTable<(u32, u64), &'static [u8]>,
+}
+
+impl Texts {
+    fn open(txn: &ReadTransaction) -> Result<Self> {
+        Ok(Self {
+            text: txn
+                .open_table(TEXT)
+                .map_err(|e| Error::Index(e.to_string()))?,
+            fields: txn
+                .open_table(TEXT_FIELDS)
+                .map_err(|e| Error::Index(e.to_string()))?,
+        })
+    }
+
+    /// What `key` retained; `None` when neither text nor fields.
+    fn get(&self, key: (u32, u64)) -> Result<Option<Retained>> {
+        let text = self
+            .text
+            .get(key)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .map(|v| v.value().to_string());
+        let fields = self
+            .fields
+            .get(key)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .map(|v| decode_text_fields(v.value()))
+            .transpose()?;
+        Ok(match (text, fields) {
+            (None, None) => None,
+            (text, fields) => Some((text, fields.unwrap_or_default())),
+        })
+    }
+}
+
+/// Analyze the retained text of up to [`REINDEX_BATCH`] indexed records
+/// of `tenant_id` with ids above `after`, with positions when
+/// `positional`. An empty page
+/// means the walk is done. Fails early when indexed records have no
+/// retained text.
+pub(super) fn tokenize_page(
+    db: &Database,
+    tenant_id: u32,
+    after: Option<u64>,
+    positional: bool,
+    analyzer: &Analyzer,
+) -> Result<Vec<(u64, Doc)>> {
+    let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    if after.is_none() {
+        refuse_unretained(&txn, tenant_id)?;
+    }
+    let from = match after {
+        Some(id) => match id.checked_add(1) {
+            Some(next) => next,
+            None => return Ok(Vec::new()),
+        },
+        None => 0,
+    };
+    let texts = Texts::open(&txn)?;
+    let lens = txn
+        .open_table(BM25_DOC_LENS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut out = Vec::new();
+    for entry in lens
+        .range((tenant_id, from)..=(tenant_id, u64::MAX))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .take(REINDEX_BATCH)
+    {
+        let (k, _) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        // Written since the check above without retention: `swap`
+        // refuses it.
+        if let Some(retained) = texts.get(k.value())? {
+            out.push((k.value().1, Doc::new(retained, analyzer, positional)));
+        }
+    }
+    Ok(out)
+}
+
+/// Bring `docs` up to date with the text table and replace the tenant's
+/// BM25 rows with the index built from them. Returns the records indexed.
+pub(super) fn swap(
+    db: &Database,
+    tenant_id: u32,
+    mut docs: BTreeMap<u64, Doc>,
+    positional: bool,
+    analyzer: &Analyzer,
+    purging: &Purging,
+) -> Result<u64> {
+    let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    purging.check(tenant_id)?;
+    {
+        // Opened under the write lock, this sees exactly the state the
+        // write transaction starts from.
+        let current = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+        refuse_unretained(&current, tenant_id)?;
+        let texts = Texts::open(&current)?;
+        let lens = current
+            .open_table(BM25_DOC_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut live = BTreeMap::new();
+        for entry in lens
+            .range((tenant_id, 0)..=(tenant_id, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+        {
+            let (k, _) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            let Some(retained) = texts.get(k.value())? else {
+                continue;
+            };
+            let id = k.value().1;
+            let doc = match docs.remove(&id) {
+                Some(doc) if doc.retained == retained => doc,
+                _ => Doc::new(retained, analyzer, positional),
+            };
+            live.insert(id, doc);
+        }
+        docs = live;
+    }
+    write_index(&txn, tenant_id, &docs)?;
+    if docs.is_empty() {
+        txn.open_table(BM25_ANALYZER)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .remove(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    } else {
+        bm25::write_analyzer(&txn, tenant_id, analyzer)?;
+    }
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    Ok(docs.len() as u64)
+}
+
+/// [`Error::Incompatible`] when a record is in the BM25 index but its
+/// text is not retained.
+fn refuse_unretained(txn: &ReadTransaction, tenant_id: u32) -> Result<()> {
+    let lens = txn
+        .open_table(BM25_DOC_LENS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let texts = Texts::open(txn)?;
+    let mut missing = 0u64;
+    for entry in lens
+        .range((tenant_id, 0)..=(tenant_id, u64::MAX))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        let (k, _) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        let key = k.value();
+        let retained = texts
+            .text
+            .get(key)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .is_some()
+            || texts
+                .fields
+                .get(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .is_some();
+        if !retained {
+            missing += 1;
+        }
+    }
+    if missing > 0 {
+        return Err(Error::Incompatible(format!(
+            "tenant {tenant_id}: {missing} indexed records have no retained text; \
+             upsert them again with text retention on before reindexing"
+        )));
+    }
+    Ok(())
+}
+
+/// Replace every BM25 row of `tenant_id` with the index of `docs`.
+fn write_index(txn: &WriteTransaction, tenant_id: u32, docs: &BTreeMap<u64, Doc>) -> Result<()> {
+    let mut dict: BTreeMap<String, u64> = BTreeMap::new();
+    for doc in docs.values() {
+        for term in doc.terms.tf.keys() {
+            if !dict.contains_key(term) {
+                dict.insert(term.clone(), 0);
+            }
+        }
+    }
+    for (id, term_id) in dict.values_mut().enumerate() {
+        *term_id = id as u64;
+    }
+
+    // Docs iterate in id order, so every posting list comes out sorted.
+    let mut lists: Vec<Vec<Posting>> = vec![Vec::new(); dict.len()];
+    let mut stats = CorpusStats {
+        doc_count: docs.len() as u64,
+        total_doc_len: 0,
+        next_term_id: dict.len() as u64,
+    };
+    let mut totals: BTreeMap<String, u64> = BTreeMap::new();
+    let range = (tenant_id, 0)..=(tenant_id, u64::MAX);
+    {
+        let mut doc_terms = txn
+            .open_table(BM25_DOC_TERMS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut doc_lens = txn
+            .open_table(BM25_DOC_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        doc_terms
+            .retain_in(range.clone(), |_, _| false)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        doc_lens
+            .retain_in(range, |_, _| false)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut field_lens = txn
+            .open_table(BM25_FIELD_LENS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        field_lens
+            .retain_in((tenant_id, 0)..=(tenant_id, u64::MAX), |_, _| false)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut doc_positions = txn
+            .open_table(BM25_POSITIONS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        doc_positions
+            .retain_in(postings::tenant_range(tenant_id), |_, _| false)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for (&id, Doc { terms: doc, .. }) in docs {
+            let mut tids = Vec::with_capacity(doc.tf.len());
+            for (term, &tf) in &doc.tf {
+                let tid = dict[term];
+                tids.push(tid);
+                lists[tid as usize].push(Posting {
+                    doc: id,
+                    tf,
+                    dl: doc.dl(term),
+                });
+                if let Some(at) = doc.positions.as_ref().and_then(|p| p.get(term)) {
+                    doc_positions
+                        .insert((tenant_id, tid, id), positions::encode(at).as_slice())
+                        .map_err(|e| Error::Index(e.to_string()))?;
+                }
+            }
+            doc_terms
+                .insert((tenant_id, id), bm25::pack_term_ids(&tids).as_slice())
+                .map_err(|e| Error::Index(e.to_string()))?;
+            doc_lens
+                .insert((tenant_id, id), doc.len)
+                .map_err(|e| Error::Index(e.to_string()))?;
+            stats.total_doc_len = stats.total_doc_len.saturating_add(u64::from(doc.len));
+            if !doc.field_lens.is_empty() {
+                field_lens
+                    .insert(
+                        (tenant_id, id),
+                        bm25::encode_lens(&doc.field_lens)?.as_slice(),
+                    )
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                for (name, &len) in &doc.field_lens {
+                    *totals.entry(name.clone()).or_default() += u64::from(len);
+                }
+            }
+        }
+    }
+    bm25::write_field_totals(txn, tenant_id, &totals)?;
+    {
+        let mut blocks = txn
+            .open_table(BM25_BLOCKS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        blocks
+            .retain_in(postings::tenant_range(tenant_id), |_, _| false)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for (tid, list) in lists.iter().enumerate() {
+            postings::write_term(&mut blocks, tenant_id, tid as u64, list)?;
+        }
+    }
+
+    let mut fst = txn
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut corpus = txn
+        .open_table(BM25_CORPUS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    if docs.is_empty() {
+        fst.remove(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        corpus
+            .remove(tenant_id)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    } else {
+        fst.insert(tenant_id, bm25::encode_term_dict(&dict)?.as_slice())
+            .map_err(|e| Error::Index(e.to_string()))?;
+        corpus
+            .insert(tenant_id, stats.pack().as_slice())
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,358 @@
+//! Online snapshot and offline restore for the embedded backend.
+//!
+//! A snapshot is itself a redb file. Every table is copied row by row
+//! from one read transaction, so the copy is exactly the state of the
+//! last commit before the snapshot started, whatever writers do in the
+//! meantime. Two extra tables ride along:
+//!
+//! | Table                       | Key          | Value                       |
+//! | --------------------------- | ------------ | --------------------------- |
+//! | `ucfp/snapshot_meta/v1`     | field name   | `format_version`, … (JSON)  |
+//! | `ucfp/snapshot_files/v1`    | file name    | sidecar file contents       |
+//!
+//! Sidecars are files the backend keeps outside redb (ANN dumps, mmap
+//! caches) in `<db>.sidecars/` next to the database; none are written
+//! today, but a snapshot carries whatever is there.
+//!
+//! Restore validates the snapshot's `FORMAT_VERSION` and table names —
+//! each name carries its table version (`ucfp/catalog/v2`) — against this
+//! build before anything on disk changes, then swaps the rebuilt file in
+//! and keeps the previous database as `<db>.pre-restore`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use redb::{
+    Database, Key, ReadTransaction, ReadableDatabase, ReadableTable, ReadableTableMetadata,
+    TableDefinition, TableHandle, Value, WriteTransaction,
+};
+
+use super::{
+    CATALOG, FINGERPRINTS, METADATA, TEXT, TEXT_FIELDS, VECTORS, bm25, changes, expiry, migrate,
+    stats, versions,
+};
+use crate::core::SnapshotInfo;
+use crate::error::{Error, Result};
+use crate::index::now_ms;
+
+const SNAPSHOT_META: TableDefinition<'_, &str, &[u8]> =
+    TableDefinition::new("ucfp/snapshot_meta/v1");
+const SNAPSHOT_FILES: TableDefinition<'_, &str, &[u8]> =
+    TableDefinition::new("ucfp/snapshot_files/v1");
+
+/// Directory for files kept beside the database.
+pub(super) fn sidecar_dir(db_path: &Path) -> PathBuf {
+    with_suffix(db_path, ".sidecars")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(suffix);
+    PathBuf::from(s)
+}
+
+fn copy_table<K: Key + 'static, V: Value + 'static>(
+    src: &ReadTransaction,
+    dst: &WriteTransaction,
+    def: TableDefinition<'_, K, V>,
+) -> Result<u64> {
+    let from = match src.open_table(def) {
+        Ok(t) => t,
+        // Older file without this table: nothing to copy.
+        Err(redb::TableError::TableDoesNotExist(_)) => return Ok(0),
+        Err(e) => return Err(Error::Index(e.to_string())),
+    };
+    let mut to = dst
+        .open_table(def)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut rows = 0;
+    for entry in from.iter().map_err(|e| Error::Index(e.to_string()))? {
+        let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        to.insert(k.value(), v.value())
+            .map_err(|e| Error::Index(e.to_string()))?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+/// Copy every backend table from `src` into `dst`; returns rows copied.
+/// New tables must be added here and in [`known_tables`]. Tables of
+/// older schemas are copied too, so a restored snapshot taken by an
+/// older build is migrated when the database is next opened.
+fn copy_all(src: &ReadTransaction, dst: &WriteTransaction) -> Result<u64> {
+    Ok(copy_table(src, dst, FINGERPRINTS)?
+        + copy_table(src, dst, METADATA)?
+        + copy_table(src, dst, VECTORS)?
+        + copy_table(src, dst, CATALOG)?
+        + copy_table(src, dst, TEXT)?
+        + copy_table(src, dst, TEXT_FIELDS)?
+        + copy_table(src, dst, bm25::BM25_TERM_FST)?
+        + copy_table(src, dst, bm25::BM25_BLOCKS)?
+        + copy_table(src, dst, bm25::BM25_POSITIONS)?
+        + copy_table(src, dst, bm25::BM25_DOC_LENS)?
+        + copy_table(src, dst, bm25::BM25_CORPUS)?
+        + copy_table(src, dst, bm25::BM25_DOC_TERMS)?
+        + copy_table(src, dst, bm25::BM25_ANALYZER)?
+        + copy_table(src, dst, bm25::BM25_FIELD_LENS)?
+        + copy_table(src, dst, bm25::BM25_FIELDS)?
+        + copy_table(src, dst, expiry::EXPIRY)?
+        + copy_table(src, dst, expiry::EXPIRY_DUE)?
+        + copy_table(src, dst, versions::VERSIONS)?
+        + copy_table(src, dst, versions::VERSION_DUE)?
+        + copy_table(src, dst, changes::CHANGES)?
+        + copy_table(src, dst, changes::CHANGE_HEAD)?
+        + copy_table(src, dst, migrate::SCHEMA)?
+        + copy_table(src, dst, stats::STATS)?
+        + copy_table(src, dst, migrate::CATALOG_V1)?
+        + copy_table(src, dst, migrate::BM25_SCORING_V1)?
+        + copy_table(src, dst, migrate::BM25_POSTINGS_V1)?)
+}
+
+/// Names (and so versions) of every table this build reads.
+pub(super) fn known_tables() -> Vec<&'static str> {
+    vec![
+        FINGERPRINTS.name(),
+        METADATA.name(),
+        VECTORS.name(),
+        CATALOG.name(),
+        TEXT.name(),
+        TEXT_FIELDS.name(),
+        bm25::BM25_TERM_FST.name(),
+        bm25::BM25_BLOCKS.name(),
+        bm25::BM25_POSITIONS.name(),
+        bm25::BM25_DOC_LENS.name(),
+        bm25::BM25_CORPUS.name(),
+        bm25::BM25_DOC_TERMS.name(),
+        bm25::BM25_ANALYZER.name(),
+        bm25::BM25_FIELD_LENS.name(),
+        bm25::BM25_FIELDS.name(),
+        expiry::EXPIRY.name(),
+        expiry::EXPIRY_DUE.name(),
+        versions::VERSIONS.name(),
+        versions::VERSION_DUE.name(),
+        changes::CHANGES.name(),
+        changes::CHANGE_HEAD.name(),
+        migrate::SCHEMA.name(),
+        stats::STATS.name(),
+    ]
+}
+
+/// The manifest tables a snapshot adds on top of [`known_tables`].
+pub(super) fn own_tables() -> [&'static str; 2] {
+    [SNAPSHOT_META.name(), SNAPSHOT_FILES.name()]
+}
+
+fn table_names(txn: &ReadTransaction) -> Result<Vec<String>> {
+    Ok(txn
+        .list_tables()
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map(|t| t.name().to_string())
+        .collect())
+}
+
+/// Write a point-in-time copy of `db` (living at `db_path`) to `dest`.
+/// Refuses to overwrite; the file only appears under `dest` once
+/// complete.
+pub(super) fn write(db: &Database, db_path: &Path, dest: &Path) -> Result<SnapshotInfo> {
+    if dest.exists() {
+        return Err(Error::Modality(format!(
+            "snapshot target {} already exists",
+            dest.display()
+        )));
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = with_suffix(dest, ".partial");
+    let _ = std::fs::remove_file(&partial);
+    let written = write_to(db, db_path, &partial);
+    if written.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    let (created_ms, tables, rows, sidecars) = written?;
+    std::fs::rename(&partial, dest)?;
+
+    Ok(SnapshotInfo {
+        path: dest.to_path_buf(),
+        bytes: std::fs::metadata(dest)?.len(),
+        format_version: crate::FORMAT_VERSION,
+        created_ms,
+        tables,
+        rows,
+        sidecars,
+    })
+}
+
+/// Body of [`write()`]: fill `partial`, returning `(created_ms, tables,
+/// rows, sidecars)`.
+fn write_to(
+    db: &Database,
+    db_path: &Path,
+    partial: &Path,
+) -> Result<(u64, Vec<String>, u64, usize)> {
+    let created_ms = now_ms();
+    let src = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let out = Database::create(partial).map_err(|e| Error::Index(e.to_string()))?;
+    let txn = out.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    let rows = copy_all(&src, &txn)?;
+    let tables = table_names(&src)?;
+    let mut sidecars = 0;
+    {
+        let mut meta = txn
+            .open_table(SNAPSHOT_META)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let fields = [
+            ("format_version", serde_json::json!(crate::FORMAT_VERSION)),
+            (
+                "crate_version",
+                serde_json::json!(env!("CARGO_PKG_VERSION")),
+            ),
+            ("created_ms", serde_json::json!(created_ms)),
+            ("tables", serde_json::json!(tables)),
+        ];
+        for (field, value) in fields {
+            meta.insert(field, value.to_string().as_bytes())
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+        let mut files = txn
+            .open_table(SNAPSHOT_FILES)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        if let Ok(dir) = std::fs::read_dir(sidecar_dir(db_path)) {
+            for entry in dir {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                files
+                    .insert(name.as_str(), std::fs::read(entry.path())?.as_slice())
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                sidecars += 1;
+            }
+        }
+    }
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    Ok((created_ms, tables, rows, sidecars))
+}
+
+/// Check `snapshot` against this build without touching anything.
+pub(super) fn validate(snapshot: &Path) -> Result<SnapshotInfo> {
+    let db = Database::open(snapshot).map_err(|e| Error::Index(e.to_string()))?;
+    let txn = db.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let meta = match txn.open_table(SNAPSHOT_META) {
+        Ok(t) => t,
+        Err(redb::TableError::TableDoesNotExist(_)) => {
+            return Err(Error::Incompatible(format!(
+                "{} is not a ucfp snapshot",
+                snapshot.display()
+            )));
+        }
+        Err(e) => return Err(Error::Index(e.to_string())),
+    };
+    let field = |name: &str| -> Result<serde_json::Value> {
+        let raw = meta
+            .get(name)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .ok_or_else(|| Error::Incompatible(format!("snapshot manifest lacks `{name}`")))?;
+        serde_json::from_slice(raw.value())
+            .map_err(|e| Error::Incompatible(format!("snapshot manifest `{name}`: {e}")))
+    };
+    let format_version = field("format_version")?.as_u64().unwrap_or(0) as u32;
+    if format_version != crate::FORMAT_VERSION {
+        return Err(Error::Incompatible(format!(
+            "snapshot FORMAT_VERSION {format_version}, this build reads {}",
+            crate::FORMAT_VERSION
+        )));
+    }
+    let mut known = known_tables();
+    known.extend(migrate::legacy_tables());
+    let tables = table_names(&txn)?;
+    let data_tables: Vec<String> = tables
+        .into_iter()
+        .filter(|t| t != SNAPSHOT_META.name() && t != SNAPSHOT_FILES.name())
+        .collect();
+    if let Some(unknown) = data_tables.iter().find(|t| !known.contains(&t.as_str())) {
+        return Err(Error::Incompatible(format!(
+            "snapshot table `{unknown}` is not a table version this build reads"
+        )));
+    }
+    let sidecars = match txn.open_table(SNAPSHOT_FILES) {
+        Ok(t) => t.len().map_err(|e| Error::Index(e.to_string()))? as usize,
+        Err(_) => 0,
+    };
+    Ok(SnapshotInfo {
+        path: snapshot.to_path_buf(),
+        bytes: std::fs::metadata(snapshot)?.len(),
+        format_version,
+        created_ms: field("created_ms")?.as_u64().unwrap_or(0),
+        tables: data_tables,
+        rows: 0,
+        sidecars,
+    })
+}
+
+/// Validate `snapshot`, rebuild it as a database next to `db_path` and
+/// swap it in. Fails without changes if the database is open elsewhere.
+pub(super) fn restore(snapshot: &Path, db_path: &Path) -> Result<SnapshotInfo> {
+    let mut info = validate(snapshot)?;
+    if db_path.exists() {
+        // redb holds an exclusive lock while a server has it open.
+        drop(Database::open(db_path).map_err(|e| {
+            Error::Index(format!(
+                "{} is in use ({e}); stop the server before restoring",
+                db_path.display()
+            ))
+        })?);
+    }
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let partial = with_suffix(db_path, ".restore.partial");
+    let _ = std::fs::remove_file(&partial);
+    let src = Database::open(snapshot).map_err(|e| Error::Index(e.to_string()))?;
+    let src_txn = src.begin_read().map_err(|e| Error::Index(e.to_string()))?;
+    let out = Database::create(&partial).map_err(|e| Error::Index(e.to_string()))?;
+    let txn = out.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    info.rows = copy_all(&src_txn, &txn)?;
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    drop(out);
+
+    let mut files = BTreeMap::new();
+    if let Ok(t) = src_txn.open_table(SNAPSHOT_FILES) {
+        for entry in t.iter().map_err(|e| Error::Index(e.to_string()))? {
+            let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            files.insert(k.value().to_string(), v.value().to_vec());
+        }
+    }
+    let sidecars = sidecar_dir(db_path);
+    let sidecars_partial = with_suffix(&sidecars, ".restore.partial");
+    let _ = std::fs::remove_dir_all(&sidecars_partial);
+    if !files.is_empty() {
+        std::fs::create_dir_all(&sidecars_partial)?;
+        for (name, bytes) in &files {
+            // File names come from a directory listing; refuse anything
+            // that would land outside the sidecar directory.
+            if Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+                return Err(Error::Incompatible(format!(
+                    "snapshot sidecar name `{name}`"
+                )));
+            }
+            std::fs::write(sidecars_partial.join(name), bytes)?;
+        }
+    }
+
+    if db_path.exists() {
+        std::fs::rename(db_path, with_suffix(db_path, ".pre-restore"))?;
+    }
+    std::fs::rename(&partial, db_path)?;
+    if sidecars.exists() {
+        let old = with_suffix(&sidecars, ".pre-restore");
+        let _ = std::fs::remove_dir_all(&old);
+        std::fs::rename(&sidecars, old)?;
+    }
+    if !files.is_empty() {
+        std::fs::rename(&sidecars_partial, &sidecars)?;
+    }
+    Ok(info)
+}
@@ -0,0 +1,241 @@
+//! Highlighted snippets of BM25 hits.
+//!
+//! With text retention on, a hit's text and text fields can be read back
+//! and cut into fragments around its matches. [`snippets`] runs the
+//! tenant's analyzer over each field with [`Analyzer::spans`], so a token
+//! is marked exactly when it indexed as one of the hit's matched terms —
+//! `Contracts` for a query term `contract` under a stemmer, say.
+//!
+//! Every match anchors a candidate fragment that opens a quarter of the
+//! fragment length before it, backed up to a word boundary, and runs to
+//! the length cap, cut back to whitespace where it can. Candidates rank
+//! by the distinct terms they hold, then by their matches, then by where
+//! they start; the best ones that do not overlap are kept, best first.
+//! Fragments are HTML-escaped, then each match is wrapped in `<mark>`.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use super::analyzer::Analyzer;
+use crate::core::{Highlight, Snippet};
+
+/// A fragment opens `fragment_len / LEAD_IN` characters before its
+/// anchoring match, as context.
+const LEAD_IN: usize = 4;
+
+/// One candidate fragment of one field.
+struct Candidate<'a> {
+    field: &'a str,
+    text: &'a str,
+    range: Range<usize>,
+    marks: Vec<Range<usize>>,
+    distinct: usize,
+}
+
+/// The best fragments of `fields` (`(field, text)` pairs) around the
+/// tokens that analyze to one of `matched` (`(field, term)` pairs).
+pub(crate) fn snippets(
+    analyzer: &Analyzer,
+    fields: &[(&str, &str)],
+    matched: &HashSet<(&str, &str)>,
+    opts: &Highlight,
+) -> Vec<Snippet> {
+    let mut candidates = Vec::new();
+    for &(field, text) in fields {
+        let mut marks: Vec<(Range<usize>, String)> = analyzer
+            .spans(text)
+            .into_iter()
+            .filter(|(_, term)| matched.contains(&(field, term.as_str())))
+            .collect();
+        // A token that analyzed to two matched terms is marked once.
+        marks.dedup_by(|a, b| a.0 == b.0);
+        for (anchor, _) in &marks {
+            let range = window(text, anchor.clone(), opts.fragment_len);
+            let first = marks.partition_point(|(m, _)| m.start < range.start);
+            let inside: Vec<&(Range<usize>, String)> = marks[first..]
+                .iter()
+                .take_while(|(m, _)| m.end <= range.end)
+                .collect();
+            let distinct = inside
+                .iter()
+                .map(|(_, term)| term.as_str())
+                .collect::<HashSet<_>>()
+                .len();
+            candidates.push(Candidate {
+                field,
+                text,
+                range,
+                marks: inside.into_iter().map(|(m, _)| m.clone()).collect(),
+                distinct,
+            });
+        }
+    }
+    // Stable, so equal candidates keep field and then text order.
+    candidates.sort_by(|a, b| {
+        b.distinct
+            .cmp(&a.distinct)
+            .then(b.marks.len().cmp(&a.marks.len()))
+    });
+    let mut kept: Vec<Candidate<'_>> = Vec::new();
+    for c in candidates {
+        if kept.len() == opts.fragments {
+            break;
+        }
+        let overlaps = kept.iter().any(|k| {
+            k.field == c.field && k.range.start < c.range.end && c.range.start < k.range.end
+        });
+        if !overlaps {
+            kept.push(c);
+        }
+    }
+    kept.into_iter()
+        .map(|c| Snippet {
+            field: c.field.to_string(),
+            fragment: render(c.text, c.range, &c.marks),
+        })
+        .collect()
+}
+
+/// Byte range of the fragment of `text` anchored on the match `anchor`,
+/// at most `len` characters unless the match alone is longer.
+fn window(text: &str, anchor: Range<usize>, len: usize) -> Range<usize> {
+    let lead = len / LEAD_IN;
+    let mut start = text[..anchor.start]
+        .char_indices()
+        .rev()
+        .take(lead)
+        .last()
+        .map_or(anchor.start, |(i, _)| i);
+    if start > 0 && !text[..start].ends_with(char::is_whitespace) {
+        // Cut into a word: start after the next space, or at the match.
+        start = text[start..anchor.start]
+            .find(char::is_whitespace)
+            .map_or(anchor.start, |i| start + i);
+    }
+    let mut end = text[start..]
+        .char_indices()
+        .nth(len)
+        .map_or(text.len(), |(i, _)| start + i)
+        .max(anchor.end);
+    if end < text.len() && !text[end..].starts_with(char::is_whitespace) {
+        end = text[anchor.end..end]
+            .rfind(char::is_whitespace)
+            .map_or(end, |i| anchor.end + i);
+    }
+    let fragment = &text[start..end];
+    let start = start + (fragment.len() - fragment.trim_start().len());
+    let end = end - (fragment.len() - fragment.trim_end().len());
+    start..end.max(start)
+}
+
+/// `text[range]`, escaped, with `marks` wrapped in `<mark>` and `…` where
+/// the range cuts into the text.
+fn render(text: &str, range: Range<usize>, marks: &[Range<usize>]) -> String {
+    let mut out = String::with_capacity(range.len() + marks.len() * 13 + 6);
+    if !text[..range.start].trim().is_empty() {
+        out.push('…');
+    }
+    let mut at = range.start;
+    for m in marks {
+        escape_into(&mut out, &text[at..m.start]);
+        out.push_str("<mark>");
+        escape_into(&mut out, &text[m.clone()]);
+        out.push_str("</mark>");
+        at = m.end;
+    }
+    escape_into(&mut out, &text[at..range.end]);
+    if !text[range.end..].trim().is_empty() {
+        out.push('…');
+    }
+    out
+}
+
+fn escape_into(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::embedded::analyzer::DEFAULT;
+
+    fn cut(text: &str, terms: &[&str], opts: Highlight) -> Vec<String> {
+        let matched = terms.iter().map(|t| ("text", *t)).collect();
+        snippets(&DEFAULT, &[("text", text)], &matched, &opts)
+            .into_iter()
+            .map(|s| s.fragment)
+            .collect()
+    }
+
+    #[test]
+    fn short_text_is_one_marked_fragment() {
+        assert_eq!(
+            cut("Rust & <b>redb</b>: rust", &["rust"], Highlight::default()),
+            ["<mark>Rust</mark> &amp; &lt;b&gt;redb&lt;/b&gt;: <mark>rust</mark>"]
+        );
+    }
+
+    #[test]
+    fn prefers_the_fragment_with_more_distinct_terms() {
+        let filler = "lorem ipsum dolor sit amet ".repeat(8);
+        let text = format!("{filler}harbour {filler}harbour crane {filler}");
+        let opts = Highlight {
+            fragments: 1,
+            fragment_len: 40,
+        };
+        let got = cut(&text, &["harbour", "crane"], opts);
+        assert_eq!(got.len(), 1);
+        assert!(
+            got[0].contains("<mark>harbour</mark> <mark>crane</mark>"),
+            "{got:?}"
+        );
+        assert!(got[0].starts_with('…') && got[0].ends_with('…'), "{got:?}");
+        // The cap counts characters of the text, not markup or ellipses.
+        let plain = got[0].replace("<mark>", "").replace("</mark>", "");
+        assert!(plain.trim_matches('…').chars().count() <= 40, "{got:?}");
+    }
+
+    #[test]
+    fn caps_fragments_and_never_overlaps_them() {
+        let text = "alpha beta gamma ".repeat(30);
+        let opts = Highlight {
+            fragments: 2,
+            fragment_len: 30,
+        };
+        let got = cut(&text, &["beta"], opts);
+        assert_eq!(got.len(), 2);
+        assert!(
+            got.iter().all(|f| f.contains("<mark>beta</mark>")),
+            "{got:?}"
+        );
+        assert!(cut(&text, &["delta"], opts).is_empty());
+    }
+
+    #[test]
+    fn marks_stemmed_and_field_scoped_matches() {
+        let a = Analyzer::parse("stem-english").unwrap();
+        let matched = [("title", "contract")].into_iter().collect();
+        let got = snippets(
+            &a,
+            &[("text", "contracts signed"), ("title", "Contracts")],
+            &matched,
+            &Highlight::default(),
+        );
+        assert_eq!(
+            got,
+            [Snippet {
+                field: "title".into(),
+                fragment: "<mark>Contracts</mark>".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Per-tenant counters for the embedded backend.
+//!
+//! | Table            | Key                 | Value   |
+//! | ---------------- | ------------------- | ------- |
+//! | `ucfp/stats/v1`  | `(tenant, counter)` | `u64`   |
+//!
+//! Counters are `records`, `vectors`, `fingerprint_bytes`,
+//! `metadata_bytes`, `vector_bytes`, and one `modality/<name>`,
+//! `algorithm/<tag>` and `model/<id>` per value seen. Every write
+//! transaction that adds, replaces or removes catalog rows collects the
+//! difference in a [`Delta`] and applies it before committing, so the
+//! counters move with the catalog and reading them is a short range scan.
+//! Counters that reach zero are removed.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use redb::{ReadTransaction, ReadableTable, TableDefinition, WriteTransaction};
+
+use super::bm25::{self, BM25_CORPUS, BM25_TERM_FST, CorpusStats};
+use super::{CATALOG, CatalogEntry};
+use crate::core::{AnnStatus, Bm25Stats, TenantStats};
+use crate::error::{Error, Result};
+
+pub(super) const STATS: TableDefinition<'_, (u32, &str), u64> =
+    TableDefinition::new("ucfp/stats/v1");
+
+type CounterKey = (u32, &'static str);
+
+/// Key range of every counter of `tenant_id`.
+fn tenant_range(tenant_id: u32) -> (Bound<CounterKey>, Bound<CounterKey>) {
+    let end = match tenant_id.checked_add(1) {
+        Some(next) => Bound::Excluded((next, "")),
+        None => Bound::Unbounded,
+    };
+    (Bound::Included((tenant_id, "")), end)
+}
+
+/// What one catalog row adds to its tenant's counters.
+fn contribution(entry: &CatalogEntry) -> Vec<(String, u64)> {
+    let modality = match entry.modality {
+        0 => "audio",
+        1 => "image",
+        2 => "text",
+        _ => "unknown",
+    };
+    let mut out = vec![
+        ("records".to_string(), 1),
+        (
+            "fingerprint_bytes".to_string(),
+            u64::from(entry.fingerprint_len),
+        ),
+        ("metadata_bytes".to_string(), u64::from(entry.metadata_len)),
+        (format!("modality/{modality}"), 1),
+        (format!("algorithm/{}", entry.algorithm), 1),
+    ];
+    if entry.embedding_dim > 0 {
+        out.push(("vectors".to_string(), 1));
+        out.push((
+            "vector_bytes".to_string(),
+            u64::from(entry.embedding_dim) * 4,
+        ));
+    }
+    if let Some(model) = &entry.model_id {
+        out.push((format!("model/{model}"), 1));
+    }
+    out
+}
+
+/// Counter changes accumulated over one write transaction.
+#[derive(Default)]
+pub(super) struct Delta(BTreeMap<(u32, String), i128>);
+
+impl Delta {
+    /// Count `entry` in.
+    pub(super) fn add(&mut self, tenant_id: u32, entry: &CatalogEntry) {
+        for (name, n) in contribution(entry) {
+            *self.0.entry((tenant_id, name)).or_default() += i128::from(n);
+        }
+    }
+
+    /// Count `entry` out.
+    pub(super) fn remove(&mut self, tenant_id: u32, entry: &CatalogEntry) {
+        for (name, n) in contribution(entry) {
+            *self.0.entry((tenant_id, name)).or_default() -= i128::from(n);
+        }
+    }
+
+    /// Write the accumulated changes into `txn`.
+    pub(super) fn apply(self, txn: &WriteTransaction) -> Result<()> {
+        let mut table = txn
+            .open_table(STATS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for ((tenant_id, name), delta) in self.0 {
+            if delta == 0 {
+                continue;
+            }
+            let key = (tenant_id, name.as_str());
+            let current = table
+                .get(key)
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map_or(0, |v| v.value());
+            let next = (i128::from(current) + delta).clamp(0, i128::from(u64::MAX)) as u64;
+            if next == 0 {
+                table.remove(key).map_err(|e| Error::Index(e.to_string()))?;
+            } else {
+                table
+                    .insert(key, next)
+                    .map_err(|e| Error::Index(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The positive counters, as absolute values. Meaningful when only
+    /// [`Delta::add`] was used, i.e. when recounting from the catalog.
+    pub(super) fn into_counts(self) -> BTreeMap<(u32, String), u64> {
+        self.0
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(k, n)| (k, n as u64))
+            .collect()
+    }
+}
+
+/// Stored counters of `tenant_id`.
+pub(super) fn read(txn: &ReadTransaction, tenant_id: u32) -> Result<BTreeMap<String, u64>> {
+    let table = txn
+        .open_table(STATS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut out = BTreeMap::new();
+    for entry in table
+        .range(tenant_range(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+        out.insert(k.value().1.to_string(), v.value());
+    }
+    Ok(out)
+}
+
+/// Every tenant with at least one counter.
+pub(super) fn tenants(txn: &ReadTransaction) -> Result<Vec<u32>> {
+    let table = txn
+        .open_table(STATS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut out = Vec::new();
+    let mut next = Some(0u32);
+    while let Some(from) = next {
+        let Some(entry) = table
+            .range((from, "")..)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .next()
+        else {
+            break;
+        };
+        let tenant = entry.map_err(|e| Error::Index(e.to_string()))?.0.value().0;
+        out.push(tenant);
+        next = tenant.checked_add(1);
+    }
+    Ok(out)
+}
+
+/// The public view of `tenant_id`: counters plus BM25 corpus stats.
+pub(super) fn tenant_stats(txn: &ReadTransaction, tenant_id: u32) -> Result<TenantStats> {
+    let counters = read(txn, tenant_id)?;
+    let get = |name: &str| counters.get(name).copied().unwrap_or(0);
+    let group = |prefix: &str| -> BTreeMap<String, u64> {
+        counters
+            .iter()
+            .filter_map(|(k, v)| k.strip_prefix(prefix).map(|k| (k.to_string(), *v)))
+            .collect()
+    };
+    let corpus = txn
+        .open_table(BM25_CORPUS)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map(|v| CorpusStats::unpack(v.value()))
+        .unwrap_or_default();
+    let vocabulary = match txn
+        .open_table(BM25_TERM_FST)
+        .map_err(|e| Error::Index(e.to_string()))?
+        .get(tenant_id)
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => bm25::vocabulary_size(v.value().to_vec())?,
+        None => 0,
+    };
+    Ok(TenantStats {
+        tenant_id,
+        records: get("records"),
+        by_modality: group("modality/"),
+        by_algorithm: group("algorithm/"),
+        by_model: group("model/"),
+        fingerprint_bytes: get("fingerprint_bytes"),
+        metadata_bytes: get("metadata_bytes"),
+        vector_bytes: get("vector_bytes"),
+        bm25: Bm25Stats {
+            doc_count: corpus.doc_count,
+            avgdl: corpus.avgdl(),
+            vocabulary,
+        },
+        ann: AnnStatus {
+            kind: "brute_force".into(),
+            vectors: get("vectors"),
+        },
+    })
+}
+
+/// Migration: build the counters from the catalog.
+pub(super) fn backfill(txn: &WriteTransaction) -> Result<u64> {
+    let counts = {
+        let catalog = txn
+            .open_table(CATALOG)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let mut delta = Delta::default();
+        for entry in catalog.iter().map_err(|e| Error::Index(e.to_string()))? {
+            let (k, v) = entry.map_err(|e| Error::Index(e.to_string()))?;
+            delta.add(k.value().0, &CatalogEntry::decode(v.value())?);
+        }
+        delta.into_counts()
+    };
+    let mut table = txn
+        .open_table(STATS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    table
+        .retain(|_, _| false)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut records = 0;
+    for ((tenant_id, name), n) in &counts {
+        if name == "records" {
+            records += n;
+        }
+        table
+            .insert((*tenant_id, name.as_str()), *n)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
+    Ok(records)
+}
+
+/// Replace every counter of `tenant_id` with `counters`.
+pub(super) fn write_tenant(
+    txn: &WriteTransaction,
+    tenant_id: u32,
+    counters: &BTreeMap<String, u64>,
+) -> Result<()> {
+    drop_tenant(txn, tenant_id)?;
+    let mut table = txn
+        .open_table(STATS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    for (name, &n) in counters.iter().filter(|(_, n)| **n > 0) {
+        table
+            .insert((tenant_id, name.as_str()), n)
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Drop every counter of `tenant_id`.
+pub(super) fn drop_tenant(txn: &WriteTransaction, tenant_id: u32) -> Result<()> {
+    let mut table = txn
+        .open_table(STATS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    table
+        .retain_in(tenant_range(tenant_id), |_, _| false)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,222 @@
+//! Record history for the embedded backend (opt-in, see
+//! [`EmbeddedBackend::with_versioning`](super::EmbeddedBackend::with_versioning)).
+//!
+//! The main tables always hold the current version. When versioning is
+//! on, an upsert first copies the row it is about to replace into
+//! `versions`, in the same redb transaction:
+//!
+//! | Table                  | Key                                   | Value                      |
+//! | ---------------------- | ------------------------------------- | -------------------------- |
+//! | `ucfp/versions/v1`     | `(tenant, record_id, version)`        | snapshot (see [`encode`])  |
+//! | `ucfp/version_due/v1`  | `(superseded_at, tenant, rid, ver)`   | `()`                       |
+//!
+//! `version_due` orders history by replacement time so the age bound of
+//! [`VersionPolicy`] is one range walk; the count bound is enforced on
+//! write. Entries whose version was already dropped are skipped.
+//!
+//! A delete (or TTL expiry) archives the row it removes, then a
+//! tombstone: the same catalog entry flagged deleted, one version on,
+//! with no blobs. History so outlives the record, and a re-insert carries
+//! on numbering from the tombstone. Without versioning a delete drops
+//! whatever history the record had.
+
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+
+use super::CatalogEntry;
+use crate::core::VersionPolicy;
+use crate::error::{Error, Result};
+use crate::index::DELETE_BATCH;
+
+pub(super) const VERSIONS: TableDefinition<'_, (u32, u64, u64), &[u8]> =
+    TableDefinition::new("ucfp/versions/v1");
+
+pub(super) const VERSION_DUE: TableDefinition<'_, (u64, u32, u64, u64), ()> =
+    TableDefinition::new("ucfp/version_due/v1");
+
+pub(super) fn bootstrap_tables(txn: &WriteTransaction) -> Result<()> {
+    let _ = txn
+        .open_table(VERSIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let _ = txn
+        .open_table(VERSION_DUE)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
+
+/// Blobs of one superseded version, borrowed from a snapshot row.
+pub(super) struct Snapshot<'a> {
+    pub catalog: CatalogEntry,
+    pub fingerprint: &'a [u8],
+    pub metadata: &'a [u8],
+    pub vector: Option<&'a [u8]>,
+}
+
+/// `len ‖ catalog JSON ‖ len ‖ fingerprint ‖ len ‖ metadata ‖ vector`,
+/// lengths u32 little-endian. The vector runs to the end of the row and
+/// is absent when empty.
+pub(super) fn encode(
+    catalog: &[u8],
+    fingerprint: &[u8],
+    metadata: &[u8],
+    vector: &[u8],
+) -> Vec<u8> {
+    let mut out =
+        Vec::with_capacity(12 + catalog.len() + fingerprint.len() + metadata.len() + vector.len());
+    for part in [catalog, fingerprint, metadata] {
+        out.extend_from_slice(&(part.len() as u32).to_le_bytes());
+        out.extend_from_slice(part);
+    }
+    out.extend_from_slice(vector);
+    out
+}
+
+pub(super) fn decode(row: &[u8]) -> Result<Snapshot<'_>> {
+    fn take<'a>(row: &mut &'a [u8]) -> Result<&'a [u8]> {
+        let malformed = || Error::Index("version snapshot: truncated row".into());
+        let (len, rest) = row.split_first_chunk::<4>().ok_or_else(malformed)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(malformed());
+        }
+        let (part, rest) = rest.split_at(len);
+        *row = rest;
+        Ok(part)
+    }
+    let mut rest = row;
+    let catalog = CatalogEntry::decode(take(&mut rest)?)?;
+    let fingerprint = take(&mut rest)?;
+    let metadata = take(&mut rest)?;
+    Ok(Snapshot {
+        catalog,
+        fingerprint,
+        metadata,
+        vector: (!rest.is_empty()).then_some(rest),
+    })
+}
+
+/// Move the version being replaced into history and enforce the count
+/// bound of `policy` for this record.
+pub(super) fn archive(
+    txn: &WriteTransaction,
+    key: (u32, u64),
+    version: u64,
+    snapshot: &[u8],
+    now_ms: u64,
+    policy: &VersionPolicy,
+) -> Result<()> {
+    let (tenant_id, record_id) = key;
+    let mut versions = txn
+        .open_table(VERSIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    versions
+        .insert((tenant_id, record_id, version), snapshot)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    if policy.keep_for_ms.is_some() {
+        txn.open_table(VERSION_DUE)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .insert((now_ms, tenant_id, record_id, version), ())
+            .map_err(|e| Error::Index(e.to_string()))?;
+    }
+    if let Some(keep) = policy.keep_versions {
+        // History holds `keep - 1`; the main tables hold the latest.
+        let history = keep.saturating_sub(1);
+        let held: Vec<u64> = versions
+            .range((tenant_id, record_id, 0)..=(tenant_id, record_id, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .map(|entry| entry.map(|(k, _)| k.value().2))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for v in &held[..held.len().saturating_sub(history)] {
+            versions
+                .remove((tenant_id, record_id, *v))
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Newest version held in history for `key`, tombstones included.
+pub(super) fn last_version(txn: &WriteTransaction, key: (u32, u64)) -> Result<Option<u64>> {
+    let (tenant_id, record_id) = key;
+    let versions = txn
+        .open_table(VERSIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let last = versions
+        .range((tenant_id, record_id, 0)..=(tenant_id, record_id, u64::MAX))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .next_back()
+        .transpose()
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(last.map(|(k, _)| k.value().2))
+}
+
+/// Drop all history of one record (deleted without versioning on).
+pub(super) fn drop_history(txn: &WriteTransaction, tenant_id: u32, record_id: u64) -> Result<()> {
+    let mut versions = txn
+        .open_table(VERSIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    versions
+        .retain_in(
+            (tenant_id, record_id, 0)..=(tenant_id, record_id, u64::MAX),
+            |_, _| false,
+        )
+        .map_err(|e| Error::Index(e.to_string()))?;
+    Ok(())
+}
+
+/// Drop up to `limit` versions superseded at or before `cutoff_ms`.
+pub(super) fn prune_due(txn: &WriteTransaction, cutoff_ms: u64, limit: usize) -> Result<u64> {
+    let mut due_table = txn
+        .open_table(VERSION_DUE)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut versions = txn
+        .open_table(VERSIONS)
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let due: Vec<(u64, u32, u64, u64)> = due_table
+        .range(..=(cutoff_ms, u32::MAX, u64::MAX, u64::MAX))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .take(limit)
+        .map(|entry| entry.map(|(k, _)| k.value()))
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| Error::Index(e.to_string()))?;
+    let mut pruned = 0;
+    for key @ (_, tenant_id, record_id, version) in due {
+        due_table
+            .remove(key)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        if versions
+            .remove((tenant_id, record_id, version))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .is_some()
+        {
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Delete up to [`DELETE_BATCH`] history rows of `tenant_id` in one
+/// transaction. Returns how many went; 0 means none are left.
+pub(super) fn drain_tenant_batch(db: &Database, tenant_id: u32) -> Result<u64> {
+    let txn = db.begin_write().map_err(|e| Error::Index(e.to_string()))?;
+    let n = {
+        let mut versions = txn
+            .open_table(VERSIONS)
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let keys: Vec<(u32, u64, u64)> = versions
+            .range((tenant_id, 0, 0)..=(tenant_id, u64::MAX, u64::MAX))
+            .map_err(|e| Error::Index(e.to_string()))?
+            .take(DELETE_BATCH)
+            .map(|entry| entry.map(|(k, _)| k.value()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        for key in &keys {
+            versions
+                .remove(*key)
+                .map_err(|e| Error::Index(e.to_string()))?;
+        }
+        keys.len() as u64
+    };
+    txn.commit().map_err(|e| Error::Index(e.to_string()))?;
+    Ok(n)
+}
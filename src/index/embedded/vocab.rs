@@ -0,0 +1,437 @@
+//! Term dictionary lookups beyond exact matches.
+//!
+//! A query term written `term*` expands to the dictionary terms starting
+//! with `term`, and `term~n` (`n` of 1 or 2, `~` alone meaning 2) to those
+//! within `n` edits of it, both by running an automaton over the tenant's
+//! `fst::Map`. At most [`MAX_EXPANSIONS`] terms are kept per query term,
+//! in a bounded heap while the dictionary streams past: the closest fuzzy
+//! matches, or the shortest completions of a prefix, among the terms that
+//! still have postings.
+//! Each is searched as its own term, its contribution scaled down by
+//! [`EXPANSION_WEIGHT`] per edit, or once for a longer completion, so an
+//! exact match still outranks a near miss.
+//!
+//! The same lookups serve term suggestions: [`complete`] ranks the
+//! completions of a prefix by document frequency for type-ahead (only
+//! the first [`MAX_COMPLETION_SCAN`] in key order, so the ranking is
+//! approximate for very short prefixes), and
+//! [`corrections`] offers the closest terms to each token of a query
+//! that found nothing. Document frequency comes from the caller, so both
+//! backends share the ranking. Like expanded query terms, the tokens
+//! looked up are split by the tenant's analyzer but not stemmed.
+//!
+//! Each lookup stays inside one field of the dictionary: a named field's
+//! terms are keyed `field␟term` (see [`super::bm25::field_key`]), and
+//! suggestions only ever come from the record text.
+
+use std::collections::BinaryHeap;
+use std::ops::ControlFlow;
+
+use fst::automaton::{Levenshtein, Str};
+use fst::{Automaton, IntoStreamer, Map as FstMap, Streamer};
+
+use super::analyzer::Analyzer;
+use super::bm25::{FIELD_SEP, TEXT_FIELD};
+use crate::core::{Correction, TermSuggestion};
+use crate::error::{Error, Result};
+
+/// Dictionary terms kept per expanded query term.
+pub(crate) const MAX_EXPANSIONS: usize = 50;
+
+/// Weight of an expansion per edit away from the query term.
+pub(crate) const EXPANSION_WEIGHT: f32 = 0.75;
+
+/// Largest edit distance a fuzzy term may ask for.
+pub(crate) const MAX_FUZZY_DISTANCE: u32 = 2;
+
+/// Dictionary keys [`complete`] reads at most, in key order, so a one
+/// letter prefix neither walks the whole dictionary nor reads the doc
+/// frequency of every term.
+pub(crate) const MAX_COMPLETION_SCAN: usize = 10_000;
+
+/// How a query term expands over the dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Expansion {
+    /// `term*`: every term starting with it.
+    Prefix,
+    /// `term~n`: every term within `n` edits of it.
+    Fuzzy(u32),
+}
+
+/// A dictionary term an expanded query term matched.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Expanded {
+    pub term: String,
+    pub term_id: u64,
+    /// Multiplier on the term's contribution.
+    pub weight: f32,
+}
+
+/// The terms of `field` that `term` expands to under `how`, best first,
+/// skipping those `live` says have no postings left. [`Error::Modality`]
+/// when the fuzzy automaton for `term` would be too large to build.
+pub(crate) fn expand(
+    dict: &FstMap<Vec<u8>>,
+    field: &str,
+    term: &str,
+    how: Expansion,
+    mut live: impl FnMut(u64) -> Result<bool>,
+) -> Result<Vec<Expanded>> {
+    // (edits, length, term, id), worst on top: popping it keeps the best.
+    let mut best: BinaryHeap<(u32, usize, String, u64)> =
+        BinaryHeap::with_capacity(MAX_EXPANSIONS + 1);
+    let mut offer = |edits: u32, t: &str, tid: u64| -> Result<ControlFlow<()>> {
+        let len = t.chars().count();
+        let beaten = best.len() == MAX_EXPANSIONS
+            && best
+                .peek()
+                .is_some_and(|w| (edits, len, t) >= (w.0, w.1, w.2.as_str()));
+        if !beaten && live(tid)? {
+            best.push((edits, len, t.to_string(), tid));
+            if best.len() > MAX_EXPANSIONS {
+                best.pop();
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    };
+    match how {
+        Expansion::Prefix => {
+            let plen = term.chars().count();
+            collect(dict, field, Str::new(term).starts_with(), |t, tid| {
+                offer(u32::from(t.chars().count() > plen), t, tid)
+            })?;
+        }
+        Expansion::Fuzzy(distance) => {
+            let automaton = Levenshtein::new(term, distance)
+                .map_err(|e| Error::Modality(format!("`{term}~{distance}`: {e}")))?;
+            collect(dict, field, automaton, |t, tid| {
+                offer(edit_distance(term, t), t, tid)
+            })?;
+        }
+    }
+    Ok(best
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(edits, _, term, term_id)| Expanded {
+            term,
+            term_id,
+            weight: EXPANSION_WEIGHT.powi(edits as i32),
+        })
+        .collect())
+}
+
+/// Up to `limit` terms completing the last token of `prefix`, most
+/// documents first. Terms whose postings are all gone are skipped.
+///
+/// Only the first [`MAX_COMPLETION_SCAN`] completions in key order are
+/// ranked: past that, a frequent term sorting late is missed, so the
+/// ranking is approximate for prefixes with more completions.
+pub(crate) fn complete(
+    dict: &FstMap<Vec<u8>>,
+    prefix: &str,
+    limit: usize,
+    analyzer: &Analyzer,
+    mut doc_freq: impl FnMut(u64) -> Result<u64>,
+) -> Result<Vec<TermSuggestion>> {
+    let Some(prefix) = analyzer.split(prefix).pop() else {
+        return Ok(Vec::new());
+    };
+    let mut found = Vec::new();
+    collect(
+        dict,
+        TEXT_FIELD,
+        Str::new(&prefix).starts_with(),
+        |t, tid| {
+            found.push((t.to_string(), tid));
+            if found.len() == MAX_COMPLETION_SCAN {
+                return Ok(ControlFlow::Break(()));
+            }
+            Ok(ControlFlow::Continue(()))
+        },
+    )?;
+    let mut out = Vec::with_capacity(found.len());
+    for (term, tid) in found {
+        let doc_freq = doc_freq(tid)?;
+        if doc_freq > 0 {
+            out.push(TermSuggestion {
+                term,
+                doc_freq,
+                distance: 0,
+            });
+        }
+    }
+    out.sort_by(|a, b| b.doc_freq.cmp(&a.doc_freq).then(a.term.cmp(&b.term)));
+    out.truncate(limit);
+    Ok(out)
+}
+
+/// For each token of `text`, up to `limit` terms within
+/// [`MAX_FUZZY_DISTANCE`] edits, closest then most documents first. A
+/// token too long for a two-edit automaton is matched within one.
+pub(crate) fn corrections(
+    dict: &FstMap<Vec<u8>>,
+    text: &str,
+    limit: usize,
+    analyzer: &Analyzer,
+    mut doc_freq: impl FnMut(u64) -> Result<u64>,
+) -> Result<Vec<Correction>> {
+    let mut out = Vec::new();
+    for token in analyzer.split(text) {
+        let automaton = Levenshtein::new(&token, MAX_FUZZY_DISTANCE)
+            .or_else(|_| Levenshtein::new(&token, 1))
+            .map_err(|e| Error::Modality(format!("`{token}`: {e}")))?;
+        let mut found = Vec::new();
+        collect(dict, TEXT_FIELD, automaton, |t, tid| {
+            found.push((t.to_string(), tid));
+            Ok(ControlFlow::Continue(()))
+        })?;
+        let mut suggestions = Vec::with_capacity(found.len());
+        for (term, tid) in found {
+            let doc_freq = doc_freq(tid)?;
+            if doc_freq > 0 {
+                suggestions.push(TermSuggestion {
+                    distance: edit_distance(&token, &term),
+                    term,
+                    doc_freq,
+                });
+            }
+        }
+        suggestions.sort_by(|a, b| {
+            (a.distance, b.doc_freq, &a.term).cmp(&(b.distance, a.doc_freq, &b.term))
+        });
+        suggestions.truncate(limit);
+        out.push(Correction { token, suggestions });
+    }
+    Ok(out)
+}
+
+/// Call `f` with each term of `field` that `automaton` matches, and its
+/// id, in key order until `f` breaks.
+fn collect<A: Automaton>(
+    dict: &FstMap<Vec<u8>>,
+    field: &str,
+    automaton: A,
+    mut f: impl FnMut(&str, u64) -> Result<ControlFlow<()>>,
+) -> Result<()> {
+    let prefix = match field {
+        TEXT_FIELD => String::new(),
+        _ => format!("{field}{FIELD_SEP}"),
+    };
+    let automaton = Prefixed {
+        prefix: prefix.as_bytes(),
+        inner: automaton,
+    };
+    let mut stream = dict.search(automaton).into_stream();
+    while let Some((key, tid)) = stream.next() {
+        // Keys are written from `String`s, so always UTF-8.
+        if let Ok(term) = std::str::from_utf8(&key[prefix.len()..])
+            && f(term, tid)?.is_break()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Matches `prefix` followed by whatever `inner` matches, provided that
+/// holds no [`FIELD_SEP`]: the text field's automaton never walks into
+/// the named fields' keys.
+struct Prefixed<'a, A> {
+    prefix: &'a [u8],
+    inner: A,
+}
+
+#[derive(Clone)]
+enum PrefixedState<S> {
+    /// This many bytes of the prefix read.
+    Prefix(usize),
+    Inner(S),
+    Dead,
+}
+
+impl<A: Automaton> Automaton for Prefixed<'_, A> {
+    type State = PrefixedState<A::State>;
+
+    fn start(&self) -> Self::State {
+        match self.prefix {
+            [] => PrefixedState::Inner(self.inner.start()),
+            _ => PrefixedState::Prefix(0),
+        }
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        matches!(state, PrefixedState::Inner(s) if self.inner.is_match(s))
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        match state {
+            PrefixedState::Prefix(_) => true,
+            PrefixedState::Inner(s) => self.inner.can_match(s),
+            PrefixedState::Dead => false,
+        }
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        match state {
+            PrefixedState::Prefix(at) if self.prefix[*at] == byte => {
+                if at + 1 == self.prefix.len() {
+                    PrefixedState::Inner(self.inner.start())
+                } else {
+                    PrefixedState::Prefix(at + 1)
+                }
+            }
+            PrefixedState::Inner(_) if byte == FIELD_SEP as u8 => PrefixedState::Dead,
+            PrefixedState::Inner(s) => PrefixedState::Inner(self.inner.accept(s, byte)),
+            _ => PrefixedState::Dead,
+        }
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, counted in chars.
+pub(crate) fn edit_distance(a: &str, b: &str) -> u32 {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<u32> = (0..=b.len() as u32).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i as u32 + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let next = (diag + u32::from(ca != cb))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::embedded::analyzer::DEFAULT;
+    use std::collections::BTreeMap;
+
+    fn dict(terms: &[&str]) -> FstMap<Vec<u8>> {
+        let sorted: BTreeMap<&str, u64> = terms.iter().copied().zip(0..).collect();
+        FstMap::from_iter(sorted).unwrap()
+    }
+
+    fn names(found: &[Expanded]) -> Vec<&str> {
+        found.iter().map(|e| e.term.as_str()).collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_chars() {
+        assert_eq!(edit_distance("colour", "color"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn expands_prefixes_shortest_first() {
+        let d = dict(&["contract", "contractor", "contracts", "con", "cotton"]);
+        let found = expand(&d, TEXT_FIELD, "contract", Expansion::Prefix, |_| Ok(true)).unwrap();
+        assert_eq!(names(&found), vec!["contract", "contracts", "contractor"]);
+        assert_eq!(found[0].weight, 1.0);
+        assert_eq!(found[1].weight, EXPANSION_WEIGHT);
+    }
+
+    #[test]
+    fn expands_within_one_field() {
+        let d = dict(&[
+            "contract",
+            "title\u{1f}contract",
+            "title\u{1f}contracts",
+            "title\u{1f}cotton",
+            "zzz\u{1f}contractor",
+        ]);
+        let found = expand(&d, "title", "contract", Expansion::Prefix, |_| Ok(true)).unwrap();
+        assert_eq!(names(&found), vec!["contract", "contracts"]);
+        let found = expand(&d, "title", "coton", Expansion::Fuzzy(1), |_| Ok(true)).unwrap();
+        assert_eq!(names(&found), vec!["cotton"]);
+        let found = expand(&d, TEXT_FIELD, "contract", Expansion::Prefix, |_| Ok(true)).unwrap();
+        assert_eq!(
+            names(&found),
+            vec!["contract"],
+            "field terms stay out of text"
+        );
+    }
+
+    #[test]
+    fn expansions_cap_after_skipping_dead_terms() {
+        let mut terms: Vec<String> = (0..100).map(|i| format!("ab{i:02}")).collect();
+        terms.push("ab".into());
+        let d = dict(&terms.iter().map(String::as_str).collect::<Vec<_>>());
+        // "ab00".."ab09" (ids 0..10) have no postings left.
+        let found = expand(&d, TEXT_FIELD, "ab", Expansion::Prefix, |tid| Ok(tid >= 10)).unwrap();
+        assert_eq!(found.len(), MAX_EXPANSIONS);
+        assert_eq!(found[0].term, "ab");
+        assert_eq!(found[1].term, "ab10");
+        assert_eq!(found[MAX_EXPANSIONS - 1].term, "ab58");
+    }
+
+    #[test]
+    fn completes_by_doc_freq() {
+        let d = dict(&["contract", "contractor", "contracts", "cotton"]);
+        let freq = |tid| Ok([5, 9, 5, 40][tid as usize]);
+        let found = complete(&d, "breach of Contr", 10, &DEFAULT, freq).unwrap();
+        let terms: Vec<(&str, u64)> = found
+            .iter()
+            .map(|s| (s.term.as_str(), s.doc_freq))
+            .collect();
+        assert_eq!(
+            terms,
+            vec![("contractor", 9), ("contract", 5), ("contracts", 5)]
+        );
+        assert_eq!(complete(&d, "contr", 1, &DEFAULT, freq).unwrap().len(), 1);
+        assert!(complete(&d, "  ", 10, &DEFAULT, freq).unwrap().is_empty());
+        // Only the first MAX_COMPLETION_SCAN keys are ranked.
+        let many: Vec<String> = (0..MAX_COMPLETION_SCAN + 1)
+            .map(|i| format!("p{i:06}"))
+            .collect();
+        let d_many = dict(&many.iter().map(String::as_str).collect::<Vec<_>>());
+        let last = MAX_COMPLETION_SCAN as u64;
+        let found = complete(&d_many, "p", 1, &DEFAULT, |tid| {
+            Ok(if tid == last { 1000 } else { 1 })
+        })
+        .unwrap();
+        assert_eq!(found[0].term, "p000000", "the term past the scan is unseen");
+        // A term whose postings are gone is not offered.
+        let gone = |tid| Ok(u64::from(tid != 1));
+        assert_eq!(
+            complete(&d, "contractor", 10, &DEFAULT, gone).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn corrects_each_token() {
+        let d = dict(&["color", "colour", "colors", "signed"]);
+        let freq = |tid| Ok([7, 3, 2, 1][tid as usize]);
+        let found = corrections(&d, "Colr signed", 10, &DEFAULT, freq).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].token, "colr");
+        let terms: Vec<(&str, u32)> = found[0]
+            .suggestions
+            .iter()
+            .map(|s| (s.term.as_str(), s.distance))
+            .collect();
+        assert_eq!(terms, vec![("color", 1), ("colour", 2), ("colors", 2)]);
+        assert_eq!(found[1].suggestions[0].term, "signed");
+        assert_eq!(found[1].suggestions[0].distance, 0);
+    }
+
+    #[test]
+    fn expands_fuzzy_terms_closest_first() {
+        let d = dict(&["colour", "color", "colours", "collar", "dolor", "cooler"]);
+        let found = expand(&d, TEXT_FIELD, "colour", Expansion::Fuzzy(1), |_| Ok(true)).unwrap();
+        assert_eq!(names(&found), vec!["colour", "color", "colours"]);
+        let found = expand(&d, TEXT_FIELD, "colour", Expansion::Fuzzy(2), |_| Ok(true)).unwrap();
+        assert_eq!(
+            names(&found),
+            vec!["colour", "color", "colours", "dolor", "collar"]
+        );
+        assert_eq!(found[4].weight, EXPANSION_WEIGHT * EXPANSION_WEIGHT);
+    }
+}
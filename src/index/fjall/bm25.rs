@@ -0,0 +1,500 @@
+//! BM25 inverted index for the fjall backend.
+//!
+//! Same scoring as the embedded backend's BM25 (see
+//! `index::embedded::bm25`), and the same block-encoded posting lists
+//! (`index::embedded::postings`): [`Blocks`] adapts a partition to the
+//! block code, so writes rewrite only the blocks they touch. Queries
+//! still score every posting of every query term. One partition per
+//! table, keys encoded as big-endian `tenant_id ‖ id` so a tenant's rows
+//! are contiguous:
+//!
+//! | Partition                  | Key                          | Value                                |
+//! | -------------------------- | ---------------------------- | ------------------------------------ |
+//! | `ucfp.bm25.term_fst.v1`    | `tenant`                     | serialized `fst::Map<term, term_id>` |
+//! | `ucfp.bm25.blocks.v1`      | `tenant ‖ term_id ‖ last doc`| one posting block                    |
+//! | `ucfp.bm25.doc_lens.v1`    | `tenant ‖ doc_id`            | `u32` le term count                  |
+//! | `ucfp.bm25.corpus.v1`      | `tenant`                     | `CorpusStats`                        |
+//! | `ucfp.bm25.doc_terms.v1`   | `tenant ‖ doc_id`            | packed term ids                      |
+//!
+//! Tokenizer, IDF, the per-term contribution and the expansion of
+//! `term*` / `term~n` query terms are the embedded module's functions, so
+//! a corpus scores identically on either engine.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+use ::fjall::{KvPair, ReadTransaction, Slice, TxKeyspace, TxPartitionHandle, WriteTransaction};
+use fst::Map as FstMap;
+
+use super::{Expired, open_partition, pair_key, tenant_key};
+use crate::core::{Correction, Hit, TermHit, TermSuggestion};
+use crate::error::{Error, Result};
+use crate::index::embedded::bm25::{
+    Clause, CorpusStats, DocTerms, IndexBatch, TEXT_FIELD, TermDictCache, all_term_ids,
+    collect_hits, idf, merge_term_dict, pack_term_ids, parse_query, term_id, term_score,
+    unpack_term_ids,
+};
+use crate::index::embedded::postings::{
+    self, BlockHeader, BlockKey, BlockRead, BlockWrite, Posting, TermCursor,
+};
+use crate::index::embedded::{analyzer, vocab};
+
+// ── Posting blocks ──────────────────────────────────────────────────────
+
+/// `(tenant, term_id, last doc)` as a 20-byte big-endian key, so rows
+/// sort like the embedded backend's tuple keys.
+fn block_key((tenant_id, term_id, last): BlockKey) -> [u8; 20] {
+    let mut k = [0u8; 20];
+    k[..12].copy_from_slice(&pair_key(tenant_id, term_id));
+    k[12..].copy_from_slice(&last.to_be_bytes());
+    k
+}
+
+/// The last doc of a [`block_key`].
+fn block_last(k: &[u8]) -> Result<u64> {
+    k.get(12..20)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or_else(|| Error::Index("malformed posting block key".into()))
+}
+
+/// The reads [`Blocks`] needs, from a snapshot or a write transaction
+/// (read-your-own-writes).
+trait TxRead {
+    fn block(&self, part: &TxPartitionHandle, key: [u8; 20]) -> Result<Option<Slice>>;
+
+    fn block_range<'a>(
+        &'a self,
+        part: &'a TxPartitionHandle,
+        keys: std::ops::RangeInclusive<[u8; 20]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = ::fjall::Result<KvPair>> + 'a>;
+}
+
+impl TxRead for &ReadTransaction {
+    fn block(&self, part: &TxPartitionHandle, key: [u8; 20]) -> Result<Option<Slice>> {
+        ReadTransaction::get(self, part, key).map_err(|e| Error::Index(e.to_string()))
+    }
+
+    fn block_range<'a>(
+        &'a self,
+        part: &'a TxPartitionHandle,
+        keys: std::ops::RangeInclusive<[u8; 20]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = ::fjall::Result<KvPair>> + 'a> {
+        Box::new(ReadTransaction::range(self, part, keys))
+    }
+}
+
+impl TxRead for &mut WriteTransaction<'_> {
+    fn block(&self, part: &TxPartitionHandle, key: [u8; 20]) -> Result<Option<Slice>> {
+        WriteTransaction::get(self, part, key).map_err(|e| Error::Index(e.to_string()))
+    }
+
+    fn block_range<'a>(
+        &'a self,
+        part: &'a TxPartitionHandle,
+        keys: std::ops::RangeInclusive<[u8; 20]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = ::fjall::Result<KvPair>> + 'a> {
+        Box::new(WriteTransaction::range(self, part, keys))
+    }
+}
+
+/// The `ucfp.bm25.blocks.v1` partition seen through `tx` as
+/// [`postings`] block rows.
+struct Blocks<'p, T> {
+    tx: T,
+    part: &'p TxPartitionHandle,
+}
+
+impl<T: TxRead> Blocks<'_, T> {
+    /// Rows of `term_id` from the block keyed `last` on, in doc order.
+    fn rows(
+        &self,
+        tenant_id: u32,
+        term_id: u64,
+        last: u64,
+    ) -> Box<dyn DoubleEndedIterator<Item = ::fjall::Result<KvPair>> + '_> {
+        self.tx.block_range(
+            self.part,
+            block_key((tenant_id, term_id, last))..=block_key((tenant_id, term_id, u64::MAX)),
+        )
+    }
+}
+
+impl<T: TxRead> BlockRead for Blocks<'_, T> {
+    fn headers(&self, tenant_id: u32, term_id: u64) -> Result<Vec<(u64, BlockHeader)>> {
+        self.rows(tenant_id, term_id, 0)
+            .map(|kv| {
+                let (k, v) = kv.map_err(|e| Error::Index(e.to_string()))?;
+                Ok((block_last(&k)?, postings::header(&v)?))
+            })
+            .collect()
+    }
+
+    fn locate(&self, tenant_id: u32, term_id: u64, doc: u64) -> Result<Option<u64>> {
+        let entry = match self.rows(tenant_id, term_id, doc).next() {
+            Some(entry) => Some(entry),
+            None => self.rows(tenant_id, term_id, 0).next_back(),
+        };
+        match entry {
+            Some(kv) => Ok(Some(block_last(
+                &kv.map_err(|e| Error::Index(e.to_string()))?.0,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn entries(&self, tenant_id: u32, term_id: u64, last: u64) -> Result<Option<Vec<Posting>>> {
+        self.tx
+            .block(self.part, block_key((tenant_id, term_id, last)))?
+            .map(|v| postings::decode(&v))
+            .transpose()
+    }
+}
+
+impl BlockWrite for Blocks<'_, &mut WriteTransaction<'_>> {
+    fn put(&mut self, key: BlockKey, block: &[u8]) -> Result<()> {
+        self.tx.insert(self.part, block_key(key), block);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: BlockKey) -> Result<()> {
+        self.tx.remove(self.part, block_key(key));
+        Ok(())
+    }
+}
+
+/// BM25 partition handles.
+#[derive(Clone)]
+pub(super) struct Bm25Tables {
+    term_fst: TxPartitionHandle,
+    blocks: TxPartitionHandle,
+    doc_lens: TxPartitionHandle,
+    corpus: TxPartitionHandle,
+    doc_terms: TxPartitionHandle,
+    dicts: Arc<TermDictCache>,
+}
+
+impl Bm25Tables {
+    pub(super) fn open(keyspace: &TxKeyspace) -> Result<Self> {
+        Ok(Self {
+            term_fst: open_partition(keyspace, "ucfp.bm25.term_fst.v1")?,
+            blocks: open_partition(keyspace, "ucfp.bm25.blocks.v1")?,
+            doc_lens: open_partition(keyspace, "ucfp.bm25.doc_lens.v1")?,
+            corpus: open_partition(keyspace, "ucfp.bm25.corpus.v1")?,
+            doc_terms: open_partition(keyspace, "ucfp.bm25.doc_terms.v1")?,
+            dicts: Arc::default(),
+        })
+    }
+}
+
+impl Bm25Tables {
+    /// Partitions keyed `tenant ‖ id` — drained by prefix on purge.
+    pub(super) fn per_id(&self) -> [&TxPartitionHandle; 3] {
+        [&self.blocks, &self.doc_lens, &self.doc_terms]
+    }
+}
+
+/// Remove a tenant's single-row BM25 state (term FST + corpus stats).
+pub(super) fn drop_tenant_heads(tx: &mut WriteTransaction<'_>, t: &Bm25Tables, tenant_id: u32) {
+    tx.remove(&t.term_fst, tenant_key(tenant_id));
+    tx.remove(&t.corpus, tenant_key(tenant_id));
+}
+
+// ── Read / write helpers (inside a write txn, read-your-own-writes) ─────
+
+fn read_corpus(tx: &WriteTransaction<'_>, t: &Bm25Tables, tenant_id: u32) -> Result<CorpusStats> {
+    Ok(tx
+        .get(&t.corpus, tenant_key(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+        .map(|v| CorpusStats::unpack(&v))
+        .unwrap_or_default())
+}
+
+// ── Batched update ──────────────────────────────────────────────────────
+
+/// Write the documents queued in `batch` inside an in-flight write txn:
+/// every touched term's rows once, the dictionary at most once per
+/// tenant.
+pub(super) fn apply(
+    tx: &mut WriteTransaction<'_>,
+    t: &Bm25Tables,
+    batch: IndexBatch,
+) -> Result<()> {
+    for (tenant_id, docs) in batch.into_tenants() {
+        apply_tenant(tx, t, tenant_id, docs)?;
+    }
+    Ok(())
+}
+
+fn apply_tenant(
+    tx: &mut WriteTransaction<'_>,
+    t: &Bm25Tables,
+    tenant_id: u32,
+    docs: BTreeMap<u64, Option<DocTerms>>,
+) -> Result<()> {
+    let dict = match tx
+        .get(&t.term_fst, tenant_key(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => Some(t.dicts.get(tenant_id, &v)?),
+        None => None,
+    };
+    let mut corpus = read_corpus(tx, t, tenant_id)?;
+    let mut corpus_changed = false;
+    let mut new_terms: BTreeMap<String, u64> = BTreeMap::new();
+    let mut removals: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+    let mut additions: BTreeMap<u64, Vec<Posting>> = BTreeMap::new();
+    for (doc, next) in docs {
+        let key = pair_key(tenant_id, doc);
+        let prev_len = tx
+            .get(&t.doc_lens, key)
+            .map_err(|e| Error::Index(e.to_string()))?
+            .and_then(|v| <[u8; 4]>::try_from(&*v).ok())
+            .map(u32::from_le_bytes);
+        if let Some(prev_len) = prev_len {
+            let tids = match tx
+                .get(&t.doc_terms, key)
+                .map_err(|e| Error::Index(e.to_string()))?
+            {
+                Some(v) => unpack_term_ids(&v),
+                None => dict.as_deref().map_or_else(Vec::new, all_term_ids),
+            };
+            for tid in tids {
+                removals.entry(tid).or_default().insert(doc);
+            }
+            corpus.doc_count = corpus.doc_count.saturating_sub(1);
+            corpus.total_doc_len = corpus.total_doc_len.saturating_sub(u64::from(prev_len));
+            corpus_changed = true;
+        }
+        let Some(next) = next else {
+            if prev_len.is_some() {
+                tx.remove(&t.doc_terms, key);
+                tx.remove(&t.doc_lens, key);
+            }
+            continue;
+        };
+        let mut tids = Vec::with_capacity(next.tf.len());
+        for (term, tf) in next.tf {
+            let tid = term_id(dict.as_deref(), &mut new_terms, &mut corpus, term)?;
+            tids.push(tid);
+            additions.entry(tid).or_default().push(Posting {
+                doc,
+                tf,
+                dl: next.len,
+            });
+        }
+        tx.insert(&t.doc_terms, key, pack_term_ids(&tids));
+        tx.insert(&t.doc_lens, key, next.len.to_le_bytes());
+        corpus.doc_count = corpus.doc_count.saturating_add(1);
+        corpus.total_doc_len = corpus.total_doc_len.saturating_add(u64::from(next.len));
+        corpus_changed = true;
+    }
+
+    let touched: BTreeSet<u64> = removals.keys().chain(additions.keys()).copied().collect();
+    let mut blocks = Blocks {
+        tx: &mut *tx,
+        part: &t.blocks,
+    };
+    for tid in touched {
+        let added = additions.remove(&tid).unwrap_or_default();
+        postings::update_term(&mut blocks, tenant_id, tid, removals.get(&tid), added)?;
+    }
+
+    if !new_terms.is_empty() {
+        let merged = merge_term_dict(dict.as_deref(), &new_terms)?;
+        tx.insert(&t.term_fst, tenant_key(tenant_id), merged.as_slice());
+        t.dicts.store(tenant_id, merged)?;
+    }
+    if corpus_changed {
+        tx.insert(&t.corpus, tenant_key(tenant_id), corpus.pack());
+    }
+    Ok(())
+}
+
+// ── Query ───────────────────────────────────────────────────────────────
+
+/// BM25 top-k search inside `tenant_id` against `rtx`, skipping the
+/// expired docs in `hidden`. Expiry is looked up in score order for the
+/// docs that would make the cut only, so `k` stays filled.
+pub(super) fn search_explain(
+    rtx: &ReadTransaction,
+    t: &Bm25Tables,
+    tenant_id: u32,
+    terms: &[&str],
+    k: usize,
+    explain: bool,
+    hidden: &Expired<'_>,
+) -> Result<Vec<Hit>> {
+    if k == 0 || terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let clauses = parse_query(terms, &analyzer::DEFAULT, &BTreeSet::new())?;
+    if let Some(c) = clauses.iter().find(|c| c.is_positional()) {
+        return Err(Error::Unsupported(format!(
+            "`{c}`: phrase and NEAR/n queries are not supported on FjallBackend"
+        )));
+    }
+    let corpus = match rtx
+        .get(&t.corpus, tenant_key(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => CorpusStats::unpack(&v),
+        None => return Ok(Vec::new()),
+    };
+    if corpus.doc_count == 0 {
+        return Ok(Vec::new());
+    }
+    let avgdl = corpus.avgdl();
+    let n = corpus.doc_count as f32;
+
+    let dict_bytes = match rtx
+        .get(&t.term_fst, tenant_key(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => v.to_vec(),
+        None => return Ok(Vec::new()),
+    };
+    let fst_map = FstMap::new(dict_bytes).map_err(|e| Error::Index(format!("fst load: {e}")))?;
+    let blocks = Blocks {
+        tx: rtx,
+        part: &t.blocks,
+    };
+
+    let mut accum: HashMap<u64, f32> = HashMap::new();
+    let mut explain_hits: HashMap<u64, Vec<TermHit>> = HashMap::new();
+
+    for clause in &clauses {
+        let (matched, expanded_from) = match &clause.clause {
+            Clause::Term(term) => match fst_map.get(term.as_bytes()) {
+                Some(term_id) => (vec![(term.clone(), term_id, 1.0)], None),
+                None => continue,
+            },
+            Clause::Expand { term, how } => {
+                let live = |tid| Ok(blocks.locate(tenant_id, tid, 0)?.is_some());
+                let matched = vocab::expand(&fst_map, TEXT_FIELD, term, *how, live)?
+                    .into_iter()
+                    .map(|e| (e.term, e.term_id, e.weight))
+                    .collect();
+                (matched, Some(clause.to_string()))
+            }
+            _ => continue,
+        };
+        for (term, term_id, weight) in matched {
+            let Some(mut cursor) = TermCursor::open(&blocks, tenant_id, term_id)? else {
+                continue;
+            };
+            let idf = idf(n, cursor.doc_freq() as f32);
+
+            while let Some(Posting {
+                doc: doc_id,
+                tf,
+                dl,
+            }) = cursor.current()
+            {
+                cursor.next()?;
+                let contribution = weight * term_score(idf, tf, dl as f32, avgdl);
+                *accum.entry(doc_id).or_insert(0.0) += contribution;
+                if explain {
+                    explain_hits.entry(doc_id).or_default().push(TermHit {
+                        term: term.clone(),
+                        idf,
+                        tf,
+                        contribution,
+                        phrase: None,
+                        expanded_from: expanded_from.clone(),
+                        field: TEXT_FIELD.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut ranked: Vec<(u64, f32)> = accum.into_iter().collect();
+    ranked.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    let mut live = HashMap::with_capacity(k);
+    for (doc, score) in ranked {
+        if live.len() == k {
+            break;
+        }
+        if !hidden.contains(doc)? {
+            live.insert(doc, score);
+        }
+    }
+    Ok(collect_hits(tenant_id, live, explain_hits, k, explain))
+}
+
+// ── Suggestions ─────────────────────────────────────────────────────────
+
+/// Completions of the last token of `prefix` in `tenant_id`'s
+/// dictionary, most documents first.
+pub(super) fn suggest(
+    keyspace: &TxKeyspace,
+    t: &Bm25Tables,
+    tenant_id: u32,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<TermSuggestion>> {
+    let rtx = keyspace.read_tx();
+    let dict = read_dict(&rtx, t, tenant_id)?;
+    vocab::complete(&dict, prefix, limit, &analyzer::DEFAULT, |tid| {
+        doc_freq(&rtx, t, tenant_id, tid)
+    })
+}
+
+/// The closest terms in `tenant_id`'s dictionary to each token of `text`.
+pub(super) fn did_you_mean(
+    keyspace: &TxKeyspace,
+    t: &Bm25Tables,
+    tenant_id: u32,
+    text: &str,
+    limit: usize,
+) -> Result<Vec<Correction>> {
+    let rtx = keyspace.read_tx();
+    let dict = read_dict(&rtx, t, tenant_id)?;
+    vocab::corrections(&dict, text, limit, &analyzer::DEFAULT, |tid| {
+        doc_freq(&rtx, t, tenant_id, tid)
+    })
+}
+
+/// `tenant_id`'s term dictionary; empty when it has none.
+fn read_dict(rtx: &ReadTransaction, t: &Bm25Tables, tenant_id: u32) -> Result<FstMap<Vec<u8>>> {
+    match rtx
+        .get(&t.term_fst, tenant_key(tenant_id))
+        .map_err(|e| Error::Index(e.to_string()))?
+    {
+        Some(v) => FstMap::new(v.to_vec()).map_err(|e| Error::Index(format!("fst load: {e}"))),
+        None => Ok(FstMap::default()),
+    }
+}
+
+/// Docs holding `term_id`, from its block headers.
+fn doc_freq(rtx: &ReadTransaction, t: &Bm25Tables, tenant_id: u32, term_id: u64) -> Result<u64> {
+    let blocks = Blocks {
+        tx: rtx,
+        part: &t.blocks,
+    };
+    Ok(blocks
+        .headers(tenant_id, term_id)?
+        .iter()
+        .map(|(_, h)| u64::from(h.count))
+        .sum())
+}
+
+/// Block headers of `term` in `tenant_id`, for tests.
+#[cfg(test)]
+pub(super) fn block_headers(
+    rtx: &ReadTransaction,
+    t: &Bm25Tables,
+    tenant_id: u32,
+    term: &str,
+) -> Vec<(u64, BlockHeader)> {
+    let dict = read_dict(rtx, t, tenant_id).unwrap();
+    let term_id = dict.get(term).expect("term in dictionary");
+    Blocks {
+        tx: rtx,
+        part: &t.blocks,
+    }
+    .headers(tenant_id, term_id)
+    .unwrap()
+}
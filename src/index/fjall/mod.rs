@@ -0,0 +1,823 @@
+//! LSM `IndexBackend` impl — fjall storage + brute-force cosine k-NN.
+//!
+//! The write-throughput escape hatch from ARCHITECTURE §6: same logical
+//! tables as [`EmbeddedBackend`](crate::EmbeddedBackend), one fjall
+//! partition per table, so switching engines is a deploy-time choice
+//! rather than a data-model change.
+//!
+//! Layout:
+//! ```text
+//! ucfp.fingerprints.v1  tenant_id BE ‖ record_id BE → SDK fingerprint bytes
+//! ucfp.metadata.v1      tenant_id BE ‖ record_id BE → application metadata
+//! ucfp.vectors.v1       tenant_id BE ‖ record_id BE → f32 array (raw little-endian)
+//! ucfp.catalog.v2       tenant_id BE ‖ record_id BE → CatalogEntry JSON
+//! ucfp.expiry.v1        tenant_id BE ‖ record_id BE → expires_at BE (TTL records only)
+//! ucfp.expiry_due.v1    expires_at BE ‖ tenant_id BE ‖ record_id BE → ∅ (sweep queue)
+//! ucfp.bm25.*           see [`bm25`]
+//! ```
+//!
+//! Big-endian key prefixes keep every tenant contiguous in the LSM's
+//! sort order, so a per-tenant scan is one `prefix(tenant_id)` walk —
+//! the fjall equivalent of redb's `(tid, 0)..=(tid, u64::MAX)` range.
+//!
+//! Every upsert/delete runs in one fjall write transaction spanning all
+//! partitions, BM25 included, so the keyword index never lags the
+//! catalog. Commits go to the journal's OS buffer; [`IndexBackend::flush`]
+//! fsyncs.
+
+mod bm25;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ::fjall::{
+    Config, PartitionCreateOptions, PersistMode, ReadTransaction, TxKeyspace, TxPartitionHandle,
+    WriteTransaction,
+};
+use bytes::Bytes;
+
+use crate::core::{
+    Correction, FingerprintMeta, Hit, Progress, Record, RecordFilter, ScanPage, TermSuggestion,
+    WriteCondition, WriteOutcome,
+};
+use crate::error::{Error, Result};
+use crate::index::embedded::bm25::IndexBatch;
+use crate::index::embedded::{CatalogEntry, decode_vector, l2_norm, rank_cosine, scan_rows};
+use crate::index::{DELETE_BATCH, IndexBackend, Purging, check_conditions, decode_cursor, now_ms};
+
+// ── Keys ────────────────────────────────────────────────────────────────
+
+/// `tenant_id` as a big-endian partition-key prefix.
+#[inline]
+fn tenant_key(tenant_id: u32) -> [u8; 4] {
+    tenant_id.to_be_bytes()
+}
+
+/// `(tenant_id, id)` as a 12-byte big-endian key. Used for records and
+/// for BM25 `(tenant, term_id)` rows alike.
+#[inline]
+fn pair_key(tenant_id: u32, id: u64) -> [u8; 12] {
+    let mut k = [0u8; 12];
+    k[..4].copy_from_slice(&tenant_id.to_be_bytes());
+    k[4..].copy_from_slice(&id.to_be_bytes());
+    k
+}
+
+/// Inverse of [`pair_key`]; `None` on a malformed key.
+#[inline]
+fn split_pair_key(k: &[u8]) -> Option<(u32, u64)> {
+    if k.len() != 12 {
+        return None;
+    }
+    let tid = u32::from_be_bytes(k[..4].try_into().ok()?);
+    let id = u64::from_be_bytes(k[4..].try_into().ok()?);
+    Some((tid, id))
+}
+
+/// Sweep-queue key: deadline first so due entries are one range walk.
+#[inline]
+fn due_key(expires_at: u64, tenant_id: u32, record_id: u64) -> [u8; 20] {
+    let mut k = [0u8; 20];
+    k[..8].copy_from_slice(&expires_at.to_be_bytes());
+    k[8..].copy_from_slice(&pair_key(tenant_id, record_id));
+    k
+}
+
+// ── Schema ──────────────────────────────────────────────────────────────
+
+/// Partition handles, one per logical table. Cheap to clone (each is an
+/// `Arc` internally), so blocking closures take their own copy.
+#[derive(Clone)]
+struct Tables {
+    fingerprints: TxPartitionHandle,
+    metadata: TxPartitionHandle,
+    vectors: TxPartitionHandle,
+    catalog: TxPartitionHandle,
+    expiry: TxPartitionHandle,
+    expiry_due: TxPartitionHandle,
+    bm25: bm25::Bm25Tables,
+}
+
+fn open_partition(keyspace: &TxKeyspace, name: &str) -> Result<TxPartitionHandle> {
+    keyspace
+        .open_partition(name, PartitionCreateOptions::default())
+        .map_err(|e| Error::Index(e.to_string()))
+}
+
+/// fjall-backed backend. One keyspace directory, one partition per table.
+#[derive(Clone)]
+pub struct FjallBackend {
+    keyspace: TxKeyspace,
+    tables: Tables,
+    path: PathBuf,
+    purging: Arc<Purging>,
+}
+
+impl FjallBackend {
+    /// Open or create a fjall keyspace at directory `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::create_dir_all(&path)?;
+        let keyspace = Config::new(&path)
+            .open_transactional()
+            .map_err(|e| Error::Index(e.to_string()))?;
+        let tables = Tables {
+            fingerprints: open_partition(&keyspace, "ucfp.fingerprints.v1")?,
+            metadata: open_partition(&keyspace, "ucfp.metadata.v1")?,
+            vectors: open_partition(&keyspace, "ucfp.vectors.v1")?,
+            catalog: open_partition(&keyspace, "ucfp.catalog.v2")?,
+            expiry: open_partition(&keyspace, "ucfp.expiry.v1")?,
+            expiry_due: open_partition(&keyspace, "ucfp.expiry_due.v1")?,
+            bm25: bm25::Bm25Tables::open(&keyspace)?,
+        };
+        Ok(Self {
+            keyspace,
+            tables,
+            path,
+            purging: Arc::default(),
+        })
+    }
+
+    /// On-disk path of the keyspace directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Drain every `tenant_id`-prefixed key of `part`, one bounded txn
+    /// per batch, bumping `progress` after each commit when given.
+    async fn drain_tenant(
+        &self,
+        part: &TxPartitionHandle,
+        tenant_id: u32,
+        progress: Option<&Progress>,
+    ) -> Result<u64> {
+        let mut removed = 0u64;
+        loop {
+            let keyspace = self.keyspace.clone();
+            let part = part.clone();
+            let n = tokio::task::spawn_blocking(move || -> Result<u64> {
+                let mut tx = keyspace.write_tx();
+                let keys = tx
+                    .prefix(&part, tenant_key(tenant_id))
+                    .take(DELETE_BATCH)
+                    .map(|kv| kv.map(|(k, _)| k))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                for k in &keys {
+                    tx.remove(&part, k.clone());
+                }
+                tx.commit().map_err(|e| Error::Index(e.to_string()))?;
+                Ok(keys.len() as u64)
+            })
+            .await
+            .map_err(|e| Error::Index(format!("join error: {e}")))??;
+            if n == 0 {
+                return Ok(removed);
+            }
+            removed += n;
+            if let Some(p) = progress {
+                p.add_batch(n);
+            }
+        }
+    }
+
+    async fn bm25_inner(
+        &self,
+        tenant_id: u32,
+        terms: &[&str],
+        k: usize,
+        filter: Option<&Bytes>,
+        explain: bool,
+    ) -> Result<Vec<Hit>> {
+        if filter.is_some() {
+            return Err(Error::Unsupported(
+                "BM25 filter pre-filtering is not yet supported on FjallBackend".into(),
+            ));
+        }
+        // Same as the redb backend: no hits while a purge drains postings.
+        if self.purging.is_purging(tenant_id) {
+            return Ok(Vec::new());
+        }
+        let this = self.clone();
+        let owned_terms: Vec<String> = terms.iter().map(|s| (*s).to_string()).collect();
+        tokio::task::spawn_blocking(move || -> Result<Vec<Hit>> {
+            let term_refs: Vec<&str> = owned_terms.iter().map(String::as_str).collect();
+            let rtx = this.keyspace.read_tx();
+            let hidden = Expired::new(&rtx, &this.tables, tenant_id, now_ms());
+            bm25::search_explain(
+                &rtx,
+                &this.tables.bm25,
+                tenant_id,
+                &term_refs,
+                k,
+                explain,
+                &hidden,
+            )
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+}
+
+#[async_trait::async_trait]
+impl IndexBackend for FjallBackend {
+    async fn upsert(&self, batch: &[Record]) -> Result<()> {
+        self.upsert_if(batch, &vec![WriteCondition::Always; batch.len()])
+            .await
+            .map(drop)
+    }
+
+    /// Conditions are checked through the write transaction itself, so
+    /// they see earlier writes of the same batch. Each write stamps the
+    /// stored version plus one, as on redb; only history is not kept.
+    async fn upsert_if(
+        &self,
+        batch: &[Record],
+        conditions: &[WriteCondition],
+    ) -> Result<Vec<WriteOutcome>> {
+        check_conditions(batch, conditions)?;
+        if let Some(rec) = batch.iter().find(|r| !r.text_fields.is_empty()) {
+            return Err(Error::Unsupported(format!(
+                "record {}: text fields are not supported on FjallBackend",
+                rec.record_id
+            )));
+        }
+        let this = self.clone();
+        let batch: Vec<Record> = batch.to_vec();
+        let conditions = conditions.to_vec();
+
+        tokio::task::spawn_blocking(move || -> Result<Vec<WriteOutcome>> {
+            let t = &this.tables;
+            let now = now_ms();
+            let mut tx = this.keyspace.write_tx();
+            for tenant_id in batch.iter().map(|r| r.tenant_id).collect::<BTreeSet<_>>() {
+                this.purging.check(tenant_id)?;
+            }
+            let mut outcomes = Vec::with_capacity(batch.len());
+            for (rec, condition) in batch.iter().zip(&conditions) {
+                let key = pair_key(rec.tenant_id, rec.record_id);
+                let stored = tx
+                    .get(&t.catalog, key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                    .map(|row| CatalogEntry::decode(&row))
+                    .transpose()?;
+                let version = stored.as_ref().map_or(0, CatalogEntry::version) + 1;
+                let outcome =
+                    condition.evaluate(stored.as_ref().and_then(|e| e.condition_state(now)));
+                outcomes.push(outcome);
+                if outcome != WriteOutcome::Written {
+                    continue;
+                }
+                tx.insert(&t.fingerprints, key, rec.fingerprint.as_ref());
+                tx.insert(&t.metadata, key, rec.metadata.as_ref());
+                match rec.embedding.as_ref() {
+                    Some(v) => tx.insert(&t.vectors, key, bytemuck::cast_slice::<f32, u8>(v)),
+                    // Drop any stale vector for this key.
+                    None => tx.remove(&t.vectors, key),
+                }
+                let entry = CatalogEntry::from_record(rec).stamped(version, now);
+                tx.insert(&t.catalog, key, entry.encode()?);
+                match rec.expires_at {
+                    Some(at) => {
+                        tx.insert(&t.expiry, key, at.to_be_bytes());
+                        tx.insert(&t.expiry_due, due_key(at, rec.tenant_id, rec.record_id), []);
+                    }
+                    None => tx.remove(&t.expiry, key),
+                }
+            }
+            // BM25 update — same txn as the fingerprint write.
+            let written = batch
+                .iter()
+                .zip(&outcomes)
+                .filter(|(_, o)| **o == WriteOutcome::Written);
+            let mut index = IndexBatch::default();
+            for (rec, _) in written {
+                match rec.text.as_deref() {
+                    Some(text) => index.index(rec.tenant_id, rec.record_id, text),
+                    None => index.clear(rec.tenant_id, rec.record_id),
+                }
+            }
+            bm25::apply(&mut tx, &t.bm25, index)?;
+            tx.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(outcomes)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn delete(&self, tenant_id: u32, ids: &[u64]) -> Result<()> {
+        let this = self.clone();
+        let ids = ids.to_vec();
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut tx = this.keyspace.write_tx();
+            this.purging.check(tenant_id)?;
+            delete_rows(&mut tx, &this.tables, tenant_id, &ids)?;
+            tx.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn knn(
+        &self,
+        tenant_id: u32,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Bytes>,
+    ) -> Result<Vec<Hit>> {
+        if filter.is_some() {
+            return Err(Error::Unsupported(
+                "knn filter pre-filtering is not yet supported on FjallBackend".into(),
+            ));
+        }
+        if query.is_empty() || k == 0 || self.purging.is_purging(tenant_id) {
+            return Ok(Vec::new());
+        }
+
+        let this = self.clone();
+        let query: Vec<f32> = query.to_vec();
+
+        tokio::task::spawn_blocking(move || -> Result<Vec<Hit>> {
+            let q_norm = l2_norm(&query);
+            if q_norm == 0.0 {
+                return Ok(Vec::new());
+            }
+            let rtx = this.keyspace.read_tx();
+            let mut candidates: Vec<(u64, Vec<f32>)> = Vec::new();
+            for kv in rtx.prefix(&this.tables.vectors, tenant_key(tenant_id)) {
+                let (key, bytes) = kv.map_err(|e| Error::Index(e.to_string()))?;
+                let Some((_, rid)) = split_pair_key(&key) else {
+                    continue;
+                };
+                if bytes.len() % 4 != 0 || bytes.len() / 4 != query.len() {
+                    continue;
+                }
+                candidates.push((rid, decode_vector(&bytes)));
+            }
+
+            // Expiry is looked up for the ranked hits only, ranking twice
+            // as deep until `k` live ones are found — as the redb backend.
+            let hidden = Expired::new(&rtx, &this.tables, tenant_id, now_ms());
+            let mut depth = k;
+            loop {
+                let ranked = rank_cosine(tenant_id, &query, q_norm, &candidates, depth);
+                let exhausted = ranked.len() < depth;
+                let mut live = Vec::with_capacity(k);
+                for hit in ranked {
+                    if !hidden.contains(hit.record_id)? {
+                        live.push(hit);
+                    }
+                }
+                if live.len() >= k || exhausted {
+                    live.truncate(k);
+                    return Ok(live);
+                }
+                depth = depth.saturating_mul(2);
+            }
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn bm25(
+        &self,
+        tenant_id: u32,
+        terms: &[&str],
+        k: usize,
+        filter: Option<&Bytes>,
+    ) -> Result<Vec<Hit>> {
+        self.bm25_inner(tenant_id, terms, k, filter, false).await
+    }
+
+    async fn bm25_explain(
+        &self,
+        tenant_id: u32,
+        terms: &[&str],
+        k: usize,
+        filter: Option<&Bytes>,
+    ) -> Result<Vec<Hit>> {
+        self.bm25_inner(tenant_id, terms, k, filter, true).await
+    }
+
+    async fn suggest_terms(
+        &self,
+        tenant_id: u32,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<TermSuggestion>> {
+        let this = self.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || {
+            bm25::suggest(&this.keyspace, &this.tables.bm25, tenant_id, &prefix, limit)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn did_you_mean(
+        &self,
+        tenant_id: u32,
+        text: &str,
+        limit: usize,
+    ) -> Result<Vec<Correction>> {
+        let this = self.clone();
+        let text = text.to_string();
+        tokio::task::spawn_blocking(move || {
+            bm25::did_you_mean(&this.keyspace, &this.tables.bm25, tenant_id, &text, limit)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let keyspace = self.keyspace.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            keyspace
+                .persist(PersistMode::SyncAll)
+                .map_err(|e| Error::Index(e.to_string()))
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn get_record_metadata(&self, tenant_id: u32, record_id: u64) -> Result<FingerprintMeta> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || -> Result<FingerprintMeta> {
+            let entry = this
+                .keyspace
+                .read_tx()
+                .get(&this.tables.catalog, pair_key(tenant_id, record_id))
+                .map_err(|e| Error::Index(e.to_string()))?
+                .map(|row| CatalogEntry::decode(&row))
+                .transpose()?
+                .filter(|entry| !entry.is_expired(now_ms()))
+                .ok_or(Error::RecordNotFound {
+                    tenant_id,
+                    record_id,
+                })?;
+            entry.into_meta(tenant_id, record_id)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn scan(
+        &self,
+        tenant_id: u32,
+        cursor: Option<&str>,
+        limit: usize,
+        filter: &RecordFilter,
+    ) -> Result<ScanPage> {
+        let Some(start) = decode_cursor(cursor)? else {
+            return Ok(ScanPage::default());
+        };
+        let this = self.clone();
+        let filter = filter.clone();
+        tokio::task::spawn_blocking(move || -> Result<ScanPage> {
+            let rtx = this.keyspace.read_tx();
+            let rows = rtx
+                .range(
+                    &this.tables.catalog,
+                    pair_key(tenant_id, start)..=pair_key(tenant_id, u64::MAX),
+                )
+                .map(|kv| {
+                    let (k, v) = kv.map_err(|e| Error::Index(e.to_string()))?;
+                    let (_, rid) = split_pair_key(&k)
+                        .ok_or_else(|| Error::Index("catalog: malformed key".into()))?;
+                    Ok((rid, v.to_vec()))
+                });
+            scan_rows(tenant_id, rows, limit, &filter)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn purge_tenant(&self, tenant_id: u32, progress: &Progress) -> Result<u64> {
+        // Same order as the redb backend: writes to the tenant are fenced
+        // off and its searches answer empty, catalog goes first (counted),
+        // then the rest by prefix, and the BM25 heads last.
+        let _fence = self.purging.begin(tenant_id)?;
+        let t = &self.tables;
+        let removed = self
+            .drain_tenant(&t.catalog, tenant_id, Some(progress))
+            .await?;
+        for part in [&t.fingerprints, &t.metadata, &t.vectors, &t.expiry]
+            .into_iter()
+            .chain(t.bm25.per_id())
+        {
+            self.drain_tenant(part, tenant_id, None).await?;
+        }
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut tx = this.keyspace.write_tx();
+            bm25::drop_tenant_heads(&mut tx, &this.tables.bm25, tenant_id);
+            tx.commit().map_err(|e| Error::Index(e.to_string()))
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))??;
+        Ok(removed)
+    }
+
+    async fn sweep_expired(&self, now_ms: u64, limit: usize) -> Result<u64> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || -> Result<u64> {
+            let t = &this.tables;
+            let mut tx = this.keyspace.write_tx();
+            // Same shape as the redb sweep: pop due entries, keep the ones
+            // whose current expiry still says "dead", drop stale ones and
+            // those of a tenant being purged.
+            let mut by_tenant: BTreeMap<u32, Vec<u64>> = BTreeMap::new();
+            let mut swept = 0usize;
+            while swept < limit {
+                let want = limit - swept;
+                let due = tx
+                    .range(&t.expiry_due, ..=due_key(now_ms, u32::MAX, u64::MAX))
+                    .take(want)
+                    .map(|kv| kv.map(|(k, _)| k))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                let exhausted = due.len() < want;
+                for k in due {
+                    tx.remove(&t.expiry_due, k.clone());
+                    let Some((tenant_id, record_id)) = split_pair_key(&k[8..]) else {
+                        continue;
+                    };
+                    let current = tx
+                        .get(&t.expiry, pair_key(tenant_id, record_id))
+                        .map_err(|e| Error::Index(e.to_string()))?
+                        .and_then(|v| <[u8; 8]>::try_from(&*v).ok())
+                        .map(u64::from_be_bytes);
+                    if current.is_some_and(|at| at <= now_ms) && !this.purging.is_purging(tenant_id)
+                    {
+                        tx.remove(&t.expiry, pair_key(tenant_id, record_id));
+                        by_tenant.entry(tenant_id).or_default().push(record_id);
+                        swept += 1;
+                    }
+                }
+                if exhausted {
+                    break;
+                }
+            }
+            for (tenant_id, ids) in &by_tenant {
+                delete_rows(&mut tx, t, *tenant_id, ids)?;
+            }
+            tx.commit().map_err(|e| Error::Index(e.to_string()))?;
+            Ok(swept as u64)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+
+    async fn get_records(&self, tenant_id: u32, ids: &[u64]) -> Result<Vec<Record>> {
+        let this = self.clone();
+        let ids = ids.to_vec();
+        tokio::task::spawn_blocking(move || -> Result<Vec<Record>> {
+            let t = &this.tables;
+            let rtx = this.keyspace.read_tx();
+            let now = now_ms();
+            let mut out = Vec::with_capacity(ids.len());
+            for id in ids {
+                let key = pair_key(tenant_id, id);
+                let Some(row) = rtx
+                    .get(&t.catalog, key)
+                    .map_err(|e| Error::Index(e.to_string()))?
+                else {
+                    continue;
+                };
+                let entry = CatalogEntry::decode(&row)?;
+                if entry.is_expired(now) {
+                    continue;
+                }
+                let fp = rtx
+                    .get(&t.fingerprints, key)
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                let md = rtx
+                    .get(&t.metadata, key)
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                let vec = rtx
+                    .get(&t.vectors, key)
+                    .map_err(|e| Error::Index(e.to_string()))?;
+                out.push(entry.into_record(
+                    tenant_id,
+                    id,
+                    fp.as_deref().unwrap_or_default(),
+                    md.as_deref().unwrap_or_default(),
+                    vec.as_deref(),
+                )?);
+            }
+            Ok(out)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("join error: {e}")))?
+    }
+}
+
+/// Remove `ids` of `tenant_id` from every partition inside `tx`.
+fn delete_rows(
+    tx: &mut WriteTransaction<'_>,
+    t: &Tables,
+    tenant_id: u32,
+    ids: &[u64],
+) -> Result<()> {
+    let mut index = IndexBatch::default();
+    for id in ids {
+        let key = pair_key(tenant_id, *id);
+        tx.remove(&t.fingerprints, key);
+        tx.remove(&t.metadata, key);
+        tx.remove(&t.vectors, key);
+        tx.remove(&t.catalog, key);
+        tx.remove(&t.expiry, key);
+        index.clear(tenant_id, *id);
+    }
+    bm25::apply(tx, &t.bm25, index)
+}
+
+/// Expiry of one tenant's records as of one instant, looked up record by
+/// record in a read snapshot.
+struct Expired<'a> {
+    rtx: &'a ReadTransaction,
+    expiry: &'a TxPartitionHandle,
+    tenant_id: u32,
+    now_ms: u64,
+}
+
+impl<'a> Expired<'a> {
+    fn new(rtx: &'a ReadTransaction, t: &'a Tables, tenant_id: u32, now_ms: u64) -> Self {
+        Self {
+            rtx,
+            expiry: &t.expiry,
+            tenant_id,
+            now_ms,
+        }
+    }
+
+    /// `true` if `record_id` is past its expiry, though not yet swept.
+    fn contains(&self, record_id: u64) -> Result<bool> {
+        let Some(v) = self
+            .rtx
+            .get(self.expiry, pair_key(self.tenant_id, record_id))
+            .map_err(|e| Error::Index(e.to_string()))?
+        else {
+            return Ok(false);
+        };
+        let at = <[u8; 8]>::try_from(&*v)
+            .map(u64::from_be_bytes)
+            .map_err(|_| Error::Index("expiry: malformed value".into()))?;
+        Ok(at <= self.now_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Modality;
+    use tempfile::tempdir;
+
+    fn rec(tenant: u32, rid: u64, embedding: Option<Vec<f32>>, text: Option<&str>) -> Record {
+        Record {
+            tenant_id: tenant,
+            record_id: rid,
+            modality: Modality::Text,
+            format_version: 1,
+            algorithm: "minhash-h128".into(),
+            config_hash: 7,
+            fingerprint: Bytes::from_static(b"fp"),
+            embedding,
+            model_id: None,
+            metadata: Bytes::from_static(b"meta"),
+            text: text.map(str::to_string),
+            text_fields: BTreeMap::new(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn pair_key_round_trips_and_sorts_by_tenant() {
+        assert_eq!(split_pair_key(&pair_key(7, 42)), Some((7, 42)));
+        assert!(pair_key(1, u64::MAX) < pair_key(2, 0));
+        assert_eq!(split_pair_key(b"short"), None);
+    }
+
+    #[tokio::test]
+    async fn survives_reopen() {
+        let dir = tempdir().unwrap();
+        {
+            let be = FjallBackend::open(dir.path()).unwrap();
+            be.upsert(&[rec(1, 1, Some(vec![1.0, 0.0]), Some("hello world"))])
+                .await
+                .unwrap();
+            be.flush().await.unwrap();
+        }
+        let be = FjallBackend::open(dir.path()).unwrap();
+        let meta = be.get_record_metadata(1, 1).await.unwrap();
+        assert_eq!(meta.algorithm, "minhash-h128");
+        assert_eq!(meta.embedding_dim, Some(2));
+        let hits = be.bm25(1, &["hello"], 10, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        let hits = be.knn(1, &[1.0, 0.0], 10, None).await.unwrap();
+        assert_eq!(hits[0].record_id, 1);
+    }
+
+    #[tokio::test]
+    async fn versions_advance_for_compare_and_set() {
+        let dir = tempdir().unwrap();
+        let be = FjallBackend::open(dir.path()).unwrap();
+        be.upsert(&[rec(1, 1, None, None)]).await.unwrap();
+        be.upsert(&[rec(1, 1, None, None)]).await.unwrap();
+        let meta = be.get_record_metadata(1, 1).await.unwrap();
+        assert_eq!(meta.version, 2);
+        assert!(meta.written_at.is_some());
+
+        let stale = WriteCondition::IfVersion(1);
+        let current = WriteCondition::IfVersion(2);
+        let outcomes = be
+            .upsert_if(
+                &[rec(1, 1, None, None), rec(1, 1, None, None)],
+                &[stale, current],
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcomes, [WriteOutcome::Conflict, WriteOutcome::Written]);
+        assert_eq!(be.get_record_metadata(1, 1).await.unwrap().version, 3);
+    }
+
+    #[tokio::test]
+    async fn writes_to_a_tenant_wait_out_its_purge() {
+        let dir = tempdir().unwrap();
+        let be = FjallBackend::open(dir.path()).unwrap();
+        be.upsert(&[rec(1, 1, None, Some("red fox"))])
+            .await
+            .unwrap();
+
+        let fence = be.purging.begin(1).unwrap();
+        assert!(matches!(
+            be.upsert(&[rec(1, 2, None, Some("blue fox"))]).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert!(matches!(
+            be.delete(1, &[1]).await,
+            Err(Error::Incompatible(_))
+        ));
+        assert!(be.bm25(1, &["fox"], 10, None).await.unwrap().is_empty());
+        drop(fence);
+
+        assert_eq!(be.bm25(1, &["fox"], 10, None).await.unwrap().len(), 1);
+        assert_eq!(be.purge_tenant(1, &Progress::default()).await.unwrap(), 1);
+        be.upsert(&[rec(1, 3, None, Some("green fox"))])
+            .await
+            .unwrap();
+        let hits = be.bm25(1, &["fox"], 10, None).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.record_id).collect::<Vec<_>>(), [3]);
+    }
+
+    #[tokio::test]
+    async fn postings_span_blocks_through_updates() {
+        let dir = tempdir().unwrap();
+        let be = FjallBackend::open(dir.path()).unwrap();
+        let n = 3 * crate::index::embedded::postings::BLOCK_LEN as u64;
+        let batch: Vec<Record> = (0..n)
+            .map(|rid| {
+                rec(
+                    1,
+                    rid,
+                    None,
+                    Some(if rid == 7 { "fox fox fox" } else { "fox" }),
+                )
+            })
+            .collect();
+        be.upsert(&batch).await.unwrap();
+        be.delete(1, &[0, n / 2, n - 1]).await.unwrap();
+        be.upsert(&[rec(1, n, None, Some("fox"))]).await.unwrap();
+
+        let rtx = be.keyspace.read_tx();
+        let blocks = bm25::block_headers(&rtx, &be.tables.bm25, 1, "fox");
+        assert!(blocks.len() > 1, "{blocks:?}");
+        assert_eq!(
+            blocks.iter().map(|(_, h)| u64::from(h.count)).sum::<u64>(),
+            n - 2
+        );
+
+        let hits = be.bm25(1, &["fox"], n as usize, None).await.unwrap();
+        assert_eq!(hits.len() as u64, n - 2);
+        assert_eq!(hits[0].record_id, 7, "highest tf first");
+        assert!(
+            !hits
+                .iter()
+                .any(|h| [0, n / 2, n - 1].contains(&h.record_id))
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_conformance_suite() {
+        let dir = tempdir().unwrap();
+        let mut n = 0u32;
+        crate::index::conformance::run_all(|| {
+            n += 1;
+            FjallBackend::open(dir.path().join(format!("conformance-{n}"))).unwrap()
+        })
+        .await;
+    }
+}
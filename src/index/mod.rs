@@ -1,16 +1,35 @@
 //! Storage + ANN behind one trait.
 //!
 //! The embedded backend (redb + hnsw_rs + roaring) lives in
-//! [`embedded`]. Future backends (Qdrant, LanceDB) plug in as separate
-//! `IndexBackend` impls without touching the matcher.
+//! [`embedded`]; the LSM alternative for write-heavy deployments lives
+//! in `fjall` (feature `fjall`). Future backends (Qdrant, LanceDB) plug
+//! in as separate `IndexBackend` impls without touching the matcher.
+//!
+//! `conformance` (feature `conformance`) is the shared test battery
+//! every backend is expected to pass. [`archive`] moves a tenant between
+//! instances on top of any backend.
 
 use bytes::Bytes;
 
-use crate::core::{FingerprintMeta, Hit, Record};
+use crate::core::{
+    Bm25Params, ChangeEvent, Correction, FingerprintMeta, FsckReport, Highlight, Hit, Progress,
+    Record, RecordFilter, ScanPage, SnapshotInfo, TenantStats, TermSuggestion, VersionSpec,
+    VersionedRecord, WriteCondition, WriteOutcome,
+};
 use crate::error::{Error, Result};
 
+pub mod archive;
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
 #[cfg(feature = "embedded")]
 pub mod embedded;
+#[cfg(feature = "fjall")]
+pub mod fjall;
+#[cfg(feature = "embedded")]
+mod purge;
+
+#[cfg(feature = "embedded")]
+pub(crate) use purge::Purging;
 
 /// Storage + ANN abstraction. The matcher composes calls against this
 /// trait; concrete backends provide the persistence.
@@ -19,6 +38,29 @@ pub trait IndexBackend: Send + Sync {
     /// Insert or replace records by `(tenant_id, record_id)`.
     async fn upsert(&self, batch: &[Record]) -> Result<()>;
 
+    /// Conditional upsert: `conditions[i]` guards `batch[i]`. All
+    /// conditions are evaluated and all passing records written in one
+    /// transaction, in batch order, so a later record sees an earlier
+    /// write to the same key. Returns one [`WriteOutcome`] per record.
+    ///
+    /// Default impl handles an all-[`WriteCondition::Always`] batch via
+    /// [`Self::upsert`] and returns [`Error::Unsupported`] otherwise —
+    /// a check outside the write transaction would race.
+    async fn upsert_if(
+        &self,
+        batch: &[Record],
+        conditions: &[WriteCondition],
+    ) -> Result<Vec<WriteOutcome>> {
+        check_conditions(batch, conditions)?;
+        if conditions.iter().any(|c| *c != WriteCondition::Always) {
+            return Err(Error::Unsupported(
+                "conditional upsert not implemented for this backend".into(),
+            ));
+        }
+        self.upsert(batch).await?;
+        Ok(vec![WriteOutcome::Written; batch.len()])
+    }
+
     /// Remove records by `(tenant_id, record_id)`. Idempotent — missing
     /// IDs are silently ignored.
     async fn delete(&self, tenant_id: u32, ids: &[u64]) -> Result<()>;
@@ -58,6 +100,49 @@ pub trait IndexBackend: Send + Sync {
         self.bm25(tenant_id, terms, k, filter).await
     }
 
+    /// Like [`Self::bm25_explain`] (or [`Self::bm25`] when `explain` is
+    /// false) but scored with `params` instead of the tenant's
+    /// configured [`Bm25Params`], for tuning a query live.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn bm25_with(
+        &self,
+        tenant_id: u32,
+        terms: &[&str],
+        k: usize,
+        filter: Option<&Bytes>,
+        explain: bool,
+        params: &Bm25Params,
+    ) -> Result<Vec<Hit>> {
+        let _ = (tenant_id, terms, k, filter, explain, params);
+        Err(Error::Unsupported(
+            "per-query BM25 parameters are not supported by this backend".into(),
+        ))
+    }
+
+    /// Fill the [`Hit::snippets`] of `hits` from their retained text,
+    /// marking the tokens that analyze to one of the hit's
+    /// [`Hit::term_hits`] — so the hits must come from an explained BM25
+    /// call. A hit without term hits, or whose text was not retained,
+    /// gets no snippets.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn highlight(&self, tenant_id: u32, hits: &mut [Hit], opts: &Highlight) -> Result<()> {
+        let _ = (tenant_id, hits, opts);
+        Err(Error::Unsupported(
+            "highlighting is not supported by this backend".into(),
+        ))
+    }
+
+    /// Whether [`Self::get_records`] hands back the text and text fields
+    /// of BM25-indexed records. When `false`, a tenant archive carries
+    /// fingerprints only and an import cannot rebuild BM25.
+    ///
+    /// Default impl returns `false`.
+    fn retains_text(&self) -> bool {
+        false
+    }
+
     /// Force pending writes to disk. Backends should already commit per
     /// upsert batch; this exists for explicit shutdown / snapshot points.
     async fn flush(&self) -> Result<()>;
@@ -75,4 +160,349 @@ pub trait IndexBackend: Send + Sync {
             "get_record_metadata not implemented for this backend".into(),
         ))
     }
+
+    /// Fetch full stored [`Record`]s — fingerprint bytes, embedding,
+    /// metadata — for `ids` inside `tenant_id`. Missing IDs are omitted
+    /// rather than erroring, so callers diff the result against what
+    /// they asked for; hits come back in request order.
+    ///
+    /// [`Record::text`] is only populated by backends that retain the
+    /// indexed text; the BM25 index alone cannot reproduce it.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn get_records(&self, tenant_id: u32, ids: &[u64]) -> Result<Vec<Record>> {
+        let _ = (tenant_id, ids);
+        Err(Error::Unsupported(
+            "get_records not implemented for this backend".into(),
+        ))
+    }
+
+    /// Walk `tenant_id`'s catalog in ascending `record_id` order, one
+    /// page of at most `limit` headers passing `filter` per call.
+    ///
+    /// `cursor` is the previous page's [`ScanPage::next_cursor`] (`None`
+    /// starts from the beginning). Cursors are positional — "after
+    /// record_id N" — so a walk stays valid while writes continue: no
+    /// record present for the whole walk is skipped or repeated.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn scan(
+        &self,
+        tenant_id: u32,
+        cursor: Option<&str>,
+        limit: usize,
+        filter: &RecordFilter,
+    ) -> Result<ScanPage> {
+        let _ = (tenant_id, cursor, limit, filter);
+        Err(Error::Unsupported(
+            "scan not implemented for this backend".into(),
+        ))
+    }
+
+    /// Delete every record in `tenant_id` matching `filter`, committing
+    /// at most [`DELETE_BATCH`] records per transaction and bumping
+    /// `progress` after each. Returns the number of records removed.
+    ///
+    /// Default impl walks [`Self::scan`] and feeds pages to
+    /// [`Self::delete`], so any backend with those two gets it for free.
+    /// Not atomic: a failure part-way leaves earlier batches deleted, and
+    /// re-running finishes the job.
+    async fn delete_where(
+        &self,
+        tenant_id: u32,
+        filter: &RecordFilter,
+        progress: &Progress,
+    ) -> Result<u64> {
+        delete_matching(self, tenant_id, filter, progress).await
+    }
+
+    /// Remove everything stored for `tenant_id` — records and every
+    /// derived structure (BM25 dictionary, postings, corpus stats, ANN
+    /// state). Bounded transactions and progress as [`Self::delete_where`].
+    ///
+    /// Default impl is `delete_where` with an empty filter; backends
+    /// with tenant-scoped index state override it to drop that too.
+    async fn purge_tenant(&self, tenant_id: u32, progress: &Progress) -> Result<u64> {
+        self.delete_where(tenant_id, &RecordFilter::default(), progress)
+            .await
+    }
+
+    /// Size and make-up of `tenant_id`, or of every tenant with records
+    /// when `None`. Served from counters the backend keeps up to date on
+    /// every write, so it is cheap enough to call per scrape.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn tenant_stats(&self, tenant_id: Option<u32>) -> Result<Vec<TenantStats>> {
+        let _ = tenant_id;
+        Err(Error::Unsupported(
+            "tenant_stats not implemented for this backend".into(),
+        ))
+    }
+
+    /// Up to `limit` dictionary terms of `tenant_id` completing the last
+    /// token of `prefix`, most documents first. For type-ahead; backends
+    /// may rank a bounded sample of the completions, so for a very short
+    /// prefix the order is approximate.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn suggest_terms(
+        &self,
+        tenant_id: u32,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<TermSuggestion>> {
+        let _ = (tenant_id, prefix, limit);
+        Err(Error::Unsupported(
+            "suggest_terms not implemented for this backend".into(),
+        ))
+    }
+
+    /// For each token of the query `text`, up to `limit` dictionary terms
+    /// of `tenant_id` within two edits of it, closest and then most
+    /// documents first. Meant for queries that came back empty.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn did_you_mean(
+        &self,
+        tenant_id: u32,
+        text: &str,
+        limit: usize,
+    ) -> Result<Vec<Correction>> {
+        let _ = (tenant_id, text, limit);
+        Err(Error::Unsupported(
+            "did_you_mean not implemented for this backend".into(),
+        ))
+    }
+
+    /// Rebuild the BM25 index of `tenant_id` from retained record text,
+    /// replacing every term, posting and corpus row. Searches keep using
+    /// the old index until the new one is swapped in. `progress` counts
+    /// records tokenized. Returns the records indexed.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn reindex_bm25(&self, tenant_id: u32, progress: &Progress) -> Result<u64> {
+        let _ = (tenant_id, progress);
+        Err(Error::Unsupported(
+            "reindex_bm25 not implemented for this backend".into(),
+        ))
+    }
+
+    /// Permanently remove up to `limit` records whose
+    /// [`Record::expires_at`] is at or before `now_ms`, across all
+    /// tenants and every table (BM25 included). Returns how many went;
+    /// callers loop until it comes back below `limit`.
+    ///
+    /// Expired records are already invisible to reads and queries —
+    /// this only reclaims their storage.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn sweep_expired(&self, now_ms: u64, limit: usize) -> Result<u64> {
+        let _ = (now_ms, limit);
+        Err(Error::Unsupported(
+            "sweep_expired not implemented for this backend".into(),
+        ))
+    }
+
+    /// Headers of every retained version of a record, oldest first; the
+    /// last entry is the current one. Without versioning enabled that is
+    /// just the current version. A record deleted with versioning on
+    /// keeps its history, ending in a [`FingerprintMeta::deleted`]
+    /// tombstone.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn list_versions(&self, tenant_id: u32, record_id: u64) -> Result<Vec<FingerprintMeta>> {
+        let _ = (tenant_id, record_id);
+        Err(Error::Unsupported(
+            "list_versions not implemented for this backend".into(),
+        ))
+    }
+
+    /// Read one version of a record. [`Error::RecordNotFound`] when the
+    /// record is gone or no retained version matches `spec`.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn get_version(
+        &self,
+        tenant_id: u32,
+        record_id: u64,
+        spec: VersionSpec,
+    ) -> Result<VersionedRecord> {
+        let _ = (tenant_id, record_id, spec);
+        Err(Error::Unsupported(
+            "get_version not implemented for this backend".into(),
+        ))
+    }
+
+    /// Apply the age bound of the backend's
+    /// [`VersionPolicy`](crate::core::VersionPolicy): drop up to `limit`
+    /// superseded versions replaced before `now_ms - keep_for`. The count
+    /// bound is enforced on write and needs no sweep. Returns how many
+    /// versions went.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn prune_versions(&self, now_ms: u64, limit: usize) -> Result<u64> {
+        let _ = (now_ms, limit);
+        Err(Error::Unsupported(
+            "prune_versions not implemented for this backend".into(),
+        ))
+    }
+
+    /// Up to `limit` change-log entries with a sequence number greater
+    /// than `seq`, oldest first. Every upsert and delete appends to the
+    /// log in the same transaction as the write, so a follower that
+    /// resumes from the last `seq` it applied misses nothing. Resuming
+    /// from a `seq` whose successors retention already dropped fails
+    /// with [`Error::ChangesPruned`] rather than skipping them. Pass `0`
+    /// to read from the oldest retained entry.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn changes_since(&self, seq: u64, limit: usize) -> Result<Vec<ChangeEvent>> {
+        let _ = (seq, limit);
+        Err(Error::Unsupported(
+            "changes_since not implemented for this backend".into(),
+        ))
+    }
+
+    /// Write a consistent point-in-time copy of the whole store to
+    /// `dest` while reads and writes continue. Refuses to overwrite an
+    /// existing file.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn snapshot(&self, dest: &std::path::Path) -> Result<SnapshotInfo> {
+        let _ = dest;
+        Err(Error::Unsupported(
+            "snapshot not implemented for this backend".into(),
+        ))
+    }
+
+    /// Retention for the change log: drop up to `limit` of the oldest
+    /// entries committed before `cutoff_ms`. Returns how many went.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn prune_changes(&self, cutoff_ms: u64, limit: usize) -> Result<u64> {
+        let _ = (cutoff_ms, limit);
+        Err(Error::Unsupported(
+            "prune_changes not implemented for this backend".into(),
+        ))
+    }
+
+    /// Check the backend's internal invariants (derived lengths, index
+    /// structures, counters) for `tenant_id`, or every tenant when
+    /// `None`, and report each violation. With `repair`, rebuild what
+    /// can be derived from the primary data and mark those repaired.
+    ///
+    /// Default impl returns [`Error::Unsupported`].
+    async fn fsck(&self, tenant_id: Option<u32>, repair: bool) -> Result<FsckReport> {
+        let _ = (tenant_id, repair);
+        Err(Error::Unsupported(
+            "fsck not implemented for this backend".into(),
+        ))
+    }
+}
+
+/// Records removed per transaction by [`IndexBackend::delete_where`] and
+/// [`IndexBackend::purge_tenant`]. Small enough that one commit never
+/// stalls concurrent writers for long; large enough to amortise it.
+pub const DELETE_BATCH: usize = 1000;
+
+/// Scan-then-delete loop behind the default [`IndexBackend::delete_where`].
+/// Deleting only ever removes rows *behind* the cursor, so the walk
+/// stays valid as it goes.
+pub(crate) async fn delete_matching<B: IndexBackend + ?Sized>(
+    backend: &B,
+    tenant_id: u32,
+    filter: &RecordFilter,
+    progress: &Progress,
+) -> Result<u64> {
+    let mut removed = 0u64;
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = backend
+            .scan(tenant_id, cursor.as_deref(), DELETE_BATCH, filter)
+            .await?;
+        if !page.records.is_empty() {
+            let ids: Vec<u64> = page.records.iter().map(|m| m.record_id).collect();
+            backend.delete(tenant_id, &ids).await?;
+            removed += ids.len() as u64;
+            progress.add_batch(ids.len() as u64);
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(removed),
+        }
+    }
+}
+
+// ── Conditional writes ──────────────────────────────────────────────────
+
+/// `upsert_if` takes one condition per record.
+pub(crate) fn check_conditions(batch: &[Record], conditions: &[WriteCondition]) -> Result<()> {
+    if batch.len() != conditions.len() {
+        return Err(Error::Modality(format!(
+            "upsert_if: {} records but {} conditions",
+            batch.len(),
+            conditions.len()
+        )));
+    }
+    Ok(())
+}
+
+// ── Expiry ──────────────────────────────────────────────────────────────
+
+/// Wall clock in unix milliseconds, the unit of [`Record::expires_at`].
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `true` once a record with this expiry must no longer be served.
+#[inline]
+pub(crate) fn is_expired(expires_at: Option<u64>, now_ms: u64) -> bool {
+    expires_at.is_some_and(|t| t <= now_ms)
+}
+
+// ── Scan cursors ────────────────────────────────────────────────────────
+
+/// Catalog rows a single [`IndexBackend::scan`] call may examine before
+/// returning a short page. Bounds the read txn when a selective filter
+/// matches little of a very large tenant.
+pub(crate) const SCAN_BUDGET: usize = 10_000;
+
+/// Versioned so the encoding can change without breaking cursors
+/// already handed to clients.
+const CURSOR_PREFIX: &str = "c1.";
+
+/// Encode "resume after `last_record_id`" as an opaque cursor.
+pub(crate) fn encode_cursor(last_record_id: u64) -> String {
+    format!("{CURSOR_PREFIX}{last_record_id:016x}")
+}
+
+/// First `record_id` a scan resuming from `cursor` should read, or
+/// `None` when the cursor already points past the end of the id space.
+pub(crate) fn decode_cursor(cursor: Option<&str>) -> Result<Option<u64>> {
+    let Some(c) = cursor else {
+        return Ok(Some(0));
+    };
+    let last = c
+        .strip_prefix(CURSOR_PREFIX)
+        .filter(|hex| hex.len() == 16)
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| Error::Modality(format!("invalid scan cursor `{c}`")))?;
+    Ok(last.checked_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        assert_eq!(decode_cursor(None).unwrap(), Some(0));
+        assert_eq!(decode_cursor(Some(&encode_cursor(41))).unwrap(), Some(42));
+        assert_eq!(decode_cursor(Some(&encode_cursor(u64::MAX))).unwrap(), None);
+        assert!(decode_cursor(Some("c1.zz")).is_err());
+        assert!(decode_cursor(Some("42")).is_err());
+    }
 }
@@ -0,0 +1,61 @@
+//! Write fence for tenants being purged, shared by the storage backends.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, Result};
+
+/// Tenants with a purge in flight. Writes to them are refused from inside
+/// their write transaction until the purge is done, so nothing lands
+/// between its drains or reuses term ids whose blocks are half gone.
+#[derive(Default)]
+pub(crate) struct Purging(Mutex<BTreeSet<u32>>);
+
+impl Purging {
+    /// Fence off `tenant_id` until the guard drops;
+    /// [`Error::Incompatible`] while another purge of it runs.
+    pub(crate) fn begin(self: &Arc<Self>, tenant_id: u32) -> Result<PurgeGuard> {
+        let mut set = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if !set.insert(tenant_id) {
+            return Err(Error::Incompatible(format!(
+                "tenant {tenant_id} is already being purged"
+            )));
+        }
+        Ok(PurgeGuard {
+            purging: self.clone(),
+            tenant_id,
+        })
+    }
+
+    /// [`Error::Incompatible`] if `tenant_id` is being purged. Call with
+    /// the write transaction open: a purge fences its tenant before its
+    /// first transaction, so a writer either commits before that or sees
+    /// the fence.
+    pub(crate) fn check(&self, tenant_id: u32) -> Result<()> {
+        if self.is_purging(tenant_id) {
+            return Err(Error::Incompatible(format!(
+                "tenant {tenant_id} is being purged; retry once it is done"
+            )));
+        }
+        Ok(())
+    }
+
+    /// `true` while a purge of `tenant_id` runs. Searches answer empty
+    /// then rather than score postings the purge is halfway through.
+    pub(crate) fn is_purging(&self, tenant_id: u32) -> bool {
+        let set = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        set.contains(&tenant_id)
+    }
+}
+
+pub(crate) struct PurgeGuard {
+    purging: Arc<Purging>,
+    tenant_id: u32,
+}
+
+impl Drop for PurgeGuard {
+    fn drop(&mut self) {
+        let mut set = self.purging.0.lock().unwrap_or_else(|e| e.into_inner());
+        set.remove(&self.tenant_id);
+    }
+}
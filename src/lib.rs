@@ -23,7 +23,7 @@
 
 mod core;
 mod error;
-mod index;
+pub mod index;
 mod ingest;
 mod matcher;
 mod modality;
@@ -32,7 +32,13 @@ mod rerank;
 #[cfg(feature = "server")]
 pub mod server;
 
-pub use crate::core::{FingerprintMeta, HitSource, Modality, Query, Record};
+pub use crate::core::{
+    AnnStatus, Bm25Field, Bm25Model, Bm25Params, Bm25Stats, ChangeEvent, ChangeKind, Correction,
+    FingerprintMeta, FsckReport, FsckViolation, Highlight, HitSource, MigrationReport,
+    MigrationStep, Modality, ParquetReport, Progress, Query, Record, RecordFilter, ScanPage,
+    SnapshotInfo, Snippet, TenantStats, TermSuggestion, VersionPolicy, VersionSpec,
+    VersionedRecord, WriteCondition, WriteOutcome,
+};
 pub use crate::error::{Error, Result};
 pub use crate::index::IndexBackend;
 pub use crate::ingest::IngestSource;
@@ -53,6 +59,11 @@ pub use crate::modality::text;
 
 #[cfg(feature = "embedded")]
 pub use crate::index::embedded::EmbeddedBackend;
+#[cfg(feature = "embedded")]
+pub use crate::index::embedded::analyzer::Analyzer;
+
+#[cfg(feature = "fjall")]
+pub use crate::index::fjall::FjallBackend;
 
 /// On-disk format version of a UCFP database.
 ///
@@ -86,6 +86,7 @@ pub fn rrf_with_sources(rankings: &[&[Hit]], sources: &[HitSource], rrf_k: u32)
                 vector_rank: vr,
                 bm25_rank: br,
                 term_hits: Vec::new(),
+                snippets: Vec::new(),
             }
         })
         .collect();
@@ -137,6 +138,8 @@ impl<'a, I: IndexBackend, R: Reranker> Matcher<'a, I, R> {
     /// - else → empty result (caller error)
     ///
     /// Reranker, when present, is applied to the top-`k` after fusion.
+    /// With [`Query::highlight`] set, the final hits then get snippets
+    /// from [`IndexBackend::highlight`].
     pub async fn search(&self, q: &Query) -> Result<Vec<Hit>> {
         let mut fused: Vec<Hit> = match (q.vector.as_ref(), q.terms.is_empty()) {
             (Some(v), false) => {
@@ -145,15 +148,8 @@ impl<'a, I: IndexBackend, R: Reranker> Matcher<'a, I, R> {
                 // backend, so they actually use independent worker threads.
                 let terms: Vec<&str> = q.terms.iter().map(String::as_str).collect();
                 let knn_fut = self.index.knn(q.tenant_id, v, q.k, q.filter.as_ref());
-                let (vec_hits, bm_hits) = if q.explain {
-                    let bm_fut =
-                        self.index
-                            .bm25_explain(q.tenant_id, &terms, q.k, q.filter.as_ref());
-                    tokio::try_join!(knn_fut, bm_fut)?
-                } else {
-                    let bm_fut = self.index.bm25(q.tenant_id, &terms, q.k, q.filter.as_ref());
-                    tokio::try_join!(knn_fut, bm_fut)?
-                };
+                let bm_fut = self.bm25(q, &terms);
+                let (vec_hits, bm_hits) = tokio::try_join!(knn_fut, bm_fut)?;
                 let mut fused = rrf_with_sources(
                     &[&vec_hits, &bm_hits],
                     &[HitSource::Vector, HitSource::Bm25],
@@ -161,7 +157,7 @@ impl<'a, I: IndexBackend, R: Reranker> Matcher<'a, I, R> {
                 );
                 // Carry forward term_hits from the BM25 ranking onto the
                 // fused output (RRF doesn't see them otherwise).
-                if q.explain {
+                if q.explain || q.highlight.is_some() {
                     use std::collections::HashMap;
                     let mut by_id: HashMap<(u32, u64), Vec<crate::core::TermHit>> = HashMap::new();
                     for h in bm_hits {
@@ -184,15 +180,7 @@ impl<'a, I: IndexBackend, R: Reranker> Matcher<'a, I, R> {
             }
             (None, false) => {
                 let terms: Vec<&str> = q.terms.iter().map(String::as_str).collect();
-                if q.explain {
-                    self.index
-                        .bm25_explain(q.tenant_id, &terms, q.k, q.filter.as_ref())
-                        .await?
-                } else {
-                    self.index
-                        .bm25(q.tenant_id, &terms, q.k, q.filter.as_ref())
-                        .await?
-                }
+                self.bm25(q, &terms).await?
             }
             (None, true) => Vec::new(),
         };
@@ -203,8 +191,37 @@ impl<'a, I: IndexBackend, R: Reranker> Matcher<'a, I, R> {
             fused = rr.rerank(q, fused).await?;
         }
 
+        if let Some(opts) = &q.highlight {
+            self.index.highlight(q.tenant_id, &mut fused, opts).await?;
+            if !q.explain {
+                for h in &mut fused {
+                    h.term_hits.clear();
+                }
+            }
+        }
+
         Ok(fused)
     }
+
+    /// The BM25 leg of `q`: explained when asked, or to be highlighted,
+    /// and scored with the query's own [`Query::bm25`] parameters when it
+    /// carries them.
+    async fn bm25(&self, q: &Query, terms: &[&str]) -> Result<Vec<Hit>> {
+        let filter = q.filter.as_ref();
+        match (&q.bm25, q.explain || q.highlight.is_some()) {
+            (Some(params), explain) => {
+                self.index
+                    .bm25_with(q.tenant_id, terms, q.k, filter, explain, params)
+                    .await
+            }
+            (None, true) => {
+                self.index
+                    .bm25_explain(q.tenant_id, terms, q.k, filter)
+                    .await
+            }
+            (None, false) => self.index.bm25(q.tenant_id, terms, q.k, filter).await,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -222,6 +239,7 @@ mod tests {
             vector_rank: None,
             bm25_rank: None,
             term_hits: Vec::new(),
+            snippets: Vec::new(),
         }
     }
 
@@ -21,6 +21,8 @@
 //! watermarked?"), not something that should be persisted as a
 //! comparable fingerprint.
 
+use std::collections::BTreeMap;
+
 use bytes::Bytes;
 
 use crate::core::{Modality, Record};
@@ -94,6 +96,8 @@ pub fn fingerprint_wang_with(
         model_id: None,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -152,6 +156,8 @@ pub fn fingerprint_panako_with(
         model_id: None,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -220,6 +226,8 @@ pub fn fingerprint_haitsma_with(
         model_id: None,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -317,6 +325,8 @@ pub fn fingerprint_neural_with(
         model_id: Some(model_path.to_string()),
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -475,6 +485,8 @@ impl StreamingWangSession {
             model_id: None,
             metadata: Bytes::new(),
             text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: None,
         }]
     }
 }
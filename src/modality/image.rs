@@ -23,6 +23,8 @@
 //! depend on the config (the config is interpreted at compare time), so
 //! the simple `fingerprint`/`fingerprint_with` path can ignore it.
 
+use std::collections::BTreeMap;
+
 use bytes::Bytes;
 #[cfg(feature = "image-perceptual")]
 use imgfprint::MultiHashConfig;
@@ -84,6 +86,8 @@ pub fn fingerprint_with(
         model_id: None,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -190,6 +194,8 @@ fn fingerprint_single(
         model_id: None,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -237,6 +243,8 @@ pub fn fingerprint_semantic(
         model_id: Some(model_path.to_string()),
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -26,6 +26,8 @@
 //! should reach for [`TextOpts::default`] and override only the fields
 //! they need.
 
+use std::collections::BTreeMap;
+
 use bytes::Bytes;
 use txtfp::{
     Canonicalizer, Fingerprinter, GraphemeTokenizer, MinHashFingerprinter, ShingleTokenizer,
@@ -232,6 +234,8 @@ pub fn fingerprint_minhash_with<const H: usize>(
         model_id: None,
         metadata: Bytes::new(),
         text: Some(prepared),
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -417,6 +421,8 @@ fn simhash_dispatch(
         model_id: None,
         metadata: Bytes::new(),
         text: Some(prepared),
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -480,6 +486,8 @@ pub fn fingerprint_tlsh(
         model_id: None,
         metadata: Bytes::new(),
         text: Some(prepared),
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -635,6 +643,8 @@ fn semantic_record(
         model_id,
         metadata: Bytes::new(),
         text: None,
+        text_fields: BTreeMap::new(),
+        expires_at: None,
     })
 }
 
@@ -725,6 +735,8 @@ impl StreamingMinHashSession {
             model_id: None,
             metadata: Bytes::new(),
             text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: None,
         }])
     }
 }
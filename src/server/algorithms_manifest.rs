@@ -12,6 +12,10 @@
 //! 3. Add a [`Tunable`] entry here.
 //!
 //! Everything else (UI, serde wire-format, defaults) flows from this file.
+//!
+//! BM25 scoring models are listed apart from the modalities, under
+//! `bm25_models`: their tunables are the keys of the `bm25` object in a
+//! `POST /v1/query` body, so the playground can re-rank a query live.
 
 // Helper builders below are used only by the per-modality `*_catalog()`
 // arms, each cfg-gated on a modality feature. Slim builds (no modality
@@ -21,6 +25,8 @@
 
 use serde::Serialize;
 
+use crate::core::Bm25Model;
+
 /// Machine-readable type of a tunable knob.
 #[derive(Clone, Copy, Debug, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -111,6 +117,8 @@ pub struct ModalityCatalog {
 #[derive(Clone, Debug, Serialize)]
 pub struct AlgorithmsResponse {
     pub modalities: Vec<ModalityCatalog>,
+    /// BM25 scoring models; each `id` is a `bm25.model` value.
+    pub bm25_models: Vec<Algorithm>,
 }
 
 // ── Builders for compactness ──────────────────────────────────────────
@@ -232,7 +240,85 @@ pub fn build() -> AlgorithmsResponse {
         #[cfg(feature = "audio")]
         audio_catalog(),
     ];
-    AlgorithmsResponse { modalities }
+    AlgorithmsResponse {
+        modalities,
+        bm25_models: bm25_catalog(),
+    }
+}
+
+/// Scoring models for BM25 queries. Not feature-gated: every build
+/// with a BM25 index can score with any of them.
+fn bm25_catalog() -> Vec<Algorithm> {
+    let k1 = || {
+        t_float(
+            "k1",
+            "k1",
+            "Term-frequency saturation: higher lets repeated matches keep adding (default 1.2).",
+            0.0,
+            3.0,
+            0.1,
+        )
+    };
+    let b = || {
+        t_float(
+            "b",
+            "b",
+            "Length normalization: 0 ignores document length, 1 fully normalizes (default 0.75).",
+            0.0,
+            1.0,
+            0.05,
+        )
+    };
+    let delta = |help| t_float("delta", "delta", help, 0.0, 2.0, 0.1);
+    let length_presets = || {
+        vec![
+            Preset {
+                id: "short-text",
+                label: "Short text (titles, product names)",
+                values: serde_json::json!({"k1": 1.2, "b": 0.3}),
+            },
+            Preset {
+                id: "long-documents",
+                label: "Long documents",
+                values: serde_json::json!({"k1": 1.5, "b": 0.9}),
+            },
+        ]
+    };
+    Bm25Model::ALL
+        .into_iter()
+        .map(|model| {
+            let (label, description, tunables) = match model {
+                Bm25Model::Bm25 => ("BM25", "Okapi BM25 (default).", vec![k1(), b()]),
+                Bm25Model::Bm25l => (
+                    "BM25L",
+                    "BM25 with the normalized frequency shifted by delta, so long documents are not over-penalized.",
+                    vec![k1(), b(), delta("Frequency shift (default 0.5).")],
+                ),
+                Bm25Model::Bm25Plus => (
+                    "BM25+",
+                    "BM25 plus delta per matched term, so any match outscores none however long the document.",
+                    vec![k1(), b(), delta("Per-match floor (default 1.0).")],
+                ),
+                Bm25Model::TfIdf => (
+                    "TF-IDF",
+                    "ln(1 + tf) · ln(1 + N/n): no saturation or length normalization.",
+                    vec![],
+                ),
+            };
+            Algorithm {
+                id: model.as_str(),
+                label,
+                description,
+                presets: if tunables.is_empty() {
+                    vec![]
+                } else {
+                    length_presets()
+                },
+                tunables,
+                inspect: false,
+            }
+        })
+        .collect()
 }
 
 #[cfg(feature = "text")]
@@ -8,10 +8,14 @@
 //! Serde renames everything to `kebab-case` so the wire format reads
 //! `?algorithm=multi-hash` rather than `MultiHash`.
 
+use std::collections::BTreeMap;
+
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
-use crate::core::{Modality, Record};
+use crate::core::{
+    Bm25Params, Correction, Modality, Record, TermSuggestion, WriteCondition, WriteOutcome,
+};
 
 // ── /v1/info ───────────────────────────────────────────────────────────
 
@@ -44,6 +48,34 @@ pub(super) struct RecordIn {
     pub model_id: Option<String>,
     #[serde(default)]
     pub metadata: Vec<u8>,
+    /// Absolute expiry, unix milliseconds.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Write only if nothing is stored under this id yet.
+    #[serde(default)]
+    pub if_absent: bool,
+    /// Write only if the stored version equals this (`0` = absent).
+    #[serde(default)]
+    pub if_version: Option<u64>,
+    /// Write only if the stored record has this `config_hash`.
+    #[serde(default)]
+    pub if_config_hash_matches: Option<u64>,
+}
+
+impl RecordIn {
+    /// The write precondition; at most one may be set.
+    pub fn condition(&self) -> crate::Result<WriteCondition> {
+        match (self.if_absent, self.if_version, self.if_config_hash_matches) {
+            (false, None, None) => Ok(WriteCondition::Always),
+            (true, None, None) => Ok(WriteCondition::IfAbsent),
+            (false, Some(v), None) => Ok(WriteCondition::IfVersion(v)),
+            (false, None, Some(h)) => Ok(WriteCondition::IfConfigHash(h)),
+            _ => Err(crate::Error::Modality(format!(
+                "record {}: set at most one of if_absent, if_version, if_config_hash_matches",
+                self.record_id
+            ))),
+        }
+    }
 }
 
 impl From<RecordIn> for Record {
@@ -60,13 +92,18 @@ impl From<RecordIn> for Record {
             model_id: r.model_id,
             metadata: Bytes::from(r.metadata),
             text: None,
+            text_fields: BTreeMap::new(),
+            expires_at: r.expires_at,
         }
     }
 }
 
 #[derive(Serialize)]
 pub(super) struct UpsertResponse {
+    /// Records actually written.
     pub upserted: usize,
+    /// Per-record outcome, in request order.
+    pub results: Vec<WriteOutcome>,
 }
 
 // ── /v1/query (POST) ───────────────────────────────────────────────────
@@ -77,9 +114,18 @@ pub(super) struct QueryRequest {
     pub modality: Modality,
     #[serde(default = "default_k")]
     pub k: usize,
-    /// Dense query vector. BM25 path lands once `IndexBackend::bm25`
-    /// is implemented; for now this is required.
-    pub vector: Vec<f32>,
+    /// Dense query vector. With `terms` too, the two rankings are fused.
+    #[serde(default)]
+    pub vector: Option<Vec<f32>>,
+    /// BM25 query text, e.g. `title:rust "force majeure" contr*`; the
+    /// pieces are joined with spaces.
+    #[serde(default)]
+    pub terms: Vec<String>,
+    /// BM25 scoring for this query only, e.g.
+    /// `{"model": "bm25l", "k1": 1.5, "b": 0.3}`; omitted keys take
+    /// their defaults. Absent, the tenant's configured parameters apply.
+    #[serde(default)]
+    pub bm25: Option<Bm25Params>,
 }
 
 pub(super) fn default_k() -> usize {
@@ -113,6 +159,10 @@ pub(super) struct HitOut {
     /// `?explain=1`. Cap is 16 terms per hit (top by contribution).
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub term_hits: Vec<TermHitOut>,
+    /// Highlighted fragments of the hit's retained text — only populated
+    /// when the request carried `?highlight=1`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snippets: Vec<SnippetOut>,
 }
 
 #[derive(Serialize)]
@@ -121,6 +171,22 @@ pub(super) struct TermHitOut {
     pub idf: f32,
     pub tf: u32,
     pub contribution: f32,
+    /// Text field the term matched in: `text` or a named field.
+    pub field: String,
+    /// Set when the term matched inside a phrase or `NEAR/n` clause.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phrase: Option<String>,
+    /// Set when the term is an expansion of a `term*` or `term~n` query term.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expanded_from: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct SnippetOut {
+    /// Text field the fragment was cut from: `text` or a named field.
+    pub field: String,
+    /// HTML-escaped, matched tokens wrapped in `<mark>`…`</mark>`.
+    pub fragment: String,
 }
 
 // ── /v1/ingest/{modality}/{tid}/{rid} (POST) ───────────────────────────
@@ -269,6 +335,9 @@ pub(super) struct AudioParams {
     /// populated for algorithms that produce one, e.g. `neural`).
     #[serde(default)]
     pub return_embedding: Option<bool>,
+    /// Expire the record this many seconds after ingest.
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
     // ── Wang-only tunables (mapped to `audiofp::classical::WangConfig`) ──
     /// `F`: target peaks paired with each anchor.
     #[serde(default)]
@@ -399,6 +468,10 @@ pub(super) struct TextParams {
     /// populated for the `semantic-*` arms).
     #[serde(default)]
     pub return_embedding: Option<bool>,
+    /// Named text fields indexed for BM25F next to the body, as a JSON
+    /// object of strings: `fields={"title":"Annual report"}`.
+    #[serde(default)]
+    pub fields: Option<String>,
 
     // ── Canonicalizer overrides (mapped to `txtfp::CanonicalizerBuilder`) ──
     /// Unicode normalization form. One of `nfc`, `nfkc`, `nfd`, `nfkd`,
@@ -522,6 +595,19 @@ pub struct FingerprintDescription {
     pub model_id: Option<String>,
     /// Length of the application metadata blob in bytes.
     pub metadata_bytes: usize,
+    /// Absolute expiry (unix milliseconds) when the record has a TTL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Per-record write sequence. Absent on views built from a bare
+    /// record (batch-get), which doesn't carry it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// When this version was written, unix milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written_at: Option<u64>,
+    /// Set on the version history entry a delete leaves behind.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
 }
 
 impl From<crate::core::FingerprintMeta> for FingerprintDescription {
@@ -538,10 +624,268 @@ impl From<crate::core::FingerprintMeta> for FingerprintDescription {
             embedding_dim: m.embedding_dim,
             model_id: m.model_id,
             metadata_bytes: m.metadata_bytes,
+            expires_at: m.expires_at,
+            version: Some(m.version),
+            written_at: m.written_at,
+            deleted: m.deleted,
+        }
+    }
+}
+
+impl From<&Record> for FingerprintDescription {
+    fn from(r: &Record) -> Self {
+        Self {
+            tenant_id: r.tenant_id,
+            record_id: r.record_id,
+            modality: r.modality,
+            algorithm: r.algorithm.clone(),
+            format_version: r.format_version,
+            config_hash: r.config_hash,
+            fingerprint_bytes: r.fingerprint.len(),
+            has_embedding: r.embedding.is_some(),
+            embedding_dim: r.embedding.as_ref().map(Vec::len),
+            model_id: r.model_id.clone(),
+            metadata_bytes: r.metadata.len(),
+            expires_at: r.expires_at,
+            version: None,
+            written_at: None,
+            deleted: false,
+        }
+    }
+}
+
+// ── /v1/records/{tid}/{rid}?include=… and batch-get ────────────────────
+
+/// `?include=fingerprint,embedding,metadata,text` on the describe route,
+/// plus `?version=n` / `?as_of=<unix ms>` for a point-in-time read.
+#[derive(Default, Deserialize)]
+pub(super) struct RecordParams {
+    #[serde(default)]
+    pub include: Option<String>,
+    #[serde(default)]
+    pub version: Option<u64>,
+    #[serde(default)]
+    pub as_of: Option<u64>,
+}
+
+/// Which stored blobs to return alongside the describe header.
+#[derive(Clone, Copy, Default)]
+pub(super) struct Include {
+    pub fingerprint: bool,
+    pub embedding: bool,
+    pub metadata: bool,
+    pub text: bool,
+}
+
+impl Include {
+    /// Parse include tokens; unknown names are a 400 so a typo doesn't
+    /// silently return less than the caller asked for.
+    pub fn parse<'a>(tokens: impl IntoIterator<Item = &'a str>) -> crate::Result<Self> {
+        let mut inc = Self::default();
+        for tok in tokens.into_iter().map(str::trim).filter(|t| !t.is_empty()) {
+            match tok {
+                "fingerprint" => inc.fingerprint = true,
+                "embedding" => inc.embedding = true,
+                "metadata" => inc.metadata = true,
+                "text" => inc.text = true,
+                other => {
+                    return Err(crate::Error::Modality(format!(
+                        "unknown include `{other}` (expected fingerprint, embedding, metadata, text)"
+                    )));
+                }
+            }
+        }
+        Ok(inc)
+    }
+}
+
+/// Describe header plus whichever blobs were requested. Without any
+/// `include` the JSON is byte-identical to [`FingerprintDescription`].
+#[derive(Serialize)]
+pub(super) struct RecordOut {
+    #[serde(flatten)]
+    pub header: FingerprintDescription,
+    /// Raw fingerprint bytes — JSON array of u8, like `RecordIn`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<u8>>,
+    /// Indexed text — only present when the backend retains it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Named text fields, under the same `include=text`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub text_fields: BTreeMap<String, String>,
+}
+
+impl RecordOut {
+    pub fn new(rec: Record, inc: Include) -> Self {
+        Self::with_header(FingerprintDescription::from(&rec), rec, inc)
+    }
+
+    /// Like [`Self::new`] but with a header read alongside the record
+    /// (carries `version` / `written_at`).
+    pub fn with_header(header: FingerprintDescription, rec: Record, inc: Include) -> Self {
+        Self {
+            header,
+            fingerprint: inc.fingerprint.then(|| rec.fingerprint.to_vec()),
+            embedding: if inc.embedding { rec.embedding } else { None },
+            metadata: inc.metadata.then(|| rec.metadata.to_vec()),
+            text: if inc.text { rec.text } else { None },
+            text_fields: if inc.text {
+                rec.text_fields
+            } else {
+                BTreeMap::new()
+            },
         }
     }
 }
 
+/// Body of `POST /v1/records/{tenant_id}/batch-get`.
+#[derive(Deserialize)]
+pub(super) struct BatchGetRequest {
+    pub record_ids: Vec<u64>,
+    /// Same names as the `?include=` query parameter.
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct BatchGetResponse {
+    /// Found records, in request order.
+    pub records: Vec<RecordOut>,
+    /// Requested ids with no stored record in this tenant.
+    pub missing: Vec<u64>,
+}
+
+/// Body of `GET /v1/records/{tid}/{rid}/versions`.
+#[derive(Serialize)]
+pub(super) struct VersionsResponse {
+    /// Retained versions, oldest first; the last one is current.
+    pub versions: Vec<FingerprintDescription>,
+}
+
+// ── /v1/records/{tid} (GET scan) ───────────────────────────────────────
+
+/// Query string for the tenant listing route.
+#[derive(Default, Deserialize)]
+pub(super) struct ScanParams {
+    /// `next_cursor` from the previous page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// `audio` | `image` | `text` (case-insensitive).
+    #[serde(default)]
+    pub modality: Option<String>,
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ScanResponse {
+    pub records: Vec<FingerprintDescription>,
+    /// Pass back as `?cursor=` for the next page; absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+// ── /v1/terms/{tid}/suggest, /v1/terms/{tid}/did-you-mean (GET) ───────
+
+/// Query string of the type-ahead route.
+#[derive(Default, Deserialize)]
+pub(super) struct SuggestParams {
+    /// Text typed so far; its last word is completed.
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(super) struct SuggestResponse {
+    /// Most documents first.
+    pub suggestions: Vec<TermSuggestion>,
+}
+
+/// Query string of the spelling-suggestion route.
+#[derive(Default, Deserialize)]
+pub(super) struct DidYouMeanParams {
+    /// The query that found nothing.
+    #[serde(default)]
+    pub q: String,
+    /// Suggestions per token.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(super) struct DidYouMeanResponse {
+    /// The query with each token replaced by its best suggestion; absent
+    /// when that changes nothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did_you_mean: Option<String>,
+    /// One entry per query token, in query order.
+    pub corrections: Vec<Correction>,
+}
+
+// ── Admin jobs ─────────────────────────────────────────────────────────
+
+/// Body of `POST /v1/admin/tenants/{tid}/delete-where`. At least one
+/// predicate is required — use the purge route to drop a whole tenant.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct DeleteWhereRequest {
+    /// `audio` | `image` | `text` (case-insensitive).
+    #[serde(default)]
+    pub modality: Option<String>,
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+/// `202 Accepted` body of the admin job routes.
+#[derive(Debug, Serialize)]
+pub(super) struct JobAccepted {
+    pub job_id: u64,
+}
+
+// ── POST /v1/admin/snapshot ────────────────────────────────────────────
+
+/// Body of the snapshot route; empty streams the snapshot back.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct SnapshotRequest {
+    /// Server-side file to write. Must not exist yet.
+    pub path: Option<String>,
+}
+
+// ── POST /v1/admin/fsck ────────────────────────────────────────────────
+
+/// Body of the fsck route; empty checks every tenant without repairing.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct FsckRequest {
+    /// Only this tenant. Required for tenant-scoped admin keys.
+    pub tenant_id: Option<u32>,
+    /// Rebuild derivable structures in place.
+    #[serde(default)]
+    pub repair: bool,
+}
+
+// ── GET /v1/changes ────────────────────────────────────────────────────
+
+/// Query string of the change-log tail.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct ChangesParams {
+    /// Resume after this sequence number (default 0 = from the start).
+    /// SSE clients may send `Last-Event-ID` instead.
+    pub since: Option<u64>,
+    /// Only this tenant's changes. Required for tenant-scoped admin keys.
+    pub tenant_id: Option<u32>,
+    /// Keep the stream open and poll for new changes (default `true`);
+    /// `false` ends it once caught up.
+    pub follow: Option<bool>,
+}
+
 // ── Session-cached input store (feature `inspect`) ─────────────────────
 
 /// Response body for `POST /v1/inputs`. The returned `input_id` lets
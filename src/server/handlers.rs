@@ -6,20 +6,27 @@ use std::sync::Arc;
 use axum::{
     extract::{Extension, Path, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
+use futures_util::StreamExt;
 
-use crate::core::{HitSource, Query, Record};
+use crate::core::{
+    ChangeEvent, FsckReport, Highlight, HitSource, Modality, Query, Record, RecordFilter,
+    TenantStats, VersionSpec, WriteOutcome,
+};
 use crate::error::Error;
 use crate::index::IndexBackend;
 use crate::matcher::Matcher;
 
 use super::apikey::ApiKeyContext;
 use super::dto::{
-    FingerprintDescription, HitOut, InfoResponse, QueryRequest, QueryResponse, RecordIn,
-    UpsertRequest, UpsertResponse,
+    BatchGetRequest, BatchGetResponse, ChangesParams, DeleteWhereRequest, DidYouMeanParams,
+    DidYouMeanResponse, FsckRequest, HitOut, Include, InfoResponse, JobAccepted, QueryRequest,
+    QueryResponse, RecordIn, RecordOut, RecordParams, ScanParams, ScanResponse, SnapshotRequest,
+    SuggestParams, SuggestResponse, UpsertRequest, UpsertResponse, VersionsResponse,
 };
 use super::error::ApiError;
+use super::jobs::{self, JobStatus};
 
 // Imports only the ingest handlers need — feature-gated so a build
 // with all three modality features off doesn't warn.
@@ -59,6 +66,23 @@ fn tenant_guard(ctx: Option<Extension<ApiKeyContext>>, path_tenant: u32) -> Resu
     Ok(())
 }
 
+/// Admin routes additionally need either the service bearer (tenant 0)
+/// or a key carrying the `admin` scope for its own tenant. Same
+/// no-context bypass as [`tenant_guard`].
+fn admin_guard(ctx: Option<Extension<ApiKeyContext>>, path_tenant: u32) -> Result<(), ApiError> {
+    if let Some(Extension(ctx)) = &ctx
+        && ctx.tenant_id != 0
+        && !ctx.scopes.iter().any(|s| s == "admin")
+    {
+        return Err(Error::Forbidden {
+            key_tenant: ctx.tenant_id,
+            path_tenant,
+        }
+        .into());
+    }
+    tenant_guard(ctx, path_tenant)
+}
+
 #[cfg(feature = "audio-watermark")]
 use super::dto::WatermarkReport as WatermarkReportDto;
 
@@ -92,14 +116,30 @@ pub(super) async fn algorithms() -> Json<crate::server::algorithms_manifest::Alg
 
 // ── POST /v1/records ───────────────────────────────────────────────────
 
+/// Records may carry one precondition (`if_absent`, `if_version`,
+/// `if_config_hash_matches`); `results` reports what happened to each.
+/// Every record's tenant must match the key; one foreign record rejects
+/// the whole batch.
 pub(super) async fn upsert<I: IndexBackend>(
     State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
     Json(req): Json<UpsertRequest>,
 ) -> Result<Json<UpsertResponse>, ApiError> {
-    let count = req.records.len();
+    for rec in &req.records {
+        tenant_guard(ctx.clone(), rec.tenant_id)?;
+    }
+    let conditions = req
+        .records
+        .iter()
+        .map(RecordIn::condition)
+        .collect::<crate::Result<Vec<_>>>()?;
     let records: Vec<Record> = req.records.into_iter().map(RecordIn::into).collect();
-    index.upsert(&records).await?;
-    Ok(Json(UpsertResponse { upserted: count }))
+    let results = index.upsert_if(&records, &conditions).await?;
+    let upserted = results
+        .iter()
+        .filter(|o| **o == WriteOutcome::Written)
+        .count();
+    Ok(Json(UpsertResponse { upserted, results }))
 }
 
 // ── DELETE /v1/records/{tenant_id}/{record_id} ─────────────────────────
@@ -114,16 +154,615 @@ pub(super) async fn delete_record<I: IndexBackend>(
     Ok(StatusCode::NO_CONTENT)
 }
 
+// ── /v1/admin ──────────────────────────────────────────────────────────
+
+/// `POST /v1/admin/tenants/{tenant_id}/delete-where` — bulk delete every
+/// record matching the body filter as a background job.
+pub(super) async fn admin_delete_where<I: IndexBackend + 'static>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    Json(body): Json<DeleteWhereRequest>,
+) -> Result<(StatusCode, Json<JobAccepted>), ApiError> {
+    admin_guard(ctx, tenant_id)?;
+    let filter = RecordFilter {
+        modality: parse_modality(body.modality.as_deref())?,
+        algorithm: body.algorithm,
+    };
+    if filter == RecordFilter::default() {
+        return Err(Error::Modality(
+            "delete-where needs at least one of `modality`, `algorithm`; \
+             use /purge to drop the whole tenant"
+                .into(),
+        )
+        .into());
+    }
+    let job_id = jobs::registry().spawn("delete_where", tenant_id, move |progress| async move {
+        index.delete_where(tenant_id, &filter, &progress).await
+    });
+    Ok((StatusCode::ACCEPTED, Json(JobAccepted { job_id })))
+}
+
+/// `POST /v1/admin/tenants/{tenant_id}/purge` — remove every record and
+/// index row of the tenant as a background job.
+pub(super) async fn admin_purge_tenant<I: IndexBackend + 'static>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+) -> Result<(StatusCode, Json<JobAccepted>), ApiError> {
+    admin_guard(ctx, tenant_id)?;
+    let job_id = jobs::registry().spawn("purge_tenant", tenant_id, move |progress| async move {
+        index.purge_tenant(tenant_id, &progress).await
+    });
+    Ok((StatusCode::ACCEPTED, Json(JobAccepted { job_id })))
+}
+
+/// `POST /v1/admin/tenants/{tenant_id}/reindex-bm25` — rebuild the
+/// tenant's BM25 index from retained text as a background job. Queries
+/// keep the old index until the rebuild swaps in.
+pub(super) async fn admin_reindex_bm25<I: IndexBackend + 'static>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+) -> Result<(StatusCode, Json<JobAccepted>), ApiError> {
+    admin_guard(ctx, tenant_id)?;
+    let job_id = jobs::registry().spawn("reindex_bm25", tenant_id, move |progress| async move {
+        index.reindex_bm25(tenant_id, &progress).await
+    });
+    Ok((StatusCode::ACCEPTED, Json(JobAccepted { job_id })))
+}
+
+/// Chunk size when streaming a snapshot file to the client.
+const SNAPSHOT_CHUNK: usize = 1 << 20;
+
+/// `POST /v1/admin/snapshot` — consistent copy of the whole store. With
+/// `{"path": …}` the server writes it there and answers with the
+/// [`SnapshotInfo`](crate::core::SnapshotInfo); with no body it streams
+/// the snapshot file back. Service key only: a snapshot spans tenants.
+pub(super) async fn admin_snapshot<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    body: axum::body::Bytes,
+) -> Result<Response, ApiError> {
+    admin_guard(ctx, 0)?;
+    let req: SnapshotRequest = if body.is_empty() {
+        SnapshotRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| Error::Modality(format!("snapshot request: {e}")))?
+    };
+    if let Some(path) = req.path {
+        let info = index.snapshot(std::path::Path::new(&path)).await?;
+        return Ok(Json(info).into_response());
+    }
+
+    let tmp = TempFile(std::env::temp_dir().join(format!(
+        "ucfp-snapshot-{}-{}.redb",
+        std::process::id(),
+        jobs::next_token()
+    )));
+    let info = index.snapshot(&tmp.0).await?;
+    let file = std::fs::File::open(&tmp.0).map_err(Error::from)?;
+    // The guard rides along with the stream so the temp file goes away
+    // once the download finishes or the client hangs up.
+    let chunks = futures_util::stream::unfold(Some((file, tmp)), |state| async move {
+        let (mut file, tmp) = state?;
+        let read = tokio::task::spawn_blocking(move || {
+            use std::io::Read;
+            let mut buf = vec![0u8; SNAPSHOT_CHUNK];
+            let n = file.read(&mut buf)?;
+            buf.truncate(n);
+            Ok::<_, std::io::Error>((file, buf))
+        })
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|r| r);
+        match read {
+            Ok((_, buf)) if buf.is_empty() => None,
+            Ok((file, buf)) => Some((Ok(bytes::Bytes::from(buf)), Some((file, tmp)))),
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    let filename = format!("attachment; filename=\"ucfp-{}.redb\"", info.created_ms);
+    Ok((
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "application/octet-stream".to_string(),
+            ),
+            (axum::http::header::CONTENT_DISPOSITION, filename),
+            (axum::http::header::CONTENT_LENGTH, info.bytes.to_string()),
+        ],
+        axum::body::Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+/// Removes the file when dropped.
+struct TempFile(std::path::PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// `POST /v1/admin/fsck` — check stored invariants and report every
+/// violation; `{"repair": true}` also rebuilds what is derivable. A
+/// tenant-scoped admin key must name its own `tenant_id`; checking every
+/// tenant takes the service key.
+pub(super) async fn admin_fsck<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    body: axum::body::Bytes,
+) -> Result<Json<FsckReport>, ApiError> {
+    let req: FsckRequest = if body.is_empty() {
+        FsckRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| Error::Modality(format!("fsck request: {e}")))?
+    };
+    admin_guard(ctx, req.tenant_id.unwrap_or(0))?;
+    Ok(Json(index.fsck(req.tenant_id, req.repair).await?))
+}
+
+/// `GET /v1/admin/jobs/{job_id}` — live progress / final outcome.
+pub(super) async fn admin_job_status(
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(job_id): Path<u64>,
+) -> Result<Json<JobStatus>, ApiError> {
+    // Unknown and foreign jobs look the same to the caller.
+    let status = jobs::registry()
+        .get(job_id)
+        .filter(|s| admin_guard(ctx, s.tenant_id).is_ok())
+        .ok_or_else(|| Error::NotFound(format!("job {job_id}")))?;
+    Ok(Json(status))
+}
+
+// ── /v1/admin/tenants/{tenant_id}/{export,import} ──────────────────────
+
+/// `GET /v1/admin/tenants/{tenant_id}/export` — stream the tenant as a
+/// portable [`archive`](crate::index::archive), a page at a time. A
+/// backend error mid-stream cuts the body short before the end line,
+/// which the importer reports as truncation.
+pub(super) async fn admin_export_tenant<I: IndexBackend + 'static>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+) -> Result<Response, ApiError> {
+    use crate::index::archive;
+
+    admin_guard(ctx, tenant_id)?;
+    // First page up front so a failing backend answers with a status.
+    let (first, n, next) = archive::export_page(index.as_ref(), tenant_id, None).await?;
+    let mut head = archive::header_line(&archive::ArchiveHeader::new(
+        tenant_id,
+        index.retains_text(),
+    ))?;
+    head.extend(first);
+
+    enum Step {
+        Page(Option<String>),
+        Done,
+    }
+    let state = (Some(head), n, Step::Page(next));
+    let body = futures_util::stream::unfold(state, move |(head, total, step)| {
+        let index = index.clone();
+        async move {
+            if let Some(head) = head {
+                return Some((Ok(bytes::Bytes::from(head)), (None, total, step)));
+            }
+            match step {
+                Step::Done => None,
+                Step::Page(None) => match archive::end_line(total) {
+                    Ok(end) => Some((Ok(bytes::Bytes::from(end)), (None, total, Step::Done))),
+                    Err(e) => Some((Err(e), (None, total, Step::Done))),
+                },
+                Step::Page(Some(cursor)) => {
+                    match archive::export_page(index.as_ref(), tenant_id, Some(&cursor)).await {
+                        Ok((lines, n, next)) => Some((
+                            Ok(bytes::Bytes::from(lines)),
+                            (None, total + n, Step::Page(next)),
+                        )),
+                        Err(e) => Some((Err(e), (None, total, Step::Done))),
+                    }
+                }
+            }
+        }
+    });
+    let filename = format!("attachment; filename=\"ucfp-tenant-{tenant_id}.ndjson\"");
+    Ok((
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "application/x-ndjson".to_string(),
+            ),
+            (axum::http::header::CONTENT_DISPOSITION, filename),
+        ],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// `POST /v1/admin/tenants/{tenant_id}/import` — load an archive into
+/// `tenant_id`, whatever tenant it was exported from. The body is parsed
+/// as it arrives and upserted in batches, so it is bounded by the request
+/// body limit rather than held in memory whole.
+pub(super) async fn admin_import_tenant<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    body: axum::body::Body,
+) -> Result<Json<crate::index::archive::ImportSummary>, ApiError> {
+    admin_guard(ctx, tenant_id)?;
+    let mut importer = crate::index::archive::Importer::new(Some(tenant_id));
+    let mut chunks = body.into_data_stream();
+    let mut buf: Vec<u8> = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| Error::Modality(format!("archive body: {e}")))?;
+        buf.extend_from_slice(&chunk);
+        let mut start = 0;
+        while let Some(nl) = buf[start..].iter().position(|b| *b == b'\n') {
+            if let Some(batch) = importer.push_line(&buf[start..start + nl])? {
+                index.upsert(&batch).await?;
+            }
+            start += nl + 1;
+        }
+        buf.drain(..start);
+    }
+    if let Some(batch) = importer.push_line(&buf)? {
+        index.upsert(&batch).await?;
+    }
+    let (rest, summary) = importer.finish()?;
+    if !rest.is_empty() {
+        index.upsert(&rest).await?;
+    }
+    Ok(Json(summary))
+}
+
+// ── GET /v1/changes ────────────────────────────────────────────────────
+
+/// Events fetched per backend call while tailing.
+const CHANGES_PAGE: usize = 500;
+/// How long a caught-up `follow` stream waits before polling again.
+const CHANGES_POLL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Tail the change log from `?since=`. NDJSON by default, one
+/// [`ChangeEvent`] per line; `Accept: text/event-stream` switches to SSE
+/// with the sequence number as event id, so a reconnecting client
+/// resumes via `Last-Event-ID`. A `since` older than retention answers
+/// `410 Gone` instead of skipping the pruned changes. Needs the service
+/// key for the global log, or an admin key plus `?tenant_id=` for one
+/// tenant's changes.
+pub(super) async fn changes<I: IndexBackend + 'static>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<ChangesParams>,
+) -> Result<Response, ApiError> {
+    admin_guard(ctx, params.tenant_id.unwrap_or(0))?;
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let since = params.since.or(last_event_id).unwrap_or(0);
+    // First page up front so a backend without a change log answers
+    // with a plain error instead of a stream that dies at once.
+    let first = index.changes_since(since, CHANGES_PAGE).await?;
+    let events = change_stream(
+        index,
+        since,
+        first,
+        params.tenant_id,
+        params.follow.unwrap_or(true),
+    );
+
+    let wants_sse = headers
+        .get(axum::http::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+    if wants_sse {
+        use axum::response::sse::{Event, KeepAlive, Sse};
+        let events = events.map(|ev| {
+            let ev = ev?;
+            Event::default()
+                .id(ev.seq.to_string())
+                .json_data(&ev)
+                .map_err(|e| Error::Index(format!("change encode: {e}")))
+        });
+        return Ok(Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response());
+    }
+    let lines = events.map(|ev| {
+        let mut line =
+            serde_json::to_vec(&ev?).map_err(|e| Error::Index(format!("change encode: {e}")))?;
+        line.push(b'\n');
+        Ok::<_, Error>(bytes::Bytes::from(line))
+    });
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Page through `changes_since` from `cursor`, starting with the
+/// already-fetched `first` page. Ends on error, or once caught up unless
+/// `follow` is set.
+fn change_stream<I: IndexBackend + 'static>(
+    index: Arc<I>,
+    cursor: u64,
+    first: Vec<ChangeEvent>,
+    tenant_id: Option<u32>,
+    follow: bool,
+) -> impl futures_util::Stream<Item = crate::Result<ChangeEvent>> + Send {
+    struct Tail<I> {
+        index: Arc<I>,
+        cursor: u64,
+        page: std::vec::IntoIter<ChangeEvent>,
+        done: bool,
+    }
+    let state = Tail {
+        index,
+        cursor,
+        page: first.into_iter(),
+        done: false,
+    };
+    futures_util::stream::unfold(state, move |mut st| async move {
+        loop {
+            if st.done {
+                return None;
+            }
+            if let Some(ev) = st.page.next() {
+                st.cursor = ev.seq;
+                if tenant_id.is_none_or(|t| t == ev.tenant_id) {
+                    return Some((Ok(ev), st));
+                }
+                continue;
+            }
+            match st.index.changes_since(st.cursor, CHANGES_PAGE).await {
+                Ok(page) if page.is_empty() => {
+                    if !follow {
+                        return None;
+                    }
+                    tokio::time::sleep(CHANGES_POLL).await;
+                }
+                Ok(page) => st.page = page.into_iter(),
+                Err(e) => {
+                    st.done = true;
+                    return Some((Err(e), st));
+                }
+            }
+        }
+    })
+}
+
 // ── GET /v1/records/{tenant_id}/{record_id} ────────────────────────────
 
+/// Describe header by default; `?include=fingerprint,embedding,metadata`
+/// switches to a full `get_records` read and attaches the blobs.
+/// `?version=n` or `?as_of=<unix ms>` reads that version from history.
 pub(super) async fn describe_record<I: IndexBackend>(
     State(index): State<Arc<I>>,
     ctx: Option<Extension<ApiKeyContext>>,
     Path((tenant_id, record_id)): Path<(u32, u64)>,
-) -> Result<Json<FingerprintDescription>, ApiError> {
+    axum::extract::Query(params): axum::extract::Query<RecordParams>,
+) -> Result<Json<RecordOut>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    let spec = match (params.version, params.as_of) {
+        (None, None) => None,
+        (Some(n), None) => Some(VersionSpec::Number(n)),
+        (None, Some(at)) => Some(VersionSpec::AsOf(at)),
+        (Some(_), Some(_)) => {
+            return Err(
+                Error::Modality("pass either `version` or `as_of`, not both".into()).into(),
+            );
+        }
+    };
+    if let Some(spec) = spec {
+        let include = match params.include.as_deref() {
+            Some(raw) => Include::parse(raw.split(','))?,
+            None => Include::default(),
+        };
+        let v = index.get_version(tenant_id, record_id, spec).await?;
+        return Ok(Json(RecordOut::with_header(
+            v.meta.into(),
+            v.record,
+            include,
+        )));
+    }
+    let Some(include) = params.include.as_deref() else {
+        let meta = index.get_record_metadata(tenant_id, record_id).await?;
+        return Ok(Json(RecordOut {
+            header: meta.into(),
+            fingerprint: None,
+            embedding: None,
+            metadata: None,
+            text: None,
+            text_fields: Default::default(),
+        }));
+    };
+    let include = Include::parse(include.split(','))?;
+    let rec = index
+        .get_records(tenant_id, &[record_id])
+        .await?
+        .pop()
+        .ok_or(Error::RecordNotFound {
+            tenant_id,
+            record_id,
+        })?;
+    Ok(Json(RecordOut::new(rec, include)))
+}
+
+// ── GET /v1/records/{tenant_id}/{record_id}/versions ───────────────────
+
+pub(super) async fn list_record_versions<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path((tenant_id, record_id)): Path<(u32, u64)>,
+) -> Result<Json<VersionsResponse>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    let versions = index.list_versions(tenant_id, record_id).await?;
+    Ok(Json(VersionsResponse {
+        versions: versions.into_iter().map(Into::into).collect(),
+    }))
+}
+
+// ── GET /v1/records/{tenant_id} ────────────────────────────────────────
+
+/// Page size when `?limit=` is absent, and the hard ceiling on it.
+const SCAN_DEFAULT_LIMIT: usize = 100;
+const SCAN_MAX_LIMIT: usize = 1000;
+
+pub(super) async fn scan_records<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    axum::extract::Query(params): axum::extract::Query<ScanParams>,
+) -> Result<Json<ScanResponse>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    let modality = parse_modality(params.modality.as_deref())?;
+    let filter = RecordFilter {
+        modality,
+        algorithm: params.algorithm,
+    };
+    let limit = params
+        .limit
+        .unwrap_or(SCAN_DEFAULT_LIMIT)
+        .clamp(1, SCAN_MAX_LIMIT);
+    let page = index
+        .scan(tenant_id, params.cursor.as_deref(), limit, &filter)
+        .await?;
+    Ok(Json(ScanResponse {
+        records: page.records.into_iter().map(Into::into).collect(),
+        next_cursor: page.next_cursor,
+    }))
+}
+
+// ── GET /v1/tenants/{tenant_id}/stats ──────────────────────────────────
+
+/// Record counts, byte totals and index status of one tenant. A tenant
+/// with no records answers with zeroes rather than 404.
+pub(super) async fn tenant_stats<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+) -> Result<Json<TenantStats>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    let stats = index
+        .tenant_stats(Some(tenant_id))
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| TenantStats {
+            tenant_id,
+            ..TenantStats::default()
+        });
+    Ok(Json(stats))
+}
+
+// ── GET /v1/terms/{tenant_id}/suggest, /did-you-mean ───────────────────
+
+/// Suggestions when `?limit=` is absent, and the hard ceiling on it.
+const SUGGEST_DEFAULT_LIMIT: usize = 10;
+const SUGGEST_MAX_LIMIT: usize = 100;
+
+/// Type-ahead: dictionary terms completing the last word of `?prefix=`,
+/// most documents first.
+pub(super) async fn suggest_terms<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    axum::extract::Query(params): axum::extract::Query<SuggestParams>,
+) -> Result<Json<SuggestResponse>, ApiError> {
     tenant_guard(ctx, tenant_id)?;
-    let meta = index.get_record_metadata(tenant_id, record_id).await?;
-    Ok(Json(meta.into()))
+    let limit = params
+        .limit
+        .unwrap_or(SUGGEST_DEFAULT_LIMIT)
+        .clamp(1, SUGGEST_MAX_LIMIT);
+    let suggestions = index
+        .suggest_terms(tenant_id, &params.prefix, limit)
+        .await?;
+    Ok(Json(SuggestResponse { suggestions }))
+}
+
+/// Closest dictionary terms to each token of `?q=`, and the query
+/// rewritten with the best of them — for a search that found nothing.
+pub(super) async fn did_you_mean<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    axum::extract::Query(params): axum::extract::Query<DidYouMeanParams>,
+) -> Result<Json<DidYouMeanResponse>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    let limit = params
+        .limit
+        .unwrap_or(SUGGEST_DEFAULT_LIMIT)
+        .clamp(1, SUGGEST_MAX_LIMIT);
+    let corrections = index.did_you_mean(tenant_id, &params.q, limit).await?;
+    let best: Vec<&str> = corrections
+        .iter()
+        .map(|c| c.suggestions.first().map_or(&c.token, |s| &s.term).as_str())
+        .collect();
+    let changed = corrections.iter().zip(&best).any(|(c, b)| c.token != *b);
+    Ok(Json(DidYouMeanResponse {
+        did_you_mean: changed.then(|| best.join(" ")),
+        corrections,
+    }))
+}
+
+/// `?modality=` / body `modality` → [`Modality`], case-insensitive.
+fn parse_modality(raw: Option<&str>) -> Result<Option<Modality>, ApiError> {
+    let Some(m) = raw.map(str::to_ascii_lowercase) else {
+        return Ok(None);
+    };
+    match m.as_str() {
+        "audio" => Ok(Some(Modality::Audio)),
+        "image" => Ok(Some(Modality::Image)),
+        "text" => Ok(Some(Modality::Text)),
+        _ => Err(Error::Modality(format!(
+            "unknown modality `{m}` (expected audio, image, text)"
+        ))
+        .into()),
+    }
+}
+
+// ── POST /v1/records/{tenant_id}/batch-get ─────────────────────────────
+
+/// Upper bound on ids per batch-get call. Keeps one request's read txn
+/// and response body bounded; reconcilers page through larger sets.
+const MAX_BATCH_GET: usize = 1000;
+
+pub(super) async fn batch_get_records<I: IndexBackend>(
+    State(index): State<Arc<I>>,
+    ctx: Option<Extension<ApiKeyContext>>,
+    Path(tenant_id): Path<u32>,
+    Json(req): Json<BatchGetRequest>,
+) -> Result<Json<BatchGetResponse>, ApiError> {
+    tenant_guard(ctx, tenant_id)?;
+    if req.record_ids.len() > MAX_BATCH_GET {
+        return Err(Error::Modality(format!(
+            "batch-get accepts at most {MAX_BATCH_GET} record_ids, got {}",
+            req.record_ids.len()
+        ))
+        .into());
+    }
+    let include = Include::parse(req.include.iter().map(String::as_str))?;
+    let found = index.get_records(tenant_id, &req.record_ids).await?;
+    let found_ids: std::collections::HashSet<u64> = found.iter().map(|r| r.record_id).collect();
+    let missing = req
+        .record_ids
+        .iter()
+        .copied()
+        .filter(|id| !found_ids.contains(id))
+        .collect();
+    Ok(Json(BatchGetResponse {
+        records: found
+            .into_iter()
+            .map(|r| RecordOut::new(r, include))
+            .collect(),
+        missing,
+    }))
 }
 
 // ── POST /v1/query ─────────────────────────────────────────────────────
@@ -134,12 +773,45 @@ pub(super) struct QueryParams {
     /// Off by default so the default response stays compact.
     #[serde(default)]
     pub explain: Option<String>,
+    /// `?highlight=1` (or `true`) adds snippets of each BM25 hit's
+    /// retained text with the matched terms marked.
+    #[serde(default)]
+    pub highlight: Option<String>,
+    /// Snippets per hit with `?highlight=1`.
+    #[serde(default)]
+    pub fragments: Option<usize>,
+    /// Characters per snippet with `?highlight=1`.
+    #[serde(default)]
+    pub fragment_len: Option<usize>,
 }
 
+/// Hard ceilings on `?fragments=` and `?fragment_len=`, and the floor
+/// on the latter so a fragment still holds some context.
+const HIGHLIGHT_MAX_FRAGMENTS: usize = 10;
+const HIGHLIGHT_MIN_FRAGMENT_LEN: usize = 20;
+const HIGHLIGHT_MAX_FRAGMENT_LEN: usize = 1000;
+
 fn parse_explain(p: &QueryParams) -> bool {
     matches!(p.explain.as_deref(), Some("1" | "true" | "yes"))
 }
 
+fn parse_highlight(p: &QueryParams) -> Option<Highlight> {
+    if !matches!(p.highlight.as_deref(), Some("1" | "true" | "yes")) {
+        return None;
+    }
+    let defaults = Highlight::default();
+    Some(Highlight {
+        fragments: p
+            .fragments
+            .unwrap_or(defaults.fragments)
+            .clamp(1, HIGHLIGHT_MAX_FRAGMENTS),
+        fragment_len: p
+            .fragment_len
+            .unwrap_or(defaults.fragment_len)
+            .clamp(HIGHLIGHT_MIN_FRAGMENT_LEN, HIGHLIGHT_MAX_FRAGMENT_LEN),
+    })
+}
+
 pub(super) async fn query<I: IndexBackend>(
     State(index): State<Arc<I>>,
     ctx: Option<Extension<ApiKeyContext>>,
@@ -147,15 +819,23 @@ pub(super) async fn query<I: IndexBackend>(
     Json(req): Json<QueryRequest>,
 ) -> Result<Json<QueryResponse>, ApiError> {
     tenant_guard(ctx, req.tenant_id)?;
+    if req.vector.is_none() && req.terms.is_empty() {
+        return Err(Error::Modality("query needs `vector`, `terms`, or both".into()).into());
+    }
+    if let Some(params) = &req.bm25 {
+        params.check()?;
+    }
     let q = Query {
         tenant_id: req.tenant_id,
         modality: req.modality,
         k: req.k.max(1),
-        vector: Some(req.vector),
-        terms: Vec::new(),
+        vector: req.vector,
+        terms: req.terms,
         filter: None,
+        bm25: req.bm25,
         rrf_k: 60,
         explain: parse_explain(&params),
+        highlight: parse_highlight(&params),
     };
     let matcher = Matcher::new(index.as_ref());
     let hits = matcher.search(&q).await?;
@@ -179,6 +859,17 @@ pub(super) async fn query<I: IndexBackend>(
                     idf: t.idf,
                     tf: t.tf,
                     contribution: t.contribution,
+                    field: t.field,
+                    phrase: t.phrase,
+                    expanded_from: t.expanded_from,
+                })
+                .collect(),
+            snippets: h
+                .snippets
+                .into_iter()
+                .map(|s| crate::server::dto::SnippetOut {
+                    field: s.field,
+                    fragment: s.fragment,
                 })
                 .collect(),
         })
@@ -386,7 +1077,13 @@ pub(super) async fn ingest_text<I: IndexBackend>(
     let text = std::str::from_utf8(&body)
         .map_err(|e| Error::Modality(format!("body is not valid UTF-8: {e}")))?;
     let opts = build_text_opts(&params)?;
-    let rec =
+    let text_fields = match params.fields.as_deref() {
+        Some(json) => serde_json::from_str(json).map_err(|e| {
+            Error::Modality(format!("fields: expected a JSON object of strings: {e}"))
+        })?,
+        None => Default::default(),
+    };
+    let mut rec =
         match params.algorithm {
             TextAlgorithm::Minhash => crate::modality::text::fingerprint_minhash_with::<
                 { crate::modality::text::DEFAULT_H },
@@ -508,6 +1205,7 @@ pub(super) async fn ingest_text<I: IndexBackend>(
                 .into());
             }
         };
+    rec.text_fields = text_fields;
     index.upsert(std::slice::from_ref(&rec)).await?;
     Ok((
         StatusCode::CREATED,
@@ -700,6 +1398,12 @@ pub(super) async fn ingest_text_preprocess<I: IndexBackend>(
 
 // ── Audio ingest ───────────────────────────────────────────────────────
 
+/// `?ttl_secs=` → absolute [`Record::expires_at`] from now.
+#[cfg(feature = "audio")]
+fn ttl_expiry(ttl_secs: Option<u64>) -> Option<u64> {
+    ttl_secs.map(|s| crate::index::now_ms().saturating_add(s.saturating_mul(1000)))
+}
+
 #[cfg(feature = "audio")]
 pub(super) async fn ingest_audio<I: IndexBackend>(
     State(index): State<Arc<I>>,
@@ -749,7 +1453,7 @@ pub(super) async fn ingest_audio<I: IndexBackend>(
         .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
         .collect();
 
-    let rec = match params.algorithm {
+    let mut rec = match params.algorithm {
         AudioAlgorithm::Wang => {
             // If any tunable is set, build a WangConfig with the override
             // and call the configurable variant; otherwise use the default.
@@ -907,6 +1611,7 @@ pub(super) async fn ingest_audio<I: IndexBackend>(
             .into());
         }
     };
+    rec.expires_at = ttl_expiry(params.ttl_secs);
     index.upsert(std::slice::from_ref(&rec)).await?;
     Ok((
         StatusCode::CREATED,
@@ -997,9 +1702,10 @@ pub(super) async fn ingest_audio_stream<I: IndexBackend>(
         session.push(&samples)?;
     }
     let mut records = session.finalize()?;
-    let rec = records
+    let mut rec = records
         .pop()
         .ok_or_else(|| Error::Modality("streaming session produced no record".into()))?;
+    rec.expires_at = ttl_expiry(params.ttl_secs);
     index.upsert(std::slice::from_ref(&rec)).await?;
     Ok((
         StatusCode::CREATED,
@@ -1123,6 +1829,7 @@ pub(super) async fn inspect_text<I: IndexBackend>(
         model_id: None,
         api_key: None,
         return_embedding: None,
+        fields: None,
         canon_normalization: q.canon_normalization,
         canon_case_fold: q.canon_case_fold,
         canon_strip_bidi: q.canon_strip_bidi,
@@ -0,0 +1,171 @@
+//! In-memory registry of long-running admin jobs (bulk delete, tenant
+//! purge, …).
+//!
+//! Admin routes spawn the backend call on a tokio task, register it
+//! here and answer `202 Accepted` with the job id; callers poll
+//! `GET /v1/admin/jobs/{job_id}` for live [`Progress`] counters and the
+//! final outcome. The registry is process-local and not persisted — a
+//! restart forgets every job, but the backend operations themselves are
+//! batch-committed, so a re-run after a crash just finishes the rest.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+
+use crate::core::Progress;
+use crate::error::Result;
+use crate::index::now_ms;
+
+/// Finished jobs kept around for polling. Oldest finished jobs are
+/// evicted first; running jobs are never evicted.
+const RETAINED_FINISHED: usize = 256;
+
+/// Lifecycle of one job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+}
+
+/// Point-in-time view of a job, as served by the poll route.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobStatus {
+    pub job_id: u64,
+    pub kind: &'static str,
+    pub tenant_id: u32,
+    pub state: JobState,
+    /// Records processed so far.
+    pub records: u64,
+    /// Transactions committed so far.
+    pub batches: u64,
+    /// Final count reported by the backend once `state == done`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_ms: Option<u64>,
+}
+
+struct Job {
+    kind: &'static str,
+    tenant_id: u32,
+    progress: Arc<Progress>,
+    outcome: Option<std::result::Result<u64, String>>,
+    started_ms: u64,
+    finished_ms: Option<u64>,
+}
+
+impl Job {
+    fn status(&self, job_id: u64) -> JobStatus {
+        let (state, removed, error) = match &self.outcome {
+            None => (JobState::Running, None, None),
+            Some(Ok(n)) => (JobState::Done, Some(*n), None),
+            Some(Err(e)) => (JobState::Failed, None, Some(e.clone())),
+        };
+        JobStatus {
+            job_id,
+            kind: self.kind,
+            tenant_id: self.tenant_id,
+            state,
+            records: self.progress.records(),
+            batches: self.progress.batches(),
+            removed,
+            error,
+            started_ms: self.started_ms,
+            finished_ms: self.finished_ms,
+        }
+    }
+}
+
+/// Process-local job table.
+pub struct JobRegistry {
+    inner: Mutex<HashMap<u64, Job>>,
+    next_id: AtomicU64,
+}
+
+impl JobRegistry {
+    fn new() -> Self {
+        Self {
+            inner: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Register a job and run `work` on a tokio task. `work` receives the
+    /// shared [`Progress`] the poll route reads from. Returns the job id.
+    pub fn spawn<F, Fut>(&'static self, kind: &'static str, tenant_id: u32, work: F) -> u64
+    where
+        F: FnOnce(Arc<Progress>) -> Fut,
+        Fut: Future<Output = Result<u64>> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let progress = Arc::new(Progress::default());
+        self.inner
+            .lock()
+            .expect("job registry mutex poisoned")
+            .insert(
+                id,
+                Job {
+                    kind,
+                    tenant_id,
+                    progress: progress.clone(),
+                    outcome: None,
+                    started_ms: now_ms(),
+                    finished_ms: None,
+                },
+            );
+        let fut = work(progress);
+        tokio::spawn(async move {
+            let outcome = fut.await;
+            if let Err(e) = &outcome {
+                tracing::warn!(job_id = id, kind, tenant_id, error = %e, "admin job failed");
+            }
+            self.finish(id, outcome.map_err(|e| e.to_string()));
+        });
+        id
+    }
+
+    /// Snapshot one job, `None` when unknown or already evicted.
+    pub fn get(&self, job_id: u64) -> Option<JobStatus> {
+        let map = self.inner.lock().expect("job registry mutex poisoned");
+        map.get(&job_id).map(|j| j.status(job_id))
+    }
+
+    fn finish(&self, job_id: u64, outcome: std::result::Result<u64, String>) {
+        let mut map = self.inner.lock().expect("job registry mutex poisoned");
+        if let Some(job) = map.get_mut(&job_id) {
+            job.outcome = Some(outcome);
+            job.finished_ms = Some(now_ms());
+        }
+        let mut finished: Vec<(u64, u64)> = map
+            .iter()
+            .filter_map(|(id, j)| j.finished_ms.map(|ms| (ms, *id)))
+            .collect();
+        if finished.len() > RETAINED_FINISHED {
+            finished.sort_unstable();
+            for (_, id) in &finished[..finished.len() - RETAINED_FINISHED] {
+                map.remove(id);
+            }
+        }
+    }
+}
+
+/// Process-unique number for naming scratch files of admin work.
+pub fn next_token() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+static JOBS: OnceLock<JobRegistry> = OnceLock::new();
+
+/// Borrow the process-wide job registry, initialising on first call.
+pub fn registry() -> &'static JobRegistry {
+    JOBS.get_or_init(JobRegistry::new)
+}
@@ -14,7 +14,8 @@
 //! auth on the protected ones without a path-string allowlist:
 //!
 //! - [`public_router`] — `/healthz`, `/v1/info` (probe + version)
-//! - [`protected_router`] — everything else (records + query + ingest)
+//! - [`protected_router`] — everything else (records + query + ingest +
+//!   admin jobs)
 //!
 //! [`router`] returns the merged form (no auth) for tests and library
 //! consumers that handle auth elsewhere.
@@ -36,6 +37,7 @@ mod extractors;
 mod handlers;
 #[cfg(feature = "inspect")]
 mod inputs_cache;
+mod jobs;
 mod ratelimit;
 mod usage;
 
@@ -115,7 +117,52 @@ where
             // chain GET + DELETE on a single `.route()` call.
             get(handlers::describe_record::<I>).delete(handlers::delete_record::<I>),
         )
-        .route("/v1/query", post(handlers::query::<I>));
+        .route(
+            "/v1/records/{tenant_id}/{record_id}/versions",
+            get(handlers::list_record_versions::<I>),
+        )
+        .route("/v1/records/{tenant_id}", get(handlers::scan_records::<I>))
+        .route(
+            "/v1/records/{tenant_id}/batch-get",
+            post(handlers::batch_get_records::<I>),
+        )
+        .route("/v1/query", post(handlers::query::<I>))
+        .route(
+            "/v1/tenants/{tenant_id}/stats",
+            get(handlers::tenant_stats::<I>),
+        )
+        .route(
+            "/v1/terms/{tenant_id}/suggest",
+            get(handlers::suggest_terms::<I>),
+        )
+        .route(
+            "/v1/terms/{tenant_id}/did-you-mean",
+            get(handlers::did_you_mean::<I>),
+        )
+        .route(
+            "/v1/admin/tenants/{tenant_id}/delete-where",
+            post(handlers::admin_delete_where::<I>),
+        )
+        .route(
+            "/v1/admin/tenants/{tenant_id}/purge",
+            post(handlers::admin_purge_tenant::<I>),
+        )
+        .route(
+            "/v1/admin/tenants/{tenant_id}/reindex-bm25",
+            post(handlers::admin_reindex_bm25::<I>),
+        )
+        .route("/v1/admin/snapshot", post(handlers::admin_snapshot::<I>))
+        .route("/v1/admin/fsck", post(handlers::admin_fsck::<I>))
+        .route(
+            "/v1/admin/tenants/{tenant_id}/export",
+            get(handlers::admin_export_tenant::<I>),
+        )
+        .route(
+            "/v1/admin/tenants/{tenant_id}/import",
+            post(handlers::admin_import_tenant::<I>),
+        )
+        .route("/v1/admin/jobs/{job_id}", get(handlers::admin_job_status))
+        .route("/v1/changes", get(handlers::changes::<I>));
 
     #[cfg(feature = "image")]
     let r = r.route(
@@ -358,12 +405,21 @@ where
 
         if path == "/v1/records" && method == axum::http::Method::POST {
             (UsageOp::Upsert, None)
-        } else if path.starts_with("/v1/records/") && method == axum::http::Method::GET {
+        } else if (path.starts_with("/v1/records/") && method == axum::http::Method::GET)
+            || path == "/v1/records/{tenant_id}/batch-get"
+            || path == "/v1/tenants/{tenant_id}/stats"
+        {
             (UsageOp::Describe, None)
         } else if path.starts_with("/v1/records/") && method == axum::http::Method::DELETE {
             (UsageOp::Delete, None)
-        } else if path == "/v1/query" && method == axum::http::Method::POST {
+        } else if (path == "/v1/query" && method == axum::http::Method::POST)
+            || path.starts_with("/v1/terms/")
+        {
             (UsageOp::Query, None)
+        } else if path.starts_with("/v1/admin/") {
+            (UsageOp::Admin, None)
+        } else if path == "/v1/changes" {
+            (UsageOp::Changes, None)
         } else if path.starts_with("/v1/ingest/text/") {
             (UsageOp::Ingest, Some(crate::core::Modality::Text))
         } else if path.starts_with("/v1/ingest/image/") {
@@ -173,6 +173,87 @@ async fn delete_returns_204_and_removes_record() {
     assert_eq!(body["hits"].as_array().unwrap().len(), 0);
 }
 
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn query_terms_with_bm25_params() {
+    let (app, _dir) = fixture().await;
+    let long = format!("rust rust {}", "filler ".repeat(50));
+    for (rid, text) in [(1, "rust"), (2, long.as_str()), (3, "other")] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/v1/ingest/text/3/{rid}"))
+                    .body(Body::from(text.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+    let query = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/query?explain=1")
+            .header("content-type", "application/json")
+            .body(json_body(body))
+            .unwrap()
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(query(serde_json::json!({
+            "tenant_id": 3, "modality": "Text", "terms": ["rust"],
+        })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["hits"][0]["record_id"], 1);
+    assert_eq!(body["hits"][0]["source"], "bm25");
+    assert_eq!(body["hits"][0]["term_hits"][0]["field"], "text");
+
+    let resp = app
+        .clone()
+        .oneshot(query(serde_json::json!({
+            "tenant_id": 3, "modality": "Text", "terms": ["rust"],
+            "bm25": {"model": "tf-idf"},
+        })))
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["hits"][0]["record_id"], 2, "{body}");
+
+    for bad in [
+        serde_json::json!({"tenant_id": 3, "modality": "Text"}),
+        serde_json::json!({
+            "tenant_id": 3, "modality": "Text", "terms": ["rust"], "bm25": {"b": 2.0},
+        }),
+    ] {
+        let resp = app.clone().oneshot(query(bad.clone())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{bad}");
+    }
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/algorithms")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    let models: Vec<&str> = body["bm25_models"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(models, ["bm25", "bm25l", "bm25-plus", "tf-idf"]);
+}
+
 // ── Modality-specific ingest routes ────────────────────────────────────
 
 #[cfg(feature = "text")]
@@ -384,263 +465,859 @@ async fn describe_record_returns_404_for_missing() {
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
-#[cfg(feature = "audio-panako")]
-#[tokio::test]
-async fn ingest_audio_panako_round_trip() {
-    let (app, _dir) = fixture().await;
-    // Panako requires exactly 8 kHz input (PANAKO_SR constant in audiofp).
-    let body = synthetic_audio_bytes(2, 8_000, 440.0);
+async fn upsert_fixture_records(app: &Router) {
+    let upsert_req = serde_json::json!({
+        "records": [
+            {
+                "tenant_id": 3, "record_id": 1,
+                "modality": "Image",
+                "format_version": 1, "algorithm": "test", "config_hash": 9,
+                "fingerprint": [1, 2, 3],
+                "embedding": [0.5, -0.25],
+                "metadata": [123, 125]
+            },
+            {
+                "tenant_id": 3, "record_id": 2,
+                "modality": "Image",
+                "format_version": 1, "algorithm": "test", "config_hash": 9,
+                "fingerprint": [4, 5]
+            }
+        ]
+    });
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/v1/ingest/audio/1/1?sample_rate=8000&algorithm=panako")
-                .body(Body::from(body))
+                .uri("/v1/records")
+                .header("content-type", "application/json")
+                .body(json_body(upsert_req))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
-    let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "audiofp-panako-v1");
+    assert_eq!(resp.status(), StatusCode::OK);
 }
 
-#[cfg(feature = "audio-haitsma")]
 #[tokio::test]
-async fn ingest_audio_haitsma_round_trip() {
+async fn get_record_include_returns_stored_blobs() {
     let (app, _dir) = fixture().await;
-    // Haitsma requires exactly 5 kHz input (HAITSMA_SR constant in audiofp).
-    let body = synthetic_audio_bytes(2, 5_000, 440.0);
+    upsert_fixture_records(&app).await;
+
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/audio/1/2?sample_rate=5000&algorithm=haitsma")
-                .body(Body::from(body))
+                .uri("/v1/records/3/1?include=fingerprint,embedding,metadata")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.status(), StatusCode::OK);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "audiofp-haitsma-v1");
-}
+    assert_eq!(body["config_hash"], 9);
+    assert_eq!(body["fingerprint"], serde_json::json!([1, 2, 3]));
+    assert_eq!(body["embedding"], serde_json::json!([0.5, -0.25]));
+    assert_eq!(body["metadata"], serde_json::json!([123, 125]));
 
-#[cfg(all(feature = "audio", not(feature = "audio-neural")))]
-#[tokio::test]
-async fn ingest_audio_neural_returns_unsupported_without_feature() {
-    let (app, _dir) = fixture().await;
-    let body = synthetic_audio_bytes(1, 8_000, 440.0);
+    // Only what was asked for.
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/audio/1/3?sample_rate=8000&algorithm=neural")
-                .body(Body::from(body))
+                .uri("/v1/records/3/1?include=metadata")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["error"], "unsupported");
-    let msg = body["message"].as_str().unwrap();
-    assert!(
-        msg.contains("audio-neural"),
-        "message names the feature: {msg}"
-    );
-}
+    assert!(body.get("fingerprint").is_none());
+    assert_eq!(body["metadata"], serde_json::json!([123, 125]));
 
-#[cfg(feature = "image-perceptual")]
-#[tokio::test]
-async fn ingest_image_phash_round_trip() {
-    let (app, _dir) = fixture().await;
-    let png = synthetic_png(64, 64);
     let resp = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/image/2/1?algorithm=phash")
-                .body(Body::from(png))
+                .uri("/v1/records/3/1?include=bogus")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
-    let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "imgfprint-phash-v1");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 }
 
-#[cfg(feature = "image-perceptual")]
 #[tokio::test]
-async fn ingest_image_dhash_round_trip() {
+async fn batch_get_reports_missing_ids() {
     let (app, _dir) = fixture().await;
-    let png = synthetic_png(64, 64);
+    upsert_fixture_records(&app).await;
+
     let resp = app
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/v1/ingest/image/2/2?algorithm=dhash")
-                .body(Body::from(png))
+                .uri("/v1/records/3/batch-get")
+                .header("content-type", "application/json")
+                .body(json_body(serde_json::json!({
+                    "record_ids": [2, 77, 1],
+                    "include": ["fingerprint"]
+                })))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.status(), StatusCode::OK);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "imgfprint-dhash-v1");
+    let records = body["records"].as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["record_id"], 2);
+    assert_eq!(records[0]["fingerprint"], serde_json::json!([4, 5]));
+    assert_eq!(records[1]["record_id"], 1);
+    assert!(records[1].get("embedding").is_none());
+    assert_eq!(body["missing"], serde_json::json!([77]));
 }
 
-#[cfg(feature = "image-perceptual")]
 #[tokio::test]
-async fn ingest_image_ahash_round_trip() {
+async fn tenant_stats_count_upserted_records() {
     let (app, _dir) = fixture().await;
-    let png = synthetic_png(64, 64);
+    upsert_fixture_records(&app).await;
+
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/image/2/3?algorithm=ahash")
-                .body(Body::from(png))
+                .uri("/v1/tenants/3/stats")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.status(), StatusCode::OK);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "imgfprint-ahash-v1");
-}
+    assert_eq!(body["records"], 2);
+    assert_eq!(body["by_modality"], serde_json::json!({"image": 2}));
+    assert_eq!(body["by_algorithm"], serde_json::json!({"test": 2}));
+    assert_eq!(body["fingerprint_bytes"], 5);
+    assert_eq!(body["metadata_bytes"], 2);
+    assert_eq!(body["vector_bytes"], 8);
+    assert_eq!(body["ann"]["vectors"], 1);
+    assert_eq!(body["bm25"]["doc_count"], 0);
 
-#[cfg(feature = "image-perceptual")]
-#[tokio::test]
-async fn ingest_image_multi_explicit_round_trip() {
-    let (app, _dir) = fixture().await;
-    let png = synthetic_png(64, 64);
     let resp = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/image/2/4?algorithm=multi")
-                .body(Body::from(png))
+                .uri("/v1/tenants/4/stats")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.status(), StatusCode::OK);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "imgfprint-multihash-v1");
+    assert_eq!(body["records"], 0);
 }
 
-#[cfg(all(feature = "image", not(feature = "image-semantic")))]
 #[tokio::test]
-async fn ingest_image_semantic_returns_clean_error_without_feature() {
+async fn scan_walks_tenant_with_cursor() {
     let (app, _dir) = fixture().await;
-    let png = synthetic_png(32, 32);
+    upsert_fixture_records(&app).await;
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let resp = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            read_json::<serde_json::Value>(resp).await
+        }
+    };
+
+    let page = get("/v1/records/3?limit=1".into()).await;
+    assert_eq!(page["records"][0]["record_id"], 1);
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+    let page = get(format!("/v1/records/3?limit=1&cursor={cursor}")).await;
+    assert_eq!(page["records"][0]["record_id"], 2);
+
+    let page = get("/v1/records/3?modality=image&algorithm=test".into()).await;
+    assert_eq!(page["records"].as_array().unwrap().len(), 2);
+    assert!(page.get("next_cursor").is_none());
+    let page = get("/v1/records/3?modality=text".into()).await;
+    assert!(page["records"].as_array().unwrap().is_empty());
+
     let resp = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/image/2/5?algorithm=semantic")
-                .body(Body::from(png))
+                .uri("/v1/records/3?cursor=garbage")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    // The main /image route refuses semantic and points at /semantic
-    // (which only exists when image-semantic is on).
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 }
 
-#[cfg(feature = "text-simhash")]
 #[tokio::test]
-async fn ingest_text_simhash_tf_round_trip() {
+async fn expired_records_vanish_from_reads() {
     let (app, _dir) = fixture().await;
+    let upsert_req = serde_json::json!({
+        "records": [
+            {
+                "tenant_id": 4, "record_id": 1,
+                "modality": "Audio",
+                "format_version": 1, "algorithm": "wang", "config_hash": 0,
+                "fingerprint": [1], "expires_at": 1
+            },
+            {
+                "tenant_id": 4, "record_id": 2,
+                "modality": "Audio",
+                "format_version": 1, "algorithm": "wang", "config_hash": 0,
+                "fingerprint": [2], "expires_at": 4102444800000u64
+            }
+        ]
+    });
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/v1/ingest/text/3/1?algorithm=simhash-tf")
-                .body(Body::from("hello world"))
+                .uri("/v1/records")
+                .header("content-type", "application/json")
+                .body(json_body(upsert_req))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
-    let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "simhash-b64-tf");
-}
+    assert_eq!(resp.status(), StatusCode::OK);
 
-#[cfg(feature = "text-simhash")]
-#[tokio::test]
-async fn ingest_text_simhash_idf_round_trip() {
-    let (app, _dir) = fixture().await;
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/v1/ingest/text/3/2?algorithm=simhash-idf")
-                .body(Body::from("hello world"))
+                .uri("/v1/records/4/1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/4")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "simhash-b64-idf");
+    let records = body["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["record_id"], 2);
+    assert_eq!(records[0]["expires_at"], 4102444800000u64);
+}
+
+/// Poll `GET /v1/admin/jobs/{id}` until the job leaves `running`.
+async fn wait_for_job(app: &Router, job_id: u64) -> serde_json::Value {
+    for _ in 0..200 {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/v1/admin/jobs/{job_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = read_json(resp).await;
+        if body["state"] != "running" {
+            return body;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("job {job_id} never finished");
 }
 
-#[cfg(feature = "text-lsh")]
 #[tokio::test]
-async fn ingest_text_lsh_round_trip() {
+async fn admin_delete_where_and_purge_run_as_jobs() {
     let (app, _dir) = fixture().await;
+    upsert_fixture_records(&app).await;
+
+    // An empty filter would silently purge — refuse it.
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/v1/ingest/text/3/3?algorithm=lsh")
-                .body(Body::from("hello world"))
+                .uri("/v1/admin/tenants/3/delete-where")
+                .header("content-type", "application/json")
+                .body(json_body(serde_json::json!({})))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::CREATED);
-    let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["algorithm"], "minhash-lsh-h128");
-}
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-#[cfg(all(feature = "text", not(feature = "text-semantic-local")))]
-#[tokio::test]
-async fn ingest_text_semantic_local_returns_unsupported_without_feature() {
-    let (app, _dir) = fixture().await;
     let resp = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/v1/ingest/text/3/9?algorithm=semantic-local&model_id=ignored")
-                .body(Body::from("hi"))
+                .uri("/v1/admin/tenants/3/delete-where")
+                .header("content-type", "application/json")
+                .body(json_body(serde_json::json!({"modality": "image"})))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
     let body: serde_json::Value = read_json(resp).await;
-    assert_eq!(body["error"], "unsupported");
-    let msg = body["message"].as_str().unwrap();
-    assert!(
-        msg.contains("text-semantic-local"),
-        "message names the feature: {msg}"
-    );
-}
-
-// ── R4 additions: ServerState fixture + auth / isolation / quota / log ─
-
-#[cfg(feature = "text")]
-mod r4 {
-    use super::*;
-    use crate::server::{
-        ApiKeyContext, ApiKeyLookup, NoopRateLimiter, NoopUsageSink, RateDecision, ServerState,
-        StaticMapKey, StaticSingleKey, TenantRateLimiter, UsageSink, router_with_state,
-    };
+    let job = wait_for_job(&app, body["job_id"].as_u64().unwrap()).await;
+    assert_eq!(job["state"], "done");
+    assert_eq!(job["kind"], "delete_where");
+    assert_eq!(job["removed"], 2);
+    assert_eq!(job["records"], 2);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/3/1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    upsert_fixture_records(&app).await;
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/admin/tenants/3/purge")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = read_json(resp).await;
+    let job = wait_for_job(&app, body["job_id"].as_u64().unwrap()).await;
+    assert_eq!(job["state"], "done");
+    assert_eq!(job["removed"], 2);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/admin/jobs/999999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn term_suggestions_complete_and_correct() {
+    let (app, _dir) = fixture().await;
+    for (rid, text) in [(1, "brown fox"), (2, "brown dog"), (3, "brownie recipe")] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/v1/ingest/text/3/{rid}"))
+                    .header("content-type", "text/plain; charset=utf-8")
+                    .body(Body::from(text))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/terms/3/suggest?prefix=quick%20Bro&limit=5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(
+        body["suggestions"],
+        serde_json::json!([
+            {"term": "brown", "doc_freq": 2, "distance": 0},
+            {"term": "brownie", "doc_freq": 1, "distance": 0},
+        ])
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/terms/3/did-you-mean?q=brwn%20fox&limit=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["did_you_mean"], "brown fox");
+    assert_eq!(body["corrections"][0]["token"], "brwn");
+    assert_eq!(body["corrections"][0]["suggestions"][0]["distance"], 1);
+    assert_eq!(body["corrections"][1]["suggestions"][0]["term"], "fox");
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/terms/3/did-you-mean?q=brown")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert!(body.get("did_you_mean").is_none(), "{body}");
+}
+
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn admin_reindex_bm25_rebuilds_from_retained_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = EmbeddedBackend::open(dir.path().join("ucfp.redb"))
+        .unwrap()
+        .with_retained_text();
+    let app = router(Arc::new(backend));
+    for (rid, text) in [(1, "the quick brown fox"), (2, "a lazy brown dog")] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/v1/ingest/text/3/{rid}"))
+                    .header("content-type", "text/plain; charset=utf-8")
+                    .body(Body::from(text))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/admin/tenants/3/reindex-bm25")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = read_json(resp).await;
+    let job = wait_for_job(&app, body["job_id"].as_u64().unwrap()).await;
+    assert_eq!(job["state"], "done");
+    assert_eq!(job["kind"], "reindex_bm25");
+    assert_eq!(job["removed"], 2);
+    assert_eq!(job["records"], 2);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/3/2?include=text")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["text"], "a lazy brown dog");
+}
+
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn ingest_text_carries_named_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = EmbeddedBackend::open(dir.path().join("ucfp.redb"))
+        .unwrap()
+        .with_retained_text();
+    let app = router(Arc::new(backend));
+    let ingest = |fields: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/v1/ingest/text/3/1?fields={fields}"))
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(Body::from("the quarterly numbers"))
+            .unwrap()
+    };
+    // {"title":"Annual report"}
+    let resp = app
+        .clone()
+        .oneshot(ingest("%7B%22title%22%3A%22Annual%20report%22%7D"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/3/1?include=text")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["text_fields"]["title"], "Annual report");
+
+    for bad in ["not-json", "%7B%22Title%22%3A%22x%22%7D"] {
+        let resp = app.clone().oneshot(ingest(bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{bad}");
+    }
+}
+
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn query_highlight_returns_capped_snippets() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = EmbeddedBackend::open(dir.path().join("ucfp.redb"))
+        .unwrap()
+        .with_retained_text();
+    let app = router(Arc::new(backend));
+    let text = format!("harbour {}", "quiet water ".repeat(10)).repeat(15);
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/text/3/1")
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(Body::from(text))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let query = |app: &Router, params: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/v1/query?{params}"))
+                .header("content-type", "application/json")
+                .body(json_body(serde_json::json!({
+                    "tenant_id": 3, "modality": "Text", "terms": ["harbour"],
+                })))
+                .unwrap(),
+        )
+    };
+
+    let resp = query(&app, "highlight=1&fragments=2&fragment_len=40")
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    let hit = &body["hits"][0];
+    assert!(hit.get("term_hits").is_none(), "{body}");
+    let snippets = hit["snippets"].as_array().unwrap();
+    assert_eq!(snippets.len(), 2, "{body}");
+    assert_eq!(snippets[0]["field"], "text");
+    let fragment = snippets[0]["fragment"].as_str().unwrap();
+    assert!(fragment.contains("<mark>harbour</mark>"), "{fragment}");
+    assert!(fragment.chars().count() <= 40 + "<mark></mark>…".len() * 2);
+
+    // Over the ceiling is clamped; explain still comes along when asked.
+    let resp = query(&app, "highlight=1&explain=1&fragments=50&fragment_len=20")
+        .await
+        .unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["hits"][0]["snippets"].as_array().unwrap().len(), 10);
+    assert_eq!(body["hits"][0]["term_hits"][0]["term"], "harbour");
+
+    let resp = query(&app, "explain=1").await.unwrap();
+    let body: serde_json::Value = read_json(resp).await;
+    assert!(body["hits"][0].get("snippets").is_none(), "{body}");
+
+    // Without retained text there is nothing to cut snippets from.
+    let (plain, _plain_dir) = fixture().await;
+    let resp = query(&plain, "highlight=1").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[cfg(feature = "audio-panako")]
+#[tokio::test]
+async fn ingest_audio_panako_round_trip() {
+    let (app, _dir) = fixture().await;
+    // Panako requires exactly 8 kHz input (PANAKO_SR constant in audiofp).
+    let body = synthetic_audio_bytes(2, 8_000, 440.0);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/audio/1/1?sample_rate=8000&algorithm=panako")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "audiofp-panako-v1");
+}
+
+#[cfg(feature = "audio-haitsma")]
+#[tokio::test]
+async fn ingest_audio_haitsma_round_trip() {
+    let (app, _dir) = fixture().await;
+    // Haitsma requires exactly 5 kHz input (HAITSMA_SR constant in audiofp).
+    let body = synthetic_audio_bytes(2, 5_000, 440.0);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/audio/1/2?sample_rate=5000&algorithm=haitsma")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "audiofp-haitsma-v1");
+}
+
+#[cfg(all(feature = "audio", not(feature = "audio-neural")))]
+#[tokio::test]
+async fn ingest_audio_neural_returns_unsupported_without_feature() {
+    let (app, _dir) = fixture().await;
+    let body = synthetic_audio_bytes(1, 8_000, 440.0);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/audio/1/3?sample_rate=8000&algorithm=neural")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["error"], "unsupported");
+    let msg = body["message"].as_str().unwrap();
+    assert!(
+        msg.contains("audio-neural"),
+        "message names the feature: {msg}"
+    );
+}
+
+#[cfg(feature = "image-perceptual")]
+#[tokio::test]
+async fn ingest_image_phash_round_trip() {
+    let (app, _dir) = fixture().await;
+    let png = synthetic_png(64, 64);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/image/2/1?algorithm=phash")
+                .body(Body::from(png))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "imgfprint-phash-v1");
+}
+
+#[cfg(feature = "image-perceptual")]
+#[tokio::test]
+async fn ingest_image_dhash_round_trip() {
+    let (app, _dir) = fixture().await;
+    let png = synthetic_png(64, 64);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/image/2/2?algorithm=dhash")
+                .body(Body::from(png))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "imgfprint-dhash-v1");
+}
+
+#[cfg(feature = "image-perceptual")]
+#[tokio::test]
+async fn ingest_image_ahash_round_trip() {
+    let (app, _dir) = fixture().await;
+    let png = synthetic_png(64, 64);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/image/2/3?algorithm=ahash")
+                .body(Body::from(png))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "imgfprint-ahash-v1");
+}
+
+#[cfg(feature = "image-perceptual")]
+#[tokio::test]
+async fn ingest_image_multi_explicit_round_trip() {
+    let (app, _dir) = fixture().await;
+    let png = synthetic_png(64, 64);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/image/2/4?algorithm=multi")
+                .body(Body::from(png))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "imgfprint-multihash-v1");
+}
+
+#[cfg(all(feature = "image", not(feature = "image-semantic")))]
+#[tokio::test]
+async fn ingest_image_semantic_returns_clean_error_without_feature() {
+    let (app, _dir) = fixture().await;
+    let png = synthetic_png(32, 32);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/image/2/5?algorithm=semantic")
+                .body(Body::from(png))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    // The main /image route refuses semantic and points at /semantic
+    // (which only exists when image-semantic is on).
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[cfg(feature = "text-simhash")]
+#[tokio::test]
+async fn ingest_text_simhash_tf_round_trip() {
+    let (app, _dir) = fixture().await;
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/text/3/1?algorithm=simhash-tf")
+                .body(Body::from("hello world"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "simhash-b64-tf");
+}
+
+#[cfg(feature = "text-simhash")]
+#[tokio::test]
+async fn ingest_text_simhash_idf_round_trip() {
+    let (app, _dir) = fixture().await;
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/text/3/2?algorithm=simhash-idf")
+                .body(Body::from("hello world"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "simhash-b64-idf");
+}
+
+#[cfg(feature = "text-lsh")]
+#[tokio::test]
+async fn ingest_text_lsh_round_trip() {
+    let (app, _dir) = fixture().await;
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/text/3/3?algorithm=lsh")
+                .body(Body::from("hello world"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["algorithm"], "minhash-lsh-h128");
+}
+
+#[cfg(all(feature = "text", not(feature = "text-semantic-local")))]
+#[tokio::test]
+async fn ingest_text_semantic_local_returns_unsupported_without_feature() {
+    let (app, _dir) = fixture().await;
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/ingest/text/3/9?algorithm=semantic-local&model_id=ignored")
+                .body(Body::from("hi"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["error"], "unsupported");
+    let msg = body["message"].as_str().unwrap();
+    assert!(
+        msg.contains("text-semantic-local"),
+        "message names the feature: {msg}"
+    );
+}
+
+// ── R4 additions: ServerState fixture + auth / isolation / quota / log ─
+
+#[cfg(feature = "text")]
+mod r4 {
+    use super::*;
+    use crate::server::{
+        ApiKeyContext, ApiKeyLookup, NoopRateLimiter, NoopUsageSink, RateDecision, ServerState,
+        StaticMapKey, StaticSingleKey, TenantRateLimiter, UsageSink, router_with_state,
+    };
 
     /// Build a `ServerState<EmbeddedBackend>` with caller-chosen
     /// trait-object impls and return the auth-wrapped router. Holds onto
@@ -937,6 +1614,12 @@ key_id = "k10"
 token = "key-t20"
 tenant_id = 20
 key_id = "k20"
+
+[[key]]
+token = "key-t10-admin"
+tenant_id = 10
+key_id = "k10-admin"
+scopes = ["admin"]
 "#,
     )
     .unwrap();
@@ -991,6 +1674,97 @@ async fn cross_tenant_read_is_forbidden() {
     assert_eq!(body["error"], "forbidden");
 }
 
+#[tokio::test]
+async fn admin_routes_need_the_admin_scope_for_the_own_tenant() {
+    let (app, _dir) = multi_tenant_fixture().await;
+    let post = |uri: &str, token: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("content-type", "application/json")
+            .body(json_body(serde_json::json!({})))
+            .unwrap()
+    };
+
+    // A plain tenant key may not run admin jobs, even on its own tenant.
+    for uri in [
+        "/v1/admin/tenants/10/purge",
+        "/v1/admin/tenants/10/delete-where",
+    ] {
+        let resp = app.clone().oneshot(post(uri, "key-t10")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{uri}");
+    }
+
+    // The admin scope covers only the key's own tenant.
+    for uri in [
+        "/v1/admin/tenants/20/purge",
+        "/v1/admin/tenants/20/delete-where",
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(post(uri, "key-t10-admin"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{uri}");
+    }
+    let resp = app
+        .oneshot(post("/v1/admin/tenants/10/purge", "key-t10-admin"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn cross_tenant_upsert_is_forbidden() {
+    let (app, _dir) = multi_tenant_fixture().await;
+    let upsert = |tenants: &[u32]| {
+        let records: Vec<_> = tenants
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                serde_json::json!({
+                    "tenant_id": t, "record_id": i,
+                    "modality": "Image",
+                    "format_version": 1, "algorithm": "test", "config_hash": 0,
+                    "fingerprint": [1]
+                })
+            })
+            .collect();
+        Request::builder()
+            .method("POST")
+            .uri("/v1/records")
+            .header("Authorization", "Bearer key-t10")
+            .header("content-type", "application/json")
+            .body(json_body(serde_json::json!({ "records": records })))
+            .unwrap()
+    };
+
+    let resp = app.clone().oneshot(upsert(&[10, 20])).await.unwrap();
+    assert_eq!(
+        resp.status(),
+        StatusCode::FORBIDDEN,
+        "one foreign record rejects the batch"
+    );
+    let resp = app.clone().oneshot(upsert(&[10, 10])).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["upserted"], 2);
+
+    // Nothing from the rejected batch landed in tenant 20.
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/20/1")
+                .header("Authorization", "Bearer key-t20")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
 #[cfg(feature = "inspect")]
 #[tokio::test]
 async fn input_cache_roundtrip_then_text_ingest_via_input_id() {
@@ -1265,3 +2039,441 @@ async fn pipeline_inspect_audio_returns_each_stage() {
     assert!(body["fingerprint_bytes"].as_u64().is_some());
     assert!(body["fingerprint_hex"].as_str().is_some());
 }
+
+#[tokio::test]
+async fn record_versions_are_listed_and_readable() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = EmbeddedBackend::open(dir.path().join("ucfp.redb"))
+        .unwrap()
+        .with_versioning(crate::core::VersionPolicy {
+            keep_versions: Some(5),
+            keep_for_ms: None,
+        });
+    let app = router(Arc::new(backend));
+
+    for fp in [1u8, 2] {
+        let upsert_req = serde_json::json!({
+            "records": [{
+                "tenant_id": 5, "record_id": 1,
+                "modality": "Image",
+                "format_version": 1, "algorithm": "test", "config_hash": 0,
+                "fingerprint": [fp]
+            }]
+        });
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/records")
+                    .header("content-type", "application/json")
+                    .body(json_body(upsert_req))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/5/1/versions")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    let versions: Vec<u64> = body["versions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["version"].as_u64().unwrap())
+        .collect();
+    assert_eq!(versions, vec![1, 2]);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/5/1?version=1&include=fingerprint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["version"], 1);
+    assert_eq!(body["fingerprint"], serde_json::json!([1]));
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/5/1?version=1&as_of=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn conditional_upsert_reports_per_record_results() {
+    let (app, _dir) = fixture().await;
+    let rec = |fp: u8, cond: serde_json::Value| {
+        let mut r = serde_json::json!({
+            "tenant_id": 6, "record_id": 1,
+            "modality": "Image",
+            "format_version": 1, "algorithm": "test", "config_hash": 3,
+            "fingerprint": [fp]
+        });
+        r.as_object_mut()
+            .unwrap()
+            .extend(cond.as_object().unwrap().clone());
+        r
+    };
+    let post = |records: Vec<serde_json::Value>| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/records")
+            .header("content-type", "application/json")
+            .body(json_body(serde_json::json!({ "records": records })))
+            .unwrap()
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(post(vec![
+            rec(1, serde_json::json!({ "if_absent": true })),
+            rec(2, serde_json::json!({ "if_absent": true })),
+            rec(3, serde_json::json!({ "if_version": 5 })),
+            rec(4, serde_json::json!({ "if_config_hash_matches": 3 })),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["upserted"], 2);
+    assert_eq!(
+        body["results"],
+        serde_json::json!(["written", "skipped", "conflict", "written"])
+    );
+
+    let resp = app
+        .oneshot(post(vec![rec(
+            5,
+            serde_json::json!({ "if_absent": true, "if_version": 1 }),
+        )]))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn change_log_tails_as_ndjson_and_sse() {
+    let (app, _dir) = fixture().await;
+    let records = [1, 2].map(|rid| {
+        serde_json::json!({
+            "tenant_id": 7, "record_id": rid,
+            "modality": "Image",
+            "format_version": 1, "algorithm": "test", "config_hash": 0,
+            "fingerprint": [1]
+        })
+    });
+    let upsert_req = serde_json::json!({ "records": records });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/records")
+                .header("content-type", "application/json")
+                .body(json_body(upsert_req))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/v1/records/7/1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/changes?since=1&follow=false")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let events: Vec<serde_json::Value> = body
+        .split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| serde_json::from_slice(l).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["seq"], 2);
+    assert_eq!(events[0]["kind"], "upsert");
+    assert_eq!(events[1]["kind"], "delete");
+    assert_eq!(events[1]["record_id"], 1);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/changes?follow=false&tenant_id=7")
+                .header("accept", "text/event-stream")
+                .header("last-event-id", "2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let text = std::str::from_utf8(&body).unwrap();
+    assert!(text.contains("id: 3"), "{text}");
+    assert!(!text.contains("id: 2"), "{text}");
+}
+
+#[tokio::test]
+async fn change_log_answers_gone_past_retention() {
+    use crate::index::IndexBackend;
+
+    let dir = tempfile::tempdir().unwrap();
+    let backend = Arc::new(EmbeddedBackend::open(dir.path().join("ucfp.redb")).unwrap());
+    let app = router(Arc::clone(&backend));
+    for rid in [1, 2] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/records")
+                    .header("content-type", "application/json")
+                    .body(json_body(serde_json::json!({ "records": [{
+                        "tenant_id": 7, "record_id": rid,
+                        "modality": "Image",
+                        "format_version": 1, "algorithm": "test", "config_hash": 0,
+                        "fingerprint": [1]
+                    }] })))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    assert_eq!(backend.prune_changes(u64::MAX, 1).await.unwrap(), 1);
+
+    let get = |uri: &'static str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+    // Seq 1 is gone but seq 2 is not, so a reader at 1 loses nothing.
+    let resp = get("/v1/changes?since=1&follow=false").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(backend.prune_changes(u64::MAX, 1).await.unwrap(), 1);
+    let resp = get("/v1/changes?since=0&follow=false").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK, "0 reads what is retained");
+    let resp = get("/v1/changes?since=1&follow=false").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::GONE);
+    let body: serde_json::Value = read_json(resp).await;
+    assert_eq!(body["error"], "gone");
+}
+
+#[tokio::test]
+async fn admin_snapshot_writes_or_streams() {
+    let (app, dir) = fixture().await;
+    let target = dir.path().join("backup.redb");
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/admin/snapshot")
+                .header("content-type", "application/json")
+                .body(json_body(serde_json::json!({ "path": target })))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let info: serde_json::Value = read_json(resp).await;
+    assert_eq!(info["format_version"], crate::FORMAT_VERSION);
+    assert!(target.exists());
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/admin/snapshot")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/octet-stream");
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let streamed = dir.path().join("streamed.redb");
+    std::fs::write(&streamed, &body).unwrap();
+    EmbeddedBackend::check_snapshot(&streamed).unwrap();
+}
+
+#[tokio::test]
+async fn admin_fsck_reports_a_clean_store() {
+    let (app, _dir) = fixture().await;
+    let upsert_req = serde_json::json!({ "records": [{
+        "tenant_id": 4, "record_id": 1,
+        "modality": "Image",
+        "format_version": 1, "algorithm": "test", "config_hash": 11,
+        "fingerprint": [1], "embedding": [0.25, 0.5]
+    }] });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/records")
+                .header("content-type", "application/json")
+                .body(json_body(upsert_req))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let fsck = |body: Body| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/admin/fsck")
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap(),
+        )
+    };
+    let resp = fsck(Body::empty()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: serde_json::Value = read_json(resp).await;
+    assert_eq!(report["tenants"], 1);
+    assert_eq!(report["records"], 1);
+    assert_eq!(report["repair"], false);
+    assert_eq!(report["violations"], serde_json::json!([]));
+
+    let resp = fsck(json_body(
+        serde_json::json!({ "tenant_id": 4, "repair": true }),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: serde_json::Value = read_json(resp).await;
+    assert_eq!(report["repair"], true);
+
+    let resp = fsck(Body::from("{")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn tenant_export_imports_under_a_new_tenant() {
+    let (app, _dir) = fixture().await;
+    let records = [1, 2, 3].map(|rid| {
+        serde_json::json!({
+            "tenant_id": 4, "record_id": rid,
+            "modality": "Image",
+            "format_version": 1, "algorithm": "test", "config_hash": 11,
+            "fingerprint": [rid], "embedding": [0.25, 0.5]
+        })
+    });
+    let upsert_req = serde_json::json!({ "records": records });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/records")
+                .header("content-type", "application/json")
+                .body(json_body(upsert_req))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/admin/tenants/4/export")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+    let archive = resp.into_body().collect().await.unwrap().to_bytes();
+    let lines: Vec<&[u8]> = archive
+        .split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .collect();
+    assert_eq!(lines.len(), 5);
+
+    let import = |body: Body| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/admin/tenants/9/import")
+            .header("content-type", "application/x-ndjson")
+            .body(body)
+            .unwrap()
+    };
+    let resp = app
+        .clone()
+        .oneshot(import(Body::from(archive.clone())))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let summary: serde_json::Value = read_json(resp).await;
+    assert_eq!(summary["source_tenant"], 4);
+    assert_eq!(summary["tenant_id"], 9);
+    assert_eq!(summary["records"], 3);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/records/9/2?include=fingerprint,embedding")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let rec: serde_json::Value = read_json(resp).await;
+    assert_eq!(rec["config_hash"], 11);
+    assert_eq!(rec["embedding"], serde_json::json!([0.25, 0.5]));
+
+    // Cut before the end line: reported, not silently accepted.
+    let truncated = archive[..archive.len() - lines[4].len() - 1].to_vec();
+    let resp = app.oneshot(import(Body::from(truncated))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
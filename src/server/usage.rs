@@ -35,10 +35,14 @@ pub enum UsageOp {
     Upsert,
     /// Search / similarity query (`POST /v1/query`).
     Query,
-    /// Metadata describe (`GET /v1/records/...`).
+    /// Record read (`GET /v1/records/...`, `POST .../batch-get`).
     Describe,
     /// Record deletion (`DELETE /v1/records/...`).
     Delete,
+    /// Admin job submission / polling (`/v1/admin/...`).
+    Admin,
+    /// Change-log tail (`GET /v1/changes`).
+    Changes,
 }
 
 /// One usage line. Constructed at the response boundary by the usage